@@ -0,0 +1,21 @@
+use anyhow::Result;
+use cinelink::backfill::{run_from_args, BackfillKind};
+use dotenvy::dotenv;
+use std::env;
+use tracing_subscriber::EnvFilter;
+
+fn init_tracing() {
+    let filter = EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new("info"));
+    tracing_subscriber::fmt()
+        .with_env_filter(filter)
+        .with_target(false)
+        .compact()
+        .init();
+}
+
+#[tokio::main]
+async fn main() -> Result<()> {
+    let _ = dotenv();
+    init_tracing();
+    run_from_args(env::args().skip(1), BackfillKind::Movie).await
+}
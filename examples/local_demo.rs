@@ -0,0 +1,270 @@
+//! End-to-end demo without real credentials.
+//!
+//! Starts mock Notion and TMDB servers, boots the real router against clients pointed at
+//! them, sends one signed `page.properties_updated` webhook for a page titled "Dune ;" and
+//! prints the update body the mock Notion received:
+//!
+//! ```bash
+//! cargo run --example local_demo
+//! ```
+//!
+//! `tests/local_demo.rs` runs this too, so it keeps working as the code changes.
+use anyhow::{Context, Result};
+use axum::body::Body;
+use axum::http::Request;
+use cinelink::anilist::AniListClient;
+use cinelink::app::{build_router, spawn_job_workers, AppState};
+use cinelink::backfill::BackfillProgress;
+use cinelink::config::AppConfig;
+use cinelink::duplicates::DuplicateIndex;
+use cinelink::failures::FailureLog;
+use cinelink::metrics::Metrics;
+use cinelink::negative_cache::NegativeCache;
+use cinelink::notion::{NotionApi, NotionClient, SharedSchema, NOTION_VERSION};
+use cinelink::queue::JobQueue;
+use cinelink::rate_limit::TokenBucket;
+use cinelink::retry::{RetryQueue, DEFAULT_RETRY_BASE_DELAY};
+use cinelink::tmdb::TmdbClient;
+use cinelink::triggers::TriggerConfig;
+use hmac::{Hmac, Mac};
+use serde_json::{json, Value};
+use sha2::Sha256;
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::{Mutex, Semaphore};
+use tower::util::ServiceExt;
+use wiremock::matchers::{method, path};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+const DATABASE_ID: &str = "demo-db";
+const PAGE_ID: &str = "demo-page";
+const WEBHOOK_SECRET: &str = "demo-secret";
+const MOVIE_ID: i32 = 693134;
+
+async fn mock_notion() -> MockServer {
+    let server = MockServer::start().await;
+    let schema = json!({
+        "object": "database",
+        "id": DATABASE_ID,
+        "properties": {
+            "Name": { "type": "title" },
+            "Type": { "type": "select" },
+            "ID": { "type": "number" },
+            "Synopsis": { "type": "rich_text" },
+            "Genre": { "type": "multi_select" },
+            "Cast": { "type": "rich_text" },
+            "Director": { "type": "rich_text" },
+            "Release Date": { "type": "date" },
+            "Year": { "type": "rich_text" },
+            "Runtime": { "type": "number" },
+            "IMDb Page": { "type": "url" }
+        }
+    });
+    let page = json!({
+        "object": "page",
+        "id": PAGE_ID,
+        "properties": {
+            "Name": { "type": "title", "title": [{ "plain_text": "Dune ;", "text": { "content": "Dune ;" } }] },
+            "Type": { "type": "select", "select": { "name": "Movie" } },
+            "ID": { "type": "number", "number": null }
+        }
+    });
+    Mock::given(method("GET"))
+        .and(path(format!("/databases/{DATABASE_ID}")))
+        .respond_with(ResponseTemplate::new(200).set_body_json(schema))
+        .mount(&server)
+        .await;
+    Mock::given(method("GET"))
+        .and(path(format!("/pages/{PAGE_ID}")))
+        .respond_with(ResponseTemplate::new(200).set_body_json(page))
+        .mount(&server)
+        .await;
+    Mock::given(method("PATCH"))
+        .and(path(format!("/pages/{PAGE_ID}")))
+        .respond_with(
+            ResponseTemplate::new(200).set_body_json(json!({ "object": "page", "id": PAGE_ID })),
+        )
+        .mount(&server)
+        .await;
+    // Duplicate detection looks for other pages with the same ID; there are none.
+    Mock::given(method("POST"))
+        .and(path(format!("/databases/{DATABASE_ID}/query")))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "results": [],
+            "has_more": false,
+            "next_cursor": null
+        })))
+        .mount(&server)
+        .await;
+    server
+}
+
+async fn mock_tmdb() -> MockServer {
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path("/search/movie"))
+        .respond_with(
+            ResponseTemplate::new(200).set_body_json(json!({ "results": [{ "id": MOVIE_ID }] })),
+        )
+        .mount(&server)
+        .await;
+    Mock::given(method("GET"))
+        .and(path(format!("/movie/{MOVIE_ID}")))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "id": MOVIE_ID,
+            "title": "Dune: Part Two",
+            "original_title": "Dune: Part Two",
+            "overview": "Paul Atreides unites with the Fremen.",
+            "release_date": "2024-02-27",
+            "runtime": 167,
+            "original_language": "en",
+            "origin_country": ["US"],
+            "poster_path": "/poster.jpg",
+            "backdrop_path": "/backdrop.jpg",
+            "genres": [{ "name": "Science Fiction" }, { "name": "Adventure" }],
+            "credits": {
+                "cast": [{ "name": "Timothée Chalamet" }, { "name": "Zendaya" }],
+                "crew": [{ "job": "Director", "name": "Denis Villeneuve" }]
+            },
+            "release_dates": {
+                "results": [{ "iso_3166_1": "US", "release_dates": [{ "certification": "PG-13" }] }]
+            },
+            "videos": {
+                "results": [{ "site": "YouTube", "type": "Trailer", "key": "Way9Dexny3w" }]
+            },
+            "external_ids": { "imdb_id": "tt15239678" },
+            "images": { "posters": [] }
+        })))
+        .mount(&server)
+        .await;
+    Mock::given(method("GET"))
+        .and(path("/configuration/countries"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!([
+            { "iso_3166_1": "US", "english_name": "United States of America" }
+        ])))
+        .mount(&server)
+        .await;
+    Mock::given(method("GET"))
+        .and(path("/configuration/languages"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!([
+            { "iso_639_1": "en", "english_name": "English" }
+        ])))
+        .mount(&server)
+        .await;
+    server
+}
+
+fn signed_webhook() -> Result<Request<Body>> {
+    let body = json!({
+        "id": "demo-event-1",
+        "timestamp": chrono::Utc::now().to_rfc3339(),
+        "type": "page.properties_updated",
+        "entity": { "id": PAGE_ID, "type": "page" },
+        "data": { "updated_properties": ["title"] }
+    })
+    .to_string();
+    let mut mac = Hmac::<Sha256>::new_from_slice(WEBHOOK_SECRET.as_bytes())?;
+    mac.update(body.as_bytes());
+    let signature = format!("sha256={}", hex::encode(mac.finalize().into_bytes()));
+    Ok(Request::post("/")
+        .header("content-type", "application/json")
+        .header("Notion-Version", NOTION_VERSION)
+        .header("x-notion-signature", signature)
+        .body(Body::from(body))?)
+}
+
+/// Runs the demo and returns the JSON body of the page update sent to the mock Notion.
+pub async fn run_demo() -> Result<Value> {
+    let notion_server = mock_notion().await;
+    let tmdb_server = mock_tmdb().await;
+
+    let notion = NotionClient::new("demo-key".to_string(), DATABASE_ID.to_string())?
+        .with_base_url(notion_server.uri());
+    let schema = notion.fetch_property_schema().await?;
+    let tmdb = TmdbClient::new("demo-key".to_string())?.with_base_url(tmdb_server.uri());
+    let config = Arc::new(AppConfig::default());
+    let metrics = Arc::new(Metrics::new());
+    let state = AppState {
+        notion: Arc::new(notion),
+        tmdb: Arc::new(tmdb),
+        anilist: Arc::new(AniListClient::new()?),
+        title_property: schema
+            .title_property
+            .clone()
+            .unwrap_or_else(|| "Name".to_string()),
+        triggers: Arc::new(TriggerConfig::default()),
+        config: config.clone(),
+        schema: Arc::new(SharedSchema::new(schema)),
+        signing_secret: WEBHOOK_SECRET.to_string(),
+        secondary_signing_secret: None,
+        rate_limits: Arc::new(Mutex::new(HashMap::new())),
+        global_limit: Arc::new(Mutex::new(TokenBucket::default())),
+        shape_warning_limit: Arc::new(Mutex::new(TokenBucket::default())),
+        recent_events: Arc::new(Mutex::new(HashMap::new())),
+        dedupe_store: None,
+        processing_sem: Arc::new(Semaphore::new(config.max_concurrent_jobs)),
+        duplicates: Arc::new(DuplicateIndex::new()),
+        negative_lookups: Arc::new(NegativeCache::new(Duration::from_secs(
+            config.negative_cache_ttl_secs,
+        ))),
+        metrics: metrics.clone(),
+        readiness: Arc::new(Mutex::new(None)),
+        admin_key: None,
+        capture_dir: None,
+        failures: Arc::new(FailureLog::new()),
+        retry: Arc::new(RetryQueue::new(
+            config.retry_max_attempts,
+            DEFAULT_RETRY_BASE_DELAY,
+            metrics,
+        )),
+        verification_token: Arc::new(Mutex::new(None)),
+        backfill: Arc::new(BackfillProgress::default()),
+        error_notifier: None,
+        in_flight_pages: Arc::new(std::sync::Mutex::new(HashMap::new())),
+        jobs: tokio_util::task::TaskTracker::new(),
+        job_queue: Arc::new(JobQueue::new(config.job_queue_capacity)),
+    };
+
+    spawn_job_workers(&state);
+    let response = build_router(state).oneshot(signed_webhook()?).await?;
+    anyhow::ensure!(
+        response.status().is_success(),
+        "webhook rejected: {}",
+        response.status()
+    );
+
+    // Processing happens in the background; wait for the PATCH to reach the mock.
+    let deadline = tokio::time::Instant::now() + Duration::from_secs(10);
+    loop {
+        let requests = notion_server
+            .received_requests()
+            .await
+            .context("request recording is enabled")?;
+        if let Some(update) = requests.iter().find(|r| r.method.as_str() == "PATCH") {
+            return Ok(serde_json::from_slice(&update.body)?);
+        }
+        anyhow::ensure!(
+            tokio::time::Instant::now() < deadline,
+            "timed out waiting for the Notion update"
+        );
+        tokio::time::sleep(Duration::from_millis(20)).await;
+    }
+}
+
+#[tokio::main]
+async fn main() -> Result<()> {
+    tracing_subscriber::fmt()
+        .with_env_filter(
+            tracing_subscriber::EnvFilter::try_from_default_env()
+                .unwrap_or_else(|_| tracing_subscriber::EnvFilter::new("info")),
+        )
+        .with_target(false)
+        .compact()
+        .init();
+
+    let update = run_demo().await?;
+    println!("Mock Notion received this page update:");
+    println!("{}", serde_json::to_string_pretty(&update)?);
+    Ok(())
+}
@@ -1,3 +1,8 @@
+use crate::budget::Provider;
+use crate::errors::UpstreamStatus;
+use crate::http::RetryingClient;
+use crate::metrics::{ANILIST_RELATIONS_CACHE, ANILIST_TITLE_CACHE};
+use crate::titles::normalize_title_key;
 use anyhow::{anyhow, Context, Result};
 use reqwest::Client;
 use serde::Deserialize;
@@ -8,19 +13,106 @@ use std::time::Duration;
 use std::time::Instant;
 use tokio::sync::Mutex;
 
-use super::AniListMapped;
+use super::{AniListMapped, RelationStrategy};
 
-const ANILIST_ENDPOINT: &str = "https://graphql.anilist.co";
-const RELATIONS_CACHE_TTL_SECS: u64 = 60 * 60 * 24; // 24 hours
-const TITLE_CACHE_TTL_SECS: u64 = 60 * 60 * 24; // 24 hours
-const MAX_CACHE_ENTRIES: usize = 20_000;
-const MAX_RETRIES: usize = 3;
+const DEFAULT_ANILIST_ENDPOINT: &str = "https://graphql.anilist.co";
+const DEFAULT_CONNECT_TIMEOUT_SECS: u64 = 5;
+const DEFAULT_REQUEST_TIMEOUT_SECS: u64 = 30;
+pub const DEFAULT_CACHE_TTL_SECS: u64 = 60 * 60 * 24; // 24 hours
+const DEFAULT_MAX_CACHE_ENTRIES: usize = 20_000;
 
 #[derive(Debug, Clone)]
 pub struct AniListClient {
-    client: Client,
+    http: RetryingClient,
+    endpoint: String,
     relations_cache: Arc<Mutex<HashMap<i32, CacheEntry<RelationsPayload>>>>,
     title_cache: Arc<Mutex<HashMap<i32, CacheEntry<MediaTitle>>>>,
+    /// How long relations and titles are kept; applies to both caches.
+    cache_ttl: Duration,
+    /// A cache that grows past this is emptied before the next insert.
+    max_cache_entries: usize,
+    relation_strategy: RelationStrategy,
+}
+
+/// Settings for an [`AniListClient`]; `AniListClient::new()` uses the defaults.
+#[derive(Debug, Clone)]
+pub struct AniListClientBuilder {
+    connect_timeout: Duration,
+    request_timeout: Duration,
+    user_agent: String,
+    http_client: Option<Client>,
+    cache_ttl: Duration,
+    max_cache_entries: usize,
+}
+
+impl Default for AniListClientBuilder {
+    fn default() -> Self {
+        Self {
+            connect_timeout: Duration::from_secs(DEFAULT_CONNECT_TIMEOUT_SECS),
+            request_timeout: Duration::from_secs(DEFAULT_REQUEST_TIMEOUT_SECS),
+            user_agent: format!("cinelink/{}", env!("CARGO_PKG_VERSION")),
+            http_client: None,
+            cache_ttl: Duration::from_secs(DEFAULT_CACHE_TTL_SECS),
+            max_cache_entries: DEFAULT_MAX_CACHE_ENTRIES,
+        }
+    }
+}
+
+impl AniListClientBuilder {
+    pub fn connect_timeout(mut self, timeout: Duration) -> Self {
+        self.connect_timeout = timeout;
+        self
+    }
+
+    /// Limit for a whole request, including reading the body.
+    pub fn request_timeout(mut self, timeout: Duration) -> Self {
+        self.request_timeout = timeout;
+        self
+    }
+
+    pub fn user_agent(mut self, user_agent: impl Into<String>) -> Self {
+        self.user_agent = user_agent.into();
+        self
+    }
+
+    /// Sends requests through `client`, sharing its connection pool; the timeouts and user
+    /// agent set here are then ignored.
+    pub fn http_client(mut self, client: Client) -> Self {
+        self.http_client = Some(client);
+        self
+    }
+
+    /// How long relations and titles are reused; `Duration::ZERO` disables both caches.
+    pub fn cache_ttl(mut self, ttl: Duration) -> Self {
+        self.cache_ttl = ttl;
+        self
+    }
+
+    pub fn max_cache_entries(mut self, max: usize) -> Self {
+        self.max_cache_entries = max;
+        self
+    }
+
+    pub fn build(self) -> Result<AniListClient> {
+        let client = match self.http_client {
+            Some(client) => client,
+            None => Client::builder()
+                .connect_timeout(self.connect_timeout)
+                .timeout(self.request_timeout)
+                .user_agent(self.user_agent)
+                .build()
+                .context("Failed to build AniList HTTP client")?,
+        };
+        Ok(AniListClient {
+            http: RetryingClient::new(client, Provider::AniList),
+            endpoint: DEFAULT_ANILIST_ENDPOINT.to_string(),
+            relations_cache: Arc::new(Mutex::new(HashMap::new())),
+            title_cache: Arc::new(Mutex::new(HashMap::new())),
+            cache_ttl: self.cache_ttl,
+            max_cache_entries: self.max_cache_entries,
+            relation_strategy: RelationStrategy::default(),
+        })
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -29,14 +121,14 @@ struct CacheEntry<T> {
     value: T,
 }
 
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum AniListMediaType {
     Anime,
     Manga,
 }
 
 impl AniListMediaType {
-    fn as_graphql(&self) -> &'static str {
+    pub(crate) fn as_graphql(&self) -> &'static str {
         match self {
             AniListMediaType::Anime => "ANIME",
             AniListMediaType::Manga => "MANGA",
@@ -46,18 +138,42 @@ impl AniListMediaType {
 
 impl AniListClient {
     pub fn new() -> Result<Self> {
-        let user_agent = format!("cinelink/{}", env!("CARGO_PKG_VERSION"));
-        let client = Client::builder()
-            .connect_timeout(Duration::from_secs(5))
-            .timeout(Duration::from_secs(30))
-            .user_agent(user_agent)
-            .build()
-            .context("Failed to build AniList HTTP client")?;
-        Ok(Self {
-            client,
-            relations_cache: Arc::new(Mutex::new(HashMap::new())),
-            title_cache: Arc::new(Mutex::new(HashMap::new())),
-        })
+        Self::builder().build()
+    }
+
+    pub fn builder() -> AniListClientBuilder {
+        AniListClientBuilder::default()
+    }
+
+    /// Points the client at another GraphQL endpoint (e.g. a local mock).
+    pub fn with_endpoint(mut self, endpoint: impl Into<String>) -> Self {
+        self.endpoint = endpoint.into();
+        self
+    }
+
+    /// Which relations count as the next season when resolving "Season N".
+    pub fn with_relation_strategy(mut self, strategy: RelationStrategy) -> Self {
+        self.relation_strategy = strategy;
+        self
+    }
+
+    pub fn relation_strategy(&self) -> RelationStrategy {
+        self.relation_strategy
+    }
+
+    pub async fn ping(&self) -> Result<()> {
+        let status = self
+            .http
+            .client()
+            .post(&self.endpoint)
+            .json(&json!({ "query": "{ __typename }" }))
+            .send()
+            .await?
+            .status();
+        if !status.is_success() {
+            return Err(anyhow!("{}", status.as_u16()));
+        }
+        Ok(())
     }
 
     pub async fn fetch_mapped(
@@ -138,7 +254,8 @@ query ($search: String!, $type: MediaType!) {
         });
 
         let res = self
-            .post_with_retry(|| self.client.post(ANILIST_ENDPOINT).json(&body))
+            .http
+            .send(|| self.http.client().post(&self.endpoint).json(&body))
             .await
             .context("AniList search request failed")?;
 
@@ -148,11 +265,15 @@ query ($search: String!, $type: MediaType!) {
             .await
             .context("Failed to read AniList search body")?;
         if !status.is_success() {
-            return Err(anyhow!(
-                "AniList search HTTP error (status {}): {}",
-                status,
-                String::from_utf8_lossy(&bytes)
-            ));
+            return Err(UpstreamStatus::new(
+                status.as_u16(),
+                format!(
+                    "AniList search HTTP error (status {}): {}",
+                    status,
+                    String::from_utf8_lossy(&bytes)
+                ),
+            )
+            .into());
         }
 
         let parsed: GraphQlResponse<Data> =
@@ -188,6 +309,93 @@ query ($search: String!, $type: MediaType!) {
             .collect())
     }
 
+    /// The AniList entry of a MyAnimeList id.
+    pub(crate) async fn find_by_mal_id(
+        &self,
+        media_type: AniListMediaType,
+        mal_id: i32,
+    ) -> Result<i32> {
+        #[derive(Deserialize)]
+        struct GraphQlResponse<T> {
+            data: Option<T>,
+            errors: Option<Vec<GraphQlError>>,
+        }
+
+        #[derive(Deserialize)]
+        struct GraphQlError {
+            message: String,
+            status: Option<i32>,
+        }
+
+        #[derive(Deserialize)]
+        struct Data {
+            #[serde(rename = "Media")]
+            media: Option<MediaId>,
+        }
+
+        #[derive(Deserialize)]
+        struct MediaId {
+            id: i32,
+        }
+
+        let query = r#"
+query ($idMal: Int!, $type: MediaType!) {
+  Media(idMal: $idMal, type: $type) { id }
+}
+"#;
+
+        let body = json!({
+            "query": query,
+            "variables": { "idMal": mal_id, "type": media_type.as_graphql() }
+        });
+
+        let res = self
+            .http
+            .send(|| self.http.client().post(&self.endpoint).json(&body))
+            .await
+            .context("AniList MAL id request failed")?;
+
+        let status = res.status();
+        let bytes = res
+            .bytes()
+            .await
+            .context("Failed to read AniList MAL id body")?;
+        if status == reqwest::StatusCode::NOT_FOUND {
+            return Err(anyhow!("No AniList entry for MAL id {}", mal_id));
+        }
+        if !status.is_success() {
+            return Err(UpstreamStatus::new(
+                status.as_u16(),
+                format!(
+                    "AniList MAL id HTTP error (status {}): {}",
+                    status,
+                    String::from_utf8_lossy(&bytes)
+                ),
+            )
+            .into());
+        }
+
+        let parsed: GraphQlResponse<Data> =
+            serde_json::from_slice(&bytes).context("Failed to parse AniList MAL id JSON")?;
+        if let Some(errors) = parsed.errors {
+            let msg = errors
+                .into_iter()
+                .map(|e| match e.status {
+                    Some(s) => format!("{} (status {})", e.message, s),
+                    None => e.message,
+                })
+                .collect::<Vec<_>>()
+                .join("; ");
+            return Err(anyhow!("AniList MAL id GraphQL error: {}", msg));
+        }
+
+        parsed
+            .data
+            .and_then(|d| d.media)
+            .map(|m| m.id)
+            .ok_or_else(|| anyhow!("No AniList entry for MAL id {}", mal_id))
+    }
+
     pub(crate) async fn fetch_relations(
         &self,
         media_type: AniListMediaType,
@@ -238,7 +446,8 @@ query ($id: Int!, $type: MediaType!) {
         });
 
         let res = self
-            .post_with_retry(|| self.client.post(ANILIST_ENDPOINT).json(&body))
+            .http
+            .send(|| self.http.client().post(&self.endpoint).json(&body))
             .await
             .context("AniList relations request failed")?;
 
@@ -248,11 +457,15 @@ query ($id: Int!, $type: MediaType!) {
             .await
             .context("Failed to read AniList relations body")?;
         if !status.is_success() {
-            return Err(anyhow!(
-                "AniList relations HTTP error (status {}): {}",
-                status,
-                String::from_utf8_lossy(&bytes)
-            ));
+            return Err(UpstreamStatus::new(
+                status.as_u16(),
+                format!(
+                    "AniList relations HTTP error (status {}): {}",
+                    status,
+                    String::from_utf8_lossy(&bytes)
+                ),
+            )
+            .into());
         }
 
         let parsed: GraphQlResponse<Data> =
@@ -312,12 +525,17 @@ query ($id: Int!, $type: MediaType!) {
     id
     idMal
     siteUrl
-    title { romaji english }
+    title { romaji english native }
     description(asHtml: false)
     format
     status
     episodes
     duration
+    chapters
+    volumes
+    meanScore
+    averageScore
+    popularity
     countryOfOrigin
     isAdult
     genres
@@ -337,18 +555,23 @@ query ($id: Int!, $type: MediaType!) {
         });
 
         let res = self
-            .post_with_retry(|| self.client.post(ANILIST_ENDPOINT).json(&body))
+            .http
+            .send(|| self.http.client().post(&self.endpoint).json(&body))
             .await
             .context("AniList request failed")?;
 
         let status = res.status();
         let bytes = res.bytes().await.context("Failed to read AniList body")?;
         if !status.is_success() {
-            return Err(anyhow!(
-                "AniList HTTP error (status {}): {}",
-                status,
-                String::from_utf8_lossy(&bytes)
-            ));
+            return Err(UpstreamStatus::new(
+                status.as_u16(),
+                format!(
+                    "AniList HTTP error (status {}): {}",
+                    status,
+                    String::from_utf8_lossy(&bytes)
+                ),
+            )
+            .into());
         }
 
         let parsed: GraphQlResponse<Data> =
@@ -415,7 +638,8 @@ query ($id: Int!, $type: MediaType!) {
         });
 
         let res = self
-            .post_with_retry(|| self.client.post(ANILIST_ENDPOINT).json(&body))
+            .http
+            .send(|| self.http.client().post(&self.endpoint).json(&body))
             .await
             .context("AniList titles request failed")?;
 
@@ -425,11 +649,15 @@ query ($id: Int!, $type: MediaType!) {
             .await
             .context("Failed to read AniList titles body")?;
         if !status.is_success() {
-            return Err(anyhow!(
-                "AniList titles HTTP error (status {}): {}",
-                status,
-                String::from_utf8_lossy(&bytes)
-            ));
+            return Err(UpstreamStatus::new(
+                status.as_u16(),
+                format!(
+                    "AniList titles HTTP error (status {}): {}",
+                    status,
+                    String::from_utf8_lossy(&bytes)
+                ),
+            )
+            .into());
         }
 
         let parsed: GraphQlResponse<Data> =
@@ -456,46 +684,17 @@ query ($id: Int!, $type: MediaType!) {
         Ok(title)
     }
 
-    async fn post_with_retry(
-        &self,
-        mut make_req: impl FnMut() -> reqwest::RequestBuilder,
-    ) -> Result<reqwest::Response> {
-        let mut attempt = 0usize;
-        loop {
-            attempt += 1;
-            let res = make_req().send().await;
-            match res {
-                Ok(resp) => {
-                    if (resp.status().as_u16() == 429 || resp.status().is_server_error())
-                        && attempt < MAX_RETRIES
-                    {
-                        let delay = retry_delay(attempt, resp.headers().get("retry-after"));
-                        tokio::time::sleep(delay).await;
-                        continue;
-                    }
-                    return Ok(resp);
-                }
-                Err(e) => {
-                    if attempt < MAX_RETRIES && (e.is_timeout() || e.is_connect() || e.is_request())
-                    {
-                        tokio::time::sleep(retry_delay(attempt, None)).await;
-                        continue;
-                    }
-                    return Err(e).context("HTTP request failed");
-                }
-            }
-        }
-    }
-
     async fn get_cached_relations(&self, id: i32) -> Option<RelationsPayload> {
         let mut guard = self.relations_cache.lock().await;
-        guard.retain(|_, v| v.inserted_at.elapsed().as_secs() < RELATIONS_CACHE_TTL_SECS);
-        guard.get(&id).map(|e| e.value.clone())
+        guard.retain(|_, v| v.inserted_at.elapsed() < self.cache_ttl);
+        let cached = guard.get(&id).map(|e| e.value.clone());
+        ANILIST_RELATIONS_CACHE.record(cached.is_some());
+        cached
     }
 
     async fn put_cached_relations(&self, id: i32, payload: RelationsPayload) {
         let mut guard = self.relations_cache.lock().await;
-        if guard.len() > MAX_CACHE_ENTRIES {
+        if guard.len() > self.max_cache_entries {
             guard.clear();
         }
         guard.insert(
@@ -509,13 +708,15 @@ query ($id: Int!, $type: MediaType!) {
 
     async fn get_cached_title(&self, id: i32) -> Option<MediaTitle> {
         let mut guard = self.title_cache.lock().await;
-        guard.retain(|_, v| v.inserted_at.elapsed().as_secs() < TITLE_CACHE_TTL_SECS);
-        guard.get(&id).map(|e| e.value.clone())
+        guard.retain(|_, v| v.inserted_at.elapsed() < self.cache_ttl);
+        let cached = guard.get(&id).map(|e| e.value.clone());
+        ANILIST_TITLE_CACHE.record(cached.is_some());
+        cached
     }
 
     async fn put_cached_title(&self, id: i32, title: MediaTitle) {
         let mut guard = self.title_cache.lock().await;
-        if guard.len() > MAX_CACHE_ENTRIES {
+        if guard.len() > self.max_cache_entries {
             guard.clear();
         }
         guard.insert(
@@ -546,6 +747,13 @@ pub(crate) struct Media {
     pub(crate) start_date: Option<FuzzyDate>,
     pub(crate) episodes: Option<i32>,
     pub(crate) duration: Option<i32>,
+    pub(crate) chapters: Option<i32>,
+    pub(crate) volumes: Option<i32>,
+    #[serde(rename = "meanScore")]
+    pub(crate) mean_score: Option<i32>,
+    #[serde(rename = "averageScore")]
+    pub(crate) average_score: Option<i32>,
+    pub(crate) popularity: Option<i32>,
     #[serde(rename = "coverImage")]
     pub(crate) cover_image: Option<CoverImage>,
     #[serde(rename = "bannerImage")]
@@ -585,6 +793,9 @@ pub(crate) struct RelationNode {
 pub(crate) struct MediaTitle {
     pub(crate) romaji: Option<String>,
     pub(crate) english: Option<String>,
+    /// Original-script title (e.g. `進撃の巨人`); only requested when fetching full media.
+    #[serde(default)]
+    pub(crate) native: Option<String>,
 }
 
 #[derive(Debug, Deserialize, Clone)]
@@ -656,42 +867,6 @@ pub(crate) struct RelationsPayload {
     pub(crate) edges: Vec<RelationEdge>,
 }
 
-fn retry_delay(attempt: usize, retry_after: Option<&reqwest::header::HeaderValue>) -> Duration {
-    if let Some(v) = retry_after.and_then(|h| h.to_str().ok()) {
-        if let Ok(secs) = v.parse::<u64>() {
-            return Duration::from_secs(secs.min(30));
-        }
-    }
-    let base_ms = 200u64.saturating_mul(2u64.saturating_pow((attempt - 1) as u32));
-    let jitter_ms = jitter_ms();
-    Duration::from_millis((base_ms + jitter_ms).min(5_000))
-}
-
-fn jitter_ms() -> u64 {
-    use std::time::{SystemTime, UNIX_EPOCH};
-    let now = SystemTime::now()
-        .duration_since(UNIX_EPOCH)
-        .unwrap_or_default();
-    (now.subsec_millis() as u64) % 100
-}
-
-fn normalize_title_key(input: &str) -> String {
-    let mut out = String::with_capacity(input.len());
-    let mut last_space = false;
-    for ch in input.chars() {
-        let ch = ch.to_ascii_lowercase();
-        let is_alnum = ch.is_ascii_alphanumeric();
-        if is_alnum {
-            out.push(ch);
-            last_space = false;
-        } else if !last_space {
-            out.push(' ');
-            last_space = true;
-        }
-    }
-    out.split_whitespace().collect::<Vec<_>>().join(" ")
-}
-
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -719,4 +894,61 @@ mod tests {
             .collect::<Vec<_>>();
         assert_eq!(cast, vec!["Char A".to_string(), "Char B".to_string()]);
     }
+
+    #[tokio::test]
+    async fn builder_timeout_applies_to_requests() {
+        let server = wiremock::MockServer::start().await;
+        wiremock::Mock::given(wiremock::matchers::method("POST"))
+            .respond_with(
+                wiremock::ResponseTemplate::new(200)
+                    .set_body_json(json!({ "data": { "__typename": "Query" } }))
+                    .set_delay(Duration::from_secs(2)),
+            )
+            .mount(&server)
+            .await;
+        let client = AniListClient::builder()
+            .request_timeout(Duration::from_millis(50))
+            .user_agent("cinelink-test")
+            .build()
+            .unwrap()
+            .with_endpoint(server.uri());
+        let err = client.ping().await.unwrap_err();
+        assert!(err
+            .downcast_ref::<reqwest::Error>()
+            .is_some_and(|e| e.is_timeout()));
+    }
+
+    #[tokio::test]
+    async fn builder_cache_ttl_controls_title_reuse() {
+        let server = wiremock::MockServer::start().await;
+        wiremock::Mock::given(wiremock::matchers::method("POST"))
+            .respond_with(wiremock::ResponseTemplate::new(200).set_body_json(json!({
+                "data": { "Media": { "title": { "romaji": "Romaji", "english": "English" } } }
+            })))
+            .mount(&server)
+            .await;
+        let requests = || async { server.received_requests().await.unwrap().len() };
+
+        let cached = AniListClient::new().unwrap().with_endpoint(server.uri());
+        for _ in 0..2 {
+            cached
+                .fetch_titles(AniListMediaType::Anime, 1)
+                .await
+                .unwrap();
+        }
+        assert_eq!(requests().await, 1);
+
+        let uncached = AniListClient::builder()
+            .cache_ttl(Duration::ZERO)
+            .build()
+            .unwrap()
+            .with_endpoint(server.uri());
+        for _ in 0..2 {
+            uncached
+                .fetch_titles(AniListMediaType::Anime, 1)
+                .await
+                .unwrap();
+        }
+        assert_eq!(requests().await, 3);
+    }
 }
@@ -1,3 +1,5 @@
+use crate::countries::country_name;
+use crate::languages;
 use anyhow::Result;
 use std::collections::HashSet;
 
@@ -12,7 +14,7 @@ impl AniListClient {
         media: Media,
     ) -> Result<AniListMapped> {
         let title = media.title.unwrap_or_default();
-        let (name, eng_name, original_title) = choose_titles(&title);
+        let (name, eng_name, original_title, native_title) = choose_titles(&title);
 
         let director = media
             .staff
@@ -36,7 +38,7 @@ impl AniListClient {
         let country_code = media.country_of_origin.clone();
         let country_of_origin = country_code
             .as_deref()
-            .map(|c| country_name_from_code(c).unwrap_or_else(|| c.to_string()));
+            .map(|c| country_name(c).unwrap_or(c).to_string());
         let language = country_code.as_deref().and_then(language_from_country);
         let is_adult = media.is_adult.unwrap_or(false);
         let content_rating = content_rating_from_is_adult(is_adult).to_string();
@@ -61,6 +63,7 @@ impl AniListClient {
             name,
             eng_name,
             original_title,
+            native_title,
             synopsis: media
                 .description
                 .as_deref()
@@ -77,6 +80,11 @@ impl AniListClient {
             year,
             runtime_minutes: media.duration.map(|d| d as f32),
             episodes: media.episodes,
+            chapters: media.chapters,
+            volumes: media.volumes,
+            mean_score: media.mean_score.map(f64::from),
+            average_score: media.average_score.map(f64::from),
+            popularity: media.popularity.map(f64::from),
             trailer,
             poster,
             backdrop: media.banner_image,
@@ -98,11 +106,12 @@ fn content_rating_from_is_adult(is_adult: bool) -> &'static str {
     }
 }
 
-fn choose_titles(title: &MediaTitle) -> (String, Option<String>, Option<String>) {
+fn choose_titles(title: &MediaTitle) -> (String, Option<String>, Option<String>, Option<String>) {
     // For anime, we avoid non-Latin scripts here:
     // - Prefer English as the "actual" title (Eng Name / main title)
     // - Use romaji as the Original Title
     // - Do not populate Eng Name (it matches the title)
+    // The original-script title is returned separately, for its own property.
     let english = title
         .english
         .as_ref()
@@ -119,8 +128,14 @@ fn choose_titles(title: &MediaTitle) -> (String, Option<String>, Option<String>)
 
     let eng_name = None;
     let original_title = romaji.map(strip_trailing_season_suffix);
-
-    (actual, eng_name, original_title)
+    let native_title = title
+        .native
+        .as_deref()
+        .map(str::trim)
+        .filter(|s| !s.is_empty())
+        .map(str::to_string);
+
+    (actual, eng_name, original_title, native_title)
 }
 
 pub(crate) fn strip_trailing_season_suffix(title: &str) -> String {
@@ -190,42 +205,27 @@ fn trailer_url(trailer: &Trailer) -> Option<String> {
     None
 }
 
+/// AniList only gives the country of origin; guess the language from it.
 fn language_from_country(country_code: &str) -> Option<String> {
-    let name = match country_code {
-        "JP" => "Japanese",
-        "KR" => "Korean",
-        "CN" | "TW" | "HK" => "Chinese",
-        "US" | "GB" | "AU" | "CA" | "NZ" | "IE" => "English",
-        "FR" | "BE" | "CH" => "French",
-        "ES" | "MX" | "AR" | "CL" | "CO" | "PE" => "Spanish",
-        "DE" | "AT" => "German",
-        "IT" => "Italian",
-        "PT" | "BR" => "Portuguese",
-        "RU" => "Russian",
-        _ => return None,
-    };
-    Some(name.to_string())
-}
-
-fn country_name_from_code(code: &str) -> Option<String> {
-    let name = match code {
-        "JP" => "Japan",
-        "KR" => "South Korea",
-        "CN" => "China",
-        "TW" => "Taiwan",
-        "HK" => "Hong Kong",
-        "US" => "United States",
-        "GB" => "United Kingdom",
-        "FR" => "France",
-        "ES" => "Spain",
-        "DE" => "Germany",
-        "IT" => "Italy",
-        "BR" => "Brazil",
-        "CA" => "Canada",
-        "AU" => "Australia",
+    let code = match country_code {
+        "JP" => "ja",
+        "KR" => "ko",
+        "CN" | "TW" | "HK" => "zh",
+        "TH" => "th",
+        "PH" => "tl",
+        "VN" => "vi",
+        "ID" => "id",
+        "IN" => "hi",
+        "US" | "GB" | "AU" | "CA" | "NZ" | "IE" => "en",
+        "FR" | "BE" | "CH" => "fr",
+        "ES" | "MX" | "AR" | "CL" | "CO" | "PE" => "es",
+        "DE" | "AT" => "de",
+        "IT" => "it",
+        "PT" | "BR" => "pt",
+        "RU" => "ru",
         _ => return None,
     };
-    Some(name.to_string())
+    languages::language_name(code).map(str::to_string)
 }
 
 fn dedupe_preserve_order(items: Vec<String>) -> Vec<String> {
@@ -264,11 +264,13 @@ mod tests {
         let t = MediaTitle {
             english: Some("English Season 2".to_string()),
             romaji: Some("Romaji Season 2".to_string()),
+            native: Some(" 進撃の巨人 ".to_string()),
         };
-        let (name, eng, original) = choose_titles(&t);
+        let (name, eng, original, native) = choose_titles(&t);
         assert_eq!(name, "English");
         assert_eq!(eng, None);
         assert_eq!(original.as_deref(), Some("Romaji"));
+        assert_eq!(native.as_deref(), Some("進撃の巨人"));
     }
 
     #[test]
@@ -306,6 +308,13 @@ mod tests {
         assert_eq!(content_rating_from_is_adult(true), "Adult");
     }
 
+    #[test]
+    fn language_comes_from_the_shared_table() {
+        assert_eq!(language_from_country("JP").as_deref(), Some("Japanese"));
+        assert_eq!(language_from_country("TH").as_deref(), Some("Thai"));
+        assert_eq!(language_from_country("ZZ"), None);
+    }
+
     #[test]
     fn dedupes_preserving_first_occurrence() {
         let input = vec![
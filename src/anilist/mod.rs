@@ -1,27 +1,41 @@
 use anyhow::Result;
 use async_trait::async_trait;
+use serde::Serialize;
 
 mod client;
 mod map;
 mod resolve;
 mod text;
 
-pub use client::{AniListClient, AniListMediaType};
+pub use client::{
+    AniListClient, AniListClientBuilder, AniListMediaType,
+    DEFAULT_CACHE_TTL_SECS as DEFAULT_ANILIST_CACHE_TTL_SECS,
+};
 pub(crate) use map::strip_trailing_season_suffix;
+pub(crate) use resolve::parse_anilist_url;
+pub use resolve::RelationStrategy;
 
 #[async_trait]
 pub trait AniListApi: Send + Sync {
     async fn resolve_anime_id(&self, query: &str, season: Option<i32>) -> Result<i32>;
     async fn fetch_anime(&self, id: i32) -> Result<AniListMapped>;
+    async fn resolve_manga_id(&self, query: &str, season: Option<i32>) -> Result<i32>;
+    async fn fetch_manga(&self, id: i32) -> Result<AniListMapped>;
+    /// Cheap call used by the readiness check.
+    async fn ping(&self) -> Result<()> {
+        Ok(())
+    }
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize)]
 pub struct AniListMapped {
     pub id: i32,
     pub id_mal: Option<i32>,
     pub name: String,
     pub eng_name: Option<String>,
     pub original_title: Option<String>,
+    /// Title in its original script (Japanese, Chinese, Korean, ...).
+    pub native_title: Option<String>,
     pub synopsis: Option<String>,
     pub genres: Vec<String>,
     pub cast: Vec<String>,
@@ -34,6 +48,15 @@ pub struct AniListMapped {
     pub year: Option<String>,
     pub runtime_minutes: Option<f32>,
     pub episodes: Option<i32>,
+    /// Manga only; `None` for anime or when AniList doesn't know yet (ongoing series).
+    pub chapters: Option<i32>,
+    pub volumes: Option<i32>,
+    /// AniList's mean score (0–100).
+    pub mean_score: Option<f64>,
+    /// AniList's weighted average score (0–100); `None` until enough users have rated it.
+    pub average_score: Option<f64>,
+    /// Number of AniList users who have the entry on their list.
+    pub popularity: Option<f64>,
     pub trailer: Option<String>,
     pub poster: Option<String>,
     pub backdrop: Option<String>,
@@ -43,11 +66,34 @@ pub struct AniListMapped {
 #[async_trait]
 impl AniListApi for AniListClient {
     async fn resolve_anime_id(&self, query: &str, season: Option<i32>) -> Result<i32> {
-        self.resolve_id_with_season(AniListMediaType::Anime, query, season)
-            .await
+        self.resolve_id_with_season(
+            AniListMediaType::Anime,
+            query,
+            season,
+            self.relation_strategy(),
+        )
+        .await
     }
 
     async fn fetch_anime(&self, id: i32) -> Result<AniListMapped> {
         self.fetch_mapped(AniListMediaType::Anime, id).await
     }
+
+    async fn resolve_manga_id(&self, query: &str, season: Option<i32>) -> Result<i32> {
+        self.resolve_id_with_season(
+            AniListMediaType::Manga,
+            query,
+            season,
+            self.relation_strategy(),
+        )
+        .await
+    }
+
+    async fn fetch_manga(&self, id: i32) -> Result<AniListMapped> {
+        self.fetch_mapped(AniListMediaType::Manga, id).await
+    }
+
+    async fn ping(&self) -> Result<()> {
+        AniListClient::ping(self).await
+    }
 }
@@ -1,11 +1,45 @@
+use crate::titles::{normalize_title_key, parse_bare_id, TitleId};
 use anyhow::{anyhow, Result};
 use std::collections::HashSet;
 
 use super::client::{AniListClient, AniListMediaType, RelationsPayload, SearchCandidate};
 
+/// Which relation edges count as "the next season" when walking a franchise forward.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum RelationStrategy {
+    /// Only `SEQUEL` edges.
+    #[default]
+    SequelOnly,
+    /// `SIDE_STORY` edges when an entry has no `SEQUEL` (bonus cours).
+    IncludeSideStory,
+    /// Also `ALTERNATIVE_VERSION` edges when there is neither (recut films).
+    IncludeAlternative,
+}
+
+impl RelationStrategy {
+    /// `sequel`, `side_story` or `alternative` (any case).
+    pub fn parse(raw: &str) -> Option<Self> {
+        match raw.trim().to_ascii_lowercase().as_str() {
+            "sequel" | "sequel_only" => Some(Self::SequelOnly),
+            "side_story" => Some(Self::IncludeSideStory),
+            "alternative" | "alternative_version" => Some(Self::IncludeAlternative),
+            _ => None,
+        }
+    }
+
+    /// Relation types to try in order; the first one with any edge wins.
+    fn relation_types(self) -> &'static [&'static str] {
+        match self {
+            Self::SequelOnly => &["SEQUEL"],
+            Self::IncludeSideStory => &["SEQUEL", "SIDE_STORY"],
+            Self::IncludeAlternative => &["SEQUEL", "SIDE_STORY", "ALTERNATIVE_VERSION"],
+        }
+    }
+}
+
 impl AniListClient {
     pub async fn resolve_id(&self, media_type: AniListMediaType, query: &str) -> Result<i32> {
-        if let Some(id) = parse_anilist_id(query) {
+        if let Some(id) = self.explicit_id(media_type, query).await? {
             return Ok(id);
         }
         self.search_id(media_type, query).await
@@ -16,13 +50,14 @@ impl AniListClient {
         media_type: AniListMediaType,
         query: &str,
         season: Option<i32>,
+        strategy: RelationStrategy,
     ) -> Result<i32> {
-        if let Some(id) = parse_anilist_id(query) {
+        if let Some(id) = self.explicit_id(media_type, query).await? {
             return Ok(id);
         }
         let candidate = self.pick_best_candidate(media_type, query).await?;
         let season = season.unwrap_or(1).max(1);
-        self.resolve_season_entry(media_type, candidate, season)
+        self.resolve_season_entry(media_type, candidate, season, strategy)
             .await
     }
 
@@ -31,12 +66,14 @@ impl AniListClient {
         media_type: AniListMediaType,
         candidate_id: i32,
         season: i32,
+        strategy: RelationStrategy,
     ) -> Result<i32> {
         let base = self.find_base_entry(media_type, candidate_id).await?;
         if season <= 1 {
             return Ok(base);
         }
-        self.follow_sequel_chain(media_type, base, season - 1).await
+        self.follow_sequel_chain(media_type, base, season - 1, strategy)
+            .await
     }
 
     async fn find_base_entry(&self, media_type: AniListMediaType, start_id: i32) -> Result<i32> {
@@ -60,6 +97,7 @@ impl AniListClient {
         media_type: AniListMediaType,
         start_id: i32,
         steps: i32,
+        strategy: RelationStrategy,
     ) -> Result<i32> {
         let mut current = start_id;
         let mut seen = HashSet::new();
@@ -68,7 +106,7 @@ impl AniListClient {
                 break;
             }
             let relations = self.fetch_relations(media_type, current).await?;
-            let sequel = pick_best_sequel_id(&relations)
+            let sequel = pick_best_sequel_id(&relations, strategy)
                 .ok_or_else(|| anyhow!("No AniList sequel found while resolving season"))?;
             current = sequel;
         }
@@ -103,6 +141,14 @@ impl AniListClient {
 
         best_id.ok_or_else(|| anyhow!("No AniList match found for '{}'", query))
     }
+
+    /// An explicit id (see `direct_id`), or the AniList entry of a `mal:5114` MyAnimeList id.
+    async fn explicit_id(&self, media_type: AniListMediaType, query: &str) -> Result<Option<i32>> {
+        match TitleId::parse(query) {
+            Some(TitleId::Mal(mal_id)) => self.find_by_mal_id(media_type, mal_id).await.map(Some),
+            _ => direct_id(media_type, query),
+        }
+    }
 }
 
 fn pick_relation_id(relations: &RelationsPayload, rel: &str) -> Option<i32> {
@@ -114,16 +160,21 @@ fn pick_relation_id(relations: &RelationsPayload, rel: &str) -> Option<i32> {
         .map(|n| n.id)
 }
 
-fn pick_best_sequel_id(relations: &RelationsPayload) -> Option<i32> {
-    let sequels: Vec<_> = relations
-        .edges
+/// The earliest continuation after the current entry. `SEQUEL` edges always win; weaker
+/// relations allowed by `strategy` are only considered when no stronger one exists.
+fn pick_best_sequel_id(relations: &RelationsPayload, strategy: RelationStrategy) -> Option<i32> {
+    let sequels: Vec<_> = strategy
+        .relation_types()
         .iter()
-        .filter(|e| e.relation_type.as_deref() == Some("SEQUEL"))
-        .filter_map(|e| e.node.as_ref())
-        .collect();
-    if sequels.is_empty() {
-        return None;
-    }
+        .map(|rel| {
+            relations
+                .edges
+                .iter()
+                .filter(|e| e.relation_type.as_deref() == Some(*rel))
+                .filter_map(|e| e.node.as_ref())
+                .collect::<Vec<_>>()
+        })
+        .find(|nodes| !nodes.is_empty())?;
 
     let current = relations.start_date.as_ref().and_then(date_key);
     let mut best_after: Option<(i32, i32)> = None; // (key, id)
@@ -173,21 +224,49 @@ fn parse_anilist_id(query: &str) -> Option<i32> {
     trimmed.parse::<i32>().ok().filter(|id| *id > 0)
 }
 
-fn normalize_title_key(input: &str) -> String {
-    let mut out = String::with_capacity(input.len());
-    let mut last_space = false;
-    for ch in input.chars() {
-        let ch = ch.to_ascii_lowercase();
-        let is_alnum = ch.is_ascii_alphanumeric();
-        if is_alnum {
-            out.push(ch);
-            last_space = false;
-        } else if !last_space {
-            out.push(' ');
-            last_space = true;
+/// An explicit id: `anilist:21`, a long bare number (see `parse_bare_id`) or an AniList media
+/// URL. A URL for the other media type, or a TMDB/IMDb id, is an error.
+fn direct_id(media_type: AniListMediaType, query: &str) -> Result<Option<i32>> {
+    match TitleId::parse(query) {
+        Some(TitleId::AniList(id)) => return Ok(Some(id)),
+        Some(TitleId::Tmdb(_) | TitleId::Imdb(_)) => {
+            return Err(anyhow!("'{}' is not an AniList id", query.trim()))
         }
+        Some(TitleId::Mal(_)) | None => {}
+    }
+    if let Some(id) = parse_bare_id(query) {
+        return Ok(Some(id));
+    }
+    match parse_anilist_url(query) {
+        Some((url_type, id)) if url_type == media_type => Ok(Some(id)),
+        Some((url_type, _)) => Err(anyhow!(
+            "AniList URL points to {} but {} was requested",
+            url_type.as_graphql().to_ascii_lowercase(),
+            media_type.as_graphql().to_ascii_lowercase()
+        )),
+        None => Ok(None),
     }
-    out.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+/// Parses `https://anilist.co/anime/176496[/slug]` (scheme and `www.` optional).
+pub(crate) fn parse_anilist_url(query: &str) -> Option<(AniListMediaType, i32)> {
+    let trimmed = query.trim();
+    let rest = trimmed
+        .strip_prefix("https://")
+        .or_else(|| trimmed.strip_prefix("http://"))
+        .unwrap_or(trimmed);
+    let mut parts = rest.split('/');
+    let host = parts.next()?.to_ascii_lowercase();
+    if host != "anilist.co" && host != "www.anilist.co" {
+        return None;
+    }
+    let media_type = match parts.next()?.to_ascii_lowercase().as_str() {
+        "anime" => AniListMediaType::Anime,
+        "manga" => AniListMediaType::Manga,
+        _ => return None,
+    };
+    let id = parts.next()?.split(['?', '#']).next()?;
+    parse_anilist_id(id).map(|id| (media_type, id))
 }
 
 fn score_candidate_title(query_key: &str, c: &SearchCandidate) -> i32 {
@@ -212,6 +291,42 @@ fn score_title(query_key: &str, english: Option<&str>, romaji: Option<&str>) ->
 mod tests {
     use super::*;
 
+    #[tokio::test]
+    async fn explicit_ids_skip_the_title_search() {
+        use serde_json::json;
+        use wiremock::matchers::{body_partial_json, method};
+        use wiremock::{Mock, MockServer, ResponseTemplate};
+
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(body_partial_json(json!({ "variables": { "idMal": 5114 } })))
+            .respond_with(
+                ResponseTemplate::new(200)
+                    .set_body_json(json!({ "data": { "Media": { "id": 5114 } } })),
+            )
+            .mount(&server)
+            .await;
+        Mock::given(method("POST"))
+            .and(body_partial_json(json!({ "variables": { "idMal": 1 } })))
+            .respond_with(ResponseTemplate::new(404).set_body_json(json!({
+                "data": { "Media": null },
+                "errors": [{ "message": "Not Found.", "status": 404 }]
+            })))
+            .mount(&server)
+            .await;
+        let client = AniListClient::new().unwrap().with_endpoint(server.uri());
+        let anime = AniListMediaType::Anime;
+
+        assert_eq!(client.resolve_id(anime, "anilist:21").await.unwrap(), 21);
+        assert_eq!(client.resolve_id(anime, "176496").await.unwrap(), 176496);
+        assert!(server.received_requests().await.unwrap().is_empty());
+
+        assert_eq!(client.resolve_id(anime, "MAL:5114").await.unwrap(), 5114);
+        let missing = client.resolve_id(anime, "mal:1").await.unwrap_err();
+        assert_eq!(missing.to_string(), "No AniList entry for MAL id 1");
+        assert!(client.resolve_id(anime, "tmdb:603").await.is_err());
+    }
+
     #[test]
     fn parses_anilist_id_only_for_digits() {
         assert_eq!(parse_anilist_id("176496"), Some(176496));
@@ -221,6 +336,54 @@ mod tests {
         assert_eq!(parse_anilist_id(""), None);
     }
 
+    #[test]
+    fn parses_anilist_anime_and_manga_urls() {
+        assert!(matches!(
+            parse_anilist_url("https://anilist.co/anime/176496"),
+            Some((AniListMediaType::Anime, 176496))
+        ));
+        assert!(matches!(
+            parse_anilist_url("https://anilist.co/manga/30009/"),
+            Some((AniListMediaType::Manga, 30009))
+        ));
+        assert!(matches!(
+            parse_anilist_url("anilist.co/anime/21/ONE-PIECE"),
+            Some((AniListMediaType::Anime, 21))
+        ));
+        assert!(matches!(
+            parse_anilist_url("https://www.anilist.co/anime/21?ref=x"),
+            Some((AniListMediaType::Anime, 21))
+        ));
+    }
+
+    #[test]
+    fn partial_or_foreign_urls_fall_back_to_search() {
+        assert!(parse_anilist_url("https://anilist.co/anime/").is_none());
+        assert!(parse_anilist_url("https://anilist.co/anime").is_none());
+        assert!(parse_anilist_url("https://anilist.co/character/123").is_none());
+        assert!(parse_anilist_url("https://myanimelist.net/anime/21").is_none());
+        assert!(parse_anilist_url("Frieren").is_none());
+        assert_eq!(
+            direct_id(AniListMediaType::Anime, "https://myanimelist.net/anime/21").unwrap(),
+            None
+        );
+    }
+
+    #[test]
+    fn url_media_type_must_match_request() {
+        assert_eq!(
+            direct_id(AniListMediaType::Manga, "https://anilist.co/manga/30009").unwrap(),
+            Some(30009)
+        );
+        assert!(direct_id(AniListMediaType::Anime, "https://anilist.co/manga/30009").is_err());
+        assert_eq!(
+            direct_id(AniListMediaType::Anime, "176496").unwrap(),
+            Some(176496)
+        );
+        // "86" is a title, not an id.
+        assert_eq!(direct_id(AniListMediaType::Anime, "86").unwrap(), None);
+    }
+
     #[test]
     fn picks_best_sequel_by_start_date() {
         let relations = RelationsPayload {
@@ -254,6 +417,63 @@ mod tests {
                 },
             ],
         };
-        assert_eq!(pick_best_sequel_id(&relations), Some(3));
+        assert_eq!(
+            pick_best_sequel_id(&relations, RelationStrategy::SequelOnly),
+            Some(3)
+        );
+    }
+
+    fn edge(relation: &str, id: i32, year: i32) -> super::super::client::RelationEdge {
+        super::super::client::RelationEdge {
+            relation_type: Some(relation.to_string()),
+            node: Some(super::super::client::RelationNode {
+                id,
+                start_date: Some(super::super::client::FuzzyDate {
+                    year: Some(year),
+                    month: Some(1),
+                    day: Some(1),
+                }),
+            }),
+        }
+    }
+
+    #[test]
+    fn side_stories_continue_the_chain_only_when_allowed_and_no_sequel_exists() {
+        let relations = RelationsPayload {
+            start_date: None,
+            edges: vec![
+                edge("SIDE_STORY", 4, 2021),
+                edge("ALTERNATIVE_VERSION", 5, 2020),
+            ],
+        };
+        assert_eq!(
+            pick_best_sequel_id(&relations, RelationStrategy::SequelOnly),
+            None
+        );
+        assert_eq!(
+            pick_best_sequel_id(&relations, RelationStrategy::IncludeSideStory),
+            Some(4)
+        );
+        let recut = RelationsPayload {
+            start_date: None,
+            edges: vec![edge("ALTERNATIVE_VERSION", 5, 2020)],
+        };
+        assert_eq!(
+            pick_best_sequel_id(&recut, RelationStrategy::IncludeSideStory),
+            None
+        );
+        assert_eq!(
+            pick_best_sequel_id(&recut, RelationStrategy::IncludeAlternative),
+            Some(5)
+        );
+
+        let both = RelationsPayload {
+            start_date: None,
+            edges: vec![edge("SIDE_STORY", 6, 2021), edge("SEQUEL", 7, 2021)],
+        };
+        assert_eq!(
+            pick_best_sequel_id(&both, RelationStrategy::IncludeAlternative),
+            Some(7)
+        );
     }
 }
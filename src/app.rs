@@ -1,35 +1,97 @@
-use crate::anilist::{AniListApi, AniListClient};
+use crate::anilist::{self, AniListApi, AniListClient, AniListMediaType};
+use crate::backfill::{
+    self, run_backfill, BackfillKind, BackfillOptions, BackfillProgress, BackfillTarget,
+};
+use crate::budget::{self, BudgetExceeded, RequestBudget};
+use crate::config::{AppConfig, OverwriteMode};
+use crate::dedupe_store::DedupeStore;
+use crate::duplicates::{duplicate_comment, DuplicateIndex, MediaKey, MediaKind};
+use crate::errors::{classify, FailureKind};
+use crate::failures::FailureLog;
+use crate::genres::normalize_genres;
+use crate::metrics::Metrics;
+use crate::negative_cache::NegativeCache;
+use crate::notify::ErrorNotifier;
 use crate::notion::{self, NotionApi, NotionClient};
 use crate::notion_fallback::fallback_schema;
+use crate::queue::JobQueue;
+use crate::rate_limit::{self, RateLimit, TokenBucket};
+use crate::retry::{RetryQueue, DEFAULT_RETRY_BASE_DELAY};
+use crate::titles::{parse_media_url, MediaUrl, TitleId};
 use crate::tmdb::{self, TmdbApi, TmdbClient};
+use crate::triggers::{Trigger, TriggerConfig};
+use crate::webhook::{self, EventKind, WebhookEvent};
 use anyhow::Result;
 use axum::{
     body::Bytes,
     extract::DefaultBodyLimit,
-    extract::State,
-    http::{header, HeaderMap, StatusCode},
+    extract::{ConnectInfo, Request, State},
+    http::{header, HeaderMap, HeaderValue, StatusCode},
+    middleware::{self, Next},
+    response::{IntoResponse, Response},
     routing::{get, post},
-    Router,
+    Extension, Json, Router,
 };
 use chrono::Utc;
 use constant_time_eq::constant_time_eq;
 use hmac::{Hmac, Mac};
 use serde_json::json;
 use sha2::Sha256;
-use std::{collections::HashMap, env, net::SocketAddr, sync::Arc};
+use std::{borrow::Cow, collections::HashMap, env, net::SocketAddr, path::PathBuf, sync::Arc};
 use tokio::sync::Mutex;
-use tokio::sync::Semaphore;
+use tokio::sync::{watch, Semaphore};
+use tokio_util::task::task_tracker::TaskTrackerToken;
+use tokio_util::task::TaskTracker;
 use tracing::{debug, error, info, warn};
 
-const MAX_BODY_BYTES: usize = 1024 * 1024; // 1MB safety cap
-const PER_IP_LIMIT: u32 = 60; // per minute
-const PER_IP_BURST: u32 = 10;
-const GLOBAL_LIMIT: u32 = 200; // per minute
-const GLOBAL_BURST: u32 = 20;
-const DEDUPE_TTL_SECS: i64 = 600; // 10 minutes
-const MAX_CONCURRENT_JOBS: usize = 8;
 const MAX_RATE_LIMIT_ENTRIES: usize = 10_000;
 const MAX_DEDUPE_ENTRIES: usize = 10_000;
+const READINESS_CACHE_SECS: u64 = 30;
+const READINESS_CHECK_TIMEOUT_SECS: u64 = 5;
+const DEEP_HEALTH_TIMEOUT_SECS: u64 = 3;
+const SHAPE_WARNINGS_PER_MINUTE: u32 = 5;
+const JOB_QUEUE_REPORT_INTERVAL: std::time::Duration = std::time::Duration::from_secs(60);
+/// `Retry-After` when the job queue is full.
+const QUEUE_FULL_RETRY_AFTER_SECS: u64 = 10;
+/// Optional header carrying the unix time a webhook signature was made at.
+const SIGNATURE_TIMESTAMP_HEADER: &str = "x-notion-signature-timestamp";
+/// How far a signed timestamp may be from the server clock.
+const SIGNATURE_MAX_SKEW_SECS: u64 = 300;
+/// Events for a page whose job started less than this long ago are dropped.
+const COALESCE_WINDOW: std::time::Duration = std::time::Duration::from_secs(2);
+const MAX_CAPTURE_NAME_LEN: usize = 128;
+/// Top-level keys Notion currently sends on webhook events.
+const KNOWN_EVENT_KEYS: &[&str] = &[
+    "id",
+    "timestamp",
+    "workspace_id",
+    "workspace_name",
+    "subscription_id",
+    "integration_id",
+    "type",
+    "authors",
+    "accessible_by",
+    "attempt_number",
+    "api_version",
+    "entity",
+    "data",
+];
+const REQUIRED_EVENT_KEYS: &[&str] = &["id", "type", "entity"];
+const DEFAULT_BIND_IP: [u8; 4] = [0, 0, 0, 0];
+const DEFAULT_PORT: u16 = 3146;
+/// `NOTION_WEBHOOK_SECRET` value that turns signature verification off, for deployments
+/// behind a relay that has already verified Notion's signatures.
+pub const INSECURE_DISABLED_SECRET: &str = "insecure-disabled";
+/// Shortest webhook secret accepted at startup (see `validate_signing_secret`).
+pub const MIN_SIGNING_SECRET_BYTES: usize = 32;
+/// Set to `skipped` on webhook responses while signature verification is disabled.
+pub const SIGNATURE_SKIPPED_HEADER: &str = "x-cinelink-signature-verification";
+/// Per-IP webhook requests allowed per minute (limit plus burst).
+pub const RATE_LIMIT_LIMIT_HEADER: &str = "x-ratelimit-limit";
+/// Per-IP webhook requests left in the current minute.
+pub const RATE_LIMIT_REMAINING_HEADER: &str = "x-ratelimit-remaining";
+/// Request header that makes a signed webhook wait for its page job and return the outcome.
+pub const WAIT_HEADER: &str = "x-cinelink-wait";
 
 #[derive(Clone)]
 pub struct AppState {
@@ -37,27 +99,176 @@ pub struct AppState {
     pub tmdb: Arc<dyn TmdbApi>,
     pub anilist: Arc<dyn AniListApi>,
     pub title_property: String,
-    pub schema: Arc<notion::PropertySchema>,
+    pub triggers: Arc<TriggerConfig>,
+    pub config: Arc<AppConfig>,
+    /// Reloaded on database schema events and `POST /admin/reload-schema`.
+    pub schema: Arc<notion::SharedSchema>,
     pub signing_secret: String,
-    pub rate_limits: Arc<Mutex<HashMap<String, WindowCounter>>>,
-    pub global_limit: Arc<Mutex<WindowCounter>>,
+    /// Also accepted while the webhook secret is rotated (`NOTION_WEBHOOK_SECRET_SECONDARY`).
+    pub secondary_signing_secret: Option<String>,
+    pub rate_limits: Arc<Mutex<HashMap<String, TokenBucket>>>,
+    pub global_limit: Arc<Mutex<TokenBucket>>,
+    /// Throttles "unknown payload shape" warnings so a format change can't flood the logs.
+    pub shape_warning_limit: Arc<Mutex<TokenBucket>>,
     pub recent_events: Arc<Mutex<HashMap<String, i64>>>,
+    /// Persists `recent_events` across restarts when `CINELINK_DEDUP_STORE` is set.
+    pub dedupe_store: Option<Arc<DedupeStore>>,
     pub processing_sem: Arc<Semaphore>,
+    pub duplicates: Arc<DuplicateIndex>,
+    /// Titles that recently matched nothing, skipped until `negative_cache_ttl_secs` passes.
+    pub negative_lookups: Arc<NegativeCache>,
+    pub metrics: Arc<Metrics>,
+    /// Last `/health/ready` result, reused for `READINESS_CACHE_SECS`.
+    pub readiness: Arc<Mutex<Option<ReadinessSnapshot>>>,
+    /// Bearer token for `/admin/*`; admin routes answer 404 when unset.
+    pub admin_key: Option<String>,
+    /// Directory of saved webhook payloads that `/admin/replay` may read by file name.
+    pub capture_dir: Option<PathBuf>,
+    pub failures: Arc<FailureLog>,
+    /// Re-runs jobs that failed transiently; drained by `spawn_retry_worker`.
+    pub retry: Arc<RetryQueue>,
+    /// Last subscription `verification_token` Notion sent, shown on `GET /verification`.
+    pub verification_token: Arc<Mutex<Option<String>>>,
+    /// Progress of the `POST /admin/backfill` run; at most one runs at a time.
+    pub backfill: Arc<BackfillProgress>,
+    /// Alerted about pages that failed or matched nothing (`CINELINK_ERROR_WEBHOOK_URL`).
+    pub error_notifier: Option<Arc<ErrorNotifier>>,
+    /// Pages a webhook job is processing right now, removed when it finishes. Later events
+    /// for the same page are dropped or wait for it instead of running alongside it.
+    pub in_flight_pages: Arc<std::sync::Mutex<HashMap<String, InFlightJob>>>,
+    /// Page jobs started by webhooks and retries. Closed when shutdown begins: webhooks are
+    /// then refused, and shutdown waits `shutdown_grace_secs` for the jobs still running.
+    pub jobs: TaskTracker,
+    /// Webhook page jobs waiting for one of the workers started by `spawn_job_workers`.
+    pub job_queue: Arc<JobQueue<PageJob>>,
+}
+
+/// What a page job did, and the page title it left behind.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PageOutcome {
+    pub updated: bool,
+    pub title: String,
+    /// The TMDB/AniList entry the page was matched to, when it was updated.
+    pub media: Option<MediaKey>,
+    /// Why nothing matched: the message added to the title, or why a backfill left it alone.
+    pub no_match: Option<String>,
+}
+
+impl PageOutcome {
+    fn updated(title: impl Into<String>, media: MediaKey) -> Self {
+        Self {
+            updated: true,
+            title: title.into(),
+            media: Some(media),
+            no_match: None,
+        }
+    }
+
+    fn skipped(title: impl Into<String>) -> Self {
+        Self {
+            updated: false,
+            title: title.into(),
+            media: None,
+            no_match: None,
+        }
+    }
+
+    /// `{"updated", "title", "provider", "id"}`; the last two are null when nothing matched.
+    fn to_json(&self) -> serde_json::Value {
+        json!({
+            "updated": self.updated,
+            "title": self.title,
+            "provider": self.media.as_ref().map(|m| m.kind.provider()),
+            "id": self.media.as_ref().map(|m| m.id),
+        })
+    }
+}
+
+/// Which provider a `POST /enrich` request should use.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum EnrichSource {
+    Tmdb,
+    AniList,
+    /// The title's trigger suffix if it has one, TMDB otherwise.
+    Auto,
+}
+
+impl EnrichSource {
+    fn parse(raw: &str) -> Option<Self> {
+        match raw.trim().to_ascii_lowercase().as_str() {
+            "tmdb" => Some(Self::Tmdb),
+            "anilist" => Some(Self::AniList),
+            "auto" => Some(Self::Auto),
+            _ => None,
+        }
+    }
+}
+
+/// How a page job decides whether to run and which provider to use.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum PageMode {
+    /// Webhooks: only titles ending with a trigger suffix.
+    Trigger,
+    /// Backfill: titles without the TMDB trigger, on pages of `kind` (`Tv`, `Movie` or `Anime`).
+    Backfill {
+        kind: BackfillKind,
+        /// Skip pages that already look enriched (`BackfillOptions::only_incomplete`).
+        only_incomplete: bool,
+    },
+    /// `POST /enrich`: any non-empty title; a trailing trigger suffix is stripped.
+    Manual(EnrichSource),
+}
+
+#[derive(Debug, Clone)]
+pub struct ReadinessSnapshot {
+    checked_at: std::time::Instant,
+    ready: bool,
+    body: serde_json::Value,
 }
 
-#[derive(Clone, Debug)]
-pub struct WindowCounter {
-    pub window: u64,
-    pub count: u32,
+/// Which limiter rejected a request.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum RateLimitScope {
+    PerIp,
+    Global,
+}
+
+impl RateLimitScope {
+    fn as_str(&self) -> &'static str {
+        match self {
+            RateLimitScope::PerIp => "per_ip",
+            RateLimitScope::Global => "global",
+        }
+    }
 }
 
 pub async fn run_server() -> Result<()> {
-    let notion: Arc<dyn NotionApi> = Arc::new(NotionClient::from_env()?);
+    let addr = parse_listen_addr(
+        env::var("CINELINK_BIND_ADDR").ok().as_deref(),
+        env::var("CINELINK_PORT").ok().as_deref(),
+    )?;
+    let triggers = Arc::new(TriggerConfig::from_env()?);
+    let config = Arc::new(AppConfig::from_env()?);
+    info!("Per-page request budget: {}", config.request_budget);
+    if config.dry_run {
+        warn!("CINELINK_DRY_RUN is set: Notion updates will be logged, not sent");
+    }
+    // One connection pool for every upstream API.
+    let http_client = crate::http::default_client()?;
+    let notion: Arc<dyn NotionApi> = Arc::new(
+        NotionClient::from_env()?
+            .with_http_client(http_client.clone())
+            .with_dry_run(config.dry_run)
+            .with_circuit_breaker(
+                config.notion_circuit_threshold,
+                std::time::Duration::from_secs(config.notion_circuit_cooldown_secs),
+            ),
+    );
     let schema = match notion.fetch_property_schema().await {
-        Ok(s) => Arc::new(s),
+        Ok(s) => s,
         Err(e) => {
             tracing::warn!("Failed to fetch Notion schema, using fallback: {}", e);
-            Arc::new(fallback_schema())
+            fallback_schema()
         }
     };
     let title_property = schema
@@ -65,262 +276,1790 @@ pub async fn run_server() -> Result<()> {
         .clone()
         .unwrap_or_else(|| "Name".to_string());
     info!("Using title property: {}", title_property);
-
-    let tmdb: Arc<dyn TmdbApi> = Arc::new(TmdbClient::from_env()?);
-    let anilist: Arc<dyn AniListApi> = Arc::new(AniListClient::new()?);
+    let schema = Arc::new(notion::SharedSchema::new(schema));
+
+    let tmdb: Arc<dyn TmdbApi> =
+        Arc::new(TmdbClient::from_env()?.with_http_client(http_client.clone()));
+    let anilist: Arc<dyn AniListApi> = Arc::new(
+        AniListClient::builder()
+            .http_client(http_client)
+            .cache_ttl(std::time::Duration::from_secs(
+                config.anilist_cache_ttl_secs,
+            ))
+            .build()?
+            .with_relation_strategy(config.anilist_relations),
+    );
     let signing_secret = env::var("NOTION_WEBHOOK_SECRET")
         .ok()
         .filter(|s| !s.is_empty())
-        .ok_or_else(|| anyhow::anyhow!("NOTION_WEBHOOK_SECRET must be set"))?;
-    info!("Webhook signature will use NOTION_WEBHOOK_SECRET");
+        .unwrap_or_default();
+    if signing_secret == INSECURE_DISABLED_SECRET {
+        warn!("==================================================================");
+        warn!("NOTION_WEBHOOK_SECRET=insecure-disabled: webhook signatures are NOT");
+        warn!("verified. Anyone who can reach this server can trigger page updates.");
+        warn!("Only use this behind a relay that verifies Notion's signatures.");
+        warn!("==================================================================");
+    } else if signing_secret.is_empty() {
+        warn!(
+            "NOTION_WEBHOOK_SECRET not set: only the subscription verification request will be \
+             accepted; set it to the logged verification_token and restart"
+        );
+    } else {
+        info!("Webhook signature will use NOTION_WEBHOOK_SECRET");
+    }
+    let secondary_signing_secret = env::var("NOTION_WEBHOOK_SECRET_SECONDARY")
+        .ok()
+        .filter(|s| !s.is_empty());
+    if secondary_signing_secret.is_some() && signing_secret != INSECURE_DISABLED_SECRET {
+        info!("Webhook signature will also accept NOTION_WEBHOOK_SECRET_SECONDARY");
+    }
+    // `ADMIN_API_KEY` is accepted as an alternative name.
+    let admin_key = ["CINELINK_ADMIN_KEY", "ADMIN_API_KEY"]
+        .iter()
+        .find_map(|key| env::var(key).ok().filter(|s| !s.trim().is_empty()));
+    if admin_key.is_none() {
+        info!("CINELINK_ADMIN_KEY not set; admin endpoints are disabled");
+    }
+    let error_notifier = ErrorNotifier::from_env()?.map(Arc::new);
+    if error_notifier.is_some() {
+        info!("Failed enrichments will be reported to CINELINK_ERROR_WEBHOOK_URL");
+    }
+    let capture_dir = env::var("CINELINK_CAPTURE_DIR")
+        .ok()
+        .filter(|s| !s.trim().is_empty())
+        .map(PathBuf::from);
 
     let rate_limits = Arc::new(Mutex::new(HashMap::new()));
-    let global_limit = Arc::new(Mutex::new(WindowCounter {
-        window: 0,
-        count: 0,
-    }));
-    let recent_events = Arc::new(Mutex::new(HashMap::new()));
-    let processing_sem = Arc::new(Semaphore::new(MAX_CONCURRENT_JOBS));
+    let global_limit = Arc::new(Mutex::new(TokenBucket::default()));
+    let shape_warning_limit = Arc::new(Mutex::new(TokenBucket::default()));
+    let (dedupe_store, recent_events) = match env::var("CINELINK_DEDUP_STORE")
+        .ok()
+        .filter(|s| !s.trim().is_empty())
+    {
+        Some(path) => {
+            let (store, events) =
+                DedupeStore::open(path, config.dedupe_ttl_secs, Utc::now().timestamp());
+            (Some(Arc::new(store)), events)
+        }
+        None => (None, HashMap::new()),
+    };
+    let recent_events = Arc::new(Mutex::new(recent_events));
+    let processing_sem = Arc::new(Semaphore::new(config.max_concurrent_jobs));
+    let duplicates = Arc::new(DuplicateIndex::new());
+    let metrics = Arc::new(Metrics::new());
+    let readiness = Arc::new(Mutex::new(None));
+    let failures = Arc::new(FailureLog::new());
+    let negative_lookups = Arc::new(NegativeCache::new(std::time::Duration::from_secs(
+        config.negative_cache_ttl_secs,
+    )));
+    let retry = Arc::new(RetryQueue::new(
+        config.retry_max_attempts,
+        DEFAULT_RETRY_BASE_DELAY,
+        metrics.clone(),
+    ));
+
+    let job_queue = Arc::new(JobQueue::new(config.job_queue_capacity));
 
     let state = AppState {
         notion,
         tmdb,
         anilist,
         title_property,
+        triggers,
+        config,
         schema,
         signing_secret,
+        secondary_signing_secret,
         rate_limits,
         global_limit,
+        shape_warning_limit,
         recent_events,
+        dedupe_store,
         processing_sem,
+        duplicates,
+        negative_lookups,
+        metrics,
+        readiness,
+        admin_key,
+        capture_dir,
+        failures,
+        retry,
+        verification_token: Arc::new(Mutex::new(None)),
+        backfill: Arc::new(BackfillProgress::default()),
+        error_notifier,
+        in_flight_pages: Arc::new(std::sync::Mutex::new(HashMap::new())),
+        jobs: TaskTracker::new(),
+        job_queue,
     };
 
+    spawn_retry_worker(&state);
+    spawn_job_workers(&state);
+    let jobs = state.jobs.clone();
+    let dedupe_store = state.dedupe_store.clone();
+    let grace = std::time::Duration::from_secs(state.config.shutdown_grace_secs);
     let app = build_router(state);
 
-    let addr = SocketAddr::from(([0, 0, 0, 0], 3146));
     info!("Listening on {}", addr);
     let listener = tokio::net::TcpListener::bind(addr).await?;
-    axum::serve(listener, app)
-        .with_graceful_shutdown(shutdown_signal())
-        .await?;
+    let closing = jobs.clone();
+    axum::serve(
+        listener,
+        app.into_make_service_with_connect_info::<SocketAddr>(),
+    )
+    .with_graceful_shutdown(async move {
+        shutdown_signal().await;
+        closing.close();
+    })
+    .await?;
+    drain_jobs(&jobs, grace).await;
+    if let Some(store) = dedupe_store {
+        store.flush().await;
+    }
     Ok(())
 }
 
-pub fn build_router(state: AppState) -> Router {
-    Router::new()
-        .route("/", post(handle_webhook))
-        .route("/health", get(health))
-        .layer(DefaultBodyLimit::max(MAX_BODY_BYTES))
-        .with_state(state)
+/// Waits up to `grace` for the page jobs still running after the server stopped. Returns how
+/// many finished and how many were abandoned.
+pub async fn drain_jobs(jobs: &TaskTracker, grace: std::time::Duration) -> (usize, usize) {
+    jobs.close();
+    let running = jobs.len();
+    if running == 0 {
+        return (0, 0);
+    }
+    info!(
+        "Waiting up to {}s for {} running page jobs",
+        grace.as_secs(),
+        running
+    );
+    let _ = tokio::time::timeout(grace, jobs.wait()).await;
+    let abandoned = jobs.len().min(running);
+    let drained = running - abandoned;
+    if abandoned == 0 {
+        info!("Drained {} page jobs", drained);
+    } else {
+        warn!(
+            "Shutdown grace period over: {} page jobs drained, {} abandoned",
+            drained, abandoned
+        );
+    }
+    (drained, abandoned)
+}
+
+/// Resolves the listen address from `CINELINK_BIND_ADDR` / `CINELINK_PORT`.
+///
+/// `CINELINK_BIND_ADDR` accepts `ip:port`, a bare `ip`, or a bare port; `CINELINK_PORT`
+/// (when set) overrides whichever port that yields. Unset or empty values keep the defaults.
+pub fn parse_listen_addr(bind: Option<&str>, port: Option<&str>) -> Result<SocketAddr> {
+    let mut addr = SocketAddr::from((DEFAULT_BIND_IP, DEFAULT_PORT));
+
+    if let Some(bind) = bind.map(str::trim).filter(|s| !s.is_empty()) {
+        if let Ok(full) = bind.parse::<SocketAddr>() {
+            addr = full;
+        } else if let Ok(ip) = bind.parse::<std::net::IpAddr>() {
+            addr.set_ip(ip);
+        } else if let Ok(p) = bind.parse::<u16>() {
+            addr.set_port(p);
+        } else {
+            anyhow::bail!(
+                "Invalid CINELINK_BIND_ADDR {:?}: expected ip:port, ip, or port",
+                bind
+            );
+        }
+    }
+
+    if let Some(port) = port.map(str::trim).filter(|s| !s.is_empty()) {
+        let p = port
+            .parse::<u16>()
+            .map_err(|_| anyhow::anyhow!("Invalid CINELINK_PORT {:?}: expected 0-65535", port))?;
+        addr.set_port(p);
+    }
+
+    Ok(addr)
+}
+
+pub fn build_router(state: AppState) -> Router {
+    let max_body_bytes = state.config.max_body_bytes;
+    Router::new()
+        .route("/", post(handle_webhook))
+        .route("/health", get(health))
+        .route("/health/ready", get(health_ready))
+        .route("/health/deep", get(health_deep))
+        .route("/metrics", get(metrics_handler))
+        .route("/status", get(status))
+        .merge(management_routes(&state))
+        .layer(DefaultBodyLimit::max(max_body_bytes))
+        .with_state(state)
+}
+
+/// Management endpoints, all behind `require_admin_key`. New ones go here rather than
+/// checking the key themselves.
+fn management_routes(state: &AppState) -> Router<AppState> {
+    Router::new()
+        .route("/verification", get(verification))
+        .route("/enrich", post(enrich))
+        .route("/search", post(search))
+        .route("/admin/replay", post(admin_replay))
+        .route("/admin/process", post(admin_process))
+        .route("/admin/reload-schema", post(admin_reload_schema))
+        .route("/admin/backfill", post(admin_backfill))
+        .route("/admin/backfill/status", get(admin_backfill_status))
+        .route_layer(middleware::from_fn_with_state(
+            state.clone(),
+            require_admin_key,
+        ))
+}
+
+async fn health() -> &'static str {
+    "OK"
+}
+
+/// Deep health check: verifies Notion, TMDB and AniList are reachable with our credentials.
+/// Results are cached so the endpoint can't be used to hammer upstream APIs.
+async fn health_ready(State(state): State<AppState>) -> Response {
+    let snapshot = {
+        // Holding the lock while checking also collapses concurrent probes into one check.
+        let mut guard = state.readiness.lock().await;
+        match guard
+            .as_ref()
+            .filter(|s| s.checked_at.elapsed().as_secs() < READINESS_CACHE_SECS)
+        {
+            Some(cached) => cached.clone(),
+            None => {
+                let fresh = check_readiness(&state).await;
+                *guard = Some(fresh.clone());
+                fresh
+            }
+        }
+    };
+    let status = if snapshot.ready {
+        StatusCode::OK
+    } else {
+        StatusCode::SERVICE_UNAVAILABLE
+    };
+    (status, Json(snapshot.body)).into_response()
+}
+
+/// Uncached probe of all three backends for container readiness probes: 200 only when Notion,
+/// TMDB and AniList all answer within `DEEP_HEALTH_TIMEOUT_SECS`, otherwise 503 with the
+/// failing ones under `failed`.
+async fn health_deep(State(state): State<AppState>) -> Response {
+    let timeout = std::time::Duration::from_secs(DEEP_HEALTH_TIMEOUT_SECS);
+    let (notion, tmdb, anilist) = check_dependencies(&state, timeout).await;
+    let mut body = serde_json::Map::new();
+    let mut failed = Vec::new();
+    for (name, result) in [("notion", notion), ("tmdb", tmdb), ("anilist", anilist)] {
+        let label = match result {
+            Ok(()) => "ok".to_string(),
+            Err(e) => {
+                failed.push(name);
+                e
+            }
+        };
+        body.insert(name.to_string(), json!(label));
+    }
+    if let Some(circuit) = state.notion.circuit_state() {
+        body.insert("notion_circuit".to_string(), json!(circuit.as_str()));
+    }
+    if failed.is_empty() {
+        return Json(serde_json::Value::Object(body)).into_response();
+    }
+    warn!("Deep health check failed for {:?}", failed);
+    body.insert("failed".to_string(), json!(failed));
+    (
+        StatusCode::SERVICE_UNAVAILABLE,
+        Json(serde_json::Value::Object(body)),
+    )
+        .into_response()
+}
+
+type DependencyResult = std::result::Result<(), String>;
+
+async fn check_dependencies(
+    state: &AppState,
+    timeout: std::time::Duration,
+) -> (DependencyResult, DependencyResult, DependencyResult) {
+    tokio::join!(
+        check_dependency(timeout, async {
+            state.notion.fetch_property_schema().await.map(|_| ())
+        }),
+        check_dependency(timeout, state.tmdb.ping()),
+        check_dependency(timeout, state.anilist.ping()),
+    )
+}
+
+async fn check_readiness(state: &AppState) -> ReadinessSnapshot {
+    let timeout = std::time::Duration::from_secs(READINESS_CHECK_TIMEOUT_SECS);
+    let (notion, tmdb, anilist) = check_dependencies(state, timeout).await;
+    // AniList only backs the `=`/`~` triggers; it is reported but doesn't fail readiness.
+    let ready = notion.is_ok() && tmdb.is_ok();
+    let label = |r: &DependencyResult| match r {
+        Ok(()) => "ok".to_string(),
+        Err(e) => e.clone(),
+    };
+    ReadinessSnapshot {
+        checked_at: std::time::Instant::now(),
+        ready,
+        body: json!({
+            "notion": label(&notion),
+            "tmdb": label(&tmdb),
+            "anilist": label(&anilist),
+        }),
+    }
+}
+
+async fn check_dependency(
+    timeout: std::time::Duration,
+    check: impl std::future::Future<Output = Result<()>>,
+) -> DependencyResult {
+    match tokio::time::timeout(timeout, check).await {
+        Ok(Ok(())) => Ok(()),
+        Ok(Err(e)) => Err(format!("error: {}", e)),
+        Err(_) => Err("error: timeout".to_string()),
+    }
+}
+
+async fn metrics_handler(State(state): State<AppState>) -> Response {
+    (
+        [(header::CONTENT_TYPE, "text/plain; version=0.0.4")],
+        state.metrics.render(),
+    )
+        .into_response()
+}
+
+async fn status(State(state): State<AppState>) -> Response {
+    let m = &state.metrics;
+    Json(json!({
+        "version": env!("CARGO_PKG_VERSION"),
+        "webhooks_received": m.webhooks_received.get(),
+        "pages_updated": m.pages_updated.get(),
+        "unknown_shape_events": m.unknown_shape_events.get(),
+    }))
+    .into_response()
+}
+
+/// Lets a management request through only with `Authorization: Bearer <CINELINK_ADMIN_KEY>`:
+/// `401` when the token is missing or wrong, `404` when no key is configured (the endpoints
+/// are disabled). Runs before the handler reads the body or touches the rate limits.
+async fn require_admin_key(
+    State(state): State<AppState>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let Some(expected) = state.admin_key.as_deref() else {
+        return StatusCode::NOT_FOUND.into_response();
+    };
+    let headers = request.headers();
+    let provided = headers
+        .get(header::AUTHORIZATION)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.strip_prefix("Bearer "))
+        .unwrap_or("");
+    if !constant_time_eq(provided.trim().as_bytes(), expected.as_bytes()) {
+        warn!("Rejecting admin request: missing or invalid bearer token");
+        return StatusCode::UNAUTHORIZED.into_response();
+    }
+    next.run(request).await
+}
+
+/// The last subscription verification token, for pasting into Notion's webhook settings.
+async fn verification(State(state): State<AppState>) -> Response {
+    match state.verification_token.lock().await.clone() {
+        Some(token) => Json(json!({ "verification_token": token })).into_response(),
+        None => admin_error(
+            StatusCode::NOT_FOUND,
+            "no verification request received yet",
+        ),
+    }
+}
+
+fn admin_error(status: StatusCode, message: impl Into<String>) -> Response {
+    (status, Json(json!({ "error": message.into() }))).into_response()
+}
+
+/// Re-runs a failed page job, identified either by `{"failure_id": n}` or by
+/// `{"capture": "<file name>"}` in `CINELINK_CAPTURE_DIR`. Dedupe is bypassed; the title
+/// must still carry a trigger.
+async fn admin_replay(State(state): State<AppState>, body: Bytes) -> Response {
+    let request: serde_json::Value = match serde_json::from_slice(&body) {
+        Ok(v) => v,
+        Err(e) => return admin_error(StatusCode::BAD_REQUEST, format!("invalid JSON: {}", e)),
+    };
+
+    let (page_id, event_id) = if let Some(id) = request.get("failure_id") {
+        let Some(id) = id.as_u64() else {
+            return admin_error(
+                StatusCode::BAD_REQUEST,
+                "failure_id must be a positive integer",
+            );
+        };
+        match state.failures.get(id) {
+            Some(entry) => (entry.page_id, entry.event_id),
+            None => return admin_error(StatusCode::NOT_FOUND, format!("no failure #{}", id)),
+        }
+    } else if let Some(name) = request.get("capture") {
+        let Some(name) = name.as_str().filter(|n| is_valid_capture_name(n)) else {
+            return admin_error(StatusCode::BAD_REQUEST, "invalid capture file name");
+        };
+        let Some(dir) = state.capture_dir.as_ref() else {
+            return admin_error(StatusCode::NOT_FOUND, "CINELINK_CAPTURE_DIR is not set");
+        };
+        let payload = match std::fs::read(dir.join(name)) {
+            Ok(raw) => serde_json::from_slice::<serde_json::Value>(&raw).ok(),
+            Err(_) => return admin_error(StatusCode::NOT_FOUND, format!("no capture {}", name)),
+        };
+        let page_id = payload
+            .as_ref()
+            .and_then(|p| p.get("entity"))
+            .and_then(|e| e.get("id"))
+            .and_then(|v| v.as_str());
+        let Some(page_id) = page_id else {
+            return admin_error(
+                StatusCode::UNPROCESSABLE_ENTITY,
+                "capture is not a webhook payload with entity.id",
+            );
+        };
+        let event_id = payload
+            .as_ref()
+            .and_then(|p| p.get("id"))
+            .and_then(|v| v.as_str())
+            .map(str::to_string);
+        (page_id.to_string(), event_id)
+    } else {
+        return admin_error(StatusCode::BAD_REQUEST, "expected failure_id or capture");
+    };
+
+    info!(page_id = %page_id, event_id = ?event_id, "Replaying page job");
+    match run_manual_job(&state, &page_id, event_id.as_deref(), PageMode::Trigger).await {
+        Ok(outcome) => {
+            Json(json!({ "page_id": page_id, "updated": outcome.updated })).into_response()
+        }
+        Err((failure_id, err)) => (
+            StatusCode::BAD_GATEWAY,
+            Json(json!({
+                "page_id": page_id,
+                "updated": false,
+                "error": err.to_string(),
+                "failure_id": failure_id,
+            })),
+        )
+            .into_response(),
+    }
+}
+
+/// Enriches one page on demand, whatever its title suffix:
+/// `{"page_id": "...", "source": "tmdb" | "anilist" | "auto"}` (`source` defaults to `auto`).
+/// Shares the job semaphore with webhooks and counts against the global rate limit only.
+async fn enrich(State(state): State<AppState>, body: Bytes) -> Response {
+    if let Err(wait) = check_global_rate_limit(&state).await {
+        warn!("Rate limit exceeded for /enrich (global)");
+        state.metrics.admin_rate_limited.inc();
+        return rate_limited_response(RateLimitScope::Global, wait);
+    }
+    let request: serde_json::Value = match serde_json::from_slice(&body) {
+        Ok(v) => v,
+        Err(e) => return admin_error(StatusCode::BAD_REQUEST, format!("invalid JSON: {}", e)),
+    };
+    let Some(page_id) = request_page_id(&request) else {
+        return admin_error(
+            StatusCode::BAD_REQUEST,
+            "page_id must be a non-empty string",
+        );
+    };
+    let source = match request.get("source") {
+        None | Some(serde_json::Value::Null) => EnrichSource::Auto,
+        Some(v) => match v.as_str().and_then(EnrichSource::parse) {
+            Some(source) => source,
+            None => {
+                return admin_error(
+                    StatusCode::BAD_REQUEST,
+                    "source must be \"tmdb\", \"anilist\" or \"auto\"",
+                )
+            }
+        },
+    };
+
+    info!(page_id = %page_id, source = ?source, "Manual enrichment requested");
+    match run_manual_job(&state, page_id, None, PageMode::Manual(source)).await {
+        Ok(outcome) => {
+            Json(json!({ "updated": outcome.updated, "title": outcome.title })).into_response()
+        }
+        Err((failure_id, err)) => (
+            StatusCode::BAD_GATEWAY,
+            Json(json!({
+                "updated": false,
+                "error": err.to_string(),
+                "failure_id": failure_id,
+            })),
+        )
+            .into_response(),
+    }
+}
+
+/// What `POST /search` looks up.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum SearchSource {
+    TmdbMovie,
+    TmdbTv,
+    AniListAnime,
+    AniListManga,
+}
+
+impl SearchSource {
+    fn parse(raw: &str) -> Option<Self> {
+        match raw.trim().to_ascii_lowercase().as_str() {
+            "tmdb_movie" => Some(Self::TmdbMovie),
+            "tmdb_tv" => Some(Self::TmdbTv),
+            "anilist_anime" => Some(Self::AniListAnime),
+            "anilist_manga" => Some(Self::AniListManga),
+            _ => None,
+        }
+    }
+}
+
+/// Runs a lookup without touching Notion:
+/// `{"query": "...", "source": "tmdb_movie" | "tmdb_tv" | "anilist_anime" | "anilist_manga",
+/// "season": 1}`, where `season` is optional (TV defaults to season 1). The reply is the
+/// mapped media, as it would be written. Shares the job semaphore and the per-page request
+/// budget with page jobs.
+async fn search(State(state): State<AppState>, body: Bytes) -> Response {
+    if let Err(wait) = check_global_rate_limit(&state).await {
+        warn!("Rate limit exceeded for /search (global)");
+        state.metrics.admin_rate_limited.inc();
+        return rate_limited_response(RateLimitScope::Global, wait);
+    }
+    let request: serde_json::Value = match serde_json::from_slice(&body) {
+        Ok(v) => v,
+        Err(e) => return admin_error(StatusCode::BAD_REQUEST, format!("invalid JSON: {}", e)),
+    };
+    let Some(query) = request
+        .get("query")
+        .and_then(|v| v.as_str())
+        .map(str::trim)
+        .filter(|q| !q.is_empty())
+    else {
+        return admin_error(StatusCode::BAD_REQUEST, "query must be a non-empty string");
+    };
+    let Some(source) = request
+        .get("source")
+        .and_then(|v| v.as_str())
+        .and_then(SearchSource::parse)
+    else {
+        return admin_error(
+            StatusCode::BAD_REQUEST,
+            "source must be \"tmdb_movie\", \"tmdb_tv\", \"anilist_anime\" or \"anilist_manga\"",
+        );
+    };
+    let season = match request.get("season") {
+        None | Some(serde_json::Value::Null) => None,
+        Some(v) => match v.as_i64().and_then(|n| i32::try_from(n).ok()) {
+            Some(n) if n >= 0 => Some(n),
+            _ => {
+                return admin_error(
+                    StatusCode::BAD_REQUEST,
+                    "season must be a non-negative integer",
+                )
+            }
+        },
+    };
+
+    let Ok(_permit) = state.processing_sem.clone().acquire_owned().await else {
+        return StatusCode::SERVICE_UNAVAILABLE.into_response();
+    };
+    info!(query = %query, source = ?source, "Search requested");
+    let budget = Arc::new(RequestBudget::new(state.config.request_budget));
+    match budget::scope(budget, search_media(&state, source, query, season)).await {
+        Ok(media) => Json(media).into_response(),
+        Err(e) => {
+            let status = match classify(&e) {
+                FailureKind::Transient => StatusCode::BAD_GATEWAY,
+                FailureKind::Permanent => StatusCode::NOT_FOUND,
+            };
+            admin_error(status, format!("{:#}", e))
+        }
+    }
+}
+
+/// The lookup chain of a page job, ending in the mapped media as JSON. As in `enrich_page`,
+/// an explicit id (`tmdb:603`, `anilist:21`, `mal:5114`) or a pasted TMDB/IMDb/AniList URL
+/// picks its provider whatever `source` says, a TMDB URL also picks movie or show (and the
+/// season), and a trailing `(2019)` narrows a TMDB title search to that year.
+async fn search_media(
+    state: &AppState,
+    source: SearchSource,
+    query: &str,
+    season: Option<i32>,
+) -> Result<serde_json::Value> {
+    let media_url = parse_media_url(query);
+    let anilist_source = matches!(
+        source,
+        SearchSource::AniListAnime | SearchSource::AniListManga
+    );
+    let source = match (&media_url, TitleId::parse(query)) {
+        (Some(MediaUrl::TmdbMovie(_)), _) => SearchSource::TmdbMovie,
+        (Some(MediaUrl::TmdbTv { .. }), _) => SearchSource::TmdbTv,
+        (Some(MediaUrl::AniList(AniListMediaType::Anime, _)), _) => SearchSource::AniListAnime,
+        (Some(MediaUrl::AniList(AniListMediaType::Manga, _)), _) => SearchSource::AniListManga,
+        (Some(MediaUrl::Imdb(_)), _) if anilist_source => SearchSource::TmdbMovie,
+        (_, Some(id)) if id.is_tmdb() && anilist_source => SearchSource::TmdbMovie,
+        (_, Some(id)) if !id.is_tmdb() && !anilist_source => SearchSource::AniListAnime,
+        _ => source,
+    };
+    let imdb_ids = match (source, tmdb::parse_imdb_id(query)) {
+        (SearchSource::TmdbMovie | SearchSource::TmdbTv, Some(imdb)) => {
+            Some(state.tmdb.lookup_imdb(&imdb).await?)
+        }
+        _ => None,
+    };
+    let (tmdb_query, year) = match tmdb::split_year_hint(query) {
+        Some((query, year)) => (query, Some(year)),
+        None => (query.to_string(), None),
+    };
+    let media = match source {
+        SearchSource::TmdbMovie => {
+            let id = match (&media_url, imdb_ids) {
+                (Some(MediaUrl::TmdbMovie(id)), _) => *id,
+                (_, Some((movie_id, _))) => {
+                    movie_id.ok_or_else(|| anyhow::anyhow!("No TMDB movie for {}", query))?
+                }
+                _ => {
+                    state
+                        .tmdb
+                        .resolve_movie_id_with_year(&tmdb_query, year)
+                        .await?
+                }
+            };
+            serde_json::to_value(state.tmdb.fetch_movie(id).await?)?
+        }
+        SearchSource::TmdbTv => {
+            let (id, season) = match (&media_url, imdb_ids) {
+                (
+                    Some(MediaUrl::TmdbTv {
+                        id,
+                        season: url_season,
+                    }),
+                    _,
+                ) => (*id, url_season.or(season)),
+                (_, Some((_, tv_id))) => (
+                    tv_id.ok_or_else(|| anyhow::anyhow!("No TMDB show for {}", query))?,
+                    season,
+                ),
+                _ => (
+                    state
+                        .tmdb
+                        .resolve_tv_id_with_year(&tmdb_query, year)
+                        .await?,
+                    season,
+                ),
+            };
+            let season = season.unwrap_or(1);
+            serde_json::to_value(state.tmdb.fetch_tv_season(id, season).await?)?
+        }
+        SearchSource::AniListAnime => {
+            let id = state.anilist.resolve_anime_id(query, season).await?;
+            serde_json::to_value(state.anilist.fetch_anime(id).await?)?
+        }
+        SearchSource::AniListManga => {
+            let id = state.anilist.resolve_manga_id(query, season).await?;
+            serde_json::to_value(state.anilist.fetch_manga(id).await?)?
+        }
+    };
+    Ok(media)
+}
+
+/// The optional `dry_run` flag of an admin request body.
+fn request_dry_run(request: &serde_json::Value) -> Result<bool, &'static str> {
+    match request.get("dry_run") {
+        None | Some(serde_json::Value::Null) => Ok(false),
+        Some(v) => v.as_bool().ok_or("dry_run must be a boolean"),
+    }
+}
+
+/// A copy of `state` whose page jobs log their Notion writes instead of sending them.
+fn dry_run_state(state: &AppState) -> AppState {
+    let mut config = (*state.config).clone();
+    config.dry_run = true;
+    AppState {
+        config: Arc::new(config),
+        ..state.clone()
+    }
+}
+
+fn request_page_id(request: &serde_json::Value) -> Option<&str> {
+    request
+        .get("page_id")
+        .and_then(|v| v.as_str())
+        .map(str::trim)
+        .filter(|id| !id.is_empty())
+}
+
+/// Runs one page job on demand: `{"page_id": "...", "force": true}`. Without `force` the
+/// title must carry a trigger, as for webhooks; with it any title is enriched, routed like
+/// `POST /enrich` with `source: auto`. The reply includes the matched provider id.
+/// `"dry_run": true` logs the update instead of writing it.
+async fn admin_process(State(state): State<AppState>, body: Bytes) -> Response {
+    let request: serde_json::Value = match serde_json::from_slice(&body) {
+        Ok(v) => v,
+        Err(e) => return admin_error(StatusCode::BAD_REQUEST, format!("invalid JSON: {}", e)),
+    };
+    let Some(page_id) = request_page_id(&request) else {
+        return admin_error(
+            StatusCode::BAD_REQUEST,
+            "page_id must be a non-empty string",
+        );
+    };
+    let force = match request.get("force") {
+        None | Some(serde_json::Value::Null) => false,
+        Some(v) => match v.as_bool() {
+            Some(force) => force,
+            None => return admin_error(StatusCode::BAD_REQUEST, "force must be a boolean"),
+        },
+    };
+    let dry_run = match request_dry_run(&request) {
+        Ok(dry_run) => dry_run,
+        Err(message) => return admin_error(StatusCode::BAD_REQUEST, message),
+    };
+    let mode = if force {
+        PageMode::Manual(EnrichSource::Auto)
+    } else {
+        PageMode::Trigger
+    };
+    let state = if dry_run {
+        dry_run_state(&state)
+    } else {
+        state
+    };
+
+    info!(page_id = %page_id, force, dry_run, "Processing page on demand");
+    match run_manual_job(&state, page_id, None, mode).await {
+        Ok(outcome) => {
+            let mut body = outcome.to_json();
+            body["page_id"] = json!(page_id);
+            Json(body).into_response()
+        }
+        Err((failure_id, err)) => (
+            StatusCode::BAD_GATEWAY,
+            Json(json!({
+                "page_id": page_id,
+                "updated": false,
+                "error": err.to_string(),
+                "failure_id": failure_id,
+            })),
+        )
+            .into_response(),
+    }
+}
+
+/// Re-fetches the database schema and swaps it in for jobs that start afterwards. On failure
+/// the current schema is kept.
+async fn reload_schema(state: &AppState) -> Result<Arc<notion::PropertySchema>> {
+    let schema = state
+        .notion
+        .fetch_property_schema()
+        .await
+        .inspect_err(|e| {
+            state.metrics.notion_errors.inc();
+            warn!(
+                "Failed to reload Notion schema, keeping the current one: {}",
+                e
+            );
+        })?;
+    if schema.title_property.as_deref() != Some(state.title_property.as_str()) {
+        warn!(
+            "Title property is now {:?}; still using '{}' until restart",
+            schema.title_property, state.title_property
+        );
+    }
+    info!("Reloaded Notion schema ({} properties)", schema.types.len());
+    state.schema.replace(schema);
+    Ok(state.schema.current())
+}
+
+async fn admin_reload_schema(State(state): State<AppState>) -> Response {
+    match reload_schema(&state).await {
+        Ok(schema) => {
+            let mut properties: Vec<&String> = schema.types.keys().collect();
+            properties.sort();
+            Json(json!({ "properties": properties })).into_response()
+        }
+        Err(e) => admin_error(StatusCode::BAD_GATEWAY, format!("{:#}", e)),
+    }
+}
+
+/// Starts a backfill in the background: optional `{"kind": "tv" | "movie" | "anime" | "all",
+/// "only_incomplete": true, "concurrency": 4, "dry_run": true}`. Answers 202 with the initial
+/// status, or 409 while a run is active.
+async fn admin_backfill(State(state): State<AppState>, body: Bytes) -> Response {
+    let request: serde_json::Value = if body.is_empty() {
+        json!({})
+    } else {
+        match serde_json::from_slice(&body) {
+            Ok(v) => v,
+            Err(e) => return admin_error(StatusCode::BAD_REQUEST, format!("invalid JSON: {}", e)),
+        }
+    };
+    let mut options = BackfillOptions::default();
+    match request.get("kind") {
+        None | Some(serde_json::Value::Null) => {}
+        Some(v) => match v.as_str().and_then(BackfillKind::parse) {
+            Some(kind) => options.kind = kind,
+            None => {
+                return admin_error(
+                    StatusCode::BAD_REQUEST,
+                    "kind must be \"tv\", \"movie\", \"anime\" or \"all\"",
+                )
+            }
+        },
+    }
+    match request.get("only_incomplete") {
+        None | Some(serde_json::Value::Null) => {}
+        Some(v) => match v.as_bool() {
+            Some(only_incomplete) => options.only_incomplete = only_incomplete,
+            None => {
+                return admin_error(StatusCode::BAD_REQUEST, "only_incomplete must be a boolean")
+            }
+        },
+    }
+    match request.get("concurrency") {
+        None | Some(serde_json::Value::Null) => {}
+        Some(v) => match v.as_u64().filter(|n| (1..=64).contains(n)) {
+            Some(n) => options.concurrency = n as usize,
+            None => {
+                return admin_error(
+                    StatusCode::BAD_REQUEST,
+                    "concurrency must be an integer from 1 to 64",
+                )
+            }
+        },
+    }
+    let dry_run = match request_dry_run(&request) {
+        Ok(dry_run) => dry_run,
+        Err(message) => return admin_error(StatusCode::BAD_REQUEST, message),
+    };
+
+    if !state.backfill.try_start() {
+        return admin_error(StatusCode::CONFLICT, "a backfill is already running");
+    }
+    info!(?options, dry_run, "Starting backfill on demand");
+    let task_state = if dry_run {
+        dry_run_state(&state)
+    } else {
+        state.clone()
+    };
+    tokio::spawn(async move {
+        let progress = task_state.backfill.clone();
+        let result = run_backfill(&task_state, &options, &progress).await;
+        if let Err(e) = &result {
+            error!("Backfill stopped: {:#}", e);
+        }
+        progress.finish(&result);
+    });
+    (StatusCode::ACCEPTED, Json(state.backfill.to_json())).into_response()
+}
+
+/// Counters of the current or last backfill run.
+async fn admin_backfill_status(State(state): State<AppState>) -> Response {
+    Json(state.backfill.to_json()).into_response()
+}
+
+/// Plain file names only, so a capture lookup can't escape `CINELINK_CAPTURE_DIR`.
+fn is_valid_capture_name(name: &str) -> bool {
+    !name.is_empty()
+        && name.len() <= MAX_CAPTURE_NAME_LEN
+        && !name.starts_with('.')
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.'))
+}
+
+/// How a webhook payload differs from the shape CineLink knows about.
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct PayloadShapeReport {
+    pub event_type: Option<String>,
+    /// `version` or `api_version`, whichever the payload carries.
+    pub version: Option<String>,
+    pub unexpected_keys: Vec<String>,
+    pub missing_keys: Vec<String>,
+}
+
+impl PayloadShapeReport {
+    pub fn is_known(&self) -> bool {
+        self.unexpected_keys.is_empty() && self.missing_keys.is_empty()
+    }
+}
+
+pub fn payload_shape_report(payload: &serde_json::Value) -> PayloadShapeReport {
+    let as_string = |v: &serde_json::Value| match v {
+        serde_json::Value::String(s) => s.clone(),
+        other => other.to_string(),
+    };
+    let Some(obj) = payload.as_object() else {
+        return PayloadShapeReport {
+            missing_keys: REQUIRED_EVENT_KEYS.iter().map(|k| k.to_string()).collect(),
+            ..Default::default()
+        };
+    };
+    let mut unexpected_keys: Vec<String> = obj
+        .keys()
+        .filter(|k| !KNOWN_EVENT_KEYS.contains(&k.as_str()))
+        .cloned()
+        .collect();
+    unexpected_keys.sort();
+    PayloadShapeReport {
+        event_type: obj.get("type").map(as_string),
+        version: obj
+            .get("version")
+            .or_else(|| obj.get("api_version"))
+            .map(as_string),
+        unexpected_keys,
+        missing_keys: REQUIRED_EVENT_KEYS
+            .iter()
+            .filter(|k| !obj.contains_key(**k))
+            .map(|k| k.to_string())
+            .collect(),
+    }
+}
+
+/// Counts and (throttled) logs payloads that deviate from the known shape; processing continues.
+async fn note_payload_shape(state: &AppState, payload: &serde_json::Value) {
+    let report = payload_shape_report(payload);
+    if report.is_known() {
+        return;
+    }
+    state.metrics.unknown_shape_events.inc();
+
+    let limit = RateLimit::per_minute(SHAPE_WARNINGS_PER_MINUTE, 0);
+    let mut guard = state.shape_warning_limit.lock().await;
+    if guard.try_take(&limit, std::time::Instant::now()).is_err() {
+        return;
+    }
+    warn!(
+        event_type = ?report.event_type,
+        version = ?report.version,
+        unexpected_keys = ?report.unexpected_keys,
+        missing_keys = ?report.missing_keys,
+        "Webhook payload deviates from the known shape"
+    );
+}
+
+async fn handle_webhook(
+    State(state): State<AppState>,
+    peer: Option<Extension<ConnectInfo<SocketAddr>>>,
+    headers: HeaderMap,
+    body: Bytes,
+) -> Response {
+    let verification_disabled = state.signing_secret == INSECURE_DISABLED_SECRET;
+    state.metrics.webhooks_received.inc();
+    let peer = peer.map(|Extension(ConnectInfo(addr))| addr);
+    let ip = client_ip(&state.config, peer, &headers);
+    if !state.config.webhook_ip_allowed(&ip) {
+        warn!(
+            "Rejecting request from {}: not in the webhook allowlist",
+            ip
+        );
+        state.metrics.ip_not_allowed.inc();
+        return StatusCode::FORBIDDEN.into_response();
+    }
+    let per_ip = check_rate_limit(&state, &ip).await;
+    let tripped = match per_ip {
+        Err(wait) => Some((RateLimitScope::PerIp, wait)),
+        Ok(_) => check_global_rate_limit(&state)
+            .await
+            .err()
+            .map(|wait| (RateLimitScope::Global, wait)),
+    };
+    let mut response = match tripped {
+        Some((scope, wait)) => {
+            warn!("Rate limit exceeded for {} ({})", ip, scope.as_str());
+            state.metrics.rate_limited.inc();
+            rate_limited_response(scope, wait)
+        }
+        None => receive_webhook(state.clone(), headers, body).await,
+    };
+    let limit = per_ip_rate_limit(&state.config).capacity();
+    let headers = response.headers_mut();
+    headers.insert(RATE_LIMIT_LIMIT_HEADER, HeaderValue::from(limit));
+    headers.insert(
+        RATE_LIMIT_REMAINING_HEADER,
+        HeaderValue::from(per_ip.unwrap_or(0)),
+    );
+    if verification_disabled {
+        headers.insert(
+            SIGNATURE_SKIPPED_HEADER,
+            HeaderValue::from_static("skipped"),
+        );
+    }
+    response
+}
+
+async fn receive_webhook(state: AppState, headers: HeaderMap, body: Bytes) -> Response {
+    if state.jobs.is_closed() {
+        // Notion retries the delivery, reaching the next instance.
+        warn!("Rejecting request: shutting down");
+        return StatusCode::SERVICE_UNAVAILABLE.into_response();
+    }
+    if body.len() > state.config.max_body_bytes {
+        warn!(
+            "Rejecting request: body too large ({} bytes > {} bytes)",
+            body.len(),
+            state.config.max_body_bytes
+        );
+        return StatusCode::PAYLOAD_TOO_LARGE.into_response();
+    }
+
+    // Enforce content type
+    let content_type_ok = headers
+        .get(header::CONTENT_TYPE)
+        .and_then(|v| v.to_str().ok())
+        .map(|v| v.starts_with("application/json"))
+        == Some(true);
+    if !content_type_ok {
+        warn!(
+            "Rejecting request: unsupported content-type {:?}",
+            headers.get(header::CONTENT_TYPE)
+        );
+        return StatusCode::UNSUPPORTED_MEDIA_TYPE.into_response();
+    }
+
+    let parsed = serde_json::from_slice::<serde_json::Value>(&body);
+    // Notion sends the verification request before the signing secret exists, so it can't be
+    // signature-checked. It is only recorded while no secret is set, and only the first one:
+    // anyone could send another to replace the token the operator is about to install. Once a
+    // secret exists it is an ordinary unsigned request.
+    if state.signing_secret.is_empty() {
+        if let Some(token) = parsed.as_ref().ok().and_then(verification_token) {
+            let mut stored = state.verification_token.lock().await;
+            if stored.is_none() {
+                info!("Received Notion webhook verification_token: {}", token);
+                *stored = Some(token.to_string());
+            } else {
+                warn!("Ignoring another verification_token; keeping the first one received");
+            }
+            return StatusCode::OK.into_response();
+        }
+    }
+
+    if state.signing_secret == INSECURE_DISABLED_SECRET {
+        debug!("Signature verification disabled; accepting unverified webhook");
+    } else {
+        let secondary = state.secondary_signing_secret.as_deref();
+        let now = Utc::now().timestamp();
+        match verify_notion_signature(&headers, &body, &state.signing_secret, secondary, now) {
+            Ok(secret) => debug!(?secret, "Webhook signature verified"),
+            Err(rejection) => {
+                // Return 200 to avoid retry amplification; we simply ignore untrusted payloads.
+                warn!(?rejection, "Webhook signature verification failed");
+                match rejection {
+                    SignatureRejection::Invalid => state.metrics.signature_failures.inc(),
+                    SignatureRejection::Stale => state.metrics.stale_signatures.inc(),
+                }
+                return StatusCode::OK.into_response();
+            }
+        }
+    }
+
+    let payload = match parsed {
+        Ok(v) => v,
+        Err(e) => {
+            warn!("Rejecting request: invalid JSON body: {}", e);
+            return StatusCode::BAD_REQUEST.into_response();
+        }
+    };
+    note_payload_shape(&state, &payload).await;
+    let event = match serde_json::from_value::<WebhookEvent>(payload) {
+        Ok(event) => event,
+        Err(e) => {
+            warn!("Ignoring event with unexpected field types: {}", e);
+            return StatusCode::OK.into_response();
+        }
+    };
+
+    match event.kind() {
+        EventKind::PageCreated | EventKind::PropertiesUpdated => {}
+        EventKind::SchemaUpdated => {
+            let state = state.clone();
+            tokio::spawn(async move {
+                let _ = reload_schema(&state).await;
+            });
+            return StatusCode::OK.into_response();
+        }
+        EventKind::Other => {
+            warn!("Ignoring event with unsupported type");
+            return StatusCode::OK.into_response();
+        }
+    }
+
+    let Some(trigger) = webhook::should_process(&event) else {
+        return StatusCode::OK.into_response();
+    };
+    let page_ids = event.page_ids();
+    if page_ids.is_empty() {
+        return StatusCode::BAD_REQUEST.into_response();
+    }
+
+    // Reserved before dedupe, so an event refused here isn't remembered as seen when Notion
+    // redelivers it. All of an event's pages are queued, or none.
+    let Some(slots) = state.job_queue.try_reserve_many(page_ids.len()) else {
+        warn!(
+            "Rejecting request: job queue full ({} jobs)",
+            state.job_queue.capacity()
+        );
+        state.metrics.queue_full.inc();
+        return queue_full_response();
+    };
+
+    let mut fresh = Vec::with_capacity(page_ids.len());
+    for page_id in page_ids {
+        let seen = match event.id.as_deref() {
+            Some(event_id) => !dedupe_event(&state, &dedupe_key(event_id, page_id)).await,
+            None => false,
+        };
+        if !seen {
+            fresh.push(page_id.to_string());
+        }
+    }
+    if fresh.is_empty() {
+        state.metrics.webhooks_deduped.inc();
+        return StatusCode::OK.into_response();
+    }
+
+    debug!(
+        page_ids = ?fresh,
+        event_id = ?event.id,
+        ?trigger,
+        "Webhook accepted; queued page checks"
+    );
+
+    let wait = headers
+        .get(WAIT_HEADER)
+        .and_then(|v| v.to_str().ok())
+        .is_some_and(|v| v.trim().eq_ignore_ascii_case("true") || v.trim() == "1");
+    let page_id = fresh[0].clone();
+    let mut receivers = Vec::with_capacity(fresh.len());
+    for (slot, page_id) in slots.zip(fresh) {
+        let (done_tx, done_rx) = tokio::sync::oneshot::channel();
+        receivers.push(done_rx);
+        state.metrics.job_queue_depth.inc();
+        slot.send(PageJob {
+            page_id,
+            event_id: event.id.clone(),
+            done: done_tx,
+            _tracked: state.jobs.token(),
+        });
+    }
+    // Only single-page events can be answered synchronously; batches are acknowledged at once.
+    let done_rx = match (wait, receivers.pop()) {
+        (true, Some(done_rx)) if receivers.is_empty() => done_rx,
+        _ => return StatusCode::OK.into_response(),
+    };
+
+    // Synchronous mode for scripts (Notion never sends the header). On timeout the job keeps
+    // running in the background.
+    let timeout = std::time::Duration::from_secs(state.config.sync_timeout_secs);
+    match tokio::time::timeout(timeout, done_rx).await {
+        Ok(Ok(Ok(outcome))) => {
+            let mut body = outcome.to_json();
+            body["page_id"] = json!(page_id);
+            Json(body).into_response()
+        }
+        Ok(Ok(Err((failure_id, err)))) => (
+            StatusCode::BAD_GATEWAY,
+            Json(json!({
+                "page_id": page_id,
+                "updated": false,
+                "error": err.to_string(),
+                "failure_id": failure_id,
+            })),
+        )
+            .into_response(),
+        Ok(Err(_)) => admin_error(StatusCode::INTERNAL_SERVER_ERROR, "page job was dropped"),
+        Err(_) => (
+            StatusCode::GATEWAY_TIMEOUT,
+            Json(json!({
+                "page_id": page_id,
+                "error": format!(
+                    "page job still running after {}s; it continues in the background",
+                    state.config.sync_timeout_secs
+                ),
+            })),
+        )
+            .into_response(),
+    }
+}
+
+/// A webhook job running for a page; dropping it wakes the events waiting for the page.
+#[derive(Debug)]
+pub struct InFlightJob {
+    done: watch::Sender<()>,
+    started: std::time::Instant,
+}
+
+/// A webhook's page job, waiting in `job_queue` for a worker.
+pub struct PageJob {
+    page_id: String,
+    event_id: Option<String>,
+    done: tokio::sync::oneshot::Sender<JobResult>,
+    /// Counts the job in `AppState::jobs` from the moment it is queued.
+    _tracked: TaskTrackerToken,
+}
+
+type JobResult = std::result::Result<PageOutcome, (u64, anyhow::Error)>;
+
+/// Starts `max_concurrent_jobs` workers that run the webhook jobs in `job_queue`, and a task
+/// logging the queue depth and busy workers every minute while there is work. Call once per
+/// state; later calls are no-ops.
+pub fn spawn_job_workers(state: &AppState) {
+    let Some(rx) = state.job_queue.take_receiver() else {
+        return;
+    };
+    let rx = Arc::new(Mutex::new(rx));
+    for _ in 0..state.config.max_concurrent_jobs {
+        let state = state.clone();
+        let rx = rx.clone();
+        tokio::spawn(async move {
+            loop {
+                let Some(job) = rx.lock().await.recv().await else {
+                    break;
+                };
+                state.metrics.job_queue_depth.dec();
+                let _busy = state.metrics.busy_workers.track();
+                run_queued_job(&state, job).await;
+            }
+        });
+    }
+
+    let state = state.clone();
+    tokio::spawn(async move {
+        let mut ticker = tokio::time::interval(JOB_QUEUE_REPORT_INTERVAL);
+        ticker.tick().await;
+        loop {
+            ticker.tick().await;
+            let queued = state.metrics.job_queue_depth.get();
+            let busy = state.metrics.busy_workers.get();
+            if queued > 0 || busy > 0 {
+                info!(
+                    "Job queue: {} waiting (capacity {}), {}/{} workers busy",
+                    queued,
+                    state.job_queue.capacity(),
+                    busy,
+                    state.config.max_concurrent_jobs
+                );
+            }
+        }
+    });
+}
+
+async fn run_queued_job(state: &AppState, job: PageJob) {
+    let result = match enter_page(state, &job.page_id).await {
+        Some(_in_flight) => {
+            run_page_job_with_retry(state, &job.page_id, job.event_id.as_deref(), 1).await
+        }
+        // Another event's job (or re-check) covers this one.
+        None => Ok(PageOutcome::skipped(String::new())),
+    };
+    let _ = job.done.send(result);
+}
+
+/// Holds a page in `in_flight_pages` until dropped.
+struct InFlightPage<'a> {
+    pages: &'a std::sync::Mutex<HashMap<String, InFlightJob>>,
+    page_id: String,
 }
 
-async fn health() -> &'static str {
-    "OK"
+impl Drop for InFlightPage<'_> {
+    fn drop(&mut self) {
+        self.pages
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .remove(&self.page_id);
+    }
 }
 
-async fn handle_webhook(
-    State(state): State<AppState>,
-    headers: HeaderMap,
-    body: Bytes,
-) -> StatusCode {
-    let ip = extract_ip(&headers);
-    if !check_rate_limit(&state, &ip).await || !check_global_rate_limit(&state).await {
-        warn!("Rate limit exceeded for {}", ip);
-        return StatusCode::TOO_MANY_REQUESTS;
+/// Claims `page_id` for a webhook job. An event arriving within `COALESCE_WINDOW` of the
+/// running job's start is dropped: Notion often sends several events for one edit, and the
+/// job reads the page after they were sent. A later one waits for that job to finish and then
+/// claims the page for a single re-check, in case it changed something the job had already
+/// read. `None` when the event needs no job of its own: it was dropped, a re-check is already
+/// waiting, or another event claimed the page first.
+async fn enter_page<'a>(state: &'a AppState, page_id: &str) -> Option<InFlightPage<'a>> {
+    let mut waited = false;
+    loop {
+        let mut done = {
+            let mut pages = state
+                .in_flight_pages
+                .lock()
+                .unwrap_or_else(|e| e.into_inner());
+            match pages.get(page_id) {
+                None => {
+                    let job = InFlightJob {
+                        done: watch::channel(()).0,
+                        started: std::time::Instant::now(),
+                    };
+                    pages.insert(page_id.to_string(), job);
+                    return Some(InFlightPage {
+                        pages: &state.in_flight_pages,
+                        page_id: page_id.to_string(),
+                    });
+                }
+                Some(job) if !waited && job.started.elapsed() < COALESCE_WINDOW => {
+                    debug!(page_id = %page_id, "Page job just started; dropping event");
+                    return None;
+                }
+                Some(job) if waited || job.done.receiver_count() > 0 => {
+                    debug!(page_id = %page_id, "Page already queued for a re-check; coalescing event");
+                    return None;
+                }
+                Some(job) => job.done.subscribe(),
+            }
+        };
+        debug!(page_id = %page_id, "Page is being processed; waiting to re-check it");
+        // Errors once the job drops its sender, which is the signal.
+        let _ = done.changed().await;
+        drop(done);
+        waited = true;
     }
+}
 
-    if body.len() > MAX_BODY_BYTES {
-        warn!(
-            "Rejecting request: body too large ({} bytes > {} bytes)",
-            body.len(),
-            MAX_BODY_BYTES
+/// Processes a page under a `processing_sem` permit. Failures are logged and stored in the
+/// failure log; the error carries the failure id.
+async fn run_page_job(
+    state: &AppState,
+    page_id: &str,
+    event_id: Option<&str>,
+    mode: PageMode,
+) -> std::result::Result<PageOutcome, (u64, anyhow::Error)> {
+    let _permit = state
+        .processing_sem
+        .clone()
+        .acquire_owned()
+        .await
+        .map_err(|e| (0, anyhow::Error::from(e)))?;
+    let result = {
+        let _active = state.metrics.active_jobs.track();
+        process_page_inner(state, page_id, event_id, mode).await
+    };
+    result.map_err(|err| {
+        let failure_id = state
+            .failures
+            .record(page_id, event_id, &format!("{:#}", err));
+        error!(
+            "Failed to process page (failure #{}): {:?}",
+            failure_id, err
         );
-        return StatusCode::PAYLOAD_TOO_LARGE;
-    }
+        (failure_id, err)
+    })
+}
 
-    // Enforce content type
-    let content_type_ok = headers
-        .get(header::CONTENT_TYPE)
-        .and_then(|v| v.to_str().ok())
-        .map(|v| v.starts_with("application/json"))
-        == Some(true);
-    if !content_type_ok {
-        warn!(
-            "Rejecting request: unsupported content-type {:?}",
-            headers.get(header::CONTENT_TYPE)
-        );
-        return StatusCode::UNSUPPORTED_MEDIA_TYPE;
+/// Runs a page job requested through the API. Nothing retries it, so a failure is final and
+/// goes to the error webhook.
+async fn run_manual_job(
+    state: &AppState,
+    page_id: &str,
+    event_id: Option<&str>,
+    mode: PageMode,
+) -> std::result::Result<PageOutcome, (u64, anyhow::Error)> {
+    let result = run_page_job(state, page_id, event_id, mode).await;
+    if let Err((_, err)) = &result {
+        notify_failure(state, page_id, err);
     }
+    result
+}
 
-    if !verify_notion_signature(&headers, &body, &state.signing_secret) {
-        // Return 200 to avoid retry amplification; we simply ignore untrusted payloads.
-        warn!("Webhook signature verification failed");
-        return StatusCode::OK;
+/// Posts a page's final failure to the error webhook, if one is configured.
+fn notify_failure(state: &AppState, page_id: &str, err: &anyhow::Error) {
+    if let Some(notifier) = &state.error_notifier {
+        notifier.notify(page_id, None, &format!("{:#}", err));
     }
+}
 
-    let payload: serde_json::Value = match serde_json::from_slice(&body) {
-        Ok(v) => v,
-        Err(e) => {
-            warn!("Rejecting request: invalid JSON body: {}", e);
-            return StatusCode::BAD_REQUEST;
-        }
-    };
-
-    if payload.get("type").and_then(|v| v.as_str()) != Some("page.properties_updated") {
-        warn!("Ignoring event with unsupported type");
-        return StatusCode::OK;
+/// Runs attempt number `attempt` of a page job and queues the next one if it failed with a
+/// transient error (timeouts, 429/5xx). Permanent failures are left in the failure log.
+async fn run_page_job_with_retry(
+    state: &AppState,
+    page_id: &str,
+    event_id: Option<&str>,
+    attempt: u32,
+) -> std::result::Result<PageOutcome, (u64, anyhow::Error)> {
+    let result = run_page_job(state, page_id, event_id, PageMode::Trigger).await;
+    if let Err((failure_id, err)) = &result {
+        queue_retry(state, page_id, event_id, attempt, *failure_id, err);
     }
+    result
+}
 
-    if let Some(event_id) = payload.get("id").and_then(|v| v.as_str()) {
-        if !dedupe_event(&state, event_id).await {
-            return StatusCode::OK;
-        }
+fn queue_retry(
+    state: &AppState,
+    page_id: &str,
+    event_id: Option<&str>,
+    attempt: u32,
+    failure_id: u64,
+    err: &anyhow::Error,
+) {
+    if classify(err) == FailureKind::Permanent {
+        debug!("Failure #{} is permanent; not retrying", failure_id);
+        notify_failure(state, page_id, err);
+        return;
     }
-
-    let updated_raw = payload
-        .get("data")
-        .and_then(|d| d.get("updated_properties"))
-        .and_then(|p| p.as_array())
-        .cloned()
-        .unwrap_or_default();
-
-    let updated_decoded: Vec<String> = updated_raw
-        .iter()
-        .filter_map(|v| v.as_str())
-        .map(|s| match urlencoding::decode(s) {
-            Ok(decoded) => decoded.into_owned(),
-            Err(_) => s.to_string(),
-        })
-        .collect();
-
-    let should_process = updated_raw.iter().any(|v| {
-        v.as_str() == Some("Siv%5D")
-            || updated_decoded.iter().any(|p| {
-                let lower = p.to_lowercase();
-                lower == "title" || lower == "season"
-            })
-    });
-    if !should_process {
-        return StatusCode::OK;
+    let next = attempt + 1;
+    let max = state.retry.max_attempts();
+    if next > max {
+        warn!(
+            "Giving up on page {} after {} attempts (failure #{})",
+            page_id, attempt, failure_id
+        );
+        notify_failure(state, page_id, err);
+    } else if state.retry.enqueue(page_id, event_id, next) {
+        info!(
+            "Retrying page {} in {:?} (attempt {}/{}, retry queue depth {})",
+            page_id,
+            state.retry.backoff(next),
+            next,
+            max,
+            state.retry.depth()
+        );
+    } else {
+        debug!("Page {} is already queued for a retry", page_id);
     }
+}
 
-    let page_id = match payload
-        .get("entity")
-        .and_then(|e| e.get("id"))
-        .and_then(|v| v.as_str())
-    {
-        Some(id) => id.to_string(),
-        None => return StatusCode::BAD_REQUEST,
+/// Starts the task that waits out each queued retry's backoff and re-runs it. Call once per
+/// state; later calls are no-ops.
+pub fn spawn_retry_worker(state: &AppState) {
+    let Some(mut rx) = state.retry.take_receiver() else {
+        return;
     };
-
-    let event_id = payload
-        .get("id")
-        .and_then(|v| v.as_str())
-        .map(|s| s.to_string());
-
-    debug!(
-        page_id = %page_id,
-        event_id = ?event_id,
-        "Webhook accepted; queued page check"
-    );
-
-    let state_for_task = state.clone();
-    let page_id_for_task = page_id.clone();
+    let state = state.clone();
     tokio::spawn(async move {
-        let _permit = match state_for_task.processing_sem.clone().acquire_owned().await {
-            Ok(p) => p,
-            Err(_) => return,
-        };
-
-        if let Err(err) = process_page(&state_for_task, &page_id_for_task, event_id.as_deref())
-            .await
-            .map(|_| ())
-        {
-            error!("Failed to process page: {:?}", err);
+        while let Some(job) = rx.recv().await {
+            let state = state.clone();
+            tokio::spawn(async move {
+                tokio::time::sleep(state.retry.backoff(job.attempt)).await;
+                if state.jobs.is_closed() {
+                    return;
+                }
+                let _job = state.jobs.token();
+                state.retry.start(&job.page_id);
+                // Like a webhook job, so a retry never races a fresh job for the same page.
+                let Some(_in_flight) = enter_page(&state, &job.page_id).await else {
+                    debug!(page_id = %job.page_id, "Another job covers this retry");
+                    return;
+                };
+                let _ = run_page_job_with_retry(
+                    &state,
+                    &job.page_id,
+                    job.event_id.as_deref(),
+                    job.attempt,
+                )
+                .await;
+            });
         }
     });
+}
+
+pub async fn process_page_backfill_tv(
+    state: &AppState,
+    page_id: &str,
+    options: &BackfillOptions,
+) -> Result<PageOutcome> {
+    let mode = PageMode::Backfill {
+        kind: BackfillKind::Tv,
+        only_incomplete: options.only_incomplete,
+    };
+    backfill_page(state, page_id, mode).await
+}
 
-    StatusCode::OK
+pub async fn process_page_backfill_movie(
+    state: &AppState,
+    page_id: &str,
+    options: &BackfillOptions,
+) -> Result<PageOutcome> {
+    let mode = PageMode::Backfill {
+        kind: BackfillKind::Movie,
+        only_incomplete: options.only_incomplete,
+    };
+    backfill_page(state, page_id, mode).await
 }
 
-pub async fn process_page_backfill_tv(state: &AppState, page_id: &str) -> Result<bool> {
-    process_page_inner(state, page_id, None, false).await
+/// A backfill doesn't retry pages, so a failure is final.
+async fn backfill_page(state: &AppState, page_id: &str, mode: PageMode) -> Result<PageOutcome> {
+    let result = process_page_inner(state, page_id, None, mode).await;
+    if let Err(err) = &result {
+        notify_failure(state, page_id, err);
+    }
+    result
 }
 
-async fn process_page(state: &AppState, page_id: &str, event_id: Option<&str>) -> Result<bool> {
-    process_page_inner(state, page_id, event_id, true).await
+/// Re-enriches an AniList page (see `backfill::target_of`) from AniList, using its stored
+/// AniList id when it has one.
+pub async fn process_page_backfill_anilist(
+    state: &AppState,
+    page_id: &str,
+    options: &BackfillOptions,
+) -> Result<PageOutcome> {
+    let mode = PageMode::Backfill {
+        kind: BackfillKind::Anime,
+        only_incomplete: options.only_incomplete,
+    };
+    backfill_page(state, page_id, mode).await
 }
 
 async fn process_page_inner(
     state: &AppState,
     page_id: &str,
     event_id: Option<&str>,
-    require_semicolon: bool,
-) -> Result<bool> {
-    let page = state.notion.fetch_page(page_id).await?;
+    mode: PageMode,
+) -> Result<PageOutcome> {
+    let started = std::time::Instant::now();
+    let budget = Arc::new(RequestBudget::new(state.config.request_budget));
+    let result = budget::scope(budget.clone(), enrich_page(state, page_id, event_id, mode)).await;
+    state.metrics.page_duration.observe(started.elapsed());
+    if matches!(result, Ok(PageOutcome { updated: true, .. })) {
+        state.metrics.pages_updated.inc();
+    }
+    info!(
+        "Page {} used {}/{} requests ({})",
+        page_id,
+        budget.total(),
+        budget.limit(),
+        budget.summary()
+    );
+    let result = if budget.exceeded() {
+        // Whatever error surfaced is a symptom; report the budget as the cause.
+        Err(BudgetExceeded {
+            limit: budget.limit(),
+        }
+        .into())
+    } else {
+        result
+    };
+    // Failures are alerted by the caller, once it knows nobody will retry them. A backfill's
+    // misses are counted in its status instead.
+    if let (
+        Some(notifier),
+        Ok(PageOutcome {
+            no_match: Some(message),
+            title,
+            ..
+        }),
+    ) = (&state.error_notifier, &result)
+    {
+        if !matches!(mode, PageMode::Backfill { .. }) {
+            notifier.notify(page_id, Some(title), message);
+        }
+    }
+    result
+}
+
+async fn enrich_page(
+    state: &AppState,
+    page_id: &str,
+    event_id: Option<&str>,
+    mode: PageMode,
+) -> Result<PageOutcome> {
+    let page = state
+        .notion
+        .fetch_page(page_id)
+        .await
+        .inspect_err(|_| state.metrics.notion_errors.inc())?;
     let props = page
         .get("properties")
         .and_then(|p| p.as_object())
         .ok_or_else(|| anyhow::anyhow!("Page has no properties"))?;
 
     // Enrich schema from live page properties (handles cases where DB schema is unavailable).
-    let mut schema = (*state.schema).clone();
+    let mut schema = (*state.schema.current()).clone();
     notion::merge_schema_from_props(&mut schema, props);
 
     let raw_title = notion::extract_title(props, &state.title_property).unwrap_or_default();
 
-    enum TriggerKind {
-        Tmdb,
-        AniList,
-    }
-
-    let (trigger_kind, clean_title) = if require_semicolon {
-        let (kind, trimmed) = if raw_title.ends_with(';') {
-            (TriggerKind::Tmdb, raw_title.trim_end_matches(';'))
-        } else if raw_title.ends_with('=') {
-            (TriggerKind::AniList, raw_title.trim_end_matches('='))
-        } else {
-            return Ok(false);
-        };
-        info!("Received trigger for page '{}'", raw_title);
-        (kind, trimmed.trim().to_string())
-    } else {
-        if raw_title.trim().is_empty() || raw_title.ends_with(';') {
-            return Ok(false);
-        }
-        info!("Backfill updating page '{}'", raw_title);
-        (TriggerKind::Tmdb, raw_title.trim().to_string())
-    };
-
     let type_value = notion::extract_select(props, "Type");
     let is_tv = type_value
         .as_deref()
         .map(|t| t.to_lowercase().contains("tv"))
         .unwrap_or(false);
 
-    let season_str = notion::extract_select(props, "Season")
-        .or_else(|| notion::extract_rich_text(props, "Season"));
-    let season_number_parsed = season_str.as_deref().and_then(tmdb::parse_season_number);
+    let (trigger_kind, clean_title) = match mode {
+        PageMode::Trigger => {
+            let Some((kind, trimmed, forced)) = state.triggers.match_title_forced(&raw_title)
+            else {
+                return Ok(PageOutcome::skipped(raw_title));
+            };
+            if !forced && already_enriched(props, kind, &state.triggers) {
+                info!(
+                    "Skipping page '{}': it already has an ID (type the trigger twice to re-enrich)",
+                    raw_title
+                );
+                return Ok(PageOutcome::skipped(raw_title));
+            }
+            info!("Received trigger for page '{}'", raw_title);
+            (kind, trimmed)
+        }
+        PageMode::Backfill {
+            kind,
+            only_incomplete,
+        } => {
+            let target = backfill::target_of(props, &state.triggers);
+            let trigger = match (kind, target) {
+                (BackfillKind::Tv, Some(BackfillTarget::Tv))
+                | (BackfillKind::Movie, Some(BackfillTarget::Movie)) => Trigger::Tmdb,
+                (BackfillKind::Anime, Some(BackfillTarget::AniList(media_type))) => {
+                    Trigger::AniList(media_type)
+                }
+                _ => return Ok(PageOutcome::skipped(raw_title)),
+            };
+            if raw_title.trim().is_empty()
+                || state.triggers.is_tmdb_armed(&raw_title)
+                || target.is_some_and(|t| only_incomplete && !backfill::is_incomplete(props, t))
+            {
+                return Ok(PageOutcome::skipped(raw_title));
+            }
+            info!("Backfill updating page '{}'", raw_title);
+            (trigger, raw_title.trim().to_string())
+        }
+        PageMode::Manual(source) => {
+            let (armed, query) = match state.triggers.match_title_forced(&raw_title) {
+                Some((kind, trimmed, _)) => (Some(kind), trimmed),
+                None => (None, raw_title.trim().to_string()),
+            };
+            if query.is_empty() {
+                return Ok(PageOutcome::skipped(raw_title));
+            }
+            let kind = match (source, armed) {
+                (EnrichSource::Tmdb, _) => Trigger::Tmdb,
+                (EnrichSource::AniList, Some(Trigger::AniList(media_type))) => {
+                    Trigger::AniList(media_type)
+                }
+                (EnrichSource::AniList, _) => Trigger::AniList(AniListMediaType::Anime),
+                (EnrichSource::Auto, armed) => armed.unwrap_or(Trigger::Tmdb),
+            };
+            info!("Manual enrichment for page '{}' ({:?})", raw_title, kind);
+            (kind, query)
+        }
+    };
+
+    // An explicit id ("tmdb:603", "mal:5114") or a pasted TMDB/IMDb/AniList URL picks its
+    // provider, whichever trigger armed the page.
+    let media_url = parse_media_url(&clean_title);
+    let trigger_kind = match (trigger_kind, TitleId::parse(&clean_title), &media_url) {
+        (Trigger::AniList(_), Some(id), _) if id.is_tmdb() => Trigger::Tmdb,
+        (Trigger::AniList(_), _, Some(url)) if url.is_tmdb() => Trigger::Tmdb,
+        (Trigger::Tmdb, Some(id), _) if !id.is_tmdb() => Trigger::AniList(AniListMediaType::Anime),
+        (Trigger::Tmdb, _, Some(MediaUrl::AniList(media_type, _))) => Trigger::AniList(*media_type),
+        (kind, _, _) => kind,
+    };
+
+    let mut season_number_parsed = page_season(props);
 
-    if matches!(trigger_kind, TriggerKind::AniList) {
+    if let Trigger::AniList(media_type) = trigger_kind {
+        // A backfilled page keeps the AniList entry it was matched to before.
+        let stored_id = match mode {
+            PageMode::Backfill { .. } => backfill::stored_anilist_id(props),
+            _ => None,
+        };
         return process_anilist_page(
             state,
             page_id,
+            &page,
             event_id,
+            mode,
+            media_type,
             raw_title,
             &clean_title,
             season_number_parsed,
+            stored_id,
             &schema,
         )
         .await;
     }
 
     let imdb_hint = tmdb::parse_imdb_id(&clean_title);
+    // "Dune (2021)" searches for "Dune" released in 2021; without such a hint, the year
+    // already on the page (if any) narrows the search.
+    let (tmdb_query, year_hint) = match tmdb::split_year_hint(&clean_title) {
+        Some((query, year)) => (query, Some(year)),
+        None => (clean_title.clone(), page_year(props)),
+    };
+    let lookup_key = match year_hint {
+        Some(year) => format!("{tmdb_query} ({year})"),
+        None => tmdb_query.clone(),
+    };
     let mut resolved_id: Option<i32> = None;
     let mut forced_tv = is_tv;
+    // A TMDB URL says whether it is a movie or a show (and maybe the season) better than the
+    // page's Type and Season.
+    match media_url {
+        Some(MediaUrl::TmdbMovie(id)) => {
+            resolved_id = Some(id);
+            forced_tv = false;
+        }
+        Some(MediaUrl::TmdbTv { id, season }) => {
+            resolved_id = Some(id);
+            forced_tv = true;
+            season_number_parsed = season.or(season_number_parsed);
+        }
+        _ => {}
+    }
+
+    // A TMDB-armed page whose Type is an anime value goes to AniList, unless the title is a
+    // TMDB/IMDb id. Without an AniList match it is looked up as a TMDB show instead.
+    let type_routed = matches!(
+        mode,
+        PageMode::Trigger | PageMode::Manual(EnrichSource::Auto)
+    ) && type_value
+        .as_deref()
+        .is_some_and(|t| state.triggers.is_anime_type(t))
+        && imdb_hint.is_none()
+        && resolved_id.is_none()
+        && tmdb::parse_tmdb_id(&clean_title).is_none();
+    if type_routed {
+        let resolved = resolve_id(
+            state,
+            mode,
+            MediaKind::Anime,
+            &clean_title,
+            season_number_parsed,
+            state
+                .anilist
+                .resolve_anime_id(&clean_title, season_number_parsed),
+        )
+        .await;
+        match resolved {
+            Ok(id) => {
+                return process_anilist_page(
+                    state,
+                    page_id,
+                    &page,
+                    event_id,
+                    mode,
+                    AniListMediaType::Anime,
+                    raw_title,
+                    &clean_title,
+                    season_number_parsed,
+                    Some(id),
+                    &schema,
+                )
+                .await;
+            }
+            Err(e) => {
+                if classify(&e) == FailureKind::Transient {
+                    state.metrics.anilist_errors.inc();
+                    return Err(e);
+                }
+                warn!(
+                    "No AniList match for anime '{}', trying TMDB TV: {}",
+                    clean_title, e
+                );
+                forced_tv = true;
+                season_number_parsed = season_number_parsed.or(Some(1));
+            }
+        }
+    }
 
     if let Some(imdb) = imdb_hint {
-        let (movie_id, tv_id) = state.tmdb.lookup_imdb(&imdb).await?;
+        let (movie_id, tv_id) = state
+            .tmdb
+            .lookup_imdb(&imdb)
+            .await
+            .inspect_err(|_| state.metrics.tmdb_errors.inc())?;
         if forced_tv {
             if let Some(id) = tv_id {
                 resolved_id = Some(id);
@@ -341,25 +2080,39 @@ async fn process_page_inner(
             Some(s) => s,
             None => {
                 warn!("TV item missing or invalid season, skipping");
-                return Ok(false);
+                return Ok(PageOutcome::skipped(raw_title));
             }
         };
         let show_id = match resolved_id {
             Some(id) => id,
-            None => match state.tmdb.resolve_tv_id(&clean_title).await {
+            None => match resolve_id(
+                state,
+                mode,
+                MediaKind::Tv,
+                &lookup_key,
+                None,
+                state.tmdb.resolve_tv_id_with_year(&tmdb_query, year_hint),
+            )
+            .await
+            {
                 Ok(id) => id,
                 Err(e) => {
+                    state.metrics.tmdb_errors.inc();
+                    // Outages go back to the retry queue; only a real miss marks the page.
+                    if classify(&e) == FailureKind::Transient {
+                        return Err(e);
+                    }
                     warn!("No TMDB match for TV '{}': {}", clean_title, e);
-                    set_error_title(
-                        &state.notion,
+                    return set_error_title(
+                        state,
+                        mode,
                         page_id,
-                        &state.title_property,
                         &schema,
                         raw_title,
+                        &clean_title,
                         "No TMDB TV match",
                     )
-                    .await?;
-                    return Ok(false);
+                    .await;
                 }
             },
         };
@@ -370,39 +2123,58 @@ async fn process_page_inner(
         match state.tmdb.fetch_tv_season(show_id, season).await {
             Ok(data) => data,
             Err(e) => {
+                state.metrics.tmdb_errors.inc();
+                if classify(&e) == FailureKind::Transient {
+                    return Err(e);
+                }
                 warn!(
                     "Failed to fetch TMDB TV season for '{}': {}",
                     clean_title, e
                 );
-                set_error_title(
-                    &state.notion,
+                return set_error_title(
+                    state,
+                    mode,
                     page_id,
-                    &state.title_property,
                     &schema,
                     raw_title,
+                    &clean_title,
                     "No TMDB TV match",
                 )
-                .await?;
-                return Ok(false);
+                .await;
             }
         }
     } else {
         let movie_id = match resolved_id {
             Some(id) => id,
-            None => match state.tmdb.resolve_movie_id(&clean_title).await {
+            None => match resolve_id(
+                state,
+                mode,
+                MediaKind::Movie,
+                &lookup_key,
+                None,
+                state
+                    .tmdb
+                    .resolve_movie_id_with_year(&tmdb_query, year_hint),
+            )
+            .await
+            {
                 Ok(id) => id,
                 Err(e) => {
+                    state.metrics.tmdb_errors.inc();
+                    if classify(&e) == FailureKind::Transient {
+                        return Err(e);
+                    }
                     warn!("No TMDB match for Movie '{}': {}", clean_title, e);
-                    set_error_title(
-                        &state.notion,
+                    return set_error_title(
+                        state,
+                        mode,
                         page_id,
-                        &state.title_property,
                         &schema,
                         raw_title,
+                        &clean_title,
                         "No TMDB movie match",
                     )
-                    .await?;
-                    return Ok(false);
+                    .await;
                 }
             },
         };
@@ -413,17 +2185,21 @@ async fn process_page_inner(
         match state.tmdb.fetch_movie(movie_id).await {
             Ok(data) => data,
             Err(e) => {
+                state.metrics.tmdb_errors.inc();
+                if classify(&e) == FailureKind::Transient {
+                    return Err(e);
+                }
                 warn!("Failed to fetch TMDB movie for '{}': {}", clean_title, e);
-                set_error_title(
-                    &state.notion,
+                return set_error_title(
+                    state,
+                    mode,
                     page_id,
-                    &state.title_property,
                     &schema,
                     raw_title,
+                    &clean_title,
                     "No TMDB movie match",
                 )
-                .await?;
-                return Ok(false);
+                .await;
             }
         }
     };
@@ -466,6 +2242,7 @@ async fn process_page_inner(
             }
         }
     }
+    let synopsis = tmdb_media.synopsis.clone();
     notion::set_value(
         &mut updates,
         "Synopsis",
@@ -475,7 +2252,9 @@ async fn process_page_inner(
     notion::set_value(
         &mut updates,
         "Genre",
-        Some(notion::ValueInput::StringList(tmdb_media.genres)),
+        Some(notion::ValueInput::StringList(normalize_genres(
+            tmdb_media.genres,
+        ))),
         &schema,
     );
     notion::set_value(
@@ -493,7 +2272,10 @@ async fn process_page_inner(
     notion::set_value(
         &mut updates,
         "Content Rating",
-        tmdb_media.content_rating.map(notion::ValueInput::Text),
+        or_clear(
+            state,
+            tmdb_media.content_rating.map(notion::ValueInput::Text),
+        ),
         &schema,
     );
     notion::set_value(
@@ -505,27 +2287,81 @@ async fn process_page_inner(
     notion::set_value(
         &mut updates,
         "Language",
-        tmdb_media.language.map(notion::ValueInput::Text),
+        or_clear(state, tmdb_media.language.map(notion::ValueInput::Text)),
         &schema,
     );
+    if !forced_tv && schema.has("Collection") {
+        notion::set_value(
+            &mut updates,
+            "Collection",
+            or_clear(state, tmdb_media.collection.map(notion::ValueInput::Text)),
+            &schema,
+        );
+    }
+    if forced_tv && schema.has("Network") {
+        notion::set_value(
+            &mut updates,
+            "Network",
+            Some(notion::ValueInput::StringList(tmdb_media.networks)),
+            &schema,
+        );
+    }
+    if state.config.max_keywords > 0 && schema.has("Keywords") {
+        let mut keywords = tmdb_media.keywords;
+        keywords.truncate(state.config.max_keywords);
+        notion::set_value(
+            &mut updates,
+            "Keywords",
+            Some(notion::ValueInput::StringList(keywords)),
+            &schema,
+        );
+    }
+    // No data for the watch region leaves both properties as they are.
+    if schema.has("Available on") && !tmdb_media.watch_providers.is_empty() {
+        notion::set_value(
+            &mut updates,
+            "Available on",
+            Some(notion::ValueInput::StringList(tmdb_media.watch_providers)),
+            &schema,
+        );
+    }
+    if schema.has("Where to Watch") {
+        notion::set_value(
+            &mut updates,
+            "Where to Watch",
+            tmdb_media.watch_link.map(notion::ValueInput::Url),
+            &schema,
+        );
+    }
+    if schema.has("Languages") && !tmdb_media.spoken_languages.is_empty() {
+        notion::set_value(
+            &mut updates,
+            "Languages",
+            Some(notion::ValueInput::StringList(tmdb_media.spoken_languages)),
+            &schema,
+        );
+    }
     notion::set_value(
         &mut updates,
         "Release Date",
-        tmdb_media.release_date.map(notion::ValueInput::Date),
+        or_clear(state, tmdb_media.release_date.map(notion::ValueInput::Date)),
         &schema,
     );
     notion::set_value(
         &mut updates,
         "Year",
-        tmdb_media.year.map(notion::ValueInput::Text),
+        or_clear(state, tmdb_media.year.map(notion::ValueInput::Text)),
         &schema,
     );
     notion::set_value(
         &mut updates,
         "Runtime",
-        tmdb_media
-            .runtime_minutes
-            .map(|r| notion::ValueInput::Number(r as f64)),
+        or_clear(
+            state,
+            tmdb_media
+                .runtime_minutes
+                .map(|r| notion::ValueInput::Number(r as f64)),
+        ),
         &schema,
     );
     if let Some(episodes) = tmdb_media.episodes {
@@ -539,19 +2375,62 @@ async fn process_page_inner(
     notion::set_value(
         &mut updates,
         "Trailer",
-        tmdb_media.trailer.map(notion::ValueInput::Url),
+        or_clear(state, tmdb_media.trailer.map(notion::ValueInput::Url)),
         &schema,
     );
     notion::set_value(
         &mut updates,
         "IMG",
-        tmdb_media.poster.clone().map(notion::ValueInput::Url),
+        or_clear(
+            state,
+            tmdb_media.poster.clone().map(notion::ValueInput::Url),
+        ),
         &schema,
     );
+    if schema.has("Score") {
+        notion::set_value(
+            &mut updates,
+            "Score",
+            or_clear(
+                state,
+                tmdb_media.vote_average.map(notion::ValueInput::Number),
+            ),
+            &schema,
+        );
+    }
+    // A rating nobody voted for says nothing, so both are left alone without votes.
+    if let Some(votes) = tmdb_media.vote_count {
+        if schema.has("TMDB Rating") {
+            notion::set_value(
+                &mut updates,
+                "TMDB Rating",
+                tmdb_media
+                    .vote_average
+                    .map(|v| notion::ValueInput::Number(one_decimal(v))),
+                &schema,
+            );
+        }
+        if schema.has("TMDB Votes") {
+            notion::set_value(
+                &mut updates,
+                "TMDB Votes",
+                Some(notion::ValueInput::Number(f64::from(votes))),
+                &schema,
+            );
+        }
+    }
+    if schema.has("Gallery") && !tmdb_media.gallery.is_empty() {
+        notion::set_value(
+            &mut updates,
+            "Gallery",
+            Some(notion::ValueInput::StringList(tmdb_media.gallery.clone())),
+            &schema,
+        );
+    }
     notion::set_value(
         &mut updates,
         "IMDb Page",
-        tmdb_media.imdb_page.map(notion::ValueInput::Url),
+        or_clear(state, tmdb_media.imdb_page.map(notion::ValueInput::Url)),
         &schema,
     );
     notion::set_value(
@@ -560,14 +2439,13 @@ async fn process_page_inner(
         Some(notion::ValueInput::Number(tmdb_media.id as f64)),
         &schema,
     );
+    set_sync_stamp(&mut updates, "TMDB", &schema);
 
     // Prepare icon/cover using poster/backdrop if available.
-    let icon = tmdb_media.poster.as_ref().map(|url| {
-        json!({
-            "type": "external",
-            "external": { "url": url }
-        })
-    });
+    let icon = icon_payload(
+        state.config.movie_icon_emoji.as_deref(),
+        tmdb_media.poster.as_deref(),
+    );
     let cover = tmdb_media.backdrop.as_ref().map(|url| {
         json!({
             "type": "external",
@@ -576,82 +2454,183 @@ async fn process_page_inner(
     });
 
     info!("Updating Notion page '{}'", tmdb_media.name);
-    state
-        .notion
-        .update_page(page_id, updates, icon, cover)
-        .await?;
+    write_page(state, page_id, &page, updates, icon, cover).await?;
+    write_synopsis_body(state, page_id, synopsis.as_deref()).await;
     info!(
         "Finished update for page '{}' -> '{}'",
         raw_title, tmdb_media.name
     );
-    Ok(true)
+    let media_key = if forced_tv {
+        MediaKey {
+            kind: MediaKind::Tv,
+            id: tmdb_media.id,
+            season: season_number_parsed,
+        }
+    } else {
+        MediaKey {
+            kind: MediaKind::Movie,
+            id: tmdb_media.id,
+            season: None,
+        }
+    };
+    flag_duplicates(state, page_id, &media_key, &tmdb_media.name).await;
+    Ok(PageOutcome::updated(tmdb_media.name, media_key))
+}
+
+/// Runs `lookup` unless `query` matched nothing for `kind` within the negative-cache TTL, in
+/// which case it fails straight away. Manual runs (`/enrich`, forced `/admin/process`) always
+/// look again.
+async fn resolve_id(
+    state: &AppState,
+    mode: PageMode,
+    kind: MediaKind,
+    query: &str,
+    season: Option<i32>,
+    lookup: impl std::future::Future<Output = Result<i32>>,
+) -> Result<i32> {
+    let cache = &state.negative_lookups;
+    if !matches!(mode, PageMode::Manual(_)) && cache.contains(kind, query, season).await {
+        anyhow::bail!("'{}' matched nothing recently (cached)", query);
+    }
+    let result = lookup.await;
+    match &result {
+        Ok(_) => cache.forget(kind, query, season).await,
+        Err(e) => cache.record_failure(kind, query, season, e).await,
+    }
+    result
+}
+
+/// The season number in `Season`, whether it is a select, text or number property.
+pub fn page_season(props: &serde_json::Map<String, serde_json::Value>) -> Option<i32> {
+    let season = notion::extract_select(props, "Season")
+        .or_else(|| notion::extract_rich_text(props, "Season"))
+        .or_else(|| notion::extract_number(props, "Season").map(|n| (n as i32).to_string()))?;
+    tmdb::parse_season_number(&season)
 }
 
+/// The year a page already carries, from `Year` or else `Release Date`.
+fn page_year(props: &serde_json::Map<String, serde_json::Value>) -> Option<i32> {
+    let year = notion::extract_rich_text(props, "Year")
+        .or_else(|| notion::extract_select(props, "Year"))
+        .or_else(|| notion::extract_number(props, "Year").map(|n| (n as i32).to_string()))
+        .or_else(|| notion::extract_date(props, "Release Date"))?;
+    year.trim()
+        .get(..4)
+        .and_then(|y| y.parse().ok())
+        .filter(|y| (1870..=2100).contains(y))
+}
+
+/// The page icon: `emoji` when configured, otherwise the poster as an external image. `None`
+/// leaves the page's icon as it is.
+fn icon_payload(emoji: Option<&str>, poster: Option<&str>) -> Option<serde_json::Value> {
+    match (emoji, poster) {
+        (Some(emoji), _) => Some(json!({ "type": "emoji", "emoji": emoji })),
+        (None, Some(url)) => Some(json!({
+            "type": "external",
+            "external": { "url": url }
+        })),
+        (None, None) => None,
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
 async fn process_anilist_page(
     state: &AppState,
     page_id: &str,
+    page: &serde_json::Value,
     event_id: Option<&str>,
+    mode: PageMode,
+    media_type: AniListMediaType,
     raw_title: String,
     query: &str,
     season: Option<i32>,
+    resolved_id: Option<i32>,
     schema: &notion::PropertySchema,
-) -> Result<bool> {
-    let anime_id = match state.anilist.resolve_anime_id(query, season).await {
+) -> Result<PageOutcome> {
+    let is_manga = matches!(media_type, AniListMediaType::Manga);
+    let resolved = if let Some(id) = resolved_id {
+        Ok(id)
+    } else if is_manga {
+        // Manga have no seasons; a Season value left on the page must not walk the sequel chain.
+        let lookup = state.anilist.resolve_manga_id(query, None);
+        resolve_id(state, mode, MediaKind::Manga, query, None, lookup).await
+    } else {
+        let lookup = state.anilist.resolve_anime_id(query, season);
+        resolve_id(state, mode, MediaKind::Anime, query, season, lookup).await
+    };
+    let media_id = match resolved {
         Ok(id) => id,
         Err(e) => {
-            warn!("No AniList match for Anime '{}': {}", query, e);
-            set_error_title(
-                &state.notion,
+            state.metrics.anilist_errors.inc();
+            if classify(&e) == FailureKind::Transient {
+                return Err(e);
+            }
+            warn!("No AniList match for {:?} '{}': {}", media_type, query, e);
+            return set_error_title(
+                state,
+                mode,
                 page_id,
-                &state.title_property,
                 schema,
                 raw_title,
+                query,
                 "No AniList match",
             )
-            .await?;
-            return Ok(false);
+            .await;
         }
     };
 
     debug!(
         page_id = %page_id,
         event_id = ?event_id,
-        anilist_id = anime_id,
+        anilist_id = media_id,
+        media_type = ?media_type,
         season = ?season,
         query = query,
         "AniList resolved id"
     );
     info!(
-        "Fetching AniList data for Anime '{}' (anilist id {})",
-        query, anime_id
+        "Fetching AniList data for {:?} '{}' (anilist id {})",
+        media_type, query, media_id
     );
-    let anime = match state.anilist.fetch_anime(anime_id).await {
+    let fetched = if is_manga {
+        state.anilist.fetch_manga(media_id).await
+    } else {
+        state.anilist.fetch_anime(media_id).await
+    };
+    let media = match fetched {
         Ok(data) => data,
         Err(e) => {
-            warn!("Failed to fetch AniList anime for '{}': {}", query, e);
-            set_error_title(
-                &state.notion,
+            state.metrics.anilist_errors.inc();
+            if classify(&e) == FailureKind::Transient {
+                return Err(e);
+            }
+            warn!(
+                "Failed to fetch AniList {:?} for '{}': {}",
+                media_type, query, e
+            );
+            return set_error_title(
+                state,
+                mode,
                 page_id,
-                &state.title_property,
                 schema,
                 raw_title,
+                query,
                 "No AniList match",
             )
-            .await?;
-            return Ok(false);
+            .await;
         }
     };
 
     let mut updates = serde_json::Map::new();
-    let updated_title = crate::anilist::strip_trailing_season_suffix(&anime.name);
-    let original_title = anime
+    let updated_title = crate::anilist::strip_trailing_season_suffix(&media.name);
+    let original_title = media
         .original_title
         .as_deref()
         .map(crate::anilist::strip_trailing_season_suffix);
 
     notion::set_title(&mut updates, &state.title_property, &updated_title, schema);
 
-    // Explicitly blank Eng Name (anime title is already the "actual" title).
+    // Explicitly blank Eng Name (AniList title is already the "actual" title).
     notion::set_value(
         &mut updates,
         "Eng Name",
@@ -664,39 +2643,51 @@ async fn process_anilist_page(
         original_title.map(notion::ValueInput::Text),
         schema,
     );
+    if schema.has("Native Title") {
+        notion::set_value(
+            &mut updates,
+            "Native Title",
+            media.native_title.clone().map(notion::ValueInput::Text),
+            schema,
+        );
+    }
+    let synopsis = media.synopsis.clone();
     notion::set_value(
         &mut updates,
         "Synopsis",
-        anime.synopsis.map(notion::ValueInput::Text),
+        media.synopsis.map(notion::ValueInput::Text),
         schema,
     );
+    let genres = if is_manga {
+        with_manga_tags(media.genres)
+    } else {
+        with_anime_tags(media.genres)
+    };
     notion::set_value(
         &mut updates,
         "Genre",
-        Some(notion::ValueInput::StringList(with_anime_tags(
-            anime.genres,
-        ))),
+        Some(notion::ValueInput::StringList(normalize_genres(genres))),
         schema,
     );
     notion::set_value(
         &mut updates,
         "Cast",
-        Some(notion::ValueInput::StringList(anime.cast)),
+        Some(notion::ValueInput::StringList(media.cast)),
         schema,
     );
     notion::set_value(
         &mut updates,
         "Director",
-        Some(notion::ValueInput::StringList(anime.director)),
+        Some(notion::ValueInput::StringList(media.director)),
         schema,
     );
     notion::set_value(
         &mut updates,
         "Content Rating",
-        Some(notion::ValueInput::Text(anime.content_rating)),
+        Some(notion::ValueInput::Text(media.content_rating)),
         schema,
     );
-    if let Some(country) = anime.country_of_origin {
+    if let Some(country) = media.country_of_origin {
         notion::set_value(
             &mut updates,
             "Country of origin",
@@ -707,69 +2698,120 @@ async fn process_anilist_page(
     notion::set_value(
         &mut updates,
         "Language",
-        anime.language.map(notion::ValueInput::Text),
+        or_clear(state, media.language.map(notion::ValueInput::Text)),
         schema,
     );
     notion::set_value(
         &mut updates,
         "Release Date",
-        anime.release_date.map(notion::ValueInput::Date),
+        or_clear(state, media.release_date.map(notion::ValueInput::Date)),
         schema,
     );
     notion::set_value(
         &mut updates,
         "Year",
-        anime.year.map(notion::ValueInput::Text),
-        schema,
-    );
-    notion::set_value(
-        &mut updates,
-        "Runtime",
-        anime
-            .runtime_minutes
-            .map(|r| notion::ValueInput::Number(r as f64)),
+        or_clear(state, media.year.map(notion::ValueInput::Text)),
         schema,
     );
-    if let Some(episodes) = anime.episodes {
+    // Manga have no episode count or runtime; leave those properties untouched.
+    if !is_manga {
         notion::set_value(
             &mut updates,
-            "Episodes",
-            Some(notion::ValueInput::Number(episodes as f64)),
+            "Runtime",
+            or_clear(
+                state,
+                media
+                    .runtime_minutes
+                    .map(|r| notion::ValueInput::Number(r as f64)),
+            ),
+            schema,
+        );
+        if let Some(episodes) = media.episodes {
+            notion::set_value(
+                &mut updates,
+                "Episodes",
+                Some(notion::ValueInput::Number(episodes as f64)),
+                schema,
+            );
+        }
+    } else {
+        // Optional manga-only columns; skip them when the database doesn't have them.
+        for (property, value) in [("Chapters", media.chapters), ("Volumes", media.volumes)] {
+            if let Some(value) = value.filter(|_| schema.has(property)) {
+                notion::set_value(
+                    &mut updates,
+                    property,
+                    Some(notion::ValueInput::Number(value as f64)),
+                    schema,
+                );
+            }
+        }
+    }
+    if schema.has("Adult") {
+        notion::set_value(
+            &mut updates,
+            "Adult",
+            Some(notion::ValueInput::Bool(media.is_adult)),
+            schema,
+        );
+    }
+    if schema.has("Score") {
+        notion::set_value(
+            &mut updates,
+            "Score",
+            or_clear(state, media.mean_score.map(notion::ValueInput::Number)),
             schema,
         );
     }
+    // Its own property when the database has one, else the TMDB rating's 0–10 scale.
+    if let Some(score) = media.average_score {
+        if schema.has("AniList Score") {
+            notion::set_value(
+                &mut updates,
+                "AniList Score",
+                Some(notion::ValueInput::Number(score)),
+                schema,
+            );
+        } else if schema.has("TMDB Rating") {
+            notion::set_value(
+                &mut updates,
+                "TMDB Rating",
+                Some(notion::ValueInput::Number(one_decimal(score / 10.0))),
+                schema,
+            );
+        }
+    }
     notion::set_value(
         &mut updates,
         "Trailer",
-        anime.trailer.map(notion::ValueInput::Url),
+        or_clear(state, media.trailer.map(notion::ValueInput::Url)),
         schema,
     );
     notion::set_value(
         &mut updates,
         "IMG",
-        anime.poster.clone().map(notion::ValueInput::Url),
+        or_clear(state, media.poster.clone().map(notion::ValueInput::Url)),
         schema,
     );
     notion::set_value(
         &mut updates,
         "IMDb Page",
-        anime.imdb_page.map(notion::ValueInput::Url),
+        or_clear(state, media.imdb_page.map(notion::ValueInput::Url)),
         schema,
     );
     notion::set_value(
         &mut updates,
         "ID",
-        Some(notion::ValueInput::Number(anime.id as f64)),
+        Some(notion::ValueInput::Number(media.id as f64)),
         schema,
     );
+    set_sync_stamp(&mut updates, "AniList", schema);
 
-    let icon = anime.poster.as_ref().map(|url| {
-        json!({
-            "type": "external",
-            "external": { "url": url }
-        })
-    });
-    let cover = anime.backdrop.as_ref().map(|url| {
+    let icon = icon_payload(
+        state.config.anime_icon_emoji.as_deref(),
+        media.poster.as_deref(),
+    );
+    let cover = media.backdrop.as_ref().map(|url| {
         json!({
             "type": "external",
             "external": { "url": url }
@@ -781,65 +2823,438 @@ async fn process_anilist_page(
         event_id = ?event_id,
         "Updating Notion page from AniList"
     );
-    info!("Updating Notion page from AniList");
-    state
-        .notion
-        .update_page(page_id, updates, icon, cover)
-        .await?;
+    info!("Updating Notion page from AniList ({:?})", media_type);
+    write_page(state, page_id, page, updates, icon, cover).await?;
+    write_synopsis_body(state, page_id, synopsis.as_deref()).await;
     info!(
         "Finished AniList update '{}' -> '{}'",
         raw_title, updated_title
     );
-    Ok(true)
+    let media_key = MediaKey {
+        kind: if is_manga {
+            MediaKind::Manga
+        } else {
+            MediaKind::Anime
+        },
+        id: media.id,
+        season: None,
+    };
+    flag_duplicates(state, page_id, &media_key, &updated_title).await;
+    Ok(PageOutcome::updated(updated_title, media_key))
+}
+
+/// Whether the page already has an `ID` from the provider `trigger` leads to: an AniList
+/// link in "IMDb Page" marks an AniList entry, anything else a TMDB one. A `;` on an anime
+/// `Type` page counts for either, since it may have been routed to AniList.
+fn already_enriched(
+    props: &serde_json::Map<String, serde_json::Value>,
+    trigger: Trigger,
+    triggers: &TriggerConfig,
+) -> bool {
+    if notion::extract_number(props, "ID").is_none() {
+        return false;
+    }
+    let anilist_link = notion::extract_url(props, "IMDb Page")
+        .and_then(|url| anilist::parse_anilist_url(&url))
+        .map(|(media_type, _)| media_type);
+    match trigger {
+        Trigger::Tmdb => {
+            anilist_link.is_none()
+                || (anilist_link == Some(AniListMediaType::Anime)
+                    && notion::extract_select(props, "Type")
+                        .is_some_and(|t| triggers.is_anime_type(&t)))
+        }
+        Trigger::AniList(media_type) => anilist_link == Some(media_type),
+    }
+}
+
+/// Properties that record the enrichment itself; written whatever the overwrite mode.
+const SYNC_PROPERTIES: [&str; 4] = ["Last Synced", "Source", SYNC_STATUS, SYNC_ERROR];
+/// Optional `Select` (or status) set to `OK` or `Error` after each attempt.
+const SYNC_STATUS: &str = "Sync Status";
+/// Optional `Rich text` holding the last failure; cleared on success.
+const SYNC_ERROR: &str = "Sync Error";
+
+/// Stamps `Last Synced` (now, UTC), `Source` and `Sync Status`, and clears `Sync Error`, for
+/// databases that have those properties.
+fn set_sync_stamp(
+    updates: &mut serde_json::Map<String, serde_json::Value>,
+    source: &str,
+    schema: &notion::PropertySchema,
+) {
+    if schema.has("Last Synced") {
+        let now = Utc::now().to_rfc3339_opts(chrono::SecondsFormat::Secs, true);
+        notion::set_value(
+            updates,
+            "Last Synced",
+            Some(notion::ValueInput::Date(now)),
+            schema,
+        );
+    }
+    if schema.has("Source") {
+        notion::set_value(
+            updates,
+            "Source",
+            Some(notion::ValueInput::Text(source.to_string())),
+            schema,
+        );
+    }
+    if schema.has(SYNC_STATUS) {
+        notion::set_value(
+            updates,
+            SYNC_STATUS,
+            Some(notion::ValueInput::Text("OK".to_string())),
+            schema,
+        );
+    }
+    if schema.has(SYNC_ERROR) {
+        updates.insert(SYNC_ERROR.to_string(), json!({ "rich_text": [] }));
+    }
+}
+
+/// `value`, or an explicit `Clear` when the source has nothing, so a value left by an earlier
+/// (wrong) match goes away. Only in `always` mode: the other modes never clear anything.
+fn or_clear(state: &AppState, value: Option<notion::ValueInput>) -> Option<notion::ValueInput> {
+    match state.config.overwrite_mode {
+        OverwriteMode::Always => Some(value.unwrap_or(notion::ValueInput::Clear)),
+        OverwriteMode::FillEmpty | OverwriteMode::Never => value,
+    }
+}
+
+/// Ratings are written to one decimal, like the sources display them.
+fn one_decimal(rating: f64) -> f64 {
+    (rating * 10.0).round() / 10.0
+}
+
+/// Sends the enrichment to Notion, minus whatever `overwrite_mode` says to keep from `page`;
+/// in dry-run mode only logs what would be sent.
+async fn write_page(
+    state: &AppState,
+    page_id: &str,
+    page: &serde_json::Value,
+    mut updates: serde_json::Map<String, serde_json::Value>,
+    mut icon: Option<serde_json::Value>,
+    mut cover: Option<serde_json::Value>,
+) -> Result<()> {
+    let mode = state.config.overwrite_mode;
+    if mode != OverwriteMode::Always {
+        let empty = serde_json::Map::new();
+        let props = page
+            .get("properties")
+            .and_then(|p| p.as_object())
+            .unwrap_or(&empty);
+        let kept: Vec<String> = updates
+            .keys()
+            .filter(|name| {
+                **name != state.title_property
+                    && !SYNC_PROPERTIES.contains(&name.as_str())
+                    && (name.as_str() != "ID" || mode == OverwriteMode::Never)
+                    && notion::has_value(props, name)
+            })
+            .cloned()
+            .collect();
+        for name in &kept {
+            updates.remove(name);
+        }
+        if !kept.is_empty() {
+            debug!("Keeping existing values of {:?} on page {}", kept, page_id);
+        }
+        if page.get("icon").is_some_and(|v| !v.is_null()) {
+            icon = None;
+        }
+        if page.get("cover").is_some_and(|v| !v.is_null()) {
+            cover = None;
+        }
+    }
+    if state.config.dry_run {
+        info!(
+            "DRY RUN: would update page {} with {} properties",
+            page_id,
+            updates.len()
+        );
+        let body = json!({ "properties": updates, "icon": icon, "cover": cover });
+        info!(
+            "{}",
+            serde_json::to_string_pretty(&body).unwrap_or_default()
+        );
+        return Ok(());
+    }
+    state
+        .notion
+        .update_page(page_id, updates, icon, cover)
+        .await
+        .inspect_err(|_| state.metrics.notion_errors.inc())
+}
+
+/// With `synopsis_as_body`, replaces the page body with the synopsis as one paragraph.
+/// Failures are logged only; the properties were already written.
+async fn write_synopsis_body(state: &AppState, page_id: &str, synopsis: Option<&str>) {
+    if !state.config.synopsis_as_body {
+        return;
+    }
+    let Some(synopsis) = synopsis.map(str::trim).filter(|s| !s.is_empty()) else {
+        return;
+    };
+    if state.config.dry_run {
+        info!(
+            "DRY RUN: would replace the body of page {} with its synopsis",
+            page_id
+        );
+        return;
+    }
+    let written = async {
+        state.notion.clear_blocks(page_id).await?;
+        state
+            .notion
+            .append_blocks(page_id, vec![notion::paragraph_block(synopsis)])
+            .await
+    }
+    .await;
+    if let Err(e) = written {
+        state.metrics.notion_errors.inc();
+        warn!(
+            "Failed to write the synopsis body of page {}: {:#}",
+            page_id, e
+        );
+    }
+}
+
+/// Leaves a comment on the page when another page already carries the same provider id.
+/// Failures are logged only; the enrichment itself already succeeded.
+async fn flag_duplicates(state: &AppState, page_id: &str, key: &MediaKey, title: &str) {
+    let found = state
+        .duplicates
+        .find_and_record(
+            state.notion.as_ref(),
+            &state.title_property,
+            key,
+            page_id,
+            title,
+        )
+        .await;
+    match found {
+        Ok(others) if !others.is_empty() => {
+            let comment = duplicate_comment(&others);
+            warn!("Page '{}' looks like a duplicate: {}", title, comment);
+            if state.config.dry_run {
+                info!("DRY RUN: would comment on page {}: {}", page_id, comment);
+            } else if let Err(e) = state.notion.add_comment(page_id, &comment).await {
+                warn!("Failed to add duplicate comment to '{}': {}", title, e);
+            }
+        }
+        Ok(_) => {}
+        Err(e) => warn!("Duplicate check failed for '{}': {}", title, e),
+    }
 }
 
-fn with_anime_tags(mut genres: Vec<String>) -> Vec<String> {
+fn with_anime_tags(genres: Vec<String>) -> Vec<String> {
     // Always tag AniList-sourced pages so they're easy to filter in Notion.
-    let tags = ["Anime", "Animation"];
+    with_leading_tags(genres, &["Anime", "Animation"])
+}
+
+fn with_manga_tags(genres: Vec<String>) -> Vec<String> {
+    with_leading_tags(genres, &["Manga"])
+}
+
+fn with_leading_tags(mut genres: Vec<String>, tags: &[&str]) -> Vec<String> {
     let tag_lc: std::collections::HashSet<String> = tags.iter().map(|t| t.to_lowercase()).collect();
     genres.retain(|g| !tag_lc.contains(&g.to_lowercase()));
-    for tag in tags.into_iter().rev() {
+    for tag in tags.iter().rev() {
         genres.insert(0, tag.to_string());
     }
     genres
 }
 
 async fn set_error_title(
-    notion: &Arc<dyn NotionApi>,
+    state: &AppState,
+    mode: PageMode,
     page_id: &str,
-    title_property: &str,
     schema: &notion::PropertySchema,
     original_title: String,
+    clean_title: &str,
     message: &str,
-) -> Result<()> {
+) -> Result<PageOutcome> {
+    state.metrics.pages_no_match.inc();
+    if matches!(mode, PageMode::Backfill { .. }) {
+        // A bulk run shouldn't mark hundreds of titles; it counts the miss instead.
+        info!("Backfill skipping page {}: {}", page_id, message);
+        return Ok(PageOutcome {
+            no_match: Some(message.to_string()),
+            ..PageOutcome::skipped(original_title)
+        });
+    }
+    if state.config.dry_run {
+        info!(
+            "DRY RUN: would mark page {} as failed: {}",
+            page_id, message
+        );
+        return Ok(PageOutcome {
+            no_match: Some(message.to_string()),
+            ..PageOutcome::skipped(original_title)
+        });
+    }
     let mut props = serde_json::Map::new();
-    let new_title = format!("{} | {}", original_title, message);
-    notion::set_title(&mut props, title_property, &new_title, schema);
-    notion
+    let new_title = if schema.has(SYNC_STATUS) || schema.has(SYNC_ERROR) {
+        // The error goes to its own properties; the title only loses its trigger.
+        if schema.has(SYNC_STATUS) {
+            notion::set_value(
+                &mut props,
+                SYNC_STATUS,
+                Some(notion::ValueInput::Text("Error".to_string())),
+                schema,
+            );
+        }
+        if schema.has(SYNC_ERROR) {
+            notion::set_value(
+                &mut props,
+                SYNC_ERROR,
+                Some(notion::ValueInput::Text(message.to_string())),
+                schema,
+            );
+        }
+        clean_title.to_string()
+    } else {
+        error_title(&original_title, message)
+    };
+    notion::set_title(&mut props, &state.title_property, &new_title, schema);
+    state
+        .notion
         .update_page(page_id, props, None, None)
         .await
-        .map_err(|e| anyhow::anyhow!("Failed to set error title: {}", e))
+        .map_err(|e| {
+            state.metrics.notion_errors.inc();
+            anyhow::anyhow!("Failed to set error title: {}", e)
+        })?;
+    Ok(PageOutcome {
+        no_match: Some(message.to_string()),
+        ..PageOutcome::skipped(new_title)
+    })
+}
+
+/// `title | message`, dropping the same suffix left by an earlier failure so it isn't repeated.
+fn error_title(title: &str, message: &str) -> String {
+    let suffix = format!(" | {}", message);
+    format!("{}{}", title.replace(&suffix, "").trim_end(), suffix)
+}
+
+/// The token from a subscription verification request (`{"verification_token": "..."}`);
+/// `None` for regular events, which always carry a `type`.
+fn verification_token(payload: &serde_json::Value) -> Option<&str> {
+    if payload.get("type").is_some() {
+        return None;
+    }
+    payload
+        .get("verification_token")
+        .and_then(|v| v.as_str())
+        .filter(|t| !t.is_empty())
+}
+
+/// Refuses a webhook secret in `key` that is too weak to sign with: shorter than
+/// `MIN_SIGNING_SECRET_BYTES`, or not printable ASCII. An empty secret (first start, before
+/// Notion's verification token is known) and `insecure-disabled` are allowed.
+pub fn validate_signing_secret(key: &str, secret: &str) -> Result<()> {
+    if secret.is_empty() || secret == INSECURE_DISABLED_SECRET {
+        return Ok(());
+    }
+    check_secret_strength(key, secret)
+}
+
+/// `validate_signing_secret` for the secondary secret, which only ever verifies signatures:
+/// it may be unset, but `insecure-disabled` there would be a publicly known HMAC key.
+pub fn validate_secondary_signing_secret(key: &str, secret: &str) -> Result<()> {
+    if secret.is_empty() {
+        return Ok(());
+    }
+    if secret == INSECURE_DISABLED_SECRET {
+        anyhow::bail!("{key} can't be {INSECURE_DISABLED_SECRET}; unset it instead");
+    }
+    check_secret_strength(key, secret)
+}
+
+fn check_secret_strength(key: &str, secret: &str) -> Result<()> {
+    if !secret.bytes().all(|b| b.is_ascii_graphic()) {
+        anyhow::bail!("{key} must be printable ASCII without spaces");
+    }
+    if secret.len() < MIN_SIGNING_SECRET_BYTES {
+        anyhow::bail!(
+            "{key} is too short ({} bytes, at least {} required)",
+            secret.len(),
+            MIN_SIGNING_SECRET_BYTES
+        );
+    }
+    Ok(())
+}
+
+/// Which webhook secret a verified signature was made with.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum SigningSecret {
+    Primary,
+    Secondary,
 }
 
-fn verify_notion_signature(headers: &HeaderMap, body: &[u8], secret: &str) -> bool {
-    let Some(sig_header) = headers
+/// Why a webhook signature was refused; each is its own `webhooks_rejected_total` reason.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum SignatureRejection {
+    /// Missing, malformed, or made with neither secret.
+    Invalid,
+    /// Signed with a timestamp outside `SIGNATURE_MAX_SKEW_SECS`.
+    Stale,
+}
+
+/// Checks `x-notion-signature` against `secret`, then `secondary`; empty secrets never match.
+/// With an `x-notion-signature-timestamp` header (unix seconds) the signature covers
+/// `timestamp + "." + body` and the timestamp must be within `SIGNATURE_MAX_SKEW_SECS` of
+/// `now`, so a captured request can't be replayed later; without it only the body is signed.
+fn verify_notion_signature(
+    headers: &HeaderMap,
+    body: &[u8],
+    secret: &str,
+    secondary: Option<&str>,
+    now: i64,
+) -> std::result::Result<SigningSecret, SignatureRejection> {
+    use SignatureRejection::Invalid;
+    let sig_header = headers
         .get("x-notion-signature")
         .and_then(|v| v.to_str().ok())
-    else {
-        return false;
-    };
+        .ok_or(Invalid)?;
     let sig_hex = sig_header.strip_prefix("sha256=").unwrap_or(sig_header);
-    let Ok(expected) = hex::decode(sig_hex) else {
-        return false;
+    let expected = hex::decode(sig_hex).map_err(|_| Invalid)?;
+
+    let signed = match headers.get(SIGNATURE_TIMESTAMP_HEADER) {
+        None => Cow::Borrowed(body),
+        Some(raw) => {
+            let raw = raw.to_str().map_err(|_| Invalid)?.trim();
+            let timestamp: i64 = raw.parse().map_err(|_| Invalid)?;
+            if now.abs_diff(timestamp) > SIGNATURE_MAX_SKEW_SECS {
+                debug!(timestamp, now, "Webhook signature timestamp is stale");
+                return Err(SignatureRejection::Stale);
+            }
+            let mut signed = format!("{raw}.").into_bytes();
+            signed.extend_from_slice(body);
+            Cow::Owned(signed)
+        }
     };
 
+    if signature_matches(&expected, &signed, secret) {
+        Ok(SigningSecret::Primary)
+    } else if secondary.is_some_and(|secondary| signature_matches(&expected, &signed, secondary)) {
+        Ok(SigningSecret::Secondary)
+    } else {
+        Err(Invalid)
+    }
+}
+
+fn signature_matches(expected: &[u8], body: &[u8], secret: &str) -> bool {
+    if secret.is_empty() {
+        return false;
+    }
     let Ok(mut mac) = Hmac::<Sha256>::new_from_slice(secret.as_bytes()) else {
         return false;
     };
     mac.update(body);
     let computed = mac.finalize().into_bytes();
 
-    expected.len() == computed.len() && constant_time_eq(&computed, &expected)
+    expected.len() == computed.len() && constant_time_eq(&computed, expected)
 }
 
 async fn shutdown_signal() {
@@ -869,54 +3284,100 @@ async fn shutdown_signal() {
     }
 }
 
-fn extract_ip(headers: &HeaderMap) -> String {
-    headers
-        .get("cf-connecting-ip")
-        .or_else(|| headers.get("x-real-ip"))
-        .or_else(|| headers.get("x-forwarded-for"))
-        .and_then(|v| v.to_str().ok())
-        .map(|s| s.split(',').next().unwrap_or(s).trim().to_string())
-        .unwrap_or_else(|| "unknown".to_string())
+/// The client's address: the TCP peer, unless the peer is a trusted proxy, whose
+/// `CF-Connecting-IP`, `X-Real-IP` or first `X-Forwarded-For` entry is used instead. Anyone
+/// can send those headers, so they mean nothing from other peers. `"unknown"` when the router
+/// runs without connect info.
+fn client_ip(config: &AppConfig, peer: Option<SocketAddr>, headers: &HeaderMap) -> String {
+    let Some(peer) = peer else {
+        return "unknown".to_string();
+    };
+    let forwarded = || {
+        headers
+            .get("cf-connecting-ip")
+            .or_else(|| headers.get("x-real-ip"))
+            .or_else(|| headers.get("x-forwarded-for"))
+            .and_then(|v| v.to_str().ok())
+            .map(|s| s.split(',').next().unwrap_or(s).trim().to_string())
+            .filter(|s| !s.is_empty())
+    };
+    if config.is_trusted_proxy(peer.ip()) {
+        if let Some(ip) = forwarded() {
+            return ip;
+        }
+    }
+    peer.ip().to_canonical().to_string()
+}
+
+fn rate_limited_response(scope: RateLimitScope, wait: std::time::Duration) -> Response {
+    let retry_after = rate_limit::retry_after_secs(wait);
+    let mut res = (
+        StatusCode::TOO_MANY_REQUESTS,
+        Json(json!({
+            "error": "rate_limited",
+            "limit": scope.as_str(),
+            "retry_after_secs": retry_after,
+        })),
+    )
+        .into_response();
+    res.headers_mut()
+        .insert(header::RETRY_AFTER, HeaderValue::from(retry_after));
+    res
+}
+
+fn queue_full_response() -> Response {
+    let mut res = (
+        StatusCode::SERVICE_UNAVAILABLE,
+        Json(json!({
+            "error": "queue_full",
+            "retry_after_secs": QUEUE_FULL_RETRY_AFTER_SECS,
+        })),
+    )
+        .into_response();
+    res.headers_mut().insert(
+        header::RETRY_AFTER,
+        HeaderValue::from(QUEUE_FULL_RETRY_AFTER_SECS),
+    );
+    res
 }
 
-async fn check_rate_limit(state: &AppState, ip: &str) -> bool {
-    let window = (Utc::now().timestamp() / 60) as u64;
-    let mut guards = state.rate_limits.lock().await;
-    if guards.len() > MAX_RATE_LIMIT_ENTRIES {
-        guards.retain(|_, v| v.window == window);
+fn per_ip_rate_limit(config: &AppConfig) -> RateLimit {
+    RateLimit::per_minute(config.per_ip_limit, config.per_ip_burst)
+}
+
+/// Takes a token from `ip`'s bucket. Returns how many whole tokens it has left, or how long
+/// until the next one when the bucket is empty.
+async fn check_rate_limit(state: &AppState, ip: &str) -> Result<u32, std::time::Duration> {
+    let limit = per_ip_rate_limit(&state.config);
+    let now = std::time::Instant::now();
+    let mut buckets = state.rate_limits.lock().await;
+    if !buckets.contains_key(ip) {
+        rate_limit::evict(&mut buckets, &limit, now, MAX_RATE_LIMIT_ENTRIES - 1);
     }
-    let entry = guards
+    buckets
         .entry(ip.to_string())
-        .or_insert(WindowCounter { window, count: 0 });
-    if entry.window != window {
-        entry.window = window;
-        entry.count = 0;
-    }
-    if entry.count >= PER_IP_LIMIT + PER_IP_BURST {
-        return false;
-    }
-    entry.count += 1;
-    true
+        .or_default()
+        .try_take(&limit, now)
 }
 
-async fn check_global_rate_limit(state: &AppState) -> bool {
-    let window = (Utc::now().timestamp() / 60) as u64;
-    let mut guard = state.global_limit.lock().await;
-    if guard.window != window {
-        guard.window = window;
-        guard.count = 0;
-    }
-    if guard.count >= GLOBAL_LIMIT + GLOBAL_BURST {
-        return false;
-    }
-    guard.count += 1;
-    true
+async fn check_global_rate_limit(state: &AppState) -> Result<u32, std::time::Duration> {
+    let limit = RateLimit::per_minute(state.config.global_limit, state.config.global_burst);
+    state
+        .global_limit
+        .lock()
+        .await
+        .try_take(&limit, std::time::Instant::now())
+}
+
+/// Dedupe entry for one page of an event; a batched event is remembered page by page.
+fn dedupe_key(event_id: &str, page_id: &str) -> String {
+    format!("{event_id}/{page_id}")
 }
 
 async fn dedupe_event(state: &AppState, event_id: &str) -> bool {
     let now = Utc::now().timestamp();
     let mut guard = state.recent_events.lock().await;
-    guard.retain(|_, ts| now - *ts <= DEDUPE_TTL_SECS);
+    guard.retain(|_, ts| now - *ts <= state.config.dedupe_ttl_secs);
     if guard.len() > MAX_DEDUPE_ENTRIES {
         guard.clear();
     }
@@ -924,5 +3385,261 @@ async fn dedupe_event(state: &AppState, event_id: &str) -> bool {
         return false;
     }
     guard.insert(event_id.to_string(), now);
+    if let Some(store) = &state.dedupe_store {
+        store.record(event_id, now);
+    }
     true
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn payload_shape_reports_unexpected_and_missing_keys() {
+        let known = json!({
+            "id": "evt", "type": "page.properties_updated", "entity": { "id": "p" },
+            "data": {}, "api_version": "2025-09-03"
+        });
+        let report = payload_shape_report(&known);
+        assert!(report.is_known());
+        assert_eq!(report.version.as_deref(), Some("2025-09-03"));
+
+        let mutated = json!({ "type": "page.moved", "version": 3, "zeta": 1, "alpha": 2 });
+        let report = payload_shape_report(&mutated);
+        assert!(!report.is_known());
+        assert_eq!(report.event_type.as_deref(), Some("page.moved"));
+        assert_eq!(report.version.as_deref(), Some("3"));
+        assert_eq!(report.unexpected_keys, vec!["alpha", "version", "zeta"]);
+        assert_eq!(report.missing_keys, vec!["id", "entity"]);
+
+        assert_eq!(
+            payload_shape_report(&json!([1, 2])).missing_keys,
+            vec!["id", "type", "entity"]
+        );
+    }
+
+    #[test]
+    fn capture_names_reject_paths_and_hidden_files() {
+        assert!(is_valid_capture_name("evt-123.json"));
+        for bad in [
+            "",
+            "../secret.json",
+            "a/b.json",
+            ".env",
+            "..",
+            "x\\y",
+            "évt.json",
+        ] {
+            assert!(!is_valid_capture_name(bad), "{bad:?} should be rejected");
+        }
+    }
+
+    #[test]
+    fn listen_addr_defaults_when_unset() {
+        let addr = parse_listen_addr(None, None).unwrap();
+        assert_eq!(addr, "0.0.0.0:3146".parse().unwrap());
+        let addr = parse_listen_addr(Some(""), Some(" ")).unwrap();
+        assert_eq!(addr, "0.0.0.0:3146".parse().unwrap());
+    }
+
+    #[test]
+    fn listen_addr_accepts_full_address_or_port() {
+        let addr = parse_listen_addr(Some("0.0.0.0:8080"), None).unwrap();
+        assert_eq!(addr, "0.0.0.0:8080".parse().unwrap());
+        let addr = parse_listen_addr(Some("8080"), None).unwrap();
+        assert_eq!(addr, "0.0.0.0:8080".parse().unwrap());
+        let addr = parse_listen_addr(None, Some("9000")).unwrap();
+        assert_eq!(addr, "0.0.0.0:9000".parse().unwrap());
+        let addr = parse_listen_addr(Some("127.0.0.1"), Some("9000")).unwrap();
+        assert_eq!(addr, "127.0.0.1:9000".parse().unwrap());
+    }
+
+    #[test]
+    fn listen_addr_rejects_malformed_values() {
+        assert!(parse_listen_addr(Some("localhost:80"), None).is_err());
+        assert!(parse_listen_addr(None, Some("70000")).is_err());
+        assert!(parse_listen_addr(None, Some("http")).is_err());
+    }
+
+    #[test]
+    fn error_title_does_not_repeat_the_suffix() {
+        assert_eq!(
+            error_title("Some Movie ;", "No TMDB movie match"),
+            "Some Movie ; | No TMDB movie match"
+        );
+        assert_eq!(
+            error_title("Some Movie | No TMDB movie match ;", "No TMDB movie match"),
+            "Some Movie ; | No TMDB movie match"
+        );
+    }
+
+    const NOW: i64 = 1_750_000_000;
+
+    fn signed_headers(body: &[u8], secret: &str) -> HeaderMap {
+        let mut mac = Hmac::<Sha256>::new_from_slice(secret.as_bytes()).unwrap();
+        mac.update(body);
+        let signature = hex::encode(mac.finalize().into_bytes());
+        let mut headers = HeaderMap::new();
+        headers.insert(
+            "x-notion-signature",
+            format!("sha256={signature}").parse().unwrap(),
+        );
+        headers
+    }
+
+    #[test]
+    fn signatures_match_either_webhook_secret() {
+        let body = br#"{"type":"page.created"}"#;
+        let verify = |headers: &HeaderMap, secondary| {
+            verify_notion_signature(headers, body, "new-secret", secondary, NOW)
+        };
+
+        let headers = signed_headers(body, "new-secret");
+        assert_eq!(verify(&headers, None), Ok(SigningSecret::Primary));
+        assert_eq!(
+            verify(&headers, Some("old-secret")),
+            Ok(SigningSecret::Primary)
+        );
+
+        let headers = signed_headers(body, "old-secret");
+        assert_eq!(verify(&headers, None), Err(SignatureRejection::Invalid));
+        assert_eq!(
+            verify(&headers, Some("old-secret")),
+            Ok(SigningSecret::Secondary)
+        );
+
+        let headers = signed_headers(body, "other-secret");
+        assert_eq!(
+            verify(&headers, Some("old-secret")),
+            Err(SignatureRejection::Invalid)
+        );
+        assert_eq!(
+            verify(&HeaderMap::new(), Some("old-secret")),
+            Err(SignatureRejection::Invalid)
+        );
+    }
+
+    #[test]
+    fn empty_secrets_never_match() {
+        let body = b"{}";
+        let headers = signed_headers(body, "");
+        assert_eq!(
+            verify_notion_signature(&headers, body, "", Some(""), NOW),
+            Err(SignatureRejection::Invalid)
+        );
+    }
+
+    #[test]
+    fn timestamped_signatures_cover_the_timestamp_and_must_be_fresh() {
+        let body = br#"{"type":"page.created"}"#;
+        let signed_at = |timestamp: i64, signed: i64| {
+            let mut message = format!("{signed}.").into_bytes();
+            message.extend_from_slice(body);
+            let mut headers = signed_headers(&message, "secret");
+            headers.insert(
+                SIGNATURE_TIMESTAMP_HEADER,
+                timestamp.to_string().parse().unwrap(),
+            );
+            verify_notion_signature(&headers, body, "secret", Some("old"), NOW)
+        };
+
+        assert_eq!(signed_at(NOW, NOW), Ok(SigningSecret::Primary));
+        assert_eq!(signed_at(NOW - 299, NOW - 299), Ok(SigningSecret::Primary));
+        // Outside the window, or a timestamp swapped after signing.
+        assert_eq!(
+            signed_at(NOW - 301, NOW - 301),
+            Err(SignatureRejection::Stale)
+        );
+        assert_eq!(
+            signed_at(NOW + 301, NOW + 301),
+            Err(SignatureRejection::Stale)
+        );
+        assert_eq!(signed_at(NOW, NOW - 60), Err(SignatureRejection::Invalid));
+
+        // A body-only signature doesn't verify once a timestamp is sent with it.
+        let mut headers = signed_headers(body, "secret");
+        assert_eq!(
+            verify_notion_signature(&headers, body, "secret", None, NOW),
+            Ok(SigningSecret::Primary)
+        );
+        headers.insert(SIGNATURE_TIMESTAMP_HEADER, NOW.to_string().parse().unwrap());
+        assert_eq!(
+            verify_notion_signature(&headers, body, "secret", None, NOW),
+            Err(SignatureRejection::Invalid)
+        );
+        headers.insert(SIGNATURE_TIMESTAMP_HEADER, "soon".parse().unwrap());
+        assert_eq!(
+            verify_notion_signature(&headers, body, "secret", None, NOW),
+            Err(SignatureRejection::Invalid)
+        );
+    }
+
+    #[test]
+    fn icon_prefers_the_configured_emoji_over_the_poster() {
+        assert_eq!(
+            icon_payload(Some("🎬"), Some("https://img/poster.jpg")),
+            Some(json!({ "type": "emoji", "emoji": "🎬" }))
+        );
+        assert_eq!(
+            icon_payload(Some("📺"), None),
+            Some(json!({ "type": "emoji", "emoji": "📺" }))
+        );
+        assert_eq!(
+            icon_payload(None, Some("https://img/poster.jpg")),
+            Some(json!({ "type": "external", "external": { "url": "https://img/poster.jpg" } }))
+        );
+        assert_eq!(icon_payload(None, None), None);
+    }
+
+    #[test]
+    fn weak_signing_secrets_are_refused() {
+        let long = "secret_tMrlL1qK5vuQAh1b6cZGhFChZTSYJlce98V0pYn7yBl";
+        assert!(validate_signing_secret("S", long).is_ok());
+        assert!(validate_signing_secret("S", &"a".repeat(32)).is_ok());
+        assert!(validate_signing_secret("S", "").is_ok());
+        assert!(validate_signing_secret("S", INSECURE_DISABLED_SECRET).is_ok());
+
+        let err = validate_signing_secret("S", "test")
+            .unwrap_err()
+            .to_string();
+        assert_eq!(err, "S is too short (4 bytes, at least 32 required)");
+        assert!(validate_signing_secret("S", &"a".repeat(31)).is_err());
+        for weak in [format!("{long} "), format!("{long}\n"), format!("{long}é")] {
+            let err = validate_signing_secret("S", &weak).unwrap_err().to_string();
+            assert!(err.contains("printable ASCII"), "{err}");
+        }
+
+        assert!(validate_secondary_signing_secret("S2", "").is_ok());
+        assert!(validate_secondary_signing_secret("S2", long).is_ok());
+        assert!(validate_secondary_signing_secret("S2", "test").is_err());
+        let err = validate_secondary_signing_secret("S2", INSECURE_DISABLED_SECRET)
+            .unwrap_err()
+            .to_string();
+        assert_eq!(err, "S2 can't be insecure-disabled; unset it instead");
+    }
+
+    #[test]
+    fn page_year_comes_from_year_then_release_date() {
+        let props = |value: serde_json::Value| value.as_object().unwrap().clone();
+        let year = props(json!({
+            "Year": { "rich_text": [{ "plain_text": "2021" }] },
+            "Release Date": { "date": { "start": "1984-12-14" } },
+        }));
+        assert_eq!(page_year(&year), Some(2021));
+        let date = props(json!({
+            "Year": { "rich_text": [] },
+            "Release Date": { "date": { "start": "1984-12-14" } },
+        }));
+        assert_eq!(page_year(&date), Some(1984));
+        assert_eq!(
+            page_year(&props(json!({ "Year": { "number": 2001.0 } }))),
+            Some(2001)
+        );
+        assert_eq!(
+            page_year(&props(json!({ "Year": { "select": { "name": "soon" } } }))),
+            None
+        );
+        assert_eq!(page_year(&props(json!({}))), None);
+    }
+}
@@ -0,0 +1,808 @@
+//! The backfill loop, shared by `POST /admin/backfill` and `examples/backfill_*.rs`.
+use crate::anilist::{parse_anilist_url, AniListApi, AniListClient, AniListMediaType};
+use crate::app::{
+    page_season, process_page_backfill_anilist, process_page_backfill_movie,
+    process_page_backfill_tv, AppState, PageOutcome,
+};
+use crate::config::AppConfig;
+use crate::duplicates::DuplicateIndex;
+use crate::failures::FailureLog;
+use crate::http;
+use crate::metrics::Metrics;
+use crate::negative_cache::NegativeCache;
+use crate::notion::{self, NotionApi, NotionClient};
+use crate::notion_fallback::fallback_schema;
+use crate::queue::JobQueue;
+use crate::rate_limit::TokenBucket;
+use crate::retry::{RetryQueue, DEFAULT_RETRY_BASE_DELAY};
+use crate::titles::normalize_title_key;
+use crate::tmdb::{TmdbApi, TmdbClient};
+use crate::triggers::TriggerConfig;
+use anyhow::{Context, Result};
+use chrono::Utc;
+use futures_util::{StreamExt, TryStreamExt};
+use serde::Serialize;
+use serde_json::{json, Map, Value};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fs::OpenOptions;
+use std::io::{BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::sync::{mpsc, Mutex as AsyncMutex, Semaphore};
+use tokio::task::JoinHandle;
+use tokio_util::task::TaskTracker;
+use tracing::{error, info, warn};
+
+pub const DEFAULT_BACKFILL_CONCURRENCY: usize = 8;
+const QUERY_PAGE_SIZE: usize = 100;
+
+/// Which pages a backfill run re-enriches.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum BackfillKind {
+    /// `Type` containing "TV", with a Season.
+    Tv,
+    /// `Type` equal to "Movie".
+    Movie,
+    /// Pages enriched from AniList (see `target_of`).
+    Anime,
+    All,
+}
+
+impl BackfillKind {
+    pub fn parse(raw: &str) -> Option<Self> {
+        match raw.trim().to_ascii_lowercase().as_str() {
+            "tv" => Some(Self::Tv),
+            "movie" => Some(Self::Movie),
+            "anime" => Some(Self::Anime),
+            "all" => Some(Self::All),
+            _ => None,
+        }
+    }
+}
+
+/// Options for the one-off backfill runs.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct BackfillOptions {
+    pub kind: BackfillKind,
+    /// Skip pages that already look enriched (see `is_incomplete`).
+    pub only_incomplete: bool,
+    /// Pages enriched at once.
+    pub concurrency: usize,
+    /// Only pages whose `Type` is exactly this (case-insensitive).
+    pub type_filter: Option<String>,
+    /// Stop after this many candidates have been dispatched.
+    pub limit: Option<usize>,
+    /// Log the candidates instead of enriching them.
+    pub list_only: bool,
+}
+
+impl Default for BackfillOptions {
+    fn default() -> Self {
+        Self {
+            kind: BackfillKind::Tv,
+            only_incomplete: false,
+            concurrency: DEFAULT_BACKFILL_CONCURRENCY,
+            type_filter: None,
+            limit: None,
+            list_only: false,
+        }
+    }
+}
+
+/// Flags of the `examples/backfill_*.rs` binaries, which differ only in the default kind.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct BackfillArgs {
+    pub options: BackfillOptions,
+    /// `--output <path>` (or `--output-jsonl`): where to append one JSON line per processed page.
+    pub output: Option<PathBuf>,
+}
+
+impl BackfillArgs {
+    /// Parses `args` (without the program name). `--dry-run` only lists the pages;
+    /// CINELINK_DRY_RUN=1 logs each update body instead.
+    pub fn parse(
+        args: impl IntoIterator<Item = String>,
+        default_kind: BackfillKind,
+    ) -> Result<Self> {
+        let mut options = BackfillOptions {
+            kind: default_kind,
+            ..BackfillOptions::default()
+        };
+        let mut output = None;
+        let mut args = args.into_iter();
+        while let Some(flag) = args.next() {
+            let mut value = || {
+                args.next()
+                    .ok_or_else(|| anyhow::anyhow!("{flag} expects a value"))
+            };
+            match flag.as_str() {
+                "--concurrency" => {
+                    let raw = value()?;
+                    options.concurrency = raw
+                        .parse::<usize>()
+                        .map_err(|_| anyhow::anyhow!("Invalid --concurrency {raw:?}"))?
+                        .clamp(1, 64);
+                }
+                "--kind" => {
+                    let raw = value()?;
+                    options.kind = BackfillKind::parse(&raw).ok_or_else(|| {
+                        anyhow::anyhow!("Invalid --kind {raw:?} (expected movie, tv, anime or all)")
+                    })?;
+                }
+                "--limit" => {
+                    let raw = value()?;
+                    let limit = raw.parse::<usize>().ok().filter(|n| *n > 0);
+                    options.limit = Some(limit.ok_or_else(|| {
+                        anyhow::anyhow!("Invalid --limit {raw:?} (expected a positive number)")
+                    })?);
+                }
+                "--filter-type" => options.type_filter = Some(value()?),
+                "--output" | "--output-jsonl" => output = Some(PathBuf::from(value()?)),
+                "--only-incomplete" => options.only_incomplete = true,
+                "--dry-run" => options.list_only = true,
+                _ => anyhow::bail!("Unknown flag {flag:?}"),
+            }
+        }
+        Ok(Self { options, output })
+    }
+}
+
+/// Builds the state a backfill binary enriches with: the real Notion, TMDB and AniList
+/// clients from the environment, no webhook secret or admin key, and `concurrency` permits.
+pub async fn state_from_env(concurrency: usize) -> Result<AppState> {
+    let config = Arc::new(AppConfig::from_env()?);
+    let http_client = http::default_client()?;
+    let notion: Arc<dyn NotionApi> = Arc::new(
+        NotionClient::from_env()?
+            .with_http_client(http_client.clone())
+            .with_dry_run(config.dry_run),
+    );
+    let schema = match notion.fetch_property_schema().await {
+        Ok(s) => s,
+        Err(e) => {
+            warn!("Failed to fetch Notion schema, using fallback: {}", e);
+            fallback_schema()
+        }
+    };
+    let title_property = schema
+        .title_property
+        .clone()
+        .unwrap_or_else(|| "Name".to_string());
+    let tmdb: Arc<dyn TmdbApi> =
+        Arc::new(TmdbClient::from_env()?.with_http_client(http_client.clone()));
+    let anilist: Arc<dyn AniListApi> =
+        Arc::new(AniListClient::builder().http_client(http_client).build()?);
+
+    let metrics = Arc::new(Metrics::new());
+    Ok(AppState {
+        notion,
+        tmdb,
+        anilist,
+        title_property,
+        triggers: Arc::new(TriggerConfig::from_env()?),
+        config: config.clone(),
+        schema: Arc::new(notion::SharedSchema::new(schema)),
+        signing_secret: String::new(),
+        secondary_signing_secret: None,
+        rate_limits: Arc::new(AsyncMutex::new(HashMap::new())),
+        global_limit: Arc::new(AsyncMutex::new(TokenBucket::default())),
+        shape_warning_limit: Arc::new(AsyncMutex::new(TokenBucket::default())),
+        recent_events: Arc::new(AsyncMutex::new(HashMap::new())),
+        dedupe_store: None,
+        processing_sem: Arc::new(Semaphore::new(concurrency)),
+        duplicates: Arc::new(DuplicateIndex::new()),
+        negative_lookups: Arc::new(NegativeCache::new(Duration::from_secs(
+            config.negative_cache_ttl_secs,
+        ))),
+        metrics: metrics.clone(),
+        readiness: Arc::new(AsyncMutex::new(None)),
+        admin_key: None,
+        capture_dir: None,
+        failures: Arc::new(FailureLog::new()),
+        retry: Arc::new(RetryQueue::new(
+            config.retry_max_attempts,
+            DEFAULT_RETRY_BASE_DELAY,
+            metrics,
+        )),
+        verification_token: Arc::new(AsyncMutex::new(None)),
+        backfill: Arc::new(BackfillProgress::default()),
+        error_notifier: None,
+        in_flight_pages: Arc::new(Mutex::new(HashMap::new())),
+        jobs: TaskTracker::new(),
+        job_queue: Arc::new(JobQueue::new(config.job_queue_capacity)),
+    })
+}
+
+/// The body of the backfill binaries: parses the command line, builds the state from the
+/// environment and runs one backfill.
+pub async fn run_from_args(
+    args: impl IntoIterator<Item = String>,
+    default_kind: BackfillKind,
+) -> Result<()> {
+    let BackfillArgs { options, output } = BackfillArgs::parse(args, default_kind)?;
+    let log = output.as_deref().map(ResultLog::open).transpose()?;
+    let state = state_from_env(options.concurrency).await?;
+
+    let progress = BackfillProgress::default();
+    progress.try_start();
+    let result = run_backfill_logged(&state, &options, &progress, log.as_ref()).await;
+    progress.finish(&result);
+    if let Some(log) = log {
+        log.finish().await?;
+    }
+    result
+}
+
+/// Which provider path a page is backfilled through.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum BackfillTarget {
+    Tv,
+    Movie,
+    AniList(AniListMediaType),
+}
+
+/// AniList pages are recognised by an AniList link in "IMDb Page" (as written by `=`/`~`),
+/// an anime `Type` value or an "Anime" genre; the rest go by `Type` ("TV ..." or "Movie").
+pub fn target_of(props: &Map<String, Value>, triggers: &TriggerConfig) -> Option<BackfillTarget> {
+    if let Some((media_type, _)) = stored_anilist_link(props) {
+        return Some(BackfillTarget::AniList(media_type));
+    }
+    let type_value = notion::extract_select(props, "Type").unwrap_or_default();
+    let anime_genre = props
+        .get("Genre")
+        .and_then(|p| p.get("multi_select"))
+        .and_then(|v| v.as_array())
+        .is_some_and(|genres| {
+            genres.iter().any(|g| {
+                g.get("name")
+                    .and_then(|n| n.as_str())
+                    .is_some_and(|n| n.eq_ignore_ascii_case("anime"))
+            })
+        });
+    if triggers.is_anime_type(&type_value) || anime_genre {
+        Some(BackfillTarget::AniList(AniListMediaType::Anime))
+    } else if type_value.to_lowercase().contains("tv") {
+        Some(BackfillTarget::Tv)
+    } else if type_value.eq_ignore_ascii_case("movie") {
+        Some(BackfillTarget::Movie)
+    } else {
+        None
+    }
+}
+
+/// The AniList id the page was enriched with: its `ID`, when "IMDb Page" links to the same
+/// AniList entry.
+pub fn stored_anilist_id(props: &Map<String, Value>) -> Option<i32> {
+    let (_, id) = stored_anilist_link(props)?;
+    let stored = notion::extract_number(props, "ID")?;
+    (stored == f64::from(id)).then_some(id)
+}
+
+fn stored_anilist_link(props: &Map<String, Value>) -> Option<(AniListMediaType, i32)> {
+    notion::extract_url(props, "IMDb Page").and_then(|url| parse_anilist_url(&url))
+}
+
+/// Whether a page still needs enriching: no `ID`, or for movies no Director either.
+pub fn is_incomplete(props: &Map<String, Value>, target: BackfillTarget) -> bool {
+    notion::extract_number(props, "ID").is_none()
+        || (target == BackfillTarget::Movie
+            && notion::extract_rich_text(props, "Director").is_none_or(|d| d.trim().is_empty()))
+}
+
+/// Counters of the current (or last) backfill run, reported by `GET /admin/backfill/status`.
+#[derive(Debug, Default)]
+pub struct BackfillProgress {
+    running: AtomicBool,
+    scanned: AtomicUsize,
+    candidates: AtomicUsize,
+    updated: AtomicUsize,
+    errors: AtomicUsize,
+    /// Candidates left alone, by reason (e.g. "No TMDB movie match").
+    skipped: Mutex<BTreeMap<String, usize>>,
+    started_at: Mutex<Option<String>>,
+    finished_at: Mutex<Option<String>>,
+    /// Why the run stopped early (e.g. the database query failed).
+    error: Mutex<Option<String>>,
+}
+
+impl BackfillProgress {
+    /// Claims the single backfill slot and resets the counters; false while a run is active.
+    pub fn try_start(&self) -> bool {
+        if self
+            .running
+            .compare_exchange(false, true, Ordering::SeqCst, Ordering::SeqCst)
+            .is_err()
+        {
+            return false;
+        }
+        for counter in [&self.scanned, &self.candidates, &self.updated, &self.errors] {
+            counter.store(0, Ordering::Relaxed);
+        }
+        self.skipped.lock().unwrap().clear();
+        *self.started_at.lock().unwrap() = Some(Utc::now().to_rfc3339());
+        *self.finished_at.lock().unwrap() = None;
+        *self.error.lock().unwrap() = None;
+        true
+    }
+
+    /// Releases the slot taken by `try_start`, keeping the counters for the status endpoint.
+    pub fn finish(&self, result: &Result<()>) {
+        if let Err(e) = result {
+            *self.error.lock().unwrap() = Some(format!("{:#}", e));
+        }
+        *self.finished_at.lock().unwrap() = Some(Utc::now().to_rfc3339());
+        self.running.store(false, Ordering::SeqCst);
+    }
+
+    pub fn scanned(&self) -> usize {
+        self.scanned.load(Ordering::Relaxed)
+    }
+
+    pub fn candidates(&self) -> usize {
+        self.candidates.load(Ordering::Relaxed)
+    }
+
+    pub fn updated(&self) -> usize {
+        self.updated.load(Ordering::Relaxed)
+    }
+
+    pub fn errors(&self) -> usize {
+        self.errors.load(Ordering::Relaxed)
+    }
+
+    pub fn skipped(&self) -> usize {
+        self.skipped.lock().unwrap().values().sum()
+    }
+
+    pub fn to_json(&self) -> Value {
+        json!({
+            "running": self.running.load(Ordering::SeqCst),
+            "scanned": self.scanned(),
+            "candidates": self.candidates(),
+            "updated": self.updated(),
+            "errors": self.errors(),
+            "skipped": *self.skipped.lock().unwrap(),
+            "started_at": *self.started_at.lock().unwrap(),
+            "finished_at": *self.finished_at.lock().unwrap(),
+            "error": *self.error.lock().unwrap(),
+        })
+    }
+
+    fn skip(&self, reason: &str) {
+        *self
+            .skipped
+            .lock()
+            .unwrap()
+            .entry(reason.to_string())
+            .or_default() += 1;
+    }
+
+    fn record(&self, result: Result<Result<PageOutcome>, tokio::task::JoinError>) {
+        match result {
+            Ok(Ok(outcome)) if outcome.updated => {
+                self.updated.fetch_add(1, Ordering::Relaxed);
+            }
+            Ok(Ok(outcome)) => {
+                let reason = outcome.no_match;
+                self.skip(reason.as_deref().unwrap_or("Nothing to update"));
+            }
+            Ok(Err(e)) => {
+                error!("Backfill task failed: {}", e);
+                self.errors.fetch_add(1, Ordering::Relaxed);
+            }
+            Err(e) => {
+                error!("Backfill task panicked: {}", e);
+                self.errors.fetch_add(1, Ordering::Relaxed);
+            }
+        }
+    }
+}
+
+/// One line of a `ResultLog`: what a backfill did to one page.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct BackfillRecord {
+    pub page_id: String,
+    pub original_title: String,
+    /// The title the page was left with; `None` when the job failed.
+    pub updated_title: Option<String>,
+    /// `"tmdb"` or `"anilist"`.
+    pub source: &'static str,
+    pub success: bool,
+    /// The failure, or why nothing matched.
+    pub error: Option<String>,
+}
+
+impl BackfillRecord {
+    fn new(
+        page_id: String,
+        original_title: String,
+        target: BackfillTarget,
+        result: &Result<Result<PageOutcome>, tokio::task::JoinError>,
+    ) -> Self {
+        let (updated_title, error) = match result {
+            Ok(Ok(outcome)) => (Some(outcome.title.clone()), outcome.no_match.clone()),
+            Ok(Err(e)) => (None, Some(format!("{:#}", e))),
+            Err(e) => (None, Some(format!("Task panicked: {}", e))),
+        };
+        Self {
+            page_id,
+            original_title,
+            updated_title,
+            source: match target {
+                BackfillTarget::Tv | BackfillTarget::Movie => "tmdb",
+                BackfillTarget::AniList(_) => "anilist",
+            },
+            success: error.is_none(),
+            error,
+        }
+    }
+}
+
+/// Appends a JSON line per processed page to a file (`--output` in the backfill
+/// examples). Lines go through a channel to a blocking writer task, so the backfill never
+/// waits on the disk; call `finish` to flush them.
+pub struct ResultLog {
+    tx: mpsc::UnboundedSender<BackfillRecord>,
+    writer: JoinHandle<std::io::Result<()>>,
+}
+
+impl ResultLog {
+    /// Opens `path` for appending (creating it), so successive runs accumulate.
+    pub fn open(path: &Path) -> Result<Self> {
+        let file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)
+            .with_context(|| format!("Failed to open {}", path.display()))?;
+        let (tx, mut rx) = mpsc::unbounded_channel::<BackfillRecord>();
+        let writer = tokio::task::spawn_blocking(move || {
+            let mut out = BufWriter::new(file);
+            while let Some(record) = rx.blocking_recv() {
+                serde_json::to_writer(&mut out, &record)?;
+                out.write_all(b"\n")?;
+            }
+            out.flush()
+        });
+        Ok(Self { tx, writer })
+    }
+
+    fn record(&self, record: BackfillRecord) {
+        // The writer only stops early on an I/O error, which `finish` reports.
+        let _ = self.tx.send(record);
+    }
+
+    /// Waits for every line to be written.
+    pub async fn finish(self) -> Result<()> {
+        drop(self.tx);
+        self.writer
+            .await
+            .context("Result log writer panicked")?
+            .context("Failed to write the result log")
+    }
+}
+
+/// Re-enriches every page of `options.kind` that has a title without the TMDB trigger,
+/// counting into `progress` as it goes. The caller claims the run with `try_start`.
+pub async fn run_backfill(
+    state: &AppState,
+    options: &BackfillOptions,
+    progress: &BackfillProgress,
+) -> Result<()> {
+    run_backfill_logged(state, options, progress, None).await
+}
+
+/// `run_backfill`, also recording each processed page in `log`.
+pub async fn run_backfill_logged(
+    state: &AppState,
+    options: &BackfillOptions,
+    progress: &BackfillProgress,
+    log: Option<&ResultLog>,
+) -> Result<()> {
+    let concurrency = options.concurrency.max(1);
+    info!(
+        "Starting {:?} backfill (concurrency={}, dry_run={}, only_incomplete={}, type={:?}, limit={:?})",
+        options.kind,
+        concurrency,
+        state.config.dry_run,
+        options.only_incomplete,
+        options.type_filter,
+        options.limit
+    );
+    let limit = options.limit.unwrap_or(usize::MAX);
+    let seen = &Mutex::new(HashSet::new());
+    notion::all_pages(state.notion.as_ref(), QUERY_PAGE_SIZE)
+        .try_filter_map(|page| async move {
+            progress.scanned.fetch_add(1, Ordering::Relaxed);
+            let Some(page_id) = page.get("id").and_then(|v| v.as_str()) else {
+                return Ok(None);
+            };
+            let Some(props) = page.get("properties").and_then(|p| p.as_object()) else {
+                return Ok(None);
+            };
+            let Some(target) = candidate(state, props, options) else {
+                return Ok(None);
+            };
+            progress.candidates.fetch_add(1, Ordering::Relaxed);
+            let title = notion::extract_title(props, &state.title_property).unwrap_or_default();
+            if !seen
+                .lock()
+                .unwrap()
+                .insert(lookup_key(props, &title, target))
+            {
+                warn!(
+                    "Skipping page {} ({:?}): '{}' was already processed in this run",
+                    page_id, target, title
+                );
+                progress.skip("Duplicate title");
+                return Ok(None);
+            }
+            Ok(Some((page_id.to_string(), target, title)))
+        })
+        .take(limit)
+        .try_for_each_concurrent(concurrency, |(page_id, target, title)| async move {
+            if options.list_only {
+                info!("Would process page {} ({:?}): {}", page_id, target, title);
+                return Ok(());
+            }
+            // Each page runs in its own task so a panic is counted instead of ending the run.
+            let state = state.clone();
+            let options = options.clone();
+            let id = page_id.clone();
+            let result = tokio::spawn(async move {
+                match target {
+                    BackfillTarget::Tv => process_page_backfill_tv(&state, &id, &options).await,
+                    BackfillTarget::Movie => {
+                        process_page_backfill_movie(&state, &id, &options).await
+                    }
+                    BackfillTarget::AniList(_) => {
+                        process_page_backfill_anilist(&state, &id, &options).await
+                    }
+                }
+            })
+            .await;
+            if let Some(log) = log {
+                log.record(BackfillRecord::new(page_id, title, target, &result));
+            }
+            progress.record(result);
+            Ok(())
+        })
+        .await?;
+    if progress.candidates() >= limit {
+        info!("Reached the limit of {} pages", limit);
+    }
+
+    if options.list_only {
+        info!(
+            "Listed {} of {} scanned pages; nothing was processed",
+            progress.candidates(),
+            progress.scanned()
+        );
+    } else if state.config.dry_run {
+        info!(
+            "DRY RUN complete: scanned {} pages, matched {} candidates, would have updated {} pages (payloads logged above)",
+            progress.scanned(),
+            progress.candidates(),
+            progress.updated()
+        );
+    } else {
+        info!(
+            "Backfill complete: scanned {} pages, matched {} candidates, updated {} pages, skipped {}, {} errors",
+            progress.scanned(),
+            progress.candidates(),
+            progress.updated(),
+            progress.skipped(),
+            progress.errors()
+        );
+    }
+    Ok(())
+}
+
+/// What a page would be looked up by: its stored AniList id, or else its normalized title,
+/// plus the season. Two pages with the same key would both be enriched with the same match,
+/// so a run only processes the first.
+fn lookup_key(props: &Map<String, Value>, title: &str, target: BackfillTarget) -> String {
+    let season = page_season(props);
+    let lookup = match stored_anilist_id(props) {
+        Some(id) => format!("#{id}"),
+        None => normalize_title_key(title),
+    };
+    format!("{target:?}:{lookup}:{season:?}")
+}
+
+fn candidate(
+    state: &AppState,
+    props: &Map<String, Value>,
+    options: &BackfillOptions,
+) -> Option<BackfillTarget> {
+    let title = notion::extract_title(props, &state.title_property).unwrap_or_default();
+    if title.trim().is_empty() || title.ends_with(';') {
+        return None;
+    }
+    if let Some(wanted) = &options.type_filter {
+        let type_value = notion::extract_select(props, "Type").unwrap_or_default();
+        if !type_value.trim().eq_ignore_ascii_case(wanted.trim()) {
+            return None;
+        }
+    }
+    let target = target_of(props, &state.triggers)?;
+    if target == BackfillTarget::Tv {
+        page_season(props)?;
+    }
+    let wanted = match options.kind {
+        BackfillKind::Tv => target == BackfillTarget::Tv,
+        BackfillKind::Movie => target == BackfillTarget::Movie,
+        BackfillKind::Anime => matches!(target, BackfillTarget::AniList(_)),
+        BackfillKind::All => true,
+    };
+    (wanted && (!options.only_incomplete || is_incomplete(props, target))).then_some(target)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn only_one_run_holds_the_slot_and_counters_reset_per_run() {
+        let progress = BackfillProgress::default();
+        assert!(progress.try_start());
+        assert!(!progress.try_start());
+        progress.scanned.fetch_add(3, Ordering::Relaxed);
+        progress.finish(&Err(anyhow::anyhow!("query failed")));
+        let status = progress.to_json();
+        assert_eq!(status["running"], false);
+        assert_eq!(status["scanned"], 3);
+        assert_eq!(status["error"], "query failed");
+
+        assert!(progress.try_start());
+        let status = progress.to_json();
+        assert_eq!(status["running"], true);
+        assert_eq!(status["scanned"], 0);
+        assert_eq!(status["error"], Value::Null);
+    }
+
+    #[test]
+    fn backfill_binaries_share_their_flags() {
+        let parse =
+            |args: &[&str], kind| BackfillArgs::parse(args.iter().map(|a| a.to_string()), kind);
+        let args = parse(
+            &[
+                "--dry-run",
+                "--only-incomplete",
+                "--limit",
+                "10",
+                "--filter-type",
+                "TV",
+                "--concurrency",
+                "500",
+                "--output-jsonl",
+                "out.jsonl",
+            ],
+            BackfillKind::Movie,
+        )
+        .unwrap();
+        assert_eq!(
+            args.options,
+            BackfillOptions {
+                kind: BackfillKind::Movie,
+                only_incomplete: true,
+                concurrency: 64,
+                type_filter: Some("TV".to_string()),
+                limit: Some(10),
+                list_only: true,
+            }
+        );
+        assert_eq!(args.output, Some(PathBuf::from("out.jsonl")));
+        let args = parse(&["--output", "out.jsonl"], BackfillKind::Tv).unwrap();
+        assert_eq!(args.output, Some(PathBuf::from("out.jsonl")));
+
+        let args = parse(&["--kind", "all"], BackfillKind::Anime).unwrap();
+        assert_eq!(args.options.kind, BackfillKind::All);
+        assert!(!args.options.list_only && !args.options.only_incomplete);
+
+        assert!(parse(&["--force"], BackfillKind::Movie).is_err());
+        assert!(parse(&["--limit", "0"], BackfillKind::Tv).is_err());
+        assert!(parse(&["--limit"], BackfillKind::Tv).is_err());
+    }
+
+    #[test]
+    fn lookup_keys_ignore_title_punctuation_but_not_the_season() {
+        let page = |season: &str| {
+            json!({ "Season": { "select": { "name": season } } })
+                .as_object()
+                .unwrap()
+                .clone()
+        };
+        let tv = BackfillTarget::Tv;
+        // A text or number Season is read the same way as a select.
+        let text = json!({ "Season": { "rich_text": [{ "plain_text": "Season 2" }] } });
+        let number = json!({ "Season": { "number": 2 } });
+        for props in [text, number] {
+            assert_eq!(
+                lookup_key(props.as_object().unwrap(), "The Office", tv),
+                lookup_key(&page("Season 2"), "The Office", tv)
+            );
+        }
+        assert_eq!(
+            lookup_key(&page("Season 1"), "The Office", tv),
+            lookup_key(&page("Season 1"), "the office!", tv)
+        );
+        assert_ne!(
+            lookup_key(&page("Season 1"), "The Office", tv),
+            lookup_key(&page("Season 2"), "The Office", tv)
+        );
+        assert_ne!(
+            lookup_key(&page("Season 1"), "The Office", tv),
+            lookup_key(&page("Season 1"), "The Office", BackfillTarget::Movie)
+        );
+
+        // Pages already linked to an AniList entry are keyed on that entry, not the title.
+        let linked = |id: i32| {
+            json!({
+                "ID": { "number": id },
+                "IMDb Page": { "url": format!("https://anilist.co/anime/{id}") }
+            })
+            .as_object()
+            .unwrap()
+            .clone()
+        };
+        let anime = BackfillTarget::AniList(AniListMediaType::Anime);
+        assert_ne!(
+            lookup_key(&linked(1), "Monster", anime),
+            lookup_key(&linked(2), "Monster", anime)
+        );
+    }
+
+    #[test]
+    fn movies_without_a_director_are_incomplete() {
+        let props = |id: Value, director: &str| {
+            json!({
+                "ID": { "number": id },
+                "Director": { "rich_text": [{ "plain_text": director }] }
+            })
+            .as_object()
+            .unwrap()
+            .clone()
+        };
+        let movie = BackfillTarget::Movie;
+        assert!(is_incomplete(&props(Value::Null, "Someone"), movie));
+        assert!(is_incomplete(&props(json!(1), ""), movie));
+        assert!(!is_incomplete(&props(json!(1), ""), BackfillTarget::Tv));
+        assert!(!is_incomplete(&props(json!(1), "Someone"), movie));
+    }
+
+    #[test]
+    fn anilist_pages_are_detected_by_link_type_or_genre() {
+        let triggers = TriggerConfig::default();
+        let page = |props: Value| props.as_object().unwrap().clone();
+        let manga = page(json!({
+            "Type": { "select": { "name": "TV Series" } },
+            "ID": { "number": 30009 },
+            "IMDb Page": { "url": "https://anilist.co/manga/30009" }
+        }));
+        assert_eq!(
+            target_of(&manga, &triggers),
+            Some(BackfillTarget::AniList(AniListMediaType::Manga))
+        );
+        assert_eq!(stored_anilist_id(&manga), Some(30009));
+
+        let by_genre = page(json!({
+            "Type": { "select": { "name": "TV Series" } },
+            "ID": { "number": 1 },
+            "Genre": { "multi_select": [{ "name": "Action" }, { "name": "Anime" }] }
+        }));
+        let anime = Some(BackfillTarget::AniList(AniListMediaType::Anime));
+        assert_eq!(target_of(&by_genre, &triggers), anime);
+        assert_eq!(stored_anilist_id(&by_genre), None);
+
+        let by_type = page(json!({ "Type": { "select": { "name": "Anime" } } }));
+        assert_eq!(target_of(&by_type, &triggers), anime);
+
+        let tmdb_show = page(json!({
+            "Type": { "select": { "name": "TV Series" } },
+            "IMDb Page": { "url": "https://www.imdb.com/title/tt0903747" }
+        }));
+        assert_eq!(target_of(&tmdb_show, &triggers), Some(BackfillTarget::Tv));
+    }
+}
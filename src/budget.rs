@@ -0,0 +1,151 @@
+//! Per-page outbound request budget.
+//!
+//! Each enrichment runs inside [`scope`]; provider clients call [`charge`] before every HTTP
+//! attempt. Outside a scope (startup schema fetch, examples) charging is a no-op.
+use anyhow::Result;
+use std::fmt;
+use std::future::Future;
+use std::sync::atomic::{AtomicBool, AtomicU32, Ordering};
+use std::sync::Arc;
+
+pub const DEFAULT_REQUEST_BUDGET: u32 = 30;
+
+tokio::task_local! {
+    static BUDGET: Arc<RequestBudget>;
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Provider {
+    Tmdb,
+    AniList,
+    Notion,
+}
+
+impl Provider {
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Tmdb => "TMDB",
+            Self::AniList => "AniList",
+            Self::Notion => "Notion",
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct RequestBudget {
+    limit: u32,
+    tmdb: AtomicU32,
+    anilist: AtomicU32,
+    notion: AtomicU32,
+    exceeded: AtomicBool,
+}
+
+#[derive(Debug)]
+pub struct BudgetExceeded {
+    pub limit: u32,
+}
+
+impl fmt::Display for BudgetExceeded {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "request budget exceeded ({} requests)", self.limit)
+    }
+}
+
+impl std::error::Error for BudgetExceeded {}
+
+impl RequestBudget {
+    pub fn new(limit: u32) -> Self {
+        Self {
+            limit,
+            tmdb: AtomicU32::new(0),
+            anilist: AtomicU32::new(0),
+            notion: AtomicU32::new(0),
+            exceeded: AtomicBool::new(false),
+        }
+    }
+
+    fn counter(&self, provider: Provider) -> &AtomicU32 {
+        match provider {
+            Provider::Tmdb => &self.tmdb,
+            Provider::AniList => &self.anilist,
+            Provider::Notion => &self.notion,
+        }
+    }
+
+    pub fn count(&self, provider: Provider) -> u32 {
+        self.counter(provider).load(Ordering::Relaxed)
+    }
+
+    pub fn total(&self) -> u32 {
+        self.count(Provider::Tmdb) + self.count(Provider::AniList) + self.count(Provider::Notion)
+    }
+
+    pub fn exceeded(&self) -> bool {
+        self.exceeded.load(Ordering::Relaxed)
+    }
+
+    pub fn limit(&self) -> u32 {
+        self.limit
+    }
+
+    /// Refuses the request (without counting it) once the limit has been reached.
+    fn charge(&self, provider: Provider) -> Result<()> {
+        if self.exceeded() || self.total() >= self.limit {
+            self.exceeded.store(true, Ordering::Relaxed);
+            return Err(BudgetExceeded { limit: self.limit }.into());
+        }
+        self.counter(provider).fetch_add(1, Ordering::Relaxed);
+        Ok(())
+    }
+
+    /// e.g. `tmdb=3 anilist=0 notion=2`
+    pub fn summary(&self) -> String {
+        format!(
+            "tmdb={} anilist={} notion={}",
+            self.count(Provider::Tmdb),
+            self.count(Provider::AniList),
+            self.count(Provider::Notion)
+        )
+    }
+}
+
+/// Runs `fut` with `budget` as the active per-page budget. Only `fut` itself sees it; tasks
+/// it spawns start outside any scope.
+pub async fn scope<F: Future>(budget: Arc<RequestBudget>, fut: F) -> F::Output {
+    BUDGET.scope(budget, fut).await
+}
+
+/// Records one outbound request against the active budget, if any.
+pub fn charge(provider: Provider) -> Result<()> {
+    BUDGET
+        .try_with(|budget| budget.charge(provider))
+        .unwrap_or(Ok(()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn charge_is_a_no_op_outside_a_scope() {
+        for _ in 0..100 {
+            assert!(charge(Provider::Tmdb).is_ok());
+        }
+    }
+
+    #[tokio::test]
+    async fn refuses_requests_past_the_limit() {
+        let budget = Arc::new(RequestBudget::new(2));
+        let results = scope(budget.clone(), async {
+            [
+                charge(Provider::AniList).is_ok(),
+                charge(Provider::Notion).is_ok(),
+                charge(Provider::Tmdb).is_ok(),
+            ]
+        })
+        .await;
+        assert_eq!(results, [true, true, false]);
+        assert!(budget.exceeded());
+        assert_eq!(budget.summary(), "tmdb=0 anilist=1 notion=1");
+    }
+}
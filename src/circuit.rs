@@ -0,0 +1,195 @@
+//! Circuit breaker for an upstream API: after a run of failures, requests fail immediately
+//! for a cool-down period instead of each one retrying against a service that is down.
+use std::fmt;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use tracing::{info, warn};
+
+pub const DEFAULT_CIRCUIT_THRESHOLD: u32 = 10;
+pub const DEFAULT_CIRCUIT_COOLDOWN_SECS: u64 = 60;
+/// A failure this long after the previous one starts a new run.
+const FAILURE_WINDOW: Duration = Duration::from_secs(60);
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CircuitState {
+    /// Requests go through.
+    Closed,
+    /// Requests fail immediately until the cool-down has passed.
+    Open,
+    /// The cool-down has passed: the next outcome closes or re-opens the circuit.
+    HalfOpen,
+}
+
+impl CircuitState {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Closed => "closed",
+            Self::Open => "open",
+            Self::HalfOpen => "half_open",
+        }
+    }
+}
+
+/// Returned instead of sending a request while the circuit is open. Counts as a transient
+/// failure, so page jobs are retried later.
+#[derive(Debug)]
+pub struct CircuitOpen {
+    pub service: &'static str,
+    pub retry_in: Duration,
+}
+
+impl fmt::Display for CircuitOpen {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} circuit breaker is open (retrying in {}s)",
+            self.service,
+            self.retry_in.as_secs().max(1)
+        )
+    }
+}
+
+impl std::error::Error for CircuitOpen {}
+
+#[derive(Debug)]
+pub struct CircuitBreaker {
+    service: &'static str,
+    threshold: u32,
+    cooldown: Duration,
+    inner: Mutex<Inner>,
+}
+
+#[derive(Debug)]
+struct Inner {
+    state: CircuitState,
+    failures: u32,
+    last_failure: Option<Instant>,
+    opened_at: Option<Instant>,
+}
+
+impl CircuitBreaker {
+    /// Opens after `threshold` consecutive failures (`0` disables the breaker) and stays open
+    /// for `cooldown`.
+    pub fn new(service: &'static str, threshold: u32, cooldown: Duration) -> Self {
+        Self {
+            service,
+            threshold,
+            cooldown,
+            inner: Mutex::new(Inner {
+                state: CircuitState::Closed,
+                failures: 0,
+                last_failure: None,
+                opened_at: None,
+            }),
+        }
+    }
+
+    pub fn state(&self) -> CircuitState {
+        let mut inner = self.inner.lock().unwrap();
+        self.half_open_if_cooled_down(&mut inner);
+        inner.state
+    }
+
+    /// Whether a request may be sent now.
+    pub fn check(&self) -> Result<(), CircuitOpen> {
+        let mut inner = self.inner.lock().unwrap();
+        self.half_open_if_cooled_down(&mut inner);
+        match (inner.state, inner.opened_at) {
+            (CircuitState::Open, Some(opened_at)) => Err(CircuitOpen {
+                service: self.service,
+                retry_in: self.cooldown.saturating_sub(opened_at.elapsed()),
+            }),
+            _ => Ok(()),
+        }
+    }
+
+    pub fn record_success(&self) {
+        let mut inner = self.inner.lock().unwrap();
+        inner.failures = 0;
+        inner.last_failure = None;
+        if inner.state != CircuitState::Closed {
+            info!("{} circuit breaker closed", self.service);
+            inner.state = CircuitState::Closed;
+            inner.opened_at = None;
+        }
+    }
+
+    pub fn record_failure(&self) {
+        if self.threshold == 0 {
+            return;
+        }
+        let mut inner = self.inner.lock().unwrap();
+        let now = Instant::now();
+        if inner
+            .last_failure
+            .is_some_and(|at| now.duration_since(at) > FAILURE_WINDOW)
+        {
+            inner.failures = 0;
+        }
+        inner.failures += 1;
+        inner.last_failure = Some(now);
+        let reopen = inner.state == CircuitState::HalfOpen;
+        if reopen || (inner.state == CircuitState::Closed && inner.failures >= self.threshold) {
+            warn!(
+                "{} circuit breaker open after {} consecutive failures; failing fast for {}s",
+                self.service,
+                inner.failures,
+                self.cooldown.as_secs()
+            );
+            inner.state = CircuitState::Open;
+            inner.opened_at = Some(now);
+        }
+    }
+
+    fn half_open_if_cooled_down(&self, inner: &mut Inner) {
+        if inner.state == CircuitState::Open
+            && inner
+                .opened_at
+                .is_some_and(|at| at.elapsed() >= self.cooldown)
+        {
+            info!("{} circuit breaker half-open; trying again", self.service);
+            inner.state = CircuitState::HalfOpen;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn opens_after_the_threshold_and_closes_after_a_successful_trial() {
+        let breaker = CircuitBreaker::new("Notion", 3, Duration::ZERO);
+        breaker.record_failure();
+        breaker.record_failure();
+        breaker.record_success();
+        breaker.record_failure();
+        breaker.record_failure();
+        assert_eq!(breaker.state(), CircuitState::Closed);
+        breaker.record_failure();
+
+        // A zero cool-down half-opens right away; a failed trial re-opens it.
+        assert_eq!(breaker.state(), CircuitState::HalfOpen);
+        breaker.record_failure();
+        assert_eq!(breaker.state(), CircuitState::HalfOpen);
+        breaker.record_success();
+        assert_eq!(breaker.state(), CircuitState::Closed);
+        assert!(breaker.check().is_ok());
+    }
+
+    #[test]
+    fn rejects_requests_while_open() {
+        let breaker = CircuitBreaker::new("Notion", 1, Duration::from_secs(60));
+        breaker.record_failure();
+        assert_eq!(breaker.state(), CircuitState::Open);
+        let err = breaker.check().unwrap_err();
+        assert!(err.retry_in > Duration::from_secs(50));
+        assert!(err
+            .to_string()
+            .starts_with("Notion circuit breaker is open"));
+
+        let disabled = CircuitBreaker::new("Notion", 0, Duration::from_secs(60));
+        disabled.record_failure();
+        assert!(disabled.check().is_ok());
+    }
+}
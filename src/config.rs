@@ -0,0 +1,553 @@
+//! Tunable server limits, read from optional `CINELINK_*` env vars with built-in defaults.
+use crate::anilist::{RelationStrategy, DEFAULT_ANILIST_CACHE_TTL_SECS};
+use crate::budget::DEFAULT_REQUEST_BUDGET;
+use crate::circuit::{DEFAULT_CIRCUIT_COOLDOWN_SECS, DEFAULT_CIRCUIT_THRESHOLD};
+use crate::queue::DEFAULT_JOB_QUEUE_CAPACITY;
+use crate::retry::DEFAULT_RETRY_MAX_ATTEMPTS;
+use anyhow::Result;
+use ipnet::IpNet;
+use std::env;
+use std::fmt::Display;
+use std::net::IpAddr;
+use std::ops::RangeInclusive;
+use std::str::FromStr;
+use tracing::debug;
+
+pub const DEFAULT_MAX_BODY_BYTES: usize = 1024 * 1024; // 1MB safety cap
+pub const DEFAULT_PER_IP_LIMIT: u32 = 60; // per minute
+pub const DEFAULT_PER_IP_BURST: u32 = 10;
+pub const DEFAULT_GLOBAL_LIMIT: u32 = 200; // per minute
+pub const DEFAULT_GLOBAL_BURST: u32 = 20;
+pub const DEFAULT_DEDUPE_TTL_SECS: i64 = 600; // 10 minutes
+pub const DEFAULT_MAX_CONCURRENT_JOBS: usize = 8;
+pub const DEFAULT_SYNC_TIMEOUT_SECS: u64 = 30;
+pub const DEFAULT_NEGATIVE_CACHE_TTL_SECS: u64 = 600; // 10 minutes
+pub const DEFAULT_SHUTDOWN_GRACE_SECS: u64 = 30;
+pub const DEFAULT_MAX_KEYWORDS: usize = 10;
+
+/// Which existing page values an enrichment may replace (`CINELINK_OVERWRITE_MODE`).
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum OverwriteMode {
+    /// Every enriched property, the icon and the cover are written.
+    #[default]
+    Always,
+    /// Properties that already have a value are kept, except the title, `ID`, `Last Synced`
+    /// and `Source`; the icon and cover are only set when missing.
+    FillEmpty,
+    /// Like `FillEmpty`, but an existing `ID` is kept too.
+    Never,
+}
+
+impl OverwriteMode {
+    /// `always`, `fill_empty` or `never` (any case).
+    pub fn parse(raw: &str) -> Option<Self> {
+        match raw.trim().to_ascii_lowercase().as_str() {
+            "always" => Some(Self::Always),
+            "fill_empty" => Some(Self::FillEmpty),
+            "never" => Some(Self::Never),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct AppConfig {
+    /// Page jobs processed at once (`processing_sem` permits and webhook workers).
+    pub max_concurrent_jobs: usize,
+    /// Webhook page jobs that may wait for a worker before webhooks are refused with 503.
+    pub job_queue_capacity: usize,
+    /// How long a webhook event id is remembered for dedupe.
+    pub dedupe_ttl_secs: i64,
+    /// Requests per minute per client IP; `per_ip_burst` more are tolerated.
+    pub per_ip_limit: u32,
+    pub per_ip_burst: u32,
+    /// Requests per minute across all clients; `global_burst` more are tolerated.
+    pub global_limit: u32,
+    pub global_burst: u32,
+    pub max_body_bytes: usize,
+    /// Max outbound requests (TMDB + AniList + Notion) a single page enrichment may make.
+    pub request_budget: u32,
+    /// Attempts per page job, including the first, before a transient failure is dropped.
+    pub retry_max_attempts: u32,
+    /// Log Notion writes instead of sending them (`CINELINK_DRY_RUN`, or `DRY_RUN`).
+    pub dry_run: bool,
+    /// How long a webhook sent with `x-cinelink-wait: true` waits for its page job.
+    pub sync_timeout_secs: u64,
+    /// How long a title that matched nothing is answered from memory; `0` disables it.
+    pub negative_cache_ttl_secs: u64,
+    /// Relations followed from one season to the next on AniList (`CINELINK_ANILIST_RELATIONS`).
+    pub anilist_relations: RelationStrategy,
+    /// How long AniList relations and titles are reused; `0` disables the cache.
+    pub anilist_cache_ttl_secs: u64,
+    /// Whether values already on the page are replaced (`CINELINK_OVERWRITE_MODE`).
+    pub overwrite_mode: OverwriteMode,
+    /// Consecutive failed Notion requests that open the circuit breaker; `0` disables it.
+    pub notion_circuit_threshold: u32,
+    /// How long an open circuit fails Notion requests before letting one through.
+    pub notion_circuit_cooldown_secs: u64,
+    /// Also write the synopsis as the page body, replacing its blocks
+    /// (`CINELINK_SYNOPSIS_AS_BODY`).
+    pub synopsis_as_body: bool,
+    /// How long shutdown waits for running page jobs once the server has stopped.
+    pub shutdown_grace_secs: u64,
+    /// Client networks allowed to call the webhook endpoint; empty allows everyone.
+    pub webhook_allowed_cidrs: Vec<IpNet>,
+    /// Peers whose forwarding headers name the client (`CINELINK_TRUSTED_PROXIES`); anyone
+    /// else is identified by their own address.
+    pub trusted_proxies: Vec<IpNet>,
+    /// Page icon for TMDB pages instead of the poster (`CINELINK_MOVIE_ICON_EMOJI`).
+    pub movie_icon_emoji: Option<String>,
+    /// Page icon for AniList pages instead of the poster (`CINELINK_ANIME_ICON_EMOJI`).
+    pub anime_icon_emoji: Option<String>,
+    /// Most keywords written to the `Keywords` property; `0` leaves it alone.
+    pub max_keywords: usize,
+}
+
+impl Default for AppConfig {
+    fn default() -> Self {
+        Self {
+            max_concurrent_jobs: DEFAULT_MAX_CONCURRENT_JOBS,
+            job_queue_capacity: DEFAULT_JOB_QUEUE_CAPACITY,
+            dedupe_ttl_secs: DEFAULT_DEDUPE_TTL_SECS,
+            per_ip_limit: DEFAULT_PER_IP_LIMIT,
+            per_ip_burst: DEFAULT_PER_IP_BURST,
+            global_limit: DEFAULT_GLOBAL_LIMIT,
+            global_burst: DEFAULT_GLOBAL_BURST,
+            max_body_bytes: DEFAULT_MAX_BODY_BYTES,
+            request_budget: DEFAULT_REQUEST_BUDGET,
+            retry_max_attempts: DEFAULT_RETRY_MAX_ATTEMPTS,
+            dry_run: false,
+            sync_timeout_secs: DEFAULT_SYNC_TIMEOUT_SECS,
+            negative_cache_ttl_secs: DEFAULT_NEGATIVE_CACHE_TTL_SECS,
+            anilist_relations: RelationStrategy::default(),
+            anilist_cache_ttl_secs: DEFAULT_ANILIST_CACHE_TTL_SECS,
+            overwrite_mode: OverwriteMode::default(),
+            notion_circuit_threshold: DEFAULT_CIRCUIT_THRESHOLD,
+            notion_circuit_cooldown_secs: DEFAULT_CIRCUIT_COOLDOWN_SECS,
+            synopsis_as_body: false,
+            shutdown_grace_secs: DEFAULT_SHUTDOWN_GRACE_SECS,
+            webhook_allowed_cidrs: Vec::new(),
+            trusted_proxies: Vec::new(),
+            movie_icon_emoji: None,
+            anime_icon_emoji: None,
+            max_keywords: DEFAULT_MAX_KEYWORDS,
+        }
+    }
+}
+
+impl AppConfig {
+    pub fn from_env() -> Result<Self> {
+        Self::from_lookup(|key| env::var(key).ok())
+    }
+
+    /// Builds the config from `lookup` (an env-like source); unset or empty values keep the
+    /// defaults, malformed or out-of-range values are errors.
+    pub fn from_lookup(lookup: impl Fn(&str) -> Option<String>) -> Result<Self> {
+        let d = Self::default();
+        Ok(Self {
+            max_concurrent_jobs: read(
+                &lookup,
+                "CINELINK_MAX_CONCURRENT_JOBS",
+                d.max_concurrent_jobs,
+                1..=256,
+            )?,
+            job_queue_capacity: read(
+                &lookup,
+                "CINELINK_JOB_QUEUE_CAPACITY",
+                d.job_queue_capacity,
+                1..=100_000,
+            )?,
+            dedupe_ttl_secs: read(
+                &lookup,
+                "CINELINK_DEDUPE_TTL_SECS",
+                d.dedupe_ttl_secs,
+                10..=86_400,
+            )?,
+            per_ip_limit: read(
+                &lookup,
+                "CINELINK_PER_IP_LIMIT",
+                d.per_ip_limit,
+                1..=100_000,
+            )?,
+            per_ip_burst: read(
+                &lookup,
+                "CINELINK_PER_IP_BURST",
+                d.per_ip_burst,
+                0..=100_000,
+            )?,
+            global_limit: read(
+                &lookup,
+                "CINELINK_GLOBAL_LIMIT",
+                d.global_limit,
+                1..=1_000_000,
+            )?,
+            global_burst: read(
+                &lookup,
+                "CINELINK_GLOBAL_BURST",
+                d.global_burst,
+                0..=1_000_000,
+            )?,
+            max_body_bytes: read(
+                &lookup,
+                "CINELINK_MAX_BODY_BYTES",
+                d.max_body_bytes,
+                1024..=16 * 1024 * 1024,
+            )?,
+            request_budget: read(
+                &lookup,
+                "CINELINK_REQUEST_BUDGET",
+                d.request_budget,
+                1..=1000,
+            )?,
+            retry_max_attempts: read(
+                &lookup,
+                "CINELINK_RETRY_MAX_ATTEMPTS",
+                d.retry_max_attempts,
+                1..=20,
+            )?,
+            dry_run: read_flag(&lookup, "CINELINK_DRY_RUN")? || read_flag(&lookup, "DRY_RUN")?,
+            sync_timeout_secs: read(
+                &lookup,
+                "CINELINK_SYNC_TIMEOUT_SECS",
+                d.sync_timeout_secs,
+                1..=300,
+            )?,
+            negative_cache_ttl_secs: read(
+                &lookup,
+                "CINELINK_NEGATIVE_CACHE_TTL_SECS",
+                d.negative_cache_ttl_secs,
+                0..=86_400,
+            )?,
+            anilist_relations: read_relations(&lookup, "CINELINK_ANILIST_RELATIONS")?,
+            anilist_cache_ttl_secs: read(
+                &lookup,
+                "CINELINK_ANILIST_CACHE_TTL_SECS",
+                d.anilist_cache_ttl_secs,
+                0..=7 * 86_400,
+            )?,
+            overwrite_mode: read_overwrite_mode(&lookup)?,
+            notion_circuit_threshold: read(
+                &lookup,
+                "CINELINK_NOTION_CIRCUIT_THRESHOLD",
+                d.notion_circuit_threshold,
+                0..=1000,
+            )?,
+            notion_circuit_cooldown_secs: read(
+                &lookup,
+                "CINELINK_NOTION_CIRCUIT_COOLDOWN_SECS",
+                d.notion_circuit_cooldown_secs,
+                1..=3600,
+            )?,
+            synopsis_as_body: read_flag(&lookup, "CINELINK_SYNOPSIS_AS_BODY")?,
+            shutdown_grace_secs: read(
+                &lookup,
+                "CINELINK_SHUTDOWN_GRACE_SECS",
+                d.shutdown_grace_secs,
+                0..=600,
+            )?,
+            webhook_allowed_cidrs: read_cidrs(
+                &lookup,
+                &["CINELINK_WEBHOOK_ALLOWED_CIDRS", "WEBHOOK_ALLOWED_CIDRS"],
+            )?,
+            trusted_proxies: read_cidrs(&lookup, &["CINELINK_TRUSTED_PROXIES"])?,
+            movie_icon_emoji: read_emoji(&lookup, "CINELINK_MOVIE_ICON_EMOJI")?,
+            anime_icon_emoji: read_emoji(&lookup, "CINELINK_ANIME_ICON_EMOJI")?,
+            max_keywords: read(&lookup, "CINELINK_MAX_KEYWORDS", d.max_keywords, 0..=100)?,
+        })
+    }
+
+    /// Whether `ip` may call the webhook endpoint; an unparsable address only passes when
+    /// there is no allowlist. IPv4-mapped IPv6 addresses are matched as IPv4.
+    pub fn webhook_ip_allowed(&self, ip: &str) -> bool {
+        if self.webhook_allowed_cidrs.is_empty() {
+            return true;
+        }
+        ip.parse::<IpAddr>().is_ok_and(|ip| {
+            let ip = ip.to_canonical();
+            self.webhook_allowed_cidrs
+                .iter()
+                .any(|net| net.contains(&ip))
+        })
+    }
+
+    /// Whether `peer` may name the client in forwarding headers.
+    pub fn is_trusted_proxy(&self, peer: IpAddr) -> bool {
+        let peer = peer.to_canonical();
+        self.trusted_proxies.iter().any(|net| net.contains(&peer))
+    }
+
+    /// Logs every active value at debug level.
+    pub fn log(&self) {
+        debug!("max_concurrent_jobs = {}", self.max_concurrent_jobs);
+        debug!("job_queue_capacity = {}", self.job_queue_capacity);
+        debug!("dedupe_ttl_secs = {}", self.dedupe_ttl_secs);
+        debug!(
+            "per_ip_limit = {} (+{} burst)",
+            self.per_ip_limit, self.per_ip_burst
+        );
+        debug!(
+            "global_limit = {} (+{} burst)",
+            self.global_limit, self.global_burst
+        );
+        debug!("max_body_bytes = {}", self.max_body_bytes);
+        debug!("request_budget = {}", self.request_budget);
+        debug!("retry_max_attempts = {}", self.retry_max_attempts);
+        debug!("dry_run = {}", self.dry_run);
+        debug!("sync_timeout_secs = {}", self.sync_timeout_secs);
+        debug!("negative_cache_ttl_secs = {}", self.negative_cache_ttl_secs);
+        debug!("anilist_relations = {:?}", self.anilist_relations);
+        debug!("anilist_cache_ttl_secs = {}", self.anilist_cache_ttl_secs);
+        debug!("overwrite_mode = {:?}", self.overwrite_mode);
+        debug!(
+            "notion_circuit = {} failures, {}s cool-down",
+            self.notion_circuit_threshold, self.notion_circuit_cooldown_secs
+        );
+        debug!("synopsis_as_body = {}", self.synopsis_as_body);
+        debug!("shutdown_grace_secs = {}", self.shutdown_grace_secs);
+        debug!("webhook_allowed_cidrs = {:?}", self.webhook_allowed_cidrs);
+        debug!("trusted_proxies = {:?}", self.trusted_proxies);
+        debug!(
+            "icon emoji = {:?} (TMDB), {:?} (AniList)",
+            self.movie_icon_emoji, self.anime_icon_emoji
+        );
+        debug!("max_keywords = {}", self.max_keywords);
+    }
+}
+
+fn read<T>(
+    lookup: &impl Fn(&str) -> Option<String>,
+    key: &str,
+    default: T,
+    range: RangeInclusive<T>,
+) -> Result<T>
+where
+    T: FromStr + PartialOrd + Display,
+{
+    let Some(raw) = lookup(key).filter(|v| !v.trim().is_empty()) else {
+        return Ok(default);
+    };
+    raw.trim()
+        .parse::<T>()
+        .ok()
+        .filter(|v| range.contains(v))
+        .ok_or_else(|| {
+            anyhow::anyhow!(
+                "Invalid {}: {:?} (expected {}-{})",
+                key,
+                raw,
+                range.start(),
+                range.end()
+            )
+        })
+}
+
+/// `1`/`true`/`yes`/`on` or `0`/`false`/`no`/`off` (any case); unset or empty is false.
+fn read_flag(lookup: &impl Fn(&str) -> Option<String>, key: &str) -> Result<bool> {
+    let Some(raw) = lookup(key).filter(|v| !v.trim().is_empty()) else {
+        return Ok(false);
+    };
+    match raw.trim().to_ascii_lowercase().as_str() {
+        "1" | "true" | "yes" | "on" => Ok(true),
+        "0" | "false" | "no" | "off" => Ok(false),
+        _ => anyhow::bail!("Invalid {}: {:?} (expected true or false)", key, raw),
+    }
+}
+
+fn read_relations(lookup: &impl Fn(&str) -> Option<String>, key: &str) -> Result<RelationStrategy> {
+    let Some(raw) = lookup(key).filter(|v| !v.trim().is_empty()) else {
+        return Ok(RelationStrategy::default());
+    };
+    RelationStrategy::parse(&raw).ok_or_else(|| {
+        anyhow::anyhow!(
+            "Invalid {}: {:?} (expected sequel, side_story or alternative)",
+            key,
+            raw
+        )
+    })
+}
+
+/// `CINELINK_OVERWRITE_MODE`, or `OVERWRITE_MODE` when that is unset.
+fn read_overwrite_mode(lookup: &impl Fn(&str) -> Option<String>) -> Result<OverwriteMode> {
+    let value = ["CINELINK_OVERWRITE_MODE", "OVERWRITE_MODE"]
+        .into_iter()
+        .find_map(|key| Some(key).zip(lookup(key).filter(|v| !v.trim().is_empty())));
+    let Some((key, raw)) = value else {
+        return Ok(OverwriteMode::default());
+    };
+    OverwriteMode::parse(&raw).ok_or_else(|| {
+        anyhow::anyhow!(
+            "Invalid {}: {:?} (expected always, fill_empty or never)",
+            key,
+            raw
+        )
+    })
+}
+
+/// An emoji to use as page icon; plain ASCII (a name like `movie`) is refused, since Notion
+/// only takes the emoji character itself.
+fn read_emoji(lookup: &impl Fn(&str) -> Option<String>, key: &str) -> Result<Option<String>> {
+    let Some(raw) = lookup(key).filter(|v| !v.trim().is_empty()) else {
+        return Ok(None);
+    };
+    let emoji = raw.trim();
+    if emoji.is_ascii() {
+        anyhow::bail!("Invalid {}: {:?} (expected an emoji, e.g. 🎬)", key, raw);
+    }
+    Ok(Some(emoji.to_string()))
+}
+
+/// The first of `keys` that is set, read as a comma-separated list of CIDRs (`10.0.0.0/8`,
+/// `2001:db8::/32`) or single addresses.
+fn read_cidrs(lookup: &impl Fn(&str) -> Option<String>, keys: &[&str]) -> Result<Vec<IpNet>> {
+    let value = keys
+        .iter()
+        .copied()
+        .find_map(|key| Some(key).zip(lookup(key).filter(|v| !v.trim().is_empty())));
+    let Some((key, raw)) = value else {
+        return Ok(Vec::new());
+    };
+    raw.split(',')
+        .map(str::trim)
+        .filter(|entry| !entry.is_empty())
+        .map(|entry| {
+            entry
+                .parse::<IpNet>()
+                .or_else(|_| entry.parse::<IpAddr>().map(IpNet::from))
+                .map_err(|_| {
+                    anyhow::anyhow!(
+                        "Invalid {}: {:?} is not a CIDR (e.g. 10.0.0.0/8) or IP address",
+                        key,
+                        entry
+                    )
+                })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    fn config(vars: &[(&str, &str)]) -> Result<AppConfig> {
+        let vars: HashMap<String, String> = vars
+            .iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect();
+        AppConfig::from_lookup(|key| vars.get(key).cloned())
+    }
+
+    #[test]
+    fn defaults_when_unset_or_empty() {
+        assert_eq!(
+            config(&[("CINELINK_PER_IP_LIMIT", "")]).unwrap(),
+            AppConfig::default()
+        );
+    }
+
+    #[test]
+    fn reads_overrides() {
+        let cfg = config(&[
+            ("CINELINK_MAX_CONCURRENT_JOBS", "16"),
+            ("CINELINK_JOB_QUEUE_CAPACITY", "1000"),
+            ("CINELINK_DEDUPE_TTL_SECS", " 3600 "),
+            ("CINELINK_GLOBAL_BURST", "0"),
+            ("CINELINK_SHUTDOWN_GRACE_SECS", "0"),
+            ("CINELINK_ANILIST_CACHE_TTL_SECS", "3600"),
+            ("CINELINK_MOVIE_ICON_EMOJI", " 🎬 "),
+            ("CINELINK_MAX_KEYWORDS", "0"),
+        ])
+        .unwrap();
+        assert_eq!(cfg.max_concurrent_jobs, 16);
+        assert_eq!(cfg.job_queue_capacity, 1000);
+        assert_eq!(cfg.dedupe_ttl_secs, 3600);
+        assert_eq!(cfg.global_burst, 0);
+        assert_eq!(cfg.shutdown_grace_secs, 0);
+        assert_eq!(cfg.anilist_cache_ttl_secs, 3600);
+        assert_eq!(cfg.movie_icon_emoji.as_deref(), Some("🎬"));
+        assert_eq!(cfg.anime_icon_emoji, None);
+        assert_eq!(cfg.max_keywords, 0);
+        assert_eq!(cfg.per_ip_limit, DEFAULT_PER_IP_LIMIT);
+        assert!(!cfg.dry_run);
+        assert!(config(&[("CINELINK_DRY_RUN", "TRUE")]).unwrap().dry_run);
+        assert!(config(&[("DRY_RUN", "true")]).unwrap().dry_run);
+        assert!(
+            config(&[("CINELINK_SYNOPSIS_AS_BODY", "1")])
+                .unwrap()
+                .synopsis_as_body
+        );
+        assert_eq!(
+            config(&[("CINELINK_ANILIST_RELATIONS", "Side_Story")])
+                .unwrap()
+                .anilist_relations,
+            RelationStrategy::IncludeSideStory
+        );
+        assert_eq!(
+            config(&[("OVERWRITE_MODE", "Fill_Empty")])
+                .unwrap()
+                .overwrite_mode,
+            OverwriteMode::FillEmpty
+        );
+    }
+
+    #[test]
+    fn rejects_malformed_and_out_of_range_values() {
+        for (key, value) in [
+            ("CINELINK_MAX_CONCURRENT_JOBS", "0"),
+            ("CINELINK_MAX_CONCURRENT_JOBS", "257"),
+            ("CINELINK_JOB_QUEUE_CAPACITY", "0"),
+            ("CINELINK_DEDUPE_TTL_SECS", "5"),
+            ("CINELINK_REQUEST_BUDGET", "lots"),
+            ("CINELINK_DRY_RUN", "maybe"),
+            ("CINELINK_ANILIST_RELATIONS", "prequel"),
+            ("CINELINK_OVERWRITE_MODE", "sometimes"),
+            ("CINELINK_NOTION_CIRCUIT_COOLDOWN_SECS", "0"),
+            ("CINELINK_SHUTDOWN_GRACE_SECS", "601"),
+            ("CINELINK_ANILIST_CACHE_TTL_SECS", "-1"),
+            ("CINELINK_ANIME_ICON_EMOJI", ":tv:"),
+            ("CINELINK_MAX_KEYWORDS", "101"),
+            ("WEBHOOK_ALLOWED_CIDRS", "10.0.0.0/8, 10.0.0.300/32"),
+            ("CINELINK_WEBHOOK_ALLOWED_CIDRS", "2001:db8::/129"),
+            ("CINELINK_TRUSTED_PROXIES", "proxy"),
+        ] {
+            let err = config(&[(key, value)]).unwrap_err().to_string();
+            assert!(err.starts_with(&format!("Invalid {key}")), "{err}");
+        }
+    }
+
+    #[test]
+    fn webhook_allowlist_matches_v4_and_v6_networks() {
+        assert!(AppConfig::default().webhook_ip_allowed("203.0.113.9"));
+        assert!(AppConfig::default().webhook_ip_allowed("unknown"));
+
+        let cfg = config(&[(
+            "WEBHOOK_ALLOWED_CIDRS",
+            "10.0.0.0/8, 192.0.2.7,2001:db8::/32",
+        )])
+        .unwrap();
+        assert_eq!(cfg.webhook_allowed_cidrs.len(), 3);
+        for ip in [
+            "10.1.2.3",
+            "192.0.2.7",
+            "::ffff:10.1.2.3",
+            "2001:db8:ffff::9",
+        ] {
+            assert!(cfg.webhook_ip_allowed(ip), "{ip}");
+        }
+        for ip in ["11.0.0.1", "192.0.2.8", "2001:db9::1", "unknown"] {
+            assert!(!cfg.webhook_ip_allowed(ip), "{ip}");
+        }
+    }
+
+    #[test]
+    fn only_listed_peers_are_trusted_proxies() {
+        let peer = |ip: &str| ip.parse::<IpAddr>().unwrap();
+        assert!(!AppConfig::default().is_trusted_proxy(peer("127.0.0.1")));
+
+        let cfg = config(&[("CINELINK_TRUSTED_PROXIES", "127.0.0.1, 172.16.0.0/12")]).unwrap();
+        for ip in ["127.0.0.1", "172.20.0.3", "::ffff:172.20.0.3"] {
+            assert!(cfg.is_trusted_proxy(peer(ip)), "{ip}");
+        }
+        assert!(!cfg.is_trusted_proxy(peer("10.0.0.1")));
+    }
+}
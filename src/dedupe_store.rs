@@ -0,0 +1,237 @@
+//! On-disk copy of the webhook dedupe set (`AppState::recent_events`), so Notion's retries
+//! after a restart aren't processed twice.
+//!
+//! The store is an append-only file with one `{"id": "...", "ts": 1700000000}` line per
+//! accepted event. It is loaded (and compacted) at startup and compacted again whenever it
+//! grows well past the live set. Compaction writes a `.tmp` file next to the store and renames
+//! it over the old one, so a crash leaves either the old or the new file, never half of one.
+//! Unreadable files or lines are skipped with a warning; store errors never stop the server.
+use serde_json::json;
+use std::collections::HashMap;
+use std::fs::{self, File, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use tokio::sync::{mpsc, oneshot};
+use tracing::{info, warn};
+
+/// Appended lines tolerated before the file is rewritten from the live set.
+const COMPACT_AFTER_LINES: usize = 20_000;
+
+/// Appends and compactions run on a blocking writer task fed through a channel, so recording
+/// an event never does file I/O while `AppState::recent_events` is locked.
+pub struct DedupeStore {
+    tx: mpsc::UnboundedSender<StoreOp>,
+}
+
+enum StoreOp {
+    Record(String, i64),
+    Flush(oneshot::Sender<()>),
+}
+
+struct StoreFile {
+    path: PathBuf,
+    ttl_secs: i64,
+    file: Option<File>,
+    lines: usize,
+    /// The writer's own copy of the events within `ttl_secs`, rewritten on compaction.
+    live: HashMap<String, i64>,
+}
+
+impl DedupeStore {
+    /// Loads the events seen within `ttl_secs` of `now` and starts the writer task, which
+    /// appends to the store. Must be called inside a tokio runtime.
+    pub fn open(path: impl Into<PathBuf>, ttl_secs: i64, now: i64) -> (Self, HashMap<String, i64>) {
+        let path = path.into();
+        let events = load(&path, ttl_secs, now);
+        let mut store = StoreFile {
+            path,
+            ttl_secs,
+            file: None,
+            lines: 0,
+            live: events.clone(),
+        };
+        store.compact();
+        info!(
+            "Loaded {} recent webhook event ids from {}",
+            events.len(),
+            store.path.display()
+        );
+        let (tx, mut rx) = mpsc::unbounded_channel();
+        tokio::task::spawn_blocking(move || {
+            while let Some(op) = rx.blocking_recv() {
+                match op {
+                    StoreOp::Record(event_id, ts) => store.record(event_id, ts),
+                    StoreOp::Flush(done) => {
+                        let _ = done.send(());
+                    }
+                }
+            }
+        });
+        (Self { tx }, events)
+    }
+
+    /// Queues one accepted event for appending.
+    pub fn record(&self, event_id: &str, ts: i64) {
+        // The writer only stops once every sender is gone.
+        let _ = self.tx.send(StoreOp::Record(event_id.to_string(), ts));
+    }
+
+    /// Waits until every event recorded so far has been written.
+    pub async fn flush(&self) {
+        let (done, written) = oneshot::channel();
+        if self.tx.send(StoreOp::Flush(done)).is_ok() {
+            let _ = written.await;
+        }
+    }
+}
+
+impl StoreFile {
+    fn record(&mut self, event_id: String, ts: i64) {
+        let line = json!({ "id": event_id, "ts": ts }).to_string();
+        self.live.insert(event_id, ts);
+        if self.lines >= COMPACT_AFTER_LINES {
+            let ttl_secs = self.ttl_secs;
+            self.live.retain(|_, seen| ts - *seen <= ttl_secs);
+            self.compact();
+            return;
+        }
+        let Some(file) = self.file.as_mut() else {
+            return;
+        };
+        if let Err(e) = writeln!(file, "{}", line) {
+            warn!(
+                "Failed to append to dedupe store {}: {}",
+                self.path.display(),
+                e
+            );
+            return;
+        }
+        self.lines += 1;
+    }
+
+    /// Rewrites the file with just `live` and reopens it for appending.
+    fn compact(&mut self) {
+        self.file = None;
+        let mut contents = String::new();
+        for (id, ts) in &self.live {
+            contents.push_str(&json!({ "id": id, "ts": ts }).to_string());
+            contents.push('\n');
+        }
+        if let Err(e) = write_atomically(&self.path, &contents) {
+            warn!(
+                "Failed to rewrite dedupe store {}: {}",
+                self.path.display(),
+                e
+            );
+        }
+        match OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)
+        {
+            Ok(file) => {
+                self.file = Some(file);
+                self.lines = self.live.len();
+            }
+            Err(e) => warn!(
+                "Dedupe store {} is not writable; event ids won't survive a restart: {}",
+                self.path.display(),
+                e
+            ),
+        }
+    }
+}
+
+/// Writes `contents` to `<path>.tmp`, flushes it to disk, then renames it over `path`.
+fn write_atomically(path: &Path, contents: &str) -> std::io::Result<()> {
+    let mut tmp = path.as_os_str().to_owned();
+    tmp.push(".tmp");
+    let tmp = PathBuf::from(tmp);
+    let mut file = File::create(&tmp)?;
+    file.write_all(contents.as_bytes())?;
+    file.sync_all()?;
+    fs::rename(&tmp, path)
+}
+
+fn load(path: &Path, ttl_secs: i64, now: i64) -> HashMap<String, i64> {
+    let raw = match fs::read_to_string(path) {
+        Ok(raw) => raw,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return HashMap::new(),
+        Err(e) => {
+            warn!("Ignoring unreadable dedupe store {}: {}", path.display(), e);
+            return HashMap::new();
+        }
+    };
+    let mut events = HashMap::new();
+    let mut skipped = 0usize;
+    for line in raw.lines().filter(|l| !l.trim().is_empty()) {
+        let entry = serde_json::from_str::<serde_json::Value>(line).ok();
+        let parsed = entry
+            .as_ref()
+            .and_then(|v| Some((v.get("id")?.as_str()?.to_string(), v.get("ts")?.as_i64()?)));
+        match parsed {
+            Some((id, ts)) if now - ts <= ttl_secs => {
+                events.insert(id, ts);
+            }
+            Some(_) => {}
+            None => skipped += 1,
+        }
+    }
+    if skipped > 0 {
+        warn!(
+            "Skipped {} malformed lines in dedupe store {}",
+            skipped,
+            path.display()
+        );
+    }
+    events
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_path(name: &str) -> PathBuf {
+        let path = std::env::temp_dir().join(format!(
+            "cinelink-dedupe-{}-{}.ndjson",
+            std::process::id(),
+            name
+        ));
+        let _ = fs::remove_file(&path);
+        path
+    }
+
+    #[tokio::test]
+    async fn recorded_events_survive_a_reopen_until_they_expire() {
+        let path = temp_path("reopen");
+        let (store, live) = DedupeStore::open(&path, 600, 1_000);
+        assert!(live.is_empty());
+        for (id, ts) in [("evt-old", 100), ("evt-new", 1_000)] {
+            store.record(id, ts);
+        }
+        store.flush().await;
+        drop(store);
+
+        let (_store, loaded) = DedupeStore::open(&path, 600, 1_200);
+        assert_eq!(loaded, HashMap::from([("evt-new".to_string(), 1_000)]));
+        // Startup compaction dropped the expired line and left no temporary file behind.
+        assert_eq!(fs::read_to_string(&path).unwrap().lines().count(), 1);
+        let mut tmp = path.clone().into_os_string();
+        tmp.push(".tmp");
+        assert!(!Path::new(&tmp).exists());
+        let _ = fs::remove_file(&path);
+    }
+
+    #[tokio::test]
+    async fn corrupt_lines_are_skipped() {
+        let path = temp_path("corrupt");
+        fs::write(
+            &path,
+            "not json\n{\"id\":\"evt-1\",\"ts\":50}\n{\"id\":7}\n\u{0}\u{1}garbage",
+        )
+        .unwrap();
+        let (_store, loaded) = DedupeStore::open(&path, 600, 100);
+        assert_eq!(loaded, HashMap::from([("evt-1".to_string(), 50)]));
+        let _ = fs::remove_file(&path);
+    }
+}
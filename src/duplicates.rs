@@ -0,0 +1,174 @@
+//! Duplicate detection: after enrichment, look for other pages carrying the same provider id.
+use crate::metrics::DUPLICATE_INDEX_CACHE;
+use crate::notion::{self, NotionApi};
+use crate::tmdb;
+use anyhow::Result;
+use serde_json::Value;
+use std::collections::HashMap;
+use std::time::Instant;
+use tokio::sync::Mutex;
+
+const INDEX_TTL_SECS: u64 = 120;
+const MAX_INDEX_ENTRIES: usize = 10_000;
+
+/// What an enriched page was matched against. TMDB movie and TV ids live in separate
+/// namespaces (and AniList ids in another), so the kind is part of the identity.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum MediaKind {
+    Movie,
+    Tv,
+    Anime,
+    Manga,
+}
+
+impl MediaKind {
+    /// The metadata source the id belongs to.
+    pub fn provider(&self) -> &'static str {
+        match self {
+            MediaKind::Movie | MediaKind::Tv => "tmdb",
+            MediaKind::Anime | MediaKind::Manga => "anilist",
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct MediaKey {
+    pub kind: MediaKind,
+    pub id: i32,
+    /// TV seasons of the same show share a TMDB id; they are only duplicates per season.
+    pub season: Option<i32>,
+}
+
+#[derive(Clone, Debug)]
+pub struct IndexedPage {
+    pub page_id: String,
+    pub title: String,
+}
+
+#[derive(Debug)]
+struct IndexEntry {
+    fetched_at: Instant,
+    pages: Vec<IndexedPage>,
+}
+
+/// Short-lived cache of "which pages carry this provider id", so backfills don't issue one
+/// filtered database query per page.
+#[derive(Debug, Default)]
+pub struct DuplicateIndex {
+    entries: Mutex<HashMap<MediaKey, IndexEntry>>,
+}
+
+impl DuplicateIndex {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns other pages already indexed under `key`, then records `page_id` under it.
+    pub async fn find_and_record(
+        &self,
+        notion: &dyn NotionApi,
+        title_property: &str,
+        key: &MediaKey,
+        page_id: &str,
+        title: &str,
+    ) -> Result<Vec<IndexedPage>> {
+        let cached = {
+            let mut guard = self.entries.lock().await;
+            guard.retain(|_, v| v.fetched_at.elapsed().as_secs() < INDEX_TTL_SECS);
+            guard.get(key).map(|e| e.pages.clone())
+        };
+        DUPLICATE_INDEX_CACHE.record(cached.is_some());
+
+        let pages = match cached {
+            Some(pages) => pages,
+            None => notion
+                .query_pages_by_number("ID", key.id as f64)
+                .await?
+                .iter()
+                .filter_map(|page| indexed_page_for_key(page, title_property, key))
+                .collect(),
+        };
+
+        let this_page = normalize_page_id(page_id);
+        let others: Vec<IndexedPage> = pages
+            .iter()
+            .filter(|p| normalize_page_id(&p.page_id) != this_page)
+            .cloned()
+            .collect();
+
+        let mut guard = self.entries.lock().await;
+        if guard.len() > MAX_INDEX_ENTRIES {
+            guard.clear();
+        }
+        let entry = guard.entry(key.clone()).or_insert_with(|| IndexEntry {
+            fetched_at: Instant::now(),
+            pages,
+        });
+        if !entry
+            .pages
+            .iter()
+            .any(|p| normalize_page_id(&p.page_id) == this_page)
+        {
+            entry.pages.push(IndexedPage {
+                page_id: page_id.to_string(),
+                title: title.to_string(),
+            });
+        }
+
+        Ok(others)
+    }
+}
+
+/// Infers what a stored page was enriched from, based on the properties CineLink writes.
+pub fn page_media_kind(props: &serde_json::Map<String, Value>) -> MediaKind {
+    let link = notion::extract_url(props, "IMDb Page").unwrap_or_default();
+    if link.contains("anilist.co/manga") {
+        return MediaKind::Manga;
+    }
+    if link.contains("anilist.co") {
+        return MediaKind::Anime;
+    }
+    let is_tv = notion::extract_select(props, "Type")
+        .map(|t| t.to_lowercase().contains("tv"))
+        .unwrap_or(false);
+    if is_tv {
+        MediaKind::Tv
+    } else {
+        MediaKind::Movie
+    }
+}
+
+pub fn duplicate_comment(others: &[IndexedPage]) -> String {
+    let titles = others
+        .iter()
+        .map(|p| format!("\"{}\"", p.title))
+        .collect::<Vec<_>>()
+        .join(", ");
+    format!("⚠ duplicate of {}", titles)
+}
+
+fn indexed_page_for_key(page: &Value, title_property: &str, key: &MediaKey) -> Option<IndexedPage> {
+    let page_id = page.get("id").and_then(|v| v.as_str())?;
+    let props = page.get("properties").and_then(|p| p.as_object())?;
+    if page_media_kind(props) != key.kind {
+        return None;
+    }
+    if key.kind == MediaKind::Tv {
+        let season = notion::extract_select(props, "Season")
+            .or_else(|| notion::extract_rich_text(props, "Season"))
+            .or_else(|| notion::extract_number(props, "Season").map(|n| (n as i32).to_string()))
+            .as_deref()
+            .and_then(tmdb::parse_season_number);
+        if season != key.season {
+            return None;
+        }
+    }
+    Some(IndexedPage {
+        page_id: page_id.to_string(),
+        title: notion::extract_title(props, title_property).unwrap_or_default(),
+    })
+}
+
+fn normalize_page_id(id: &str) -> String {
+    id.replace('-', "").to_ascii_lowercase()
+}
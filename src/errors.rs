@@ -0,0 +1,98 @@
+//! Error categories for deciding whether a failed page job is worth retrying.
+use crate::budget::BudgetExceeded;
+use crate::circuit::CircuitOpen;
+use crate::notion::NotionApiError;
+use std::fmt;
+
+/// A non-success HTTP status from an upstream API (TMDB, AniList, Notion).
+#[derive(Debug)]
+pub struct UpstreamStatus {
+    pub status: u16,
+    message: String,
+}
+
+impl UpstreamStatus {
+    pub fn new(status: u16, message: impl Into<String>) -> Self {
+        Self {
+            status,
+            message: message.into(),
+        }
+    }
+}
+
+impl fmt::Display for UpstreamStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.message)
+    }
+}
+
+impl std::error::Error for UpstreamStatus {}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FailureKind {
+    /// Timeouts, connection errors, 429 and 5xx responses, an open circuit: may succeed later.
+    Transient,
+    /// Everything else (no match, 4xx, bad data, budget exceeded): retrying won't help.
+    Permanent,
+}
+
+fn is_retryable_status(status: u16) -> bool {
+    status == 429 || (500..600).contains(&status)
+}
+
+pub fn classify(err: &anyhow::Error) -> FailureKind {
+    if err.downcast_ref::<BudgetExceeded>().is_some() {
+        return FailureKind::Permanent;
+    }
+    for cause in err.chain() {
+        let transient = if let Some(e) = cause.downcast_ref::<reqwest::Error>() {
+            e.is_timeout()
+                || e.is_connect()
+                || e.is_request()
+                || e.status().is_some_and(|s| is_retryable_status(s.as_u16()))
+        } else if let Some(e) = cause.downcast_ref::<UpstreamStatus>() {
+            is_retryable_status(e.status)
+        } else if let Some(e) = cause.downcast_ref::<NotionApiError>() {
+            is_retryable_status(e.status.as_u16())
+        } else {
+            cause.downcast_ref::<CircuitOpen>().is_some()
+        };
+        if transient {
+            return FailureKind::Transient;
+        }
+    }
+    FailureKind::Permanent
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anyhow::Context;
+
+    #[test]
+    fn server_errors_are_transient_even_with_context() {
+        let err = anyhow::Error::new(UpstreamStatus::new(503, "Notion page request failed"))
+            .context("Failed to fetch page");
+        assert_eq!(classify(&err), FailureKind::Transient);
+        let err: anyhow::Error = UpstreamStatus::new(429, "slow down").into();
+        assert_eq!(classify(&err), FailureKind::Transient);
+        let err = anyhow::Error::new(CircuitOpen {
+            service: "Notion",
+            retry_in: std::time::Duration::from_secs(30),
+        })
+        .context("Failed to fetch page");
+        assert_eq!(classify(&err), FailureKind::Transient);
+    }
+
+    #[test]
+    fn client_errors_and_misses_are_permanent() {
+        let err: anyhow::Error = UpstreamStatus::new(404, "not found").into();
+        assert_eq!(classify(&err), FailureKind::Permanent);
+        let err = anyhow::anyhow!("No TMDB movie found for 'x'");
+        assert_eq!(classify(&err), FailureKind::Permanent);
+        let err: anyhow::Error = Err::<(), _>(BudgetExceeded { limit: 3 })
+            .context("aborted")
+            .unwrap_err();
+        assert_eq!(classify(&err), FailureKind::Permanent);
+    }
+}
@@ -0,0 +1,74 @@
+//! Bounded in-memory log of page jobs that failed, so they can be replayed by id
+//! (`POST /admin/replay`) once the root cause is fixed.
+use std::collections::VecDeque;
+use std::sync::Mutex;
+
+/// Oldest entries are dropped once this many failures are held.
+const MAX_FAILURES: usize = 200;
+
+#[derive(Clone, Debug)]
+pub struct FailureEntry {
+    pub id: u64,
+    pub page_id: String,
+    pub event_id: Option<String>,
+    pub error: String,
+    pub failed_at: i64,
+}
+
+#[derive(Debug, Default)]
+pub struct FailureLog {
+    inner: Mutex<FailureLogInner>,
+}
+
+#[derive(Debug, Default)]
+struct FailureLogInner {
+    next_id: u64,
+    entries: VecDeque<FailureEntry>,
+}
+
+impl FailureLog {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Stores a failure and returns its id (ids start at 1 and are never reused).
+    pub fn record(&self, page_id: &str, event_id: Option<&str>, error: &str) -> u64 {
+        let mut guard = self.inner.lock().unwrap_or_else(|e| e.into_inner());
+        guard.next_id += 1;
+        let id = guard.next_id;
+        if guard.entries.len() >= MAX_FAILURES {
+            guard.entries.pop_front();
+        }
+        guard.entries.push_back(FailureEntry {
+            id,
+            page_id: page_id.to_string(),
+            event_id: event_id.map(str::to_string),
+            error: error.to_string(),
+            failed_at: chrono::Utc::now().timestamp(),
+        });
+        id
+    }
+
+    pub fn get(&self, id: u64) -> Option<FailureEntry> {
+        let guard = self.inner.lock().unwrap_or_else(|e| e.into_inner());
+        guard.entries.iter().find(|e| e.id == id).cloned()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn evicts_oldest_entries_past_the_cap() {
+        let log = FailureLog::new();
+        for i in 0..MAX_FAILURES + 1 {
+            log.record(&format!("page-{i}"), None, "boom");
+        }
+        assert!(log.get(1).is_none());
+        let last = log
+            .get(MAX_FAILURES as u64 + 1)
+            .expect("latest failure kept");
+        assert_eq!(last.page_id, format!("page-{MAX_FAILURES}"));
+    }
+}
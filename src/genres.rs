@@ -0,0 +1,93 @@
+//! Genre/tag clean-up shared by the TMDB and AniList flows, so the Notion multi-select
+//! doesn't accumulate "Sci-Fi" / "sci-fi" style variants.
+use std::collections::HashSet;
+
+/// Tokens kept upper-case regardless of input casing.
+const ACRONYMS: &[&str] = &["TV", "OVA", "ONA", "3D"];
+/// Joining words kept lower-case unless they start the genre ("Slice of Life").
+const MINOR_WORDS: &[&str] = &["of", "and", "the", "in", "on", "de", "du", "et"];
+
+/// Trims, title-cases and drops case-insensitive duplicates, keeping the first occurrence.
+pub fn normalize_genres(genres: Vec<String>) -> Vec<String> {
+    let mut seen = HashSet::new();
+    genres
+        .into_iter()
+        .map(|g| title_case_genre(&g))
+        .filter(|g| !g.is_empty())
+        .filter(|g| seen.insert(g.to_lowercase()))
+        .collect()
+}
+
+fn title_case_genre(genre: &str) -> String {
+    genre
+        .split_whitespace()
+        .enumerate()
+        .map(|(i, word)| {
+            word.split('-')
+                .map(|part| title_case_word(part, i == 0))
+                .collect::<Vec<_>>()
+                .join("-")
+        })
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+fn title_case_word(word: &str, first: bool) -> String {
+    if let Some(acronym) = ACRONYMS.iter().find(|a| a.eq_ignore_ascii_case(word)) {
+        return acronym.to_string();
+    }
+    let lower = word.to_lowercase();
+    if !first && MINOR_WORDS.contains(&lower.as_str()) {
+        return lower;
+    }
+    let mut chars = lower.chars();
+    match chars.next() {
+        Some(c) => c.to_uppercase().chain(chars).collect(),
+        None => String::new(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn strings(items: &[&str]) -> Vec<String> {
+        items.iter().map(|s| s.to_string()).collect()
+    }
+
+    #[test]
+    fn title_cases_with_acronyms_and_minor_words() {
+        assert_eq!(
+            normalize_genres(strings(&[
+                " sci-fi ",
+                "tv movie",
+                "ova",
+                "slice of life",
+                "SCIENCE FICTION"
+            ])),
+            strings(&[
+                "Sci-Fi",
+                "TV Movie",
+                "OVA",
+                "Slice of Life",
+                "Science Fiction"
+            ])
+        );
+    }
+
+    #[test]
+    fn collapses_case_insensitive_duplicates() {
+        assert_eq!(
+            normalize_genres(strings(&["Sci-Fi", "sci-fi", "Action", "ACTION", ""])),
+            strings(&["Sci-Fi", "Action"])
+        );
+    }
+
+    #[test]
+    fn keeps_ampersand_genres_intact() {
+        assert_eq!(
+            normalize_genres(strings(&["Action & Adventure", "3d"])),
+            strings(&["Action & Adventure", "3D"])
+        );
+    }
+}
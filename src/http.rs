@@ -0,0 +1,187 @@
+//! HTTP plumbing shared by the TMDB, Notion and AniList clients: one `reqwest::Client` (and
+//! so one connection pool) and one retry policy.
+//!
+//! 429s, 5xx answers, timeouts and connection errors are retried with exponential backoff
+//! (or after `Retry-After`, capped at 30s). Every attempt is charged to the page's request
+//! budget.
+use crate::budget::{self, Provider};
+use anyhow::{Context, Result};
+use reqwest::header::HeaderValue;
+use reqwest::{Client, RequestBuilder, Response};
+use std::time::Duration;
+
+/// Attempts per request, including the first.
+pub const DEFAULT_MAX_RETRIES: usize = 3;
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(5);
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(30);
+const MAX_RETRY_AFTER_SECS: u64 = 30;
+const MAX_BACKOFF_MS: u64 = 5_000;
+
+/// The client every upstream API shares unless one is injected.
+pub fn default_client() -> Result<Client> {
+    Client::builder()
+        .connect_timeout(CONNECT_TIMEOUT)
+        .timeout(REQUEST_TIMEOUT)
+        .user_agent(format!("cinelink/{}", env!("CARGO_PKG_VERSION")))
+        .build()
+        .context("Failed to build HTTP client")
+}
+
+#[derive(Debug, Clone)]
+pub struct RetryingClient {
+    client: Client,
+    provider: Provider,
+    max_retries: usize,
+}
+
+impl RetryingClient {
+    pub fn new(client: Client, provider: Provider) -> Self {
+        Self {
+            client,
+            provider,
+            max_retries: DEFAULT_MAX_RETRIES,
+        }
+    }
+
+    /// Attempts per request, including the first (at least one).
+    pub fn with_max_retries(mut self, max_retries: usize) -> Self {
+        self.max_retries = max_retries.max(1);
+        self
+    }
+
+    /// The underlying client, for building requests.
+    pub fn client(&self) -> &Client {
+        &self.client
+    }
+
+    /// Sends the request built by `make_req`, retrying as described in the module docs. The
+    /// last response is returned whatever its status; only a network error that outlasts the
+    /// retries is an `Err`.
+    ///
+    /// Every attempt is charged to the per-page budget of the calling task (see
+    /// `budget::scope`). The budget is task-local: a request sent from a task started with
+    /// `tokio::spawn` inside the scope is not counted, so keep provider calls on the job's
+    /// own task (joining futures is fine).
+    pub async fn send(&self, mut make_req: impl FnMut() -> RequestBuilder) -> Result<Response> {
+        let mut attempt = 0;
+        loop {
+            attempt += 1;
+            budget::charge(self.provider)?;
+            let last = attempt >= self.max_retries;
+            match make_req().send().await {
+                Ok(res) => {
+                    if !last && is_retryable_status(res.status().as_u16()) {
+                        let delay = retry_delay(attempt, res.headers().get("retry-after"));
+                        tokio::time::sleep(delay).await;
+                        continue;
+                    }
+                    return Ok(res);
+                }
+                Err(e) => {
+                    if !last && (e.is_timeout() || e.is_connect() || e.is_request()) {
+                        tokio::time::sleep(retry_delay(attempt, None)).await;
+                        continue;
+                    }
+                    return Err(e).context(format!("{} HTTP request failed", self.provider.name()));
+                }
+            }
+        }
+    }
+}
+
+fn is_retryable_status(status: u16) -> bool {
+    status == 429 || (500..600).contains(&status)
+}
+
+/// `Retry-After` in seconds when present, else 200ms doubling per attempt plus jitter.
+pub fn retry_delay(attempt: usize, retry_after: Option<&HeaderValue>) -> Duration {
+    if let Some(v) = retry_after.and_then(|h| h.to_str().ok()) {
+        if let Ok(secs) = v.trim().parse::<u64>() {
+            return Duration::from_secs(secs.min(MAX_RETRY_AFTER_SECS));
+        }
+    }
+    let base_ms = 200u64.saturating_mul(2u64.saturating_pow(attempt.saturating_sub(1) as u32));
+    Duration::from_millis((base_ms + jitter_ms()).min(MAX_BACKOFF_MS))
+}
+
+fn jitter_ms() -> u64 {
+    use std::time::{SystemTime, UNIX_EPOCH};
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default();
+    (now.subsec_millis() as u64) % 100
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use wiremock::matchers::method;
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    fn retrying(max_retries: usize) -> RetryingClient {
+        RetryingClient::new(default_client().unwrap(), Provider::Tmdb).with_max_retries(max_retries)
+    }
+
+    #[test]
+    fn delay_honors_retry_after_and_backs_off() {
+        let header = HeaderValue::from_static("7");
+        assert_eq!(retry_delay(1, Some(&header)), Duration::from_secs(7));
+        let header = HeaderValue::from_static("3600");
+        assert_eq!(retry_delay(1, Some(&header)), Duration::from_secs(30));
+        assert!(retry_delay(1, None) < Duration::from_millis(300));
+        assert!(retry_delay(3, None) >= Duration::from_millis(800));
+        assert_eq!(retry_delay(20, None), Duration::from_millis(MAX_BACKOFF_MS));
+    }
+
+    #[tokio::test]
+    async fn retries_throttled_and_failed_answers_until_one_succeeds() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .respond_with(ResponseTemplate::new(429).insert_header("retry-after", "0"))
+            .up_to_n_times(1)
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .respond_with(ResponseTemplate::new(503))
+            .up_to_n_times(1)
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .respond_with(ResponseTemplate::new(200))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let http = retrying(3);
+        let res = http.send(|| http.client().get(server.uri())).await.unwrap();
+        assert_eq!(res.status().as_u16(), 200);
+    }
+
+    #[tokio::test]
+    async fn returns_the_last_answer_once_attempts_run_out() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .respond_with(ResponseTemplate::new(500))
+            .expect(2)
+            .mount(&server)
+            .await;
+
+        let http = retrying(2);
+        let res = http.send(|| http.client().get(server.uri())).await.unwrap();
+        assert_eq!(res.status().as_u16(), 500);
+    }
+
+    #[tokio::test]
+    async fn client_errors_are_not_retried() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .respond_with(ResponseTemplate::new(404))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let http = retrying(3);
+        let res = http.send(|| http.client().get(server.uri())).await.unwrap();
+        assert_eq!(res.status().as_u16(), 404);
+    }
+}
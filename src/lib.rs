@@ -1,5 +1,26 @@
 pub mod anilist;
 pub mod app;
+pub mod backfill;
+pub mod budget;
+pub mod circuit;
+pub mod config;
+pub mod countries;
+pub mod dedupe_store;
+pub mod duplicates;
+pub mod errors;
+pub mod failures;
+pub mod genres;
+pub mod http;
+pub mod languages;
+pub mod metrics;
+pub mod negative_cache;
+pub mod notify;
 pub mod notion;
 pub mod notion_fallback;
+pub mod queue;
+pub mod rate_limit;
+pub mod retry;
+pub mod titles;
 pub mod tmdb;
+pub mod triggers;
+pub mod webhook;
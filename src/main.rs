@@ -4,28 +4,50 @@ use std::env;
 use tracing::{info, warn};
 use tracing_subscriber::EnvFilter;
 
+/// Compact human-readable lines by default; `CINELINK_LOG_FORMAT=json` switches to one JSON
+/// object per line (with targets, for filtering in log aggregators).
 fn init_tracing() {
     let filter = EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new("info"));
-    tracing_subscriber::fmt()
-        .with_env_filter(filter)
-        .with_target(false)
-        .compact()
-        .init();
+    let json = env::var("CINELINK_LOG_FORMAT").is_ok_and(|v| v.trim().eq_ignore_ascii_case("json"));
+    let builder = tracing_subscriber::fmt().with_env_filter(filter);
+    if json {
+        builder.json().init();
+    } else {
+        builder.with_target(false).compact().init();
+    }
 }
 
 fn check_env() -> Result<()> {
-    let required = [
-        "NOTION_API_KEY",
-        "NOTION_DATABASE_ID",
-        "TMDB_API_KEY",
-        "NOTION_WEBHOOK_SECRET",
-    ];
+    let required = ["NOTION_API_KEY", "NOTION_DATABASE_ID", "TMDB_API_KEY"];
     for key in required {
         if env::var(key).is_err() {
             anyhow::bail!("Missing required environment variable: {}", key);
         }
     }
     info!("All required environment variables are set");
+    let skip_secret_check = env::args()
+        .skip(1)
+        .any(|arg| arg == "--skip-secret-validation")
+        || env::var("CINELINK_SKIP_SECRET_CHECK")
+            .is_ok_and(|v| matches!(v.trim().to_ascii_lowercase().as_str(), "1" | "true"));
+    let primary_key = "NOTION_WEBHOOK_SECRET";
+    let secondary_key = "NOTION_WEBHOOK_SECRET_SECONDARY";
+    let secondary = env::var(secondary_key).unwrap_or_default();
+    if skip_secret_check {
+        warn!("Webhook secret validation skipped; don't do this in production");
+        // The sentinel only means something for the primary secret; never sign with it.
+        if secondary == cinelink::app::INSECURE_DISABLED_SECRET {
+            cinelink::app::validate_secondary_signing_secret(secondary_key, &secondary)?;
+        }
+    } else {
+        let primary = env::var(primary_key).unwrap_or_default();
+        cinelink::app::validate_signing_secret(primary_key, &primary)?;
+        cinelink::app::validate_secondary_signing_secret(secondary_key, &secondary)?;
+    }
+    // Optional; validated here so a bad value fails fast, before any network setup.
+    let triggers = cinelink::triggers::TriggerConfig::from_env()?;
+    info!("Title triggers: {}", triggers.describe());
+    cinelink::config::AppConfig::from_env()?.log();
     Ok(())
 }
 
@@ -0,0 +1,358 @@
+//! In-process counters exported in the Prometheus text format on `GET /metrics`.
+use std::fmt::Write;
+use std::sync::atomic::{AtomicI64, AtomicU64, Ordering};
+use std::time::Duration;
+
+/// Upper bounds (seconds) of the page processing duration histogram.
+const DURATION_BUCKETS: &[f64] = &[0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0, 30.0, 60.0];
+
+#[derive(Debug, Default)]
+pub struct Counter(AtomicU64);
+
+impl Counter {
+    pub const fn new() -> Self {
+        Self(AtomicU64::new(0))
+    }
+
+    pub fn inc(&self) {
+        self.0.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn get(&self) -> u64 {
+        self.0.load(Ordering::Relaxed)
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct Gauge(AtomicI64);
+
+impl Gauge {
+    pub fn inc(&self) {
+        self.0.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn dec(&self) {
+        self.0.fetch_sub(1, Ordering::Relaxed);
+    }
+
+    pub fn get(&self) -> i64 {
+        self.0.load(Ordering::Relaxed)
+    }
+
+    /// Increments the gauge until the guard is dropped, so an early return or a panic in the
+    /// tracked work can't leave it raised.
+    pub fn track(&self) -> GaugeGuard<'_> {
+        self.inc();
+        GaugeGuard(self)
+    }
+}
+
+pub struct GaugeGuard<'a>(&'a Gauge);
+
+impl Drop for GaugeGuard<'_> {
+    fn drop(&mut self) {
+        self.0.dec();
+    }
+}
+
+/// Hit/miss counts for one in-memory cache.
+#[derive(Debug, Default)]
+pub struct CacheCounters {
+    pub hits: Counter,
+    pub misses: Counter,
+}
+
+impl CacheCounters {
+    pub const fn new() -> Self {
+        Self {
+            hits: Counter::new(),
+            misses: Counter::new(),
+        }
+    }
+
+    pub fn record(&self, hit: bool) {
+        if hit {
+            self.hits.inc();
+        } else {
+            self.misses.inc();
+        }
+    }
+}
+
+// The caches live inside the provider clients rather than on `AppState`, so their counters
+// are process-wide.
+pub static TMDB_MOVIE_CACHE: CacheCounters = CacheCounters::new();
+pub static TMDB_SHOW_CACHE: CacheCounters = CacheCounters::new();
+pub static TMDB_SEARCH_CACHE: CacheCounters = CacheCounters::new();
+pub static ANILIST_RELATIONS_CACHE: CacheCounters = CacheCounters::new();
+pub static ANILIST_TITLE_CACHE: CacheCounters = CacheCounters::new();
+pub static DUPLICATE_INDEX_CACHE: CacheCounters = CacheCounters::new();
+pub static NEGATIVE_LOOKUP_CACHE: CacheCounters = CacheCounters::new();
+
+#[derive(Debug)]
+pub struct Histogram {
+    buckets: Vec<AtomicU64>,
+    sum_micros: AtomicU64,
+    count: AtomicU64,
+}
+
+impl Default for Histogram {
+    fn default() -> Self {
+        Self {
+            buckets: DURATION_BUCKETS.iter().map(|_| AtomicU64::new(0)).collect(),
+            sum_micros: AtomicU64::new(0),
+            count: AtomicU64::new(0),
+        }
+    }
+}
+
+impl Histogram {
+    pub fn observe(&self, elapsed: Duration) {
+        let secs = elapsed.as_secs_f64();
+        for (bound, bucket) in DURATION_BUCKETS.iter().zip(&self.buckets) {
+            if secs <= *bound {
+                bucket.fetch_add(1, Ordering::Relaxed);
+            }
+        }
+        self.sum_micros
+            .fetch_add(elapsed.as_micros() as u64, Ordering::Relaxed);
+        self.count.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn count(&self) -> u64 {
+        self.count.load(Ordering::Relaxed)
+    }
+
+    fn render(&self, out: &mut String, name: &str, help: &str) {
+        let _ = writeln!(out, "# HELP {name} {help}");
+        let _ = writeln!(out, "# TYPE {name} histogram");
+        for (bound, bucket) in DURATION_BUCKETS.iter().zip(&self.buckets) {
+            let _ = writeln!(
+                out,
+                "{name}_bucket{{le=\"{bound}\"}} {}",
+                bucket.load(Ordering::Relaxed)
+            );
+        }
+        let count = self.count();
+        let _ = writeln!(out, "{name}_bucket{{le=\"+Inf\"}} {count}");
+        let sum = self.sum_micros.load(Ordering::Relaxed) as f64 / 1_000_000.0;
+        let _ = writeln!(out, "{name}_sum {sum}");
+        let _ = writeln!(out, "{name}_count {count}");
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct Metrics {
+    pub webhooks_received: Counter,
+    pub signature_failures: Counter,
+    pub stale_signatures: Counter,
+    pub rate_limited: Counter,
+    pub admin_rate_limited: Counter,
+    pub webhooks_deduped: Counter,
+    pub queue_full: Counter,
+    pub ip_not_allowed: Counter,
+    pub pages_updated: Counter,
+    pub pages_no_match: Counter,
+    pub active_jobs: Gauge,
+    pub retry_queue_depth: Gauge,
+    pub job_queue_depth: Gauge,
+    pub busy_workers: Gauge,
+    pub tmdb_errors: Counter,
+    pub anilist_errors: Counter,
+    pub notion_errors: Counter,
+    pub unknown_shape_events: Counter,
+    pub page_duration: Histogram,
+}
+
+impl Metrics {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn render(&self) -> String {
+        let counters = [
+            (
+                "webhooks_received",
+                "Webhook requests received.",
+                &self.webhooks_received,
+            ),
+            // The per-reason counters from before `webhooks_rejected_total`, kept for existing
+            // dashboards until they move over.
+            (
+                "signature_failures",
+                "Deprecated: webhooks_rejected_total{reason=\"signature\"}.",
+                &self.signature_failures,
+            ),
+            (
+                "rate_limited",
+                "Deprecated: webhooks_rejected_total{reason=\"rate_limit\"}.",
+                &self.rate_limited,
+            ),
+            (
+                "webhooks_deduped",
+                "Deprecated: webhooks_rejected_total{reason=\"dedupe\"}.",
+                &self.webhooks_deduped,
+            ),
+            (
+                "admin_rate_limited",
+                "Admin /enrich and /search requests refused by the global rate limit.",
+                &self.admin_rate_limited,
+            ),
+            (
+                "pages_updated",
+                "Notion pages successfully enriched.",
+                &self.pages_updated,
+            ),
+            (
+                "pages_no_match",
+                "Pages whose title matched nothing upstream.",
+                &self.pages_no_match,
+            ),
+            (
+                "tmdb_errors",
+                "TMDB lookups that failed.",
+                &self.tmdb_errors,
+            ),
+            (
+                "anilist_errors",
+                "AniList lookups that failed.",
+                &self.anilist_errors,
+            ),
+            (
+                "notion_errors",
+                "Notion API calls that failed.",
+                &self.notion_errors,
+            ),
+            (
+                "unknown_shape_events",
+                "Webhook payloads that deviated from the known shape.",
+                &self.unknown_shape_events,
+            ),
+        ];
+
+        let mut out = String::new();
+        for (name, help, counter) in counters {
+            let _ = writeln!(out, "# HELP cinelink_{name}_total {help}");
+            let _ = writeln!(out, "# TYPE cinelink_{name}_total counter");
+            let _ = writeln!(out, "cinelink_{name}_total {}", counter.get());
+        }
+
+        let _ = writeln!(
+            out,
+            "# HELP cinelink_webhooks_rejected_total Webhooks not processed, by reason."
+        );
+        let _ = writeln!(out, "# TYPE cinelink_webhooks_rejected_total counter");
+        for (reason, counter) in [
+            ("rate_limit", &self.rate_limited),
+            ("signature", &self.signature_failures),
+            ("stale", &self.stale_signatures),
+            ("dedupe", &self.webhooks_deduped),
+            ("queue_full", &self.queue_full),
+            ("ip_allowlist", &self.ip_not_allowed),
+        ] {
+            let _ = writeln!(
+                out,
+                "cinelink_webhooks_rejected_total{{reason=\"{reason}\"}} {}",
+                counter.get()
+            );
+        }
+
+        let gauges = [
+            (
+                "active_jobs",
+                "Page jobs currently holding a processing slot.",
+                &self.active_jobs,
+            ),
+            (
+                "retry_queue_depth",
+                "Failed pages waiting for a retry.",
+                &self.retry_queue_depth,
+            ),
+            (
+                "job_queue_depth",
+                "Webhook page jobs waiting for a worker.",
+                &self.job_queue_depth,
+            ),
+            (
+                "busy_workers",
+                "Webhook workers currently running a page job.",
+                &self.busy_workers,
+            ),
+        ];
+        for (name, help, gauge) in gauges {
+            let _ = writeln!(out, "# HELP cinelink_{name} {help}");
+            let _ = writeln!(out, "# TYPE cinelink_{name} gauge");
+            let _ = writeln!(out, "cinelink_{name} {}", gauge.get());
+        }
+
+        let caches = [
+            ("tmdb_movie", &TMDB_MOVIE_CACHE),
+            ("tmdb_show", &TMDB_SHOW_CACHE),
+            ("tmdb_search", &TMDB_SEARCH_CACHE),
+            ("anilist_relations", &ANILIST_RELATIONS_CACHE),
+            ("anilist_title", &ANILIST_TITLE_CACHE),
+            ("duplicate_index", &DUPLICATE_INDEX_CACHE),
+            ("negative_lookup", &NEGATIVE_LOOKUP_CACHE),
+        ];
+        for (kind, help) in [
+            ("hits", "Cache lookups served"),
+            ("misses", "Cache lookups missed"),
+        ] {
+            let _ = writeln!(out, "# HELP cinelink_cache_{kind}_total {help}, by cache.");
+            let _ = writeln!(out, "# TYPE cinelink_cache_{kind}_total counter");
+            for (cache, counters) in caches {
+                let counter = if kind == "hits" {
+                    &counters.hits
+                } else {
+                    &counters.misses
+                };
+                let _ = writeln!(
+                    out,
+                    "cinelink_cache_{kind}_total{{cache=\"{cache}\"}} {}",
+                    counter.get()
+                );
+            }
+        }
+        self.page_duration.render(
+            &mut out,
+            "cinelink_page_processing_seconds",
+            "Time spent processing a page (fetch, lookup, update).",
+        );
+        out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn renders_counters_and_cumulative_buckets() {
+        let metrics = Metrics::new();
+        metrics.webhooks_received.inc();
+        metrics.webhooks_received.inc();
+        metrics.page_duration.observe(Duration::from_millis(300));
+        metrics.page_duration.observe(Duration::from_secs(3));
+
+        {
+            let _active = metrics.active_jobs.track();
+            assert_eq!(metrics.active_jobs.get(), 1);
+        }
+
+        let text = metrics.render();
+        assert!(text.contains("# TYPE cinelink_webhooks_received_total counter\n"));
+        assert!(text.contains("cinelink_webhooks_received_total 2\n"));
+        assert!(text.contains("cinelink_notion_errors_total 0\n"));
+        assert!(text.contains("cinelink_webhooks_rejected_total{reason=\"signature\"} 0\n"));
+        assert!(text.contains("cinelink_webhooks_rejected_total{reason=\"stale\"} 0\n"));
+        assert!(text.contains("cinelink_signature_failures_total 0\n"));
+        assert!(text.contains("# TYPE cinelink_active_jobs gauge\ncinelink_active_jobs 0\n"));
+        assert!(text.contains("cinelink_cache_misses_total{cache=\"tmdb_show\"} "));
+        assert!(text.contains("cinelink_page_processing_seconds_bucket{le=\"0.25\"} 0\n"));
+        assert!(text.contains("cinelink_page_processing_seconds_bucket{le=\"0.5\"} 1\n"));
+        assert!(text.contains("cinelink_page_processing_seconds_bucket{le=\"5\"} 2\n"));
+        assert!(text.contains("cinelink_page_processing_seconds_bucket{le=\"+Inf\"} 2\n"));
+        assert!(text.contains("cinelink_page_processing_seconds_sum 3.3\n"));
+        assert!(text.contains("cinelink_page_processing_seconds_count 2\n"));
+    }
+}
@@ -0,0 +1,114 @@
+//! Short-lived memory of lookups that matched nothing, so an unmatchable title ("wip notes;")
+//! doesn't cost a provider search every time the row is touched.
+use crate::budget::BudgetExceeded;
+use crate::duplicates::MediaKind;
+use crate::errors::{self, FailureKind};
+use crate::metrics::NEGATIVE_LOOKUP_CACHE;
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+use tokio::sync::Mutex;
+
+const MAX_ENTRIES: usize = 10_000;
+
+#[derive(Debug)]
+pub struct NegativeCache {
+    ttl: Duration,
+    entries: Mutex<HashMap<String, Instant>>,
+}
+
+impl NegativeCache {
+    /// A zero `ttl` disables the cache.
+    pub fn new(ttl: Duration) -> Self {
+        Self {
+            ttl,
+            entries: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Whether `query` found nothing for `kind` within the TTL.
+    pub async fn contains(&self, kind: MediaKind, query: &str, season: Option<i32>) -> bool {
+        if self.ttl.is_zero() {
+            return false;
+        }
+        let mut entries = self.entries.lock().await;
+        entries.retain(|_, at| at.elapsed() < self.ttl);
+        let hit = entries.contains_key(&key(kind, query, season));
+        NEGATIVE_LOOKUP_CACHE.record(hit);
+        hit
+    }
+
+    /// Remembers a failed lookup, unless the failure may go away on its own (timeouts, 5xx,
+    /// an exhausted request budget).
+    pub async fn record_failure(
+        &self,
+        kind: MediaKind,
+        query: &str,
+        season: Option<i32>,
+        err: &anyhow::Error,
+    ) {
+        if self.ttl.is_zero()
+            || err.downcast_ref::<BudgetExceeded>().is_some()
+            || errors::classify(err) == FailureKind::Transient
+        {
+            return;
+        }
+        let mut entries = self.entries.lock().await;
+        if entries.len() >= MAX_ENTRIES {
+            entries.clear();
+        }
+        entries.insert(key(kind, query, season), Instant::now());
+    }
+
+    /// Drops a remembered failure once the lookup has succeeded (e.g. on a forced run).
+    pub async fn forget(&self, kind: MediaKind, query: &str, season: Option<i32>) {
+        if self.ttl.is_zero() {
+            return;
+        }
+        self.entries.lock().await.remove(&key(kind, query, season));
+    }
+}
+
+/// Provider, kind, season and the normalized query: AniList anime lookups follow the season.
+fn key(kind: MediaKind, query: &str, season: Option<i32>) -> String {
+    let season = season.map(|s| s.to_string()).unwrap_or_default();
+    format!(
+        "{}:{:?}:{}:{}",
+        kind.provider(),
+        kind,
+        season,
+        query.trim().to_lowercase()
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::errors::UpstreamStatus;
+
+    #[tokio::test]
+    async fn remembers_permanent_failures_per_kind_and_normalized_query() {
+        let cache = NegativeCache::new(Duration::from_secs(60));
+        let not_found = anyhow::anyhow!("No TMDB movie found for 'wip notes'");
+        cache
+            .record_failure(MediaKind::Movie, "WIP notes ", None, &not_found)
+            .await;
+        assert!(cache.contains(MediaKind::Movie, "wip notes", None).await);
+        assert!(!cache.contains(MediaKind::Tv, "wip notes", None).await);
+
+        let unavailable = anyhow::Error::new(UpstreamStatus::new(503, "TMDB unavailable"));
+        cache
+            .record_failure(MediaKind::Tv, "wip notes", None, &unavailable)
+            .await;
+        assert!(!cache.contains(MediaKind::Tv, "wip notes", None).await);
+    }
+
+    #[tokio::test]
+    async fn zero_ttl_disables_the_cache() {
+        let cache = NegativeCache::new(Duration::ZERO);
+        let not_found = anyhow::anyhow!("no match");
+        cache
+            .record_failure(MediaKind::Anime, "wip", Some(2), &not_found)
+            .await;
+        assert!(!cache.contains(MediaKind::Anime, "wip", Some(2)).await);
+    }
+}
@@ -0,0 +1,61 @@
+//! Alerts for pages that couldn't be enriched, POSTed to `CINELINK_ERROR_WEBHOOK_URL` so a
+//! container without log aggregation still reports them.
+//!
+//! Each alert is `{"page_id": "...", "title": "...", "error": "..."}`. Sending happens in the
+//! background; a failed alert is logged and never affects the page job.
+use anyhow::{Context, Result};
+use reqwest::Client;
+use serde_json::json;
+use std::time::Duration;
+use tracing::{debug, warn};
+
+const NOTIFY_TIMEOUT_SECS: u64 = 10;
+
+#[derive(Debug, Clone)]
+pub struct ErrorNotifier {
+    client: Client,
+    url: String,
+}
+
+impl ErrorNotifier {
+    pub fn new(url: impl Into<String>) -> Result<Self> {
+        let client = Client::builder()
+            .timeout(Duration::from_secs(NOTIFY_TIMEOUT_SECS))
+            .build()
+            .context("Failed to build error webhook HTTP client")?;
+        Ok(Self {
+            client,
+            url: url.into(),
+        })
+    }
+
+    /// Reads `CINELINK_ERROR_WEBHOOK_URL`; `None` when it is unset or empty.
+    pub fn from_env() -> Result<Option<Self>> {
+        std::env::var("CINELINK_ERROR_WEBHOOK_URL")
+            .ok()
+            .map(|url| url.trim().to_string())
+            .filter(|url| !url.is_empty())
+            .map(Self::new)
+            .transpose()
+    }
+
+    /// Sends the alert without waiting for it. `title` is `null` when the page couldn't be read.
+    pub fn notify(&self, page_id: &str, title: Option<&str>, error: &str) {
+        let body = json!({ "page_id": page_id, "title": title, "error": error });
+        let request = self.client.post(&self.url).json(&body);
+        let page_id = page_id.to_string();
+        tokio::spawn(async move {
+            match request.send().await {
+                Ok(res) if res.status().is_success() => {
+                    debug!("Sent error alert for page {}", page_id);
+                }
+                Ok(res) => warn!(
+                    "Error webhook answered {} for page {}",
+                    res.status().as_u16(),
+                    page_id
+                ),
+                Err(e) => warn!("Failed to send error alert for page {}: {}", page_id, e),
+            }
+        });
+    }
+}
@@ -1,10 +1,18 @@
+use crate::budget::{BudgetExceeded, Provider};
+use crate::circuit::{
+    CircuitBreaker, CircuitState, DEFAULT_CIRCUIT_COOLDOWN_SECS, DEFAULT_CIRCUIT_THRESHOLD,
+};
+use crate::errors::UpstreamStatus;
+use crate::http::{self, RetryingClient};
 use anyhow::{Context, Result};
 use async_trait::async_trait;
+use futures_util::stream::{self, Stream, TryStreamExt};
 use reqwest::Client;
 use serde::Deserialize;
 use serde_json::{json, Map, Value};
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::env;
+use std::sync::{Arc, RwLock};
 use std::time::Duration;
 use tokio::sync::OnceCell;
 use tracing::{info, warn};
@@ -12,14 +20,25 @@ use tracing::{info, warn};
 use crate::notion_fallback::fallback_schema;
 
 pub const NOTION_VERSION: &str = "2025-09-03";
-const MAX_RETRIES: usize = 3;
+const DEFAULT_NOTION_BASE: &str = "https://api.notion.com/v1";
+/// Notion rejects `multi_select` arrays longer than this.
+const MAX_MULTI_SELECT_OPTIONS: usize = 100;
+/// Notion rejects multi-select option names longer than this (in characters).
+const MAX_OPTION_CHARS: usize = 100;
+/// Notion rejects `rich_text` items whose content is longer than this (in characters).
+const MAX_RICH_TEXT_CHARS: usize = 2000;
 
 #[derive(Debug, Clone)]
 pub struct NotionClient {
-    client: Client,
+    http: RetryingClient,
     api_key: String,
+    base_url: String,
     pub database_id: String,
     data_source_id: OnceCell<String>,
+    /// Log page updates and comments instead of sending them.
+    dry_run: bool,
+    /// Shared by clones, so every request sees the same run of failures.
+    circuit: Arc<CircuitBreaker>,
 }
 
 #[derive(Debug, Deserialize)]
@@ -29,8 +48,8 @@ struct NotionErrorBody {
 }
 
 #[derive(Debug)]
-struct NotionApiError {
-    status: reqwest::StatusCode,
+pub(crate) struct NotionApiError {
+    pub(crate) status: reqwest::StatusCode,
     code: Option<String>,
     message: Option<String>,
     raw: String,
@@ -71,6 +90,62 @@ pub trait NotionApi: Send + Sync {
         icon: Option<Value>,
         cover: Option<Value>,
     ) -> Result<()>;
+    /// Pages whose `property` number equals `value`. Implementations without query support
+    /// report no matches, which simply disables duplicate detection.
+    async fn query_pages_by_number(&self, _property: &str, _value: f64) -> Result<Vec<Value>> {
+        Ok(Vec::new())
+    }
+    /// Adds a page-level comment. No-op unless the implementation supports comments.
+    async fn add_comment(&self, _page_id: &str, _text: &str) -> Result<()> {
+        Ok(())
+    }
+    /// Appends `blocks` to the page body. No-op unless the implementation supports blocks.
+    async fn append_blocks(&self, _page_id: &str, _blocks: Vec<Value>) -> Result<()> {
+        Ok(())
+    }
+    /// Deletes every block in the page body. No-op unless the implementation supports blocks.
+    async fn clear_blocks(&self, _page_id: &str) -> Result<()> {
+        Ok(())
+    }
+    /// The circuit breaker guarding the API, if the implementation has one.
+    fn circuit_state(&self) -> Option<CircuitState> {
+        None
+    }
+    /// One page of all database pages, for backfills. Implementations without query support
+    /// list nothing.
+    async fn list_pages(
+        &self,
+        _start_cursor: Option<&str>,
+        _page_size: usize,
+    ) -> Result<DatabaseQueryResponse> {
+        Ok(DatabaseQueryResponse {
+            results: Vec::new(),
+            has_more: false,
+            next_cursor: None,
+        })
+    }
+}
+
+/// Every database page, one at a time, fetched `page_size` at a time as the stream is read.
+/// Stops after the first failed query.
+pub fn all_pages(
+    api: &dyn NotionApi,
+    page_size: usize,
+) -> impl Stream<Item = Result<Value>> + Send + '_ {
+    // `None` once the last batch has been fetched; the first query has no cursor.
+    stream::try_unfold(Some(None::<String>), move |cursor| async move {
+        let Some(cursor) = cursor else {
+            return anyhow::Ok(None);
+        };
+        let resp = api.list_pages(cursor.as_deref(), page_size).await?;
+        let next = if resp.has_more {
+            resp.next_cursor.map(Some)
+        } else {
+            None
+        };
+        Ok(Some((stream::iter(resp.results.into_iter().map(Ok)), next)))
+    })
+    .try_flatten()
 }
 
 #[derive(Debug, Clone, PartialEq)]
@@ -83,15 +158,68 @@ pub enum PropertyType {
     MultiSelect,
     Files,
     Date,
+    /// A select with status groups ("Not started", "In progress", "Done").
+    Status,
+    Checkbox,
+    /// Notion users; never written.
+    People,
+    /// Links to pages of another database; never written.
+    Relation,
     Unknown(String),
 }
 
+impl PropertyType {
+    /// Maps a Notion property `type` string.
+    fn from_notion(t: &str) -> Self {
+        match t {
+            "title" => Self::Title,
+            "rich_text" => Self::RichText,
+            "url" => Self::Url,
+            "number" => Self::Number,
+            "select" => Self::Select,
+            "multi_select" => Self::MultiSelect,
+            "files" => Self::Files,
+            "date" => Self::Date,
+            "status" => Self::Status,
+            "checkbox" => Self::Checkbox,
+            "people" => Self::People,
+            "relation" => Self::Relation,
+            other => Self::Unknown(other.to_string()),
+        }
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct PropertySchema {
     pub types: HashMap<String, PropertyType>,
     pub title_property: Option<String>,
 }
 
+impl PropertySchema {
+    pub fn has(&self, property: &str) -> bool {
+        self.types.contains_key(property)
+    }
+}
+
+/// The database schema shared by all jobs, replaceable at runtime when the database changes.
+/// Jobs take a snapshot with `current()`, so a reload never affects one already running.
+#[derive(Debug)]
+pub struct SharedSchema(RwLock<Arc<PropertySchema>>);
+
+impl SharedSchema {
+    pub fn new(schema: PropertySchema) -> Self {
+        Self(RwLock::new(Arc::new(schema)))
+    }
+
+    pub fn current(&self) -> Arc<PropertySchema> {
+        self.0.read().unwrap_or_else(|e| e.into_inner()).clone()
+    }
+
+    pub fn replace(&self, schema: PropertySchema) {
+        *self.0.write().unwrap_or_else(|e| e.into_inner()) = Arc::new(schema);
+    }
+}
+
 #[derive(Debug, Clone)]
 pub enum ValueInput {
     Text(String),
@@ -99,61 +227,89 @@ pub enum ValueInput {
     Number(f64),
     Url(String),
     Date(String),
+    Bool(bool),
+    /// Empties the property (no-op for titles, status, checkbox and read-only types).
+    Clear,
 }
 
 impl NotionClient {
     pub fn from_env() -> Result<Self> {
         let api_key = env::var("NOTION_API_KEY").context("NOTION_API_KEY not set")?;
         let database_id = env::var("NOTION_DATABASE_ID").context("NOTION_DATABASE_ID not set")?;
-        let env_data_source_id = env::var("NOTION_DATA_SOURCE_ID")
+        let client = Self::new(api_key, database_id)?;
+        if let Some(ds) = env::var("NOTION_DATA_SOURCE_ID")
             .ok()
-            .filter(|s| !s.trim().is_empty());
-        let user_agent = format!("cinelink/{}", env!("CARGO_PKG_VERSION"));
-        let client = Client::builder()
-            .connect_timeout(Duration::from_secs(5))
-            .timeout(Duration::from_secs(30))
-            .user_agent(user_agent)
-            .build()
-            .context("Failed to build Notion HTTP client")?;
-        let data_source_id = OnceCell::new();
-        if let Some(ds) = env_data_source_id {
-            let _ = data_source_id.set(ds);
+            .filter(|s| !s.trim().is_empty())
+        {
+            let _ = client.data_source_id.set(ds);
         }
+        Ok(client)
+    }
+
+    pub fn new(api_key: String, database_id: String) -> Result<Self> {
         Ok(Self {
-            client,
+            http: RetryingClient::new(http::default_client()?, Provider::Notion),
             api_key,
+            base_url: DEFAULT_NOTION_BASE.to_string(),
             database_id,
-            data_source_id,
+            data_source_id: OnceCell::new(),
+            dry_run: false,
+            circuit: Arc::new(CircuitBreaker::new(
+                "Notion",
+                DEFAULT_CIRCUIT_THRESHOLD,
+                Duration::from_secs(DEFAULT_CIRCUIT_COOLDOWN_SECS),
+            )),
         })
     }
 
+    /// Points the client at another Notion-compatible API root (e.g. a local mock).
+    pub fn with_base_url(mut self, base_url: impl Into<String>) -> Self {
+        self.base_url = base_url.into().trim_end_matches('/').to_string();
+        self
+    }
+
+    /// Sends requests through `client`, sharing its connection pool.
+    pub fn with_http_client(mut self, client: Client) -> Self {
+        self.http = RetryingClient::new(client, Provider::Notion);
+        self
+    }
+
+    pub fn with_dry_run(mut self, dry_run: bool) -> Self {
+        self.dry_run = dry_run;
+        self
+    }
+
+    /// Fails requests fast for `cooldown` after `threshold` consecutive failed requests
+    /// (`0` disables it).
+    pub fn with_circuit_breaker(mut self, threshold: u32, cooldown: Duration) -> Self {
+        self.circuit = Arc::new(CircuitBreaker::new("Notion", threshold, cooldown));
+        self
+    }
+
+    /// Sends through the shared retry policy, failing fast while the circuit is open. A
+    /// final 429/5xx or network error counts against the circuit.
     async fn send_with_retry(
         &self,
-        mut make_req: impl FnMut() -> reqwest::RequestBuilder,
+        make_req: impl FnMut() -> reqwest::RequestBuilder,
     ) -> Result<reqwest::Response> {
-        for attempt in 1..=MAX_RETRIES {
-            let res = make_req().send().await;
-            match res {
-                Ok(resp) => {
-                    let status = resp.status();
-                    if (status.as_u16() == 429 || status.is_server_error()) && attempt < MAX_RETRIES
-                    {
-                        let delay = retry_delay(attempt, resp.headers().get("retry-after"));
-                        tokio::time::sleep(delay).await;
-                        continue;
-                    }
-                    return Ok(resp);
+        self.circuit.check()?;
+        match self.http.send(make_req).await {
+            Ok(resp) => {
+                let status = resp.status();
+                if status.as_u16() == 429 || status.is_server_error() {
+                    self.circuit.record_failure();
+                } else {
+                    self.circuit.record_success();
                 }
-                Err(e) => {
-                    if attempt < MAX_RETRIES && (e.is_timeout() || e.is_connect()) {
-                        tokio::time::sleep(retry_delay(attempt, None)).await;
-                        continue;
-                    }
-                    return Err(e).context("Notion request failed");
+                Ok(resp)
+            }
+            Err(e) => {
+                if e.downcast_ref::<BudgetExceeded>().is_none() {
+                    self.circuit.record_failure();
                 }
+                Err(e)
             }
         }
-        unreachable!("loop returns on success/final error")
     }
 
     async fn resolve_data_source_id(&self) -> Result<String> {
@@ -161,10 +317,11 @@ impl NotionClient {
             return Ok(existing.clone());
         }
 
-        let url = format!("https://api.notion.com/v1/databases/{}", self.database_id);
+        let url = format!("{}/databases/{}", self.base_url, self.database_id);
         let res = self
             .send_with_retry(|| {
-                self.client
+                self.http
+                    .client()
                     .get(&url)
                     .header("Authorization", format!("Bearer {}", self.api_key))
                     .header("Notion-Version", NOTION_VERSION)
@@ -178,11 +335,15 @@ impl NotionClient {
             .await
             .context("Failed to read Notion database response body")?;
         if !status.is_success() {
-            return Err(anyhow::anyhow!(
-                "Notion database request failed (status {}): {}",
-                status,
-                String::from_utf8_lossy(&bytes)
-            ));
+            return Err(UpstreamStatus::new(
+                status.as_u16(),
+                format!(
+                    "Notion database request failed (status {}): {}",
+                    status,
+                    String::from_utf8_lossy(&bytes)
+                ),
+            )
+            .into());
         }
 
         let body: Value =
@@ -203,17 +364,22 @@ impl NotionClient {
     async fn post_query(
         &self,
         url: &str,
+        filter: Option<&Value>,
         start_cursor: Option<&str>,
         page_size: usize,
     ) -> Result<DatabaseQueryResponse> {
         let mut body = json!({ "page_size": page_size });
+        if let Some(filter) = filter {
+            body["filter"] = filter.clone();
+        }
         if let Some(cursor) = start_cursor {
             body["start_cursor"] = Value::String(cursor.to_string());
         }
 
         let res = self
             .send_with_retry(|| {
-                self.client
+                self.http
+                    .client()
                     .post(url)
                     .header("Authorization", format!("Bearer {}", self.api_key))
                     .header("Notion-Version", NOTION_VERSION)
@@ -241,21 +407,41 @@ impl NotionClient {
         serde_json::from_slice(&bytes).context("Failed to parse Notion query JSON")
     }
 
+    /// Every page of the database, without managing cursors; see [`all_pages`].
+    pub fn query_database_all(
+        &self,
+        page_size: usize,
+    ) -> impl Stream<Item = Result<Value>> + Send + '_ {
+        all_pages(self, page_size)
+    }
+
     pub async fn query_database_page(
         &self,
         start_cursor: Option<&str>,
         page_size: usize,
+    ) -> Result<DatabaseQueryResponse> {
+        self.query_database_filtered(None, start_cursor, page_size)
+            .await
+    }
+
+    pub async fn query_database_filtered(
+        &self,
+        filter: Option<&Value>,
+        start_cursor: Option<&str>,
+        page_size: usize,
     ) -> Result<DatabaseQueryResponse> {
         if let Some(ds_id) = self.data_source_id.get() {
-            let url_ds = format!("https://api.notion.com/v1/data_sources/{}/query", ds_id);
-            return self.post_query(&url_ds, start_cursor, page_size).await;
+            let url_ds = format!("{}/data_sources/{}/query", self.base_url, ds_id);
+            return self
+                .post_query(&url_ds, filter, start_cursor, page_size)
+                .await;
         }
 
-        let url_db = format!(
-            "https://api.notion.com/v1/databases/{}/query",
-            self.database_id
-        );
-        match self.post_query(&url_db, start_cursor, page_size).await {
+        let url_db = format!("{}/databases/{}/query", self.base_url, self.database_id);
+        match self
+            .post_query(&url_db, filter, start_cursor, page_size)
+            .await
+        {
             Ok(r) => Ok(r),
             Err(e) => {
                 let is_invalid_request_url = e
@@ -265,9 +451,11 @@ impl NotionClient {
 
                 if is_invalid_request_url {
                     let ds_id = self.resolve_data_source_id().await?;
-                    let url_ds = format!("https://api.notion.com/v1/data_sources/{}/query", ds_id);
+                    let url_ds = format!("{}/data_sources/{}/query", self.base_url, ds_id);
                     info!("Database query endpoint rejected; using data source query endpoint");
-                    return self.post_query(&url_ds, start_cursor, page_size).await;
+                    return self
+                        .post_query(&url_ds, filter, start_cursor, page_size)
+                        .await;
                 }
                 Err(e)
             }
@@ -277,11 +465,16 @@ impl NotionClient {
 
 #[async_trait]
 impl NotionApi for NotionClient {
+    fn circuit_state(&self) -> Option<CircuitState> {
+        Some(self.circuit.state())
+    }
+
     async fn fetch_property_schema(&self) -> Result<PropertySchema> {
-        let url = format!("https://api.notion.com/v1/databases/{}", self.database_id);
+        let url = format!("{}/databases/{}", self.base_url, self.database_id);
         let res = self
             .send_with_retry(|| {
-                self.client
+                self.http
+                    .client()
                     .get(&url)
                     .header("Authorization", format!("Bearer {}", self.api_key))
                     .header("Notion-Version", NOTION_VERSION)
@@ -295,11 +488,15 @@ impl NotionApi for NotionClient {
             .await
             .context("Failed to read Notion response body")?;
         if !status.is_success() {
-            return Err(anyhow::anyhow!(
-                "Notion database request failed (status {}): {}",
-                status,
-                String::from_utf8_lossy(&bytes)
-            ));
+            return Err(UpstreamStatus::new(
+                status.as_u16(),
+                format!(
+                    "Notion database request failed (status {}): {}",
+                    status,
+                    String::from_utf8_lossy(&bytes)
+                ),
+            )
+            .into());
         }
 
         let body: Value =
@@ -322,10 +519,11 @@ impl NotionApi for NotionClient {
     }
 
     async fn fetch_page(&self, page_id: &str) -> Result<Value> {
-        let url = format!("https://api.notion.com/v1/pages/{}", page_id);
+        let url = format!("{}/pages/{}", self.base_url, page_id);
         let res = self
             .send_with_retry(|| {
-                self.client
+                self.http
+                    .client()
                     .get(&url)
                     .header("Authorization", format!("Bearer {}", self.api_key))
                     .header("Notion-Version", NOTION_VERSION)
@@ -339,11 +537,15 @@ impl NotionApi for NotionClient {
             .await
             .context("Failed to read Notion page response")?;
         if !status.is_success() {
-            return Err(anyhow::anyhow!(
-                "Notion page request failed (status {}): {}",
-                status,
-                String::from_utf8_lossy(&bytes)
-            ));
+            return Err(UpstreamStatus::new(
+                status.as_u16(),
+                format!(
+                    "Notion page request failed (status {}): {}",
+                    status,
+                    String::from_utf8_lossy(&bytes)
+                ),
+            )
+            .into());
         }
 
         serde_json::from_slice(&bytes).context("Failed to parse page JSON")
@@ -356,7 +558,7 @@ impl NotionApi for NotionClient {
         icon: Option<Value>,
         cover: Option<Value>,
     ) -> Result<()> {
-        let url = format!("https://api.notion.com/v1/pages/{}", page_id);
+        let url = format!("{}/pages/{}", self.base_url, page_id);
         let mut body = json!({ "properties": properties });
         if let Some(icon_val) = icon {
             body["icon"] = icon_val;
@@ -364,10 +566,19 @@ impl NotionApi for NotionClient {
         if let Some(cover_val) = cover {
             body["cover"] = cover_val;
         }
+        if self.dry_run {
+            info!(
+                "DRY RUN: would update page {} with:\n{}",
+                page_id,
+                serde_json::to_string_pretty(&body).unwrap_or_default()
+            );
+            return Ok(());
+        }
 
         let res = self
             .send_with_retry(|| {
-                self.client
+                self.http
+                    .client()
                     .patch(&url)
                     .header("Authorization", format!("Bearer {}", self.api_key))
                     .header("Notion-Version", NOTION_VERSION)
@@ -382,15 +593,216 @@ impl NotionApi for NotionClient {
             .await
             .context("Failed to read Notion update response")?;
         if !status.is_success() {
-            return Err(anyhow::anyhow!(
-                "Notion page update failed (status {}): {}",
-                status,
-                String::from_utf8_lossy(&bytes)
-            ));
+            return Err(UpstreamStatus::new(
+                status.as_u16(),
+                format!(
+                    "Notion page update failed (status {}): {}",
+                    status,
+                    String::from_utf8_lossy(&bytes)
+                ),
+            )
+            .into());
         }
 
         Ok(())
     }
+
+    async fn query_pages_by_number(&self, property: &str, value: f64) -> Result<Vec<Value>> {
+        let filter = json!({ "property": property, "number": { "equals": value } });
+        let mut pages = Vec::new();
+        let mut cursor: Option<String> = None;
+        loop {
+            let resp = self
+                .query_database_filtered(Some(&filter), cursor.as_deref(), 100)
+                .await?;
+            pages.extend(resp.results);
+            if !resp.has_more {
+                break;
+            }
+            cursor = resp.next_cursor;
+            if cursor.is_none() {
+                break;
+            }
+        }
+        Ok(pages)
+    }
+
+    async fn list_pages(
+        &self,
+        start_cursor: Option<&str>,
+        page_size: usize,
+    ) -> Result<DatabaseQueryResponse> {
+        self.query_database_page(start_cursor, page_size).await
+    }
+
+    async fn add_comment(&self, page_id: &str, text: &str) -> Result<()> {
+        let url = format!("{}/comments", self.base_url);
+        let body = json!({
+            "parent": { "page_id": page_id },
+            "rich_text": [{ "text": { "content": text } }]
+        });
+        if self.dry_run {
+            info!("DRY RUN: would comment on page {}: {}", page_id, text);
+            return Ok(());
+        }
+
+        let res = self
+            .send_with_retry(|| {
+                self.http
+                    .client()
+                    .post(&url)
+                    .header("Authorization", format!("Bearer {}", self.api_key))
+                    .header("Notion-Version", NOTION_VERSION)
+                    .json(&body)
+            })
+            .await
+            .context("Failed to create Notion comment")?;
+
+        let status = res.status();
+        let bytes = res
+            .bytes()
+            .await
+            .context("Failed to read Notion comment response")?;
+        if !status.is_success() {
+            return Err(UpstreamStatus::new(
+                status.as_u16(),
+                format!(
+                    "Notion comment request failed (status {}): {}",
+                    status,
+                    String::from_utf8_lossy(&bytes)
+                ),
+            )
+            .into());
+        }
+
+        Ok(())
+    }
+
+    async fn append_blocks(&self, page_id: &str, blocks: Vec<Value>) -> Result<()> {
+        if blocks.is_empty() {
+            return Ok(());
+        }
+        let url = format!("{}/blocks/{}/children", self.base_url, page_id);
+        let body = json!({ "children": blocks });
+        if self.dry_run {
+            info!(
+                "DRY RUN: would append to page {}:\n{}",
+                page_id,
+                serde_json::to_string_pretty(&body).unwrap_or_default()
+            );
+            return Ok(());
+        }
+
+        let res = self
+            .send_with_retry(|| {
+                self.http
+                    .client()
+                    .patch(&url)
+                    .header("Authorization", format!("Bearer {}", self.api_key))
+                    .header("Notion-Version", NOTION_VERSION)
+                    .json(&body)
+            })
+            .await
+            .context("Failed to append Notion blocks")?;
+        ensure_success(res, "Notion block append").await?;
+        Ok(())
+    }
+
+    async fn clear_blocks(&self, page_id: &str) -> Result<()> {
+        if self.dry_run {
+            info!("DRY RUN: would clear the body of page {}", page_id);
+            return Ok(());
+        }
+
+        #[derive(Deserialize)]
+        struct Children {
+            results: Vec<Value>,
+            has_more: bool,
+            next_cursor: Option<String>,
+        }
+
+        // Collect first: deleting while paginating would shift the cursor.
+        let mut ids = Vec::new();
+        let mut cursor: Option<String> = None;
+        loop {
+            let mut url = format!(
+                "{}/blocks/{}/children?page_size=100",
+                self.base_url, page_id
+            );
+            if let Some(c) = &cursor {
+                url.push_str(&format!("&start_cursor={c}"));
+            }
+            let res = self
+                .send_with_retry(|| {
+                    self.http
+                        .client()
+                        .get(&url)
+                        .header("Authorization", format!("Bearer {}", self.api_key))
+                        .header("Notion-Version", NOTION_VERSION)
+                })
+                .await
+                .context("Failed to list Notion blocks")?;
+            let bytes = ensure_success(res, "Notion block listing").await?;
+            let page: Children =
+                serde_json::from_slice(&bytes).context("Failed to parse block children JSON")?;
+            ids.extend(
+                page.results
+                    .iter()
+                    .filter_map(|b| b.get("id").and_then(|id| id.as_str()))
+                    .map(str::to_string),
+            );
+            cursor = page.next_cursor.filter(|_| page.has_more);
+            if cursor.is_none() {
+                break;
+            }
+        }
+
+        for id in ids {
+            let url = format!("{}/blocks/{}", self.base_url, id);
+            let res = self
+                .send_with_retry(|| {
+                    self.http
+                        .client()
+                        .delete(&url)
+                        .header("Authorization", format!("Bearer {}", self.api_key))
+                        .header("Notion-Version", NOTION_VERSION)
+                })
+                .await
+                .context("Failed to delete Notion block")?;
+            ensure_success(res, "Notion block delete").await?;
+        }
+        Ok(())
+    }
+}
+
+/// Fails with the response body when `res` is not a success.
+async fn ensure_success(res: reqwest::Response, what: &str) -> Result<Vec<u8>> {
+    let status = res.status();
+    let bytes = res
+        .bytes()
+        .await
+        .with_context(|| format!("Failed to read {what} response"))?;
+    if !status.is_success() {
+        return Err(UpstreamStatus::new(
+            status.as_u16(),
+            format!(
+                "{what} failed (status {}): {}",
+                status,
+                String::from_utf8_lossy(&bytes)
+            ),
+        )
+        .into());
+    }
+    Ok(bytes.to_vec())
+}
+
+/// A paragraph block holding `text`, split like a rich text property.
+pub fn paragraph_block(text: &str) -> Value {
+    json!({
+        "object": "block",
+        "type": "paragraph",
+        "paragraph": { "rich_text": rich_text_chunks(text) }
+    })
 }
 
 pub fn extract_title(props: &Map<String, Value>, name: &str) -> Option<String> {
@@ -430,6 +842,48 @@ pub fn extract_rich_text(props: &Map<String, Value>, name: &str) -> Option<Strin
         .map(|s| s.to_string())
 }
 
+pub fn extract_url(props: &Map<String, Value>, name: &str) -> Option<String> {
+    props
+        .get(name)
+        .and_then(|p| p.get("url"))
+        .and_then(|v| v.as_str())
+        .map(|s| s.to_string())
+}
+
+/// Whether the page property `name` holds anything: non-blank text, a number, an option,
+/// a URL, a date or at least one file.
+pub fn has_value(props: &Map<String, Value>, name: &str) -> bool {
+    const VALUE_KEYS: [&str; 9] = [
+        "title",
+        "rich_text",
+        "number",
+        "select",
+        "multi_select",
+        "status",
+        "url",
+        "date",
+        "files",
+    ];
+    let Some(prop) = props.get(name) else {
+        return false;
+    };
+    let Some(value) = VALUE_KEYS.iter().find_map(|key| prop.get(*key)) else {
+        return false;
+    };
+    match value {
+        Value::Null => false,
+        Value::String(s) => !s.trim().is_empty(),
+        Value::Array(items) => items.iter().any(|item| {
+            // Text items count only when they have visible characters.
+            item.get("plain_text")
+                .or_else(|| item.get("text").and_then(|t| t.get("content")))
+                .and_then(|t| t.as_str())
+                .is_none_or(|t| !t.trim().is_empty())
+        }),
+        _ => true,
+    }
+}
+
 pub fn extract_number(props: &Map<String, Value>, name: &str) -> Option<f64> {
     props
         .get(name)
@@ -437,6 +891,32 @@ pub fn extract_number(props: &Map<String, Value>, name: &str) -> Option<f64> {
         .and_then(|v| v.as_f64())
 }
 
+/// Option names of a multi-select property; empty when it is missing or has none.
+pub fn extract_multi_select(props: &Map<String, Value>, name: &str) -> Vec<String> {
+    props
+        .get(name)
+        .and_then(|p| p.get("multi_select"))
+        .and_then(|v| v.as_array())
+        .map(|options| {
+            options
+                .iter()
+                .filter_map(|o| o.get("name")?.as_str())
+                .map(|s| s.to_string())
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+/// Start of a date property, as Notion returns it (`2024-01-01` or a full timestamp).
+pub fn extract_date(props: &Map<String, Value>, name: &str) -> Option<String> {
+    props
+        .get(name)
+        .and_then(|p| p.get("date"))
+        .and_then(|d| d.get("start"))
+        .and_then(|v| v.as_str())
+        .map(|s| s.to_string())
+}
+
 pub fn set_title(
     target: &mut Map<String, Value>,
     property: &str,
@@ -448,6 +928,7 @@ pub fn set_title(
         .get(property)
         .cloned()
         .unwrap_or(PropertyType::Title);
+    let value = truncate_for_notion(value);
     if matches!(prop_type, PropertyType::Title) {
         target.insert(
             property.to_string(),
@@ -475,17 +956,7 @@ pub fn merge_schema_from_props(schema: &mut PropertySchema, props: &Map<String,
             continue;
         }
         if let Some(t) = prop.get("type").and_then(|v| v.as_str()) {
-            let mapped = match t {
-                "title" => PropertyType::Title,
-                "rich_text" => PropertyType::RichText,
-                "url" => PropertyType::Url,
-                "number" => PropertyType::Number,
-                "select" => PropertyType::Select,
-                "multi_select" => PropertyType::MultiSelect,
-                "files" => PropertyType::Files,
-                "date" => PropertyType::Date,
-                other => PropertyType::Unknown(other.to_string()),
-            };
+            let mapped = PropertyType::from_notion(t);
             if mapped == PropertyType::Title && schema.title_property.is_none() {
                 schema.title_property = Some(name.clone());
             }
@@ -500,17 +971,7 @@ fn schema_from_properties(props: &Map<String, Value>) -> PropertySchema {
 
     for (name, def) in props {
         if let Some(t) = def.get("type").and_then(|v| v.as_str()) {
-            let mapped = match t {
-                "title" => PropertyType::Title,
-                "rich_text" => PropertyType::RichText,
-                "url" => PropertyType::Url,
-                "number" => PropertyType::Number,
-                "select" => PropertyType::Select,
-                "multi_select" => PropertyType::MultiSelect,
-                "files" => PropertyType::Files,
-                "date" => PropertyType::Date,
-                other => PropertyType::Unknown(other.to_string()),
-            };
+            let mapped = PropertyType::from_notion(t);
             if mapped == PropertyType::Title {
                 title_property = Some(name.clone());
             }
@@ -550,39 +1011,79 @@ pub fn set_value(
         .get(property)
         .cloned()
         .unwrap_or(PropertyType::RichText);
+    if matches!(val, ValueInput::Clear) {
+        if let Some(p) = clear_payload(&prop_type) {
+            target.insert(property.to_string(), p);
+        }
+        return;
+    }
 
     let payload = match prop_type {
         PropertyType::Title => Some(json!({
             "title": [
-                { "text": { "content": string_value(val.clone()) } }
-            ]
-        })),
-        PropertyType::RichText | PropertyType::Unknown(_) => Some(json!({
-            "rich_text": [
-                { "text": { "content": string_value(val) } }
+                { "text": { "content": truncate_for_notion(&string_value(val)) } }
             ]
         })),
+        // Long text (a synopsis, a joined list) is spread over several items.
+        PropertyType::RichText | PropertyType::Unknown(_) => {
+            Some(json!({ "rich_text": rich_text_chunks(&string_value(val)) }))
+        }
         PropertyType::Url => string_value_opt(val).map(|s| json!({ "url": s })),
         PropertyType::Number => match val {
             ValueInput::Number(n) => Some(json!({ "number": n })),
             _ => None,
         },
         PropertyType::Select => string_value_opt(val).map(|s| json!({ "select": { "name": s } })),
-        PropertyType::MultiSelect => Some(json!({
-            "multi_select": match val {
-                ValueInput::StringList(list) => list.into_iter().map(|n| json!({ "name": n })).collect::<Vec<_>>(),
-                other => vec![json!({ "name": string_value(other) })],
+        PropertyType::Status => string_value_opt(val).map(|s| json!({ "status": { "name": s } })),
+        PropertyType::Checkbox => match val {
+            ValueInput::Bool(b) => Some(json!({ "checkbox": b })),
+            _ => None,
+        },
+        PropertyType::People | PropertyType::Relation => {
+            warn!(
+                "Not writing '{}': it is a {:?} property, which CineLink leaves alone",
+                property, prop_type
+            );
+            None
+        }
+        PropertyType::MultiSelect => {
+            let mut names = sanitize_options(match val {
+                ValueInput::StringList(list) => list,
+                other => vec![string_value(other)],
+            });
+            if names.len() > MAX_MULTI_SELECT_OPTIONS {
+                warn!(
+                    "Dropping {} of {} values for multi-select '{}' (Notion allows {})",
+                    names.len() - MAX_MULTI_SELECT_OPTIONS,
+                    names.len(),
+                    property,
+                    MAX_MULTI_SELECT_OPTIONS
+                );
+                names.truncate(MAX_MULTI_SELECT_OPTIONS);
             }
-        })),
-        PropertyType::Files => string_value_opt(val).map(|s| {
-            json!({
-                "files": [{
-                    "name": "external",
-                    "type": "external",
-                    "external": { "url": s }
-                }]
+            Some(json!({
+                "multi_select": names.into_iter().map(|n| json!({ "name": n })).collect::<Vec<_>>()
+            }))
+        }
+        PropertyType::Files => {
+            // A list becomes one external file entry per URL; anything else a single entry.
+            let urls = match val {
+                ValueInput::StringList(list) => list,
+                other => string_value_opt(other).into_iter().collect(),
+            };
+            (!urls.is_empty()).then(|| {
+                json!({
+                    "files": urls
+                        .into_iter()
+                        .map(|url| json!({
+                            "name": "external",
+                            "type": "external",
+                            "external": { "url": url }
+                        }))
+                        .collect::<Vec<_>>()
+                })
             })
-        }),
+        }
         PropertyType::Date => string_value_opt(val).map(|s| json!({ "date": { "start": s } })),
     };
 
@@ -591,6 +1092,87 @@ pub fn set_value(
     }
 }
 
+/// The payload that empties a property of `prop_type`; `None` for types that can't be emptied.
+fn clear_payload(prop_type: &PropertyType) -> Option<Value> {
+    match prop_type {
+        PropertyType::RichText => Some(json!({ "rich_text": [] })),
+        PropertyType::Url => Some(json!({ "url": null })),
+        PropertyType::Number => Some(json!({ "number": null })),
+        PropertyType::Select => Some(json!({ "select": null })),
+        PropertyType::MultiSelect => Some(json!({ "multi_select": [] })),
+        PropertyType::Files => Some(json!({ "files": [] })),
+        PropertyType::Date => Some(json!({ "date": null })),
+        PropertyType::Title
+        | PropertyType::Status
+        | PropertyType::Checkbox
+        | PropertyType::People
+        | PropertyType::Relation
+        | PropertyType::Unknown(_) => None,
+    }
+}
+
+/// Makes names valid multi-select options: Notion doesn't allow commas in an option (they
+/// become spaces, so "Korea, Republic of" stays one option) or names over `MAX_OPTION_CHARS`
+/// characters. Empty names are dropped, and so are repeats that differ only in case.
+fn sanitize_options(names: Vec<String>) -> Vec<String> {
+    let mut seen = HashSet::new();
+    names
+        .into_iter()
+        .filter_map(|name| {
+            let name = name.replace(',', " ");
+            let name: String = name
+                .split_whitespace()
+                .collect::<Vec<_>>()
+                .join(" ")
+                .chars()
+                .take(MAX_OPTION_CHARS)
+                .collect();
+            let name = name.trim_end().to_string();
+            (!name.is_empty() && seen.insert(name.to_lowercase())).then_some(name)
+        })
+        .collect()
+}
+
+/// Trims `text` (a title, which can't span several items) to `MAX_RICH_TEXT_CHARS` characters
+/// (not bytes), the last one being "…" when anything was cut.
+pub fn truncate_for_notion(text: &str) -> String {
+    if text.chars().count() <= MAX_RICH_TEXT_CHARS {
+        return text.to_string();
+    }
+    let mut truncated: String = text.chars().take(MAX_RICH_TEXT_CHARS - 1).collect();
+    truncated.push('…');
+    truncated
+}
+
+/// Splits `content` into rich_text items of at most `MAX_RICH_TEXT_CHARS` characters, breaking
+/// after whitespace where possible so words stay whole.
+fn rich_text_chunks(content: &str) -> Vec<Value> {
+    if content.is_empty() {
+        return vec![json!({ "text": { "content": "" } })];
+    }
+    let mut items = Vec::new();
+    let mut rest = content;
+    while !rest.is_empty() {
+        let chunk = next_chunk(rest);
+        items.push(json!({ "text": { "content": chunk } }));
+        rest = &rest[chunk.len()..];
+    }
+    items
+}
+
+/// The longest prefix of `text` within `MAX_RICH_TEXT_CHARS` characters that ends on whitespace;
+/// a single word longer than that is cut mid-word, still on a character boundary.
+fn next_chunk(text: &str) -> &str {
+    let Some((limit, _)) = text.char_indices().nth(MAX_RICH_TEXT_CHARS) else {
+        return text;
+    };
+    let head = &text[..limit];
+    match head.char_indices().rev().find(|(_, c)| c.is_whitespace()) {
+        Some((i, c)) if i > 0 => &text[..i + c.len_utf8()],
+        _ => head,
+    }
+}
+
 fn string_value(val: ValueInput) -> String {
     match val {
         ValueInput::Text(s) => s,
@@ -598,6 +1180,8 @@ fn string_value(val: ValueInput) -> String {
         ValueInput::Number(n) => n.to_string(),
         ValueInput::Url(s) => s,
         ValueInput::Date(s) => s,
+        ValueInput::Bool(b) => b.to_string(),
+        ValueInput::Clear => String::new(),
     }
 }
 
@@ -605,27 +1189,326 @@ fn string_value_opt(val: ValueInput) -> Option<String> {
     match val {
         ValueInput::Text(s) => Some(s),
         ValueInput::StringList(list) => list.first().cloned(),
-        ValueInput::Number(_) => None,
+        ValueInput::Number(_) | ValueInput::Bool(_) | ValueInput::Clear => None,
         ValueInput::Url(s) => Some(s),
         ValueInput::Date(s) => Some(s),
     }
 }
 
-fn retry_delay(attempt: usize, retry_after: Option<&reqwest::header::HeaderValue>) -> Duration {
-    if let Some(v) = retry_after.and_then(|h| h.to_str().ok()) {
-        if let Ok(secs) = v.parse::<u64>() {
-            return Duration::from_secs(secs.min(30));
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn schema_with(property: &str, prop_type: PropertyType) -> PropertySchema {
+        PropertySchema {
+            title_property: None,
+            types: HashMap::from([(property.to_string(), prop_type)]),
         }
     }
-    let base_ms = 200u64.saturating_mul(2u64.saturating_pow((attempt - 1) as u32));
-    let jitter_ms = jitter_ms();
-    Duration::from_millis((base_ms + jitter_ms).min(5_000))
-}
 
-fn jitter_ms() -> u64 {
-    use std::time::{SystemTime, UNIX_EPOCH};
-    let now = SystemTime::now()
-        .duration_since(UNIX_EPOCH)
-        .unwrap_or_default();
-    (now.subsec_millis() as u64) % 100
+    fn names(count: usize) -> ValueInput {
+        ValueInput::StringList((0..count).map(|i| format!("Cast Member {i:03}")).collect())
+    }
+
+    #[test]
+    fn multi_select_is_capped_at_the_notion_limit() {
+        let mut target = Map::new();
+        let schema = schema_with("Cast", PropertyType::MultiSelect);
+        set_value(&mut target, "Cast", Some(names(150)), &schema);
+        let options = target["Cast"]["multi_select"].as_array().unwrap();
+        assert_eq!(options.len(), MAX_MULTI_SELECT_OPTIONS);
+        assert_eq!(options[99]["name"], "Cast Member 099");
+    }
+
+    fn strings(names: &[&str]) -> Vec<String> {
+        names.iter().map(|n| n.to_string()).collect()
+    }
+
+    #[test]
+    fn option_names_lose_commas_and_are_cut_to_the_notion_limit() {
+        assert_eq!(
+            sanitize_options(strings(&["Monkey D., Luffy", "Korea, Republic of"])),
+            strings(&["Monkey D. Luffy", "Korea Republic of"])
+        );
+        let long = format!("{} tail", "x".repeat(MAX_OPTION_CHARS - 1));
+        let cut = sanitize_options(vec![long]);
+        // The cut lands on the space before "tail", which is trimmed.
+        assert_eq!(cut, vec!["x".repeat(MAX_OPTION_CHARS - 1)]);
+        let cut = sanitize_options(vec!["é".repeat(MAX_OPTION_CHARS + 10)]);
+        assert_eq!(cut[0].chars().count(), MAX_OPTION_CHARS);
+    }
+
+    #[test]
+    fn empty_and_repeated_option_names_are_dropped() {
+        assert_eq!(
+            sanitize_options(strings(&["Drama", "", " , ", "drama", "DRAMA ", "Comedy"])),
+            strings(&["Drama", "Comedy"])
+        );
+        let mut target = Map::new();
+        let schema = schema_with("Genre", PropertyType::MultiSelect);
+        set_value(
+            &mut target,
+            "Genre",
+            Some(ValueInput::Text(String::new())),
+            &schema,
+        );
+        assert_eq!(target["Genre"], json!({ "multi_select": [] }));
+    }
+
+    #[test]
+    fn long_rich_text_joins_are_chunked() {
+        let mut target = Map::new();
+        let schema = schema_with("Cast", PropertyType::RichText);
+        set_value(&mut target, "Cast", Some(names(150)), &schema);
+        let items = target["Cast"]["rich_text"].as_array().unwrap();
+        // 150 names of 15 chars joined by ", " = 2548 chars.
+        assert_eq!(items.len(), 2);
+        let contents: Vec<&str> = items
+            .iter()
+            .map(|i| i["text"]["content"].as_str().unwrap())
+            .collect();
+        // The first item ends between two words, just short of the limit.
+        assert!(contents[0].chars().count() <= MAX_RICH_TEXT_CHARS);
+        assert!(contents[0].ends_with(' '));
+        assert_eq!(contents.concat(), string_value(names(150)));
+    }
+
+    #[test]
+    fn short_rich_text_stays_a_single_item() {
+        let mut target = Map::new();
+        let schema = schema_with("Synopsis", PropertyType::RichText);
+        set_value(
+            &mut target,
+            "Synopsis",
+            Some(ValueInput::Text("Short".into())),
+            &schema,
+        );
+        assert_eq!(
+            target["Synopsis"],
+            json!({ "rich_text": [{ "text": { "content": "Short" } }] })
+        );
+    }
+
+    #[test]
+    fn long_text_is_truncated_on_character_boundaries() {
+        let long = "é".repeat(MAX_RICH_TEXT_CHARS + 1);
+        let truncated = truncate_for_notion(&long);
+        assert_eq!(truncated.chars().count(), MAX_RICH_TEXT_CHARS);
+        assert!(truncated.ends_with("é…"));
+
+        let short = "é".repeat(MAX_RICH_TEXT_CHARS - 1);
+        assert_eq!(truncate_for_notion(&short), short);
+
+        let mut target = Map::new();
+        let schema = schema_with("Name", PropertyType::Title);
+        set_title(&mut target, "Name", &long, &schema);
+        assert_eq!(
+            target["Name"]["title"][0]["text"]["content"],
+            json!(truncated)
+        );
+    }
+
+    #[test]
+    fn long_synopses_are_split_between_words() {
+        // 5000 characters of two-byte words, so a byte-based cut would land mid-character.
+        let synopsis = "ééééééééé ".repeat(500);
+        let mut target = Map::new();
+        let schema = schema_with("Synopsis", PropertyType::RichText);
+        set_value(
+            &mut target,
+            "Synopsis",
+            Some(ValueInput::Text(synopsis.clone())),
+            &schema,
+        );
+        let contents: Vec<&str> = target["Synopsis"]["rich_text"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|i| i["text"]["content"].as_str().unwrap())
+            .collect();
+        assert_eq!(contents.len(), 3);
+        for chunk in &contents {
+            assert!(chunk.chars().count() <= MAX_RICH_TEXT_CHARS);
+            assert!(chunk.ends_with(' '));
+        }
+        assert_eq!(contents.concat(), synopsis);
+
+        // A single word longer than an item is still cut on a character boundary.
+        let word = "é".repeat(MAX_RICH_TEXT_CHARS + 5);
+        let chunks = rich_text_chunks(&word);
+        assert_eq!(chunks.len(), 2);
+        assert_eq!(
+            chunks[1]["text"]["content"]
+                .as_str()
+                .unwrap()
+                .chars()
+                .count(),
+            5
+        );
+    }
+
+    #[test]
+    fn checkboxes_are_written_and_people_and_relations_skipped() {
+        let mut schema = schema_with("Name", PropertyType::Title);
+        let props = json!({
+            "Adult": { "type": "checkbox", "checkbox": false },
+            "Director": { "type": "people", "people": [] },
+            "Sequel": { "type": "relation", "relation": [] },
+        });
+        merge_schema_from_props(&mut schema, props.as_object().unwrap());
+        assert_eq!(schema.types["Adult"], PropertyType::Checkbox);
+        assert_eq!(schema.types["Director"], PropertyType::People);
+        assert_eq!(schema.types["Sequel"], PropertyType::Relation);
+
+        let mut target = Map::new();
+        set_value(&mut target, "Adult", Some(ValueInput::Bool(true)), &schema);
+        set_value(
+            &mut target,
+            "Director",
+            Some(ValueInput::StringList(vec!["Someone".into()])),
+            &schema,
+        );
+        set_value(
+            &mut target,
+            "Sequel",
+            Some(ValueInput::Text("x".into())),
+            &schema,
+        );
+        assert_eq!(
+            Value::Object(target),
+            json!({ "Adult": { "checkbox": true } })
+        );
+    }
+
+    #[test]
+    fn clear_empties_each_property_type() {
+        let mut schema = schema_with("Name", PropertyType::Title);
+        for (name, prop_type) in [
+            ("Synopsis", PropertyType::RichText),
+            ("Trailer", PropertyType::Url),
+            ("Runtime", PropertyType::Number),
+            ("Language", PropertyType::Select),
+            ("Genre", PropertyType::MultiSelect),
+            ("IMG", PropertyType::Files),
+            ("Release Date", PropertyType::Date),
+            ("Adult", PropertyType::Checkbox),
+        ] {
+            schema.types.insert(name.to_string(), prop_type);
+        }
+        let mut target = Map::new();
+        for name in schema.types.keys() {
+            set_value(&mut target, name, Some(ValueInput::Clear), &schema);
+        }
+        assert_eq!(
+            Value::Object(target),
+            json!({
+                "Synopsis": { "rich_text": [] },
+                "Trailer": { "url": null },
+                "Runtime": { "number": null },
+                "Language": { "select": null },
+                "Genre": { "multi_select": [] },
+                "IMG": { "files": [] },
+                "Release Date": { "date": null },
+            })
+        );
+    }
+
+    #[test]
+    fn extracts_multi_select_names_and_date_starts() {
+        let props = json!({
+            "Genre": { "type": "multi_select", "multi_select": [
+                { "id": "a", "name": "Drama", "color": "red" },
+                { "id": "b", "name": "Sci-Fi", "color": "blue" }
+            ] },
+            "Cast": { "type": "multi_select", "multi_select": [] },
+            "Release Date": { "type": "date", "date": { "start": "2024-01-01", "end": null } },
+            "Last Synced": { "type": "date", "date": null }
+        });
+        let props = props.as_object().unwrap();
+        assert_eq!(extract_multi_select(props, "Genre"), ["Drama", "Sci-Fi"]);
+        assert!(extract_multi_select(props, "Cast").is_empty());
+        assert!(extract_multi_select(props, "Missing").is_empty());
+        assert_eq!(
+            extract_date(props, "Release Date").as_deref(),
+            Some("2024-01-01")
+        );
+        assert_eq!(extract_date(props, "Last Synced"), None);
+        assert_eq!(extract_date(props, "Genre"), None);
+    }
+
+    #[test]
+    fn status_properties_are_detected_and_written_by_name() {
+        let mut schema = schema_with("Name", PropertyType::Title);
+        let props = json!({ "Watch Status": { "type": "status", "status": null } });
+        merge_schema_from_props(&mut schema, props.as_object().unwrap());
+        assert_eq!(schema.types["Watch Status"], PropertyType::Status);
+
+        let mut target = Map::new();
+        set_value(
+            &mut target,
+            "Watch Status",
+            Some(ValueInput::Text("Not started".into())),
+            &schema,
+        );
+        assert_eq!(
+            target["Watch Status"],
+            json!({ "status": { "name": "Not started" } })
+        );
+    }
+
+    #[test]
+    fn has_value_ignores_blank_and_null_properties() {
+        let props = json!({
+            "Synopsis": { "rich_text": [{ "plain_text": "Hand-written" }] },
+            "Blank": { "rich_text": [{ "plain_text": "  " }] },
+            "Empty": { "rich_text": [] },
+            "ID": { "number": null },
+            "Runtime": { "number": 0 },
+            "Genre": { "multi_select": [{ "name": "Drama" }] },
+            "Trailer": { "url": "" },
+        });
+        let props = props.as_object().unwrap();
+        assert!(has_value(props, "Synopsis"));
+        assert!(has_value(props, "Runtime"));
+        assert!(has_value(props, "Genre"));
+        for name in ["Blank", "Empty", "ID", "Trailer", "Missing"] {
+            assert!(!has_value(props, name), "{name}");
+        }
+    }
+
+    #[tokio::test]
+    async fn clear_blocks_deletes_every_child_across_pages() {
+        use wiremock::matchers::{method, path, query_param};
+        use wiremock::{Mock, MockServer, ResponseTemplate};
+
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/blocks/page-1/children"))
+            .and(query_param("start_cursor", "c2"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "results": [{ "id": "b2" }], "has_more": false, "next_cursor": null
+            })))
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/blocks/page-1/children"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "results": [{ "id": "b1" }], "has_more": true, "next_cursor": "c2"
+            })))
+            .mount(&server)
+            .await;
+        for id in ["b1", "b2"] {
+            Mock::given(method("DELETE"))
+                .and(path(format!("/blocks/{id}")))
+                .respond_with(ResponseTemplate::new(200).set_body_json(json!({})))
+                .expect(1)
+                .mount(&server)
+                .await;
+        }
+
+        let client = NotionClient::new("key".into(), "db".into())
+            .unwrap()
+            .with_base_url(server.uri());
+        client.clear_blocks("page-1").await.unwrap();
+    }
 }
@@ -1,4 +1,8 @@
 //! Fallback schema in case database fetch fails, matching expected property types.
+//! `status` properties (`PropertyType::Status`) are left out: their names and options are
+//! specific to each database, so they are only written once the real schema is known. The same
+//! goes for optional properties such as the `Adult` checkbox or the `Collection` select, which a
+//! database may not have; `people` and `relation` properties are never written.
 use crate::notion::{PropertySchema, PropertyType};
 use std::collections::HashMap;
 
@@ -24,6 +28,8 @@ pub fn fallback_schema() -> PropertySchema {
     types.insert("ID".to_string(), PropertyType::Number);
     types.insert("Season".to_string(), PropertyType::Select);
     types.insert("Type".to_string(), PropertyType::Select);
+    types.insert("Last Synced".to_string(), PropertyType::Date);
+    types.insert("Source".to_string(), PropertyType::Select);
 
     PropertySchema {
         types,
@@ -0,0 +1,69 @@
+//! Bounded queue of webhook page jobs, drained by a fixed pool of workers (see
+//! `app::spawn_job_workers`). When it is full, webhooks are refused instead of piling up.
+use std::sync::Mutex;
+use tokio::sync::mpsc;
+
+pub const DEFAULT_JOB_QUEUE_CAPACITY: usize = 256;
+
+pub struct JobQueue<T> {
+    tx: mpsc::Sender<T>,
+    rx: Mutex<Option<mpsc::Receiver<T>>>,
+}
+
+impl<T> JobQueue<T> {
+    pub fn new(capacity: usize) -> Self {
+        let (tx, rx) = mpsc::channel(capacity.max(1));
+        Self {
+            tx,
+            rx: Mutex::new(Some(rx)),
+        }
+    }
+
+    /// Reserves a slot for one job; `None` when the queue is full (or no worker is left).
+    pub fn try_reserve(&self) -> Option<mpsc::Permit<'_, T>> {
+        self.tx.try_reserve().ok()
+    }
+
+    /// Reserves `n` slots at once; `None` unless all of them are free.
+    pub fn try_reserve_many(&self, n: usize) -> Option<mpsc::PermitIterator<'_, T>> {
+        self.tx.try_reserve_many(n).ok()
+    }
+
+    /// Jobs waiting for a worker, reserved slots included.
+    pub fn depth(&self) -> usize {
+        self.tx.max_capacity() - self.tx.capacity()
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.tx.max_capacity()
+    }
+
+    /// Hands the receiving end to the workers; `None` if it was already taken.
+    pub fn take_receiver(&self) -> Option<mpsc::Receiver<T>> {
+        self.rx.lock().unwrap_or_else(|e| e.into_inner()).take()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn refuses_jobs_once_full_until_one_is_taken() {
+        let queue = JobQueue::new(2);
+        queue.try_reserve().unwrap().send(1);
+        let reserved = queue.try_reserve().unwrap();
+        assert_eq!(queue.depth(), 2);
+        assert!(queue.try_reserve().is_none());
+        reserved.send(2);
+
+        let mut rx = queue.take_receiver().unwrap();
+        assert!(queue.take_receiver().is_none());
+        assert_eq!(rx.recv().await, Some(1));
+        assert_eq!(queue.depth(), 1);
+        assert!(queue.try_reserve_many(2).is_none());
+        let mut slots = queue.try_reserve_many(1).unwrap();
+        slots.next().unwrap().send(3);
+        assert_eq!(queue.capacity(), 2);
+    }
+}
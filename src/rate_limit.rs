@@ -0,0 +1,186 @@
+//! Token buckets for the webhook rate limits: a client may send `limit + burst` requests at
+//! once, then tokens trickle back at `limit` per minute. Unlike fixed one-minute windows,
+//! there is no boundary at which the full allowance resets.
+use std::collections::HashMap;
+use std::hash::Hash;
+use std::time::{Duration, Instant};
+
+/// Size and refill rate shared by every bucket of one limiter.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct RateLimit {
+    capacity: f64,
+    refill_per_sec: f64,
+}
+
+impl RateLimit {
+    /// `per_minute` requests a minute, plus `burst` more when the bucket is full.
+    pub fn per_minute(per_minute: u32, burst: u32) -> Self {
+        Self {
+            capacity: f64::from(per_minute) + f64::from(burst),
+            refill_per_sec: f64::from(per_minute) / 60.0,
+        }
+    }
+
+    pub fn capacity(&self) -> u32 {
+        self.capacity as u32
+    }
+}
+
+/// A bucket that has never been used is full.
+#[derive(Clone, Debug, Default)]
+pub struct TokenBucket {
+    tokens: f64,
+    updated: Option<Instant>,
+}
+
+impl TokenBucket {
+    /// Takes one token. Returns the whole tokens left, or how long until the next one is
+    /// available when the bucket is empty.
+    pub fn try_take(&mut self, limit: &RateLimit, now: Instant) -> Result<u32, Duration> {
+        self.refill(limit, now);
+        if self.tokens < 1.0 {
+            let missing = 1.0 - self.tokens;
+            return Err(if limit.refill_per_sec > 0.0 {
+                Duration::from_secs_f64(missing / limit.refill_per_sec)
+            } else {
+                Duration::MAX
+            });
+        }
+        self.tokens -= 1.0;
+        Ok(self.tokens.floor() as u32)
+    }
+
+    /// When the bucket was last touched; `None` if never.
+    pub fn last_used(&self) -> Option<Instant> {
+        self.updated
+    }
+
+    /// Whether the bucket has refilled completely, i.e. holds nothing worth remembering.
+    pub fn is_full(&self, limit: &RateLimit, now: Instant) -> bool {
+        match self.updated {
+            None => true,
+            Some(at) => {
+                let elapsed = now.saturating_duration_since(at).as_secs_f64();
+                self.tokens + elapsed * limit.refill_per_sec >= limit.capacity
+            }
+        }
+    }
+
+    fn refill(&mut self, limit: &RateLimit, now: Instant) {
+        self.tokens = match self.updated {
+            None => limit.capacity,
+            Some(at) => {
+                let elapsed = now.saturating_duration_since(at).as_secs_f64();
+                (self.tokens + elapsed * limit.refill_per_sec).min(limit.capacity)
+            }
+        };
+        self.updated = Some(now);
+    }
+}
+
+/// Whole seconds to put in `Retry-After` for a wait of `wait` (1..=60).
+pub fn retry_after_secs(wait: Duration) -> u64 {
+    wait.as_secs_f64().ceil().clamp(1.0, 60.0) as u64
+}
+
+/// Shrinks `buckets` to at most `max` entries: full buckets go first (forgetting them changes
+/// nothing), then the least recently used.
+pub fn evict<K: Eq + Hash + Clone>(
+    buckets: &mut HashMap<K, TokenBucket>,
+    limit: &RateLimit,
+    now: Instant,
+    max: usize,
+) {
+    if buckets.len() <= max {
+        return;
+    }
+    buckets.retain(|_, bucket| !bucket.is_full(limit, now));
+    if buckets.len() <= max {
+        return;
+    }
+    let mut by_age: Vec<(Option<Instant>, K)> = buckets
+        .iter()
+        .map(|(key, bucket)| (bucket.last_used(), key.clone()))
+        .collect();
+    by_age.sort_by_key(|(at, _)| *at);
+    let excess = buckets.len() - max;
+    for (_, key) in by_age.into_iter().take(excess) {
+        buckets.remove(&key);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn allows_the_burst_then_refills_at_the_per_minute_rate() {
+        let limit = RateLimit::per_minute(60, 10);
+        let start = Instant::now();
+        let mut bucket = TokenBucket::default();
+        for sent in 1..=70 {
+            assert_eq!(bucket.try_take(&limit, start), Ok(70 - sent));
+        }
+        assert_eq!(bucket.try_take(&limit, start), Err(Duration::from_secs(1)));
+
+        // One token a second comes back, never more than the capacity.
+        let later = start + Duration::from_millis(2_500);
+        assert_eq!(bucket.try_take(&limit, later), Ok(1));
+        assert_eq!(bucket.try_take(&limit, later), Ok(0));
+        assert_eq!(
+            bucket.try_take(&limit, later),
+            Err(Duration::from_millis(500))
+        );
+        let idle = later + Duration::from_secs(3_600);
+        assert_eq!(bucket.try_take(&limit, idle), Ok(69));
+    }
+
+    #[test]
+    fn a_burst_across_a_minute_boundary_is_not_doubled() {
+        let limit = RateLimit::per_minute(60, 10);
+        let start = Instant::now();
+        let mut bucket = TokenBucket::default();
+        let allowed = (0..70)
+            .filter(|_| bucket.try_take(&limit, start).is_ok())
+            .count();
+        assert_eq!(allowed, 70);
+
+        // Two seconds later only the two refilled tokens are available.
+        let soon = start + Duration::from_secs(2);
+        let allowed = (0..70)
+            .filter(|_| bucket.try_take(&limit, soon).is_ok())
+            .count();
+        assert_eq!(allowed, 2);
+    }
+
+    #[test]
+    fn retry_after_rounds_up_within_a_minute() {
+        assert_eq!(retry_after_secs(Duration::ZERO), 1);
+        assert_eq!(retry_after_secs(Duration::from_millis(300)), 1);
+        assert_eq!(retry_after_secs(Duration::from_millis(1_200)), 2);
+        assert_eq!(retry_after_secs(Duration::MAX), 60);
+    }
+
+    #[test]
+    fn eviction_drops_full_buckets_then_the_least_recently_used() {
+        let limit = RateLimit::per_minute(60, 0);
+        let start = Instant::now();
+        let mut buckets = HashMap::new();
+        for (i, ip) in ["a", "b", "c", "d"].into_iter().enumerate() {
+            let at = start + Duration::from_secs(i as u64 * 10);
+            let bucket: &mut TokenBucket = buckets.entry(ip).or_default();
+            for _ in 0..50 {
+                bucket.try_take(&limit, at).unwrap();
+            }
+        }
+
+        // At 50s only "a" (down to 10 tokens at 0s) has refilled completely.
+        let now = start + Duration::from_secs(50);
+        evict(&mut buckets, &limit, now, 3);
+        assert_eq!(buckets.len(), 3);
+        assert!(!buckets.contains_key("a"));
+
+        evict(&mut buckets, &limit, now, 1);
+        assert_eq!(buckets.keys().collect::<Vec<_>>(), vec![&"d"]);
+    }
+}
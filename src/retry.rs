@@ -0,0 +1,132 @@
+//! Background retry of page jobs that failed with a transient error.
+//!
+//! Failed jobs are sent over an mpsc channel to a worker (see `app::spawn_retry_worker`),
+//! which waits out an exponential backoff before re-running them. A page is queued at most
+//! once at a time.
+use crate::metrics::Metrics;
+use std::collections::HashSet;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::sync::mpsc;
+
+/// Attempts per page, including the original one.
+pub const DEFAULT_RETRY_MAX_ATTEMPTS: u32 = 4;
+pub const DEFAULT_RETRY_BASE_DELAY: Duration = Duration::from_secs(30);
+const MAX_RETRY_DELAY: Duration = Duration::from_secs(30 * 60);
+
+#[derive(Clone, Debug)]
+pub struct RetryJob {
+    pub page_id: String,
+    pub event_id: Option<String>,
+    /// The attempt this job will make (2 for the first retry).
+    pub attempt: u32,
+}
+
+pub struct RetryQueue {
+    max_attempts: u32,
+    base_delay: Duration,
+    tx: mpsc::UnboundedSender<RetryJob>,
+    rx: Mutex<Option<mpsc::UnboundedReceiver<RetryJob>>>,
+    queued: Mutex<HashSet<String>>,
+    metrics: Arc<Metrics>,
+}
+
+impl RetryQueue {
+    pub fn new(max_attempts: u32, base_delay: Duration, metrics: Arc<Metrics>) -> Self {
+        let (tx, rx) = mpsc::unbounded_channel();
+        Self {
+            max_attempts,
+            base_delay,
+            tx,
+            rx: Mutex::new(Some(rx)),
+            queued: Mutex::new(HashSet::new()),
+            metrics,
+        }
+    }
+
+    pub fn max_attempts(&self) -> u32 {
+        self.max_attempts
+    }
+
+    /// Delay before `attempt`: the base delay, doubled for each earlier retry.
+    pub fn backoff(&self, attempt: u32) -> Duration {
+        let doublings = attempt.saturating_sub(2).min(16);
+        self.base_delay
+            .saturating_mul(1 << doublings)
+            .min(MAX_RETRY_DELAY)
+    }
+
+    pub fn depth(&self) -> usize {
+        self.queued.lock().unwrap_or_else(|e| e.into_inner()).len()
+    }
+
+    /// Queues `attempt` for `page_id`. Returns false when attempts are used up, the page is
+    /// already queued, or no worker is listening.
+    pub fn enqueue(&self, page_id: &str, event_id: Option<&str>, attempt: u32) -> bool {
+        if attempt > self.max_attempts {
+            return false;
+        }
+        let mut queued = self.queued.lock().unwrap_or_else(|e| e.into_inner());
+        if !queued.insert(page_id.to_string()) {
+            return false;
+        }
+        let job = RetryJob {
+            page_id: page_id.to_string(),
+            event_id: event_id.map(str::to_string),
+            attempt,
+        };
+        if self.tx.send(job).is_err() {
+            queued.remove(page_id);
+            return false;
+        }
+        self.metrics.retry_queue_depth.inc();
+        true
+    }
+
+    /// Marks a queued job as started, so a new failure can queue the page again.
+    pub fn start(&self, page_id: &str) {
+        let removed = self
+            .queued
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .remove(page_id);
+        if removed {
+            self.metrics.retry_queue_depth.dec();
+        }
+    }
+
+    /// Hands the receiving end to the worker; `None` if it was already taken.
+    pub fn take_receiver(&self) -> Option<mpsc::UnboundedReceiver<RetryJob>> {
+        self.rx.lock().unwrap_or_else(|e| e.into_inner()).take()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn queue() -> RetryQueue {
+        RetryQueue::new(4, Duration::from_secs(30), Arc::new(Metrics::new()))
+    }
+
+    #[test]
+    fn backoff_doubles_per_retry_and_is_capped() {
+        let q = queue();
+        assert_eq!(q.backoff(2), Duration::from_secs(30));
+        assert_eq!(q.backoff(3), Duration::from_secs(60));
+        assert_eq!(q.backoff(4), Duration::from_secs(120));
+        assert_eq!(q.backoff(40), MAX_RETRY_DELAY);
+    }
+
+    #[test]
+    fn pages_are_queued_once_until_started() {
+        let q = queue();
+        assert!(q.enqueue("page-1", None, 2));
+        assert!(!q.enqueue("page-1", None, 2));
+        assert_eq!(q.depth(), 1);
+        q.start("page-1");
+        assert_eq!(q.depth(), 0);
+        assert!(q.enqueue("page-1", None, 3));
+        assert!(!q.enqueue("page-2", None, 5), "past max attempts");
+    }
+}
@@ -0,0 +1,244 @@
+//! Title comparison shared by the provider searches and the backfill's duplicate check, and
+//! the ids or URLs a title may carry instead of a name.
+use crate::anilist::{parse_anilist_url, AniListMediaType};
+
+/// Bare numbers shorter than this are searched as titles ("1917", "300"); longer ones are
+/// taken as an id, since no one names a film after a five-digit number.
+pub const MIN_BARE_ID_DIGITS: usize = 5;
+
+/// An id typed in place of a title: `tmdb:603` (or `#603`), `imdb:tt0133093` (or just
+/// `tt0133093`), `anilist:21` or `mal:5114`. Prefixes are case-insensitive.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum TitleId {
+    Tmdb(i32),
+    /// Lower-case, `tt`-prefixed.
+    Imdb(String),
+    AniList(i32),
+    /// A MyAnimeList id, looked up through AniList's `idMal`.
+    Mal(i32),
+}
+
+impl TitleId {
+    pub fn parse(title: &str) -> Option<Self> {
+        let title = title.trim();
+        if let Some(id) = title.strip_prefix('#') {
+            return parse_id(id).map(Self::Tmdb);
+        }
+        let Some((prefix, value)) = title.split_once(':') else {
+            return parse_imdb(title).map(Self::Imdb);
+        };
+        let value = value.trim();
+        match prefix.trim().to_ascii_lowercase().as_str() {
+            "tmdb" => parse_id(value).map(Self::Tmdb),
+            "imdb" => parse_imdb(value)
+                .or_else(|| parse_id(value).map(|_| format!("tt{value}")))
+                .map(Self::Imdb),
+            "anilist" => parse_id(value).map(Self::AniList),
+            "mal" => parse_id(value).map(Self::Mal),
+            _ => None,
+        }
+    }
+
+    /// Whether the id belongs to TMDB's side (TMDB or IMDb) rather than AniList's.
+    pub fn is_tmdb(&self) -> bool {
+        matches!(self, Self::Tmdb(_) | Self::Imdb(_))
+    }
+}
+
+/// A TMDB, IMDb or AniList page URL pasted as the title.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum MediaUrl {
+    /// `themoviedb.org/movie/603-the-matrix`
+    TmdbMovie(i32),
+    /// `themoviedb.org/tv/1396-breaking-bad[/season/2]`
+    TmdbTv { id: i32, season: Option<i32> },
+    /// `imdb.com/title/tt0133093/`, lower-cased; the kind is left to TMDB "Find by ID".
+    Imdb(String),
+    /// `anilist.co/anime/21/one-piece`
+    AniList(AniListMediaType, i32),
+}
+
+impl MediaUrl {
+    pub fn is_tmdb(&self) -> bool {
+        !matches!(self, Self::AniList(..))
+    }
+}
+
+/// Recognises a pasted page URL (scheme, `www.` and any query or fragment optional).
+pub fn parse_media_url(input: &str) -> Option<MediaUrl> {
+    let trimmed = input.trim();
+    if let Some((media_type, id)) = parse_anilist_url(trimmed) {
+        return Some(MediaUrl::AniList(media_type, id));
+    }
+    let rest = trimmed
+        .strip_prefix("https://")
+        .or_else(|| trimmed.strip_prefix("http://"))
+        .unwrap_or(trimmed);
+    let rest = rest.split(['?', '#']).next()?;
+    let mut parts = rest.split('/').filter(|p| !p.is_empty());
+    let host = parts.next()?.to_ascii_lowercase();
+    let host = host
+        .strip_prefix("www.")
+        .or_else(|| host.strip_prefix("m."))
+        .unwrap_or(&host);
+    match (host, parts.next()?) {
+        ("themoviedb.org", "movie") => parse_slug_id(parts.next()?).map(MediaUrl::TmdbMovie),
+        ("themoviedb.org", "tv") => {
+            let id = parse_slug_id(parts.next()?)?;
+            let season = match (parts.next(), parts.next()) {
+                (Some("season"), Some(season)) => Some(season.parse().ok()?),
+                _ => None,
+            };
+            Some(MediaUrl::TmdbTv { id, season })
+        }
+        ("imdb.com", "title") => parse_imdb(parts.next()?).map(MediaUrl::Imdb),
+        _ => None,
+    }
+}
+
+/// `603-the-matrix` (or just `603`) is 603.
+fn parse_slug_id(segment: &str) -> Option<i32> {
+    parse_id(segment.split('-').next()?)
+}
+
+/// A title that is only digits, long enough to be an id (see `MIN_BARE_ID_DIGITS`).
+pub fn parse_bare_id(title: &str) -> Option<i32> {
+    let title = title.trim();
+    if title.len() < MIN_BARE_ID_DIGITS {
+        return None;
+    }
+    parse_id(title)
+}
+
+fn parse_id(value: &str) -> Option<i32> {
+    if value.is_empty() || !value.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+    value.parse().ok().filter(|id| *id > 0)
+}
+
+fn parse_imdb(value: &str) -> Option<String> {
+    let lower = value.to_ascii_lowercase();
+    let digits = lower.strip_prefix("tt")?;
+    (!digits.is_empty() && digits.bytes().all(|b| b.is_ascii_digit())).then_some(lower)
+}
+
+/// Lowercased ASCII letters and digits, with every other run of characters collapsed to one
+/// space, so "Spider-Man: No Way Home" and "spider man no way home" compare equal.
+pub fn normalize_title_key(input: &str) -> String {
+    let mut out = String::with_capacity(input.len());
+    let mut last_space = false;
+    for ch in input.chars() {
+        let ch = ch.to_ascii_lowercase();
+        let is_alnum = ch.is_ascii_alphanumeric();
+        if is_alnum {
+            out.push(ch);
+            last_space = false;
+        } else if !last_space {
+            out.push(' ');
+            last_space = true;
+        }
+    }
+    out.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn punctuation_case_and_spacing_are_ignored() {
+        assert_eq!(
+            normalize_title_key("Spider-Man: No Way Home"),
+            "spider man no way home"
+        );
+        assert_eq!(normalize_title_key("  THE   office  "), "the office");
+        assert_eq!(normalize_title_key("?!"), "");
+    }
+
+    #[test]
+    fn prefixed_ids_are_parsed_by_source() {
+        assert_eq!(TitleId::parse("tmdb:603"), Some(TitleId::Tmdb(603)));
+        assert_eq!(TitleId::parse(" TMDB: 603 "), Some(TitleId::Tmdb(603)));
+        assert_eq!(TitleId::parse("#603"), Some(TitleId::Tmdb(603)));
+        let matrix = Some(TitleId::Imdb("tt0133093".to_string()));
+        assert_eq!(TitleId::parse("imdb:tt0133093"), matrix);
+        assert_eq!(TitleId::parse("imdb:0133093"), matrix);
+        assert_eq!(TitleId::parse("TT0133093"), matrix);
+        assert_eq!(TitleId::parse("anilist:21"), Some(TitleId::AniList(21)));
+        assert_eq!(TitleId::parse("Mal:5114"), Some(TitleId::Mal(5114)));
+        for title in [
+            "1917",
+            "603",
+            "tmdb:",
+            "tmdb:abc",
+            "mal:0",
+            "#",
+            "# 603",
+            "#1 Cheerleader Camp",
+            "tt",
+            "Mission: Impossible",
+            "Star Wars: Episode IV",
+        ] {
+            assert_eq!(TitleId::parse(title), None, "{title}");
+        }
+        assert!(TitleId::parse("imdb:tt1").unwrap().is_tmdb());
+        assert!(!TitleId::parse("mal:1").unwrap().is_tmdb());
+    }
+
+    #[test]
+    fn pasted_page_urls_give_the_id_kind_and_season() {
+        assert_eq!(
+            parse_media_url("https://www.themoviedb.org/movie/603-the-matrix"),
+            Some(MediaUrl::TmdbMovie(603))
+        );
+        assert_eq!(
+            parse_media_url("themoviedb.org/movie/603?language=fr-FR"),
+            Some(MediaUrl::TmdbMovie(603))
+        );
+        assert_eq!(
+            parse_media_url("https://www.themoviedb.org/tv/1396/season/2"),
+            Some(MediaUrl::TmdbTv {
+                id: 1396,
+                season: Some(2)
+            })
+        );
+        assert_eq!(
+            parse_media_url("https://www.themoviedb.org/tv/1396-breaking-bad/"),
+            Some(MediaUrl::TmdbTv {
+                id: 1396,
+                season: None
+            })
+        );
+        assert_eq!(
+            parse_media_url("https://m.imdb.com/title/tt0133093/?ref_=nv_sr_1"),
+            Some(MediaUrl::Imdb("tt0133093".to_string()))
+        );
+        let one_piece = MediaUrl::AniList(AniListMediaType::Anime, 21);
+        assert!(!one_piece.is_tmdb());
+        assert_eq!(
+            parse_media_url("https://anilist.co/anime/21/"),
+            Some(one_piece)
+        );
+
+        for input in [
+            "The Matrix",
+            "tmdb:603",
+            "https://www.themoviedb.org/person/6384-keanu-reeves",
+            "https://www.themoviedb.org/movie/the-matrix",
+            "https://www.themoviedb.org/tv/1396/season/two",
+            "https://www.imdb.com/name/nm0000206/",
+            "https://example.com/movie/603",
+        ] {
+            assert_eq!(parse_media_url(input), None, "{input}");
+        }
+    }
+
+    #[test]
+    fn only_long_bare_numbers_are_ids() {
+        assert_eq!(parse_bare_id("1917"), None);
+        assert_eq!(parse_bare_id("300"), None);
+        assert_eq!(parse_bare_id(" 438631 "), Some(438631));
+        assert_eq!(parse_bare_id("12345a"), None);
+    }
+}
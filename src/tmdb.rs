@@ -1,22 +1,143 @@
+use crate::budget::Provider;
+use crate::countries;
+use crate::errors::UpstreamStatus;
+use crate::http::{self, RetryingClient};
+use crate::languages;
+use crate::metrics::{CacheCounters, TMDB_MOVIE_CACHE, TMDB_SEARCH_CACHE, TMDB_SHOW_CACHE};
+use crate::titles::{normalize_title_key, parse_bare_id, parse_media_url, MediaUrl, TitleId};
 use anyhow::{anyhow, Context, Result};
 use async_trait::async_trait;
 use reqwest::Client;
-use serde::Deserialize;
-use std::collections::HashMap;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, VecDeque};
 use std::env;
-use std::time::Duration;
-use tokio::sync::OnceCell;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::sync::{Mutex, OnceCell};
+use tracing::debug;
 
-const TMDB_BASE: &str = "https://api.themoviedb.org/3";
+const DEFAULT_TMDB_BASE: &str = "https://api.themoviedb.org/3";
 const POSTER_BASE: &str = "https://image.tmdb.org/t/p/original";
-const MAX_RETRIES: usize = 3;
+/// Alternate posters written to the optional "Gallery" property.
+const GALLERY_SIZE: usize = 4;
+/// Original languages for which the original title and a poster in that language are used;
+/// keep in sync with `include_image_language` in the appended requests.
+const ORIGINAL_LANGUAGES: &[&str] = &["fr", "es", "de"];
+const DEFAULT_CACHE_TTL_SECS: u64 = 60 * 60 * 24; // 24 hours
+const MAX_CACHE_ENTRIES: usize = 20_000;
+const MAX_SEARCH_CACHE_ENTRIES: usize = 1_000;
+/// Country whose streaming services are reported (`TMDB_WATCH_REGION`).
+const DEFAULT_WATCH_REGION: &str = "US";
 
 #[derive(Debug, Clone)]
 pub struct TmdbClient {
-    client: Client,
+    http: RetryingClient,
     api_key: String,
+    base_url: String,
     countries: OnceCell<HashMap<String, String>>,
     languages: OnceCell<HashMap<String, String>>,
+    cache_ttl: Duration,
+    movie_cache: Arc<Mutex<HashMap<i32, CacheEntry<MovieAppended>>>>,
+    show_cache: Arc<Mutex<HashMap<i32, CacheEntry<ShowAppended>>>>,
+    search_cache: Arc<Mutex<SearchCache>>,
+    watch_region: String,
+}
+
+#[derive(Debug, Clone)]
+struct CacheEntry<T> {
+    inserted_at: Instant,
+    value: T,
+}
+
+/// Title search results keyed on `"movie:<query>"` / `"tv:<query>"`, so pages sharing a
+/// title (e.g. one page per season) search TMDB once. Evicts the least recently used entry.
+#[derive(Debug)]
+struct SearchCache {
+    capacity: usize,
+    entries: HashMap<String, CacheEntry<i32>>,
+    order: VecDeque<String>,
+}
+
+impl SearchCache {
+    fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            entries: HashMap::new(),
+            order: VecDeque::new(),
+        }
+    }
+
+    fn get(&mut self, key: &str, ttl: Duration) -> Option<i32> {
+        let entry = self.entries.get(key)?;
+        if entry.inserted_at.elapsed() >= ttl {
+            self.entries.remove(key);
+            self.order.retain(|k| k != key);
+            return None;
+        }
+        let id = entry.value;
+        self.touch(key);
+        Some(id)
+    }
+
+    fn insert(&mut self, key: String, id: i32) {
+        if self.capacity == 0 {
+            return;
+        }
+        if self.entries.contains_key(&key) {
+            self.touch(&key);
+        } else {
+            while self.entries.len() >= self.capacity {
+                match self.order.pop_front() {
+                    Some(oldest) => {
+                        self.entries.remove(&oldest);
+                    }
+                    None => break,
+                }
+            }
+            self.order.push_back(key.clone());
+        }
+        self.entries.insert(
+            key,
+            CacheEntry {
+                inserted_at: Instant::now(),
+                value: id,
+            },
+        );
+    }
+
+    fn touch(&mut self, key: &str) {
+        if let Some(pos) = self.order.iter().position(|k| k == key) {
+            if let Some(k) = self.order.remove(pos) {
+                self.order.push_back(k);
+            }
+        }
+    }
+}
+
+fn search_cache_key(kind: &str, query: &str) -> String {
+    format!("{kind}:{}", query.trim().to_lowercase())
+}
+
+#[derive(Clone, Copy, Debug)]
+enum SearchKind {
+    Movie,
+    Tv,
+}
+
+impl SearchKind {
+    fn path(self) -> &'static str {
+        match self {
+            SearchKind::Movie => "movie",
+            SearchKind::Tv => "tv",
+        }
+    }
+
+    fn not_found(self, query: &str) -> anyhow::Error {
+        match self {
+            SearchKind::Movie => anyhow!("No TMDB movie found for '{}'", query),
+            SearchKind::Tv => anyhow!("No TMDB TV show found for '{}'", query),
+        }
+    }
 }
 
 #[async_trait]
@@ -25,12 +146,26 @@ pub trait TmdbApi: Send + Sync {
     async fn search_tv(&self, query: &str) -> Result<i32>;
     async fn resolve_movie_id(&self, query: &str) -> Result<i32>;
     async fn resolve_tv_id(&self, query: &str) -> Result<i32>;
+    /// Like `resolve_movie_id`, preferring movies released in `year`.
+    async fn resolve_movie_id_with_year(&self, query: &str, year: Option<i32>) -> Result<i32> {
+        let _ = year;
+        self.resolve_movie_id(query).await
+    }
+    /// Like `resolve_tv_id`, preferring shows that aired in `year`.
+    async fn resolve_tv_id_with_year(&self, query: &str, year: Option<i32>) -> Result<i32> {
+        let _ = year;
+        self.resolve_tv_id(query).await
+    }
     async fn lookup_imdb(&self, imdb_id: &str) -> Result<(Option<i32>, Option<i32>)>;
     async fn fetch_movie(&self, id: i32) -> Result<MediaData>;
     async fn fetch_tv_season(&self, id: i32, season: i32) -> Result<MediaData>;
+    /// Cheap authenticated call used by the readiness check.
+    async fn ping(&self) -> Result<()> {
+        Ok(())
+    }
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize)]
 pub struct MediaData {
     pub id: i32,
     pub name: String,
@@ -43,6 +178,8 @@ pub struct MediaData {
     pub content_rating: Option<String>,
     pub country_of_origin: Vec<String>,
     pub language: Option<String>,
+    /// Every spoken language, original first; empty when TMDB doesn't list them.
+    pub spoken_languages: Vec<String>,
     pub original_language: String,
     pub release_date: Option<String>,
     pub year: Option<String>,
@@ -50,41 +187,170 @@ pub struct MediaData {
     pub episodes: Option<usize>,
     pub trailer: Option<String>,
     pub poster: Option<String>,
+    /// Alternate posters (excluding `poster`), best first.
+    pub gallery: Vec<String>,
     #[allow(dead_code)]
     pub backdrop: Option<String>,
     pub imdb_page: Option<String>,
+    /// TMDB's vote average (0–10); `None` when nobody has voted yet.
+    pub vote_average: Option<f64>,
+    /// How many TMDB users voted; `None` when nobody has yet.
+    pub vote_count: Option<u32>,
+    /// Movies only: the franchise collection the movie belongs to.
+    pub collection: Option<String>,
+    /// TV only: the networks or streaming services the show aired on.
+    pub networks: Vec<String>,
+    /// Keywords or tags describing the title, in the source's order.
+    pub keywords: Vec<String>,
+    /// Subscription streaming services offering the title in the watch region.
+    pub watch_providers: Vec<String>,
+    /// JustWatch page listing where to watch the title in the watch region.
+    pub watch_link: Option<String>,
 }
 
 impl TmdbClient {
     pub fn from_env() -> Result<Self> {
         let api_key = env::var("TMDB_API_KEY").context("TMDB_API_KEY not set")?;
-        let user_agent = format!("cinelink/{}", env!("CARGO_PKG_VERSION"));
-        let client = Client::builder()
-            .connect_timeout(Duration::from_secs(5))
-            .timeout(Duration::from_secs(30))
-            .user_agent(user_agent)
-            .build()
-            .context("Failed to build TMDB HTTP client")?;
+        let cache_ttl_secs = match env::var("TMDB_CACHE_TTL_SECS") {
+            Ok(raw) => raw
+                .trim()
+                .parse::<u64>()
+                .with_context(|| format!("Invalid TMDB_CACHE_TTL_SECS: {raw}"))?,
+            Err(_) => DEFAULT_CACHE_TTL_SECS,
+        };
+        let watch_region = match env::var("TMDB_WATCH_REGION") {
+            Ok(raw) if !raw.trim().is_empty() => parse_watch_region(&raw)
+                .with_context(|| format!("Invalid TMDB_WATCH_REGION: {raw}"))?,
+            _ => DEFAULT_WATCH_REGION.to_string(),
+        };
+        let mut client = Self::new(api_key)?;
+        client.cache_ttl = Duration::from_secs(cache_ttl_secs);
+        client.watch_region = watch_region;
+        Ok(client)
+    }
+
+    pub fn new(api_key: String) -> Result<Self> {
         Ok(Self {
-            client,
+            http: RetryingClient::new(http::default_client()?, Provider::Tmdb),
             api_key,
+            base_url: DEFAULT_TMDB_BASE.to_string(),
             countries: OnceCell::new(),
             languages: OnceCell::new(),
+            cache_ttl: Duration::from_secs(DEFAULT_CACHE_TTL_SECS),
+            movie_cache: Arc::new(Mutex::new(HashMap::new())),
+            show_cache: Arc::new(Mutex::new(HashMap::new())),
+            search_cache: Arc::new(Mutex::new(SearchCache::new(MAX_SEARCH_CACHE_ENTRIES))),
+            watch_region: DEFAULT_WATCH_REGION.to_string(),
         })
     }
 
+    /// Points the client at another TMDB-compatible API root (e.g. a local mock).
+    pub fn with_base_url(mut self, base_url: impl Into<String>) -> Self {
+        self.base_url = base_url.into().trim_end_matches('/').to_string();
+        self
+    }
+
+    /// Sends requests through `client`, sharing its connection pool.
+    pub fn with_http_client(mut self, client: Client) -> Self {
+        self.http = RetryingClient::new(client, Provider::Tmdb);
+        self
+    }
+
+    async fn cached_search(&self, key: &str) -> Option<i32> {
+        let cached = self.search_cache.lock().await.get(key, self.cache_ttl);
+        TMDB_SEARCH_CACHE.record(cached.is_some());
+        cached
+    }
+
+    /// Best-matching search result for `query` (see `pick_search_result`), restricted to
+    /// `year` when given.
+    async fn search(
+        &self,
+        kind: SearchKind,
+        query: &str,
+        year: Option<i32>,
+    ) -> Result<Option<i32>> {
+        #[derive(Deserialize)]
+        struct SearchResponse {
+            results: Vec<SearchResult>,
+        }
+
+        let key = match year {
+            Some(year) => search_cache_key(&format!("{}@{year}", kind.path()), query),
+            None => search_cache_key(kind.path(), query),
+        };
+        if let Some(id) = self.cached_search(&key).await {
+            return Ok(Some(id));
+        }
+        let mut url = format!(
+            "{}/search/{}?api_key={}&query={}&language=en-US",
+            self.base_url,
+            kind.path(),
+            self.api_key,
+            urlencoding::encode(query)
+        );
+        // For TV, `year` (rather than `first_air_date_year`) matches any episode's air date,
+        // so the year of a later season still finds the show.
+        if let Some(year) = year {
+            url.push_str(&format!("&year={year}"));
+        }
+        let data: SearchResponse = self.get_json(&url).await?;
+        let best = pick_search_result(query, &data.results);
+        if let Some((result, score)) = best {
+            debug!(
+                "TMDB {} search '{}' picked {} '{}' ({}) with score {} out of {} results",
+                kind.path(),
+                query,
+                result.id,
+                result.title.as_deref().unwrap_or_default(),
+                result.release_date.as_deref().unwrap_or("no date"),
+                score,
+                data.results.len()
+            );
+        }
+        let id = best.map(|(result, _)| result.id);
+        if let Some(id) = id {
+            self.search_cache.lock().await.insert(key, id);
+        }
+        Ok(id)
+    }
+
+    /// Searches within `year` first; a year that matches nothing (a typo, or a date the page
+    /// got from an earlier wrong match) falls back to the plain search.
+    async fn search_with_year(
+        &self,
+        kind: SearchKind,
+        query: &str,
+        year: Option<i32>,
+    ) -> Result<i32> {
+        if year.is_some() {
+            if let Some(id) = self.search(kind, query, year).await? {
+                return Ok(id);
+            }
+            debug!(
+                "No TMDB {} result for '{}' in {:?}",
+                kind.path(),
+                query,
+                year
+            );
+        }
+        self.search(kind, query, None)
+            .await?
+            .ok_or_else(|| kind.not_found(query))
+    }
+
     async fn fetch_movie_images(&self, id: i32, lang: &str) -> Result<ImageResponse> {
         let url = format!(
-            "{TMDB_BASE}/movie/{id}/images?include_image_language={lang},null&api_key={}",
-            self.api_key
+            "{}/movie/{id}/images?include_image_language={lang},null&api_key={}",
+            self.base_url, self.api_key
         );
         self.get_json(&url).await
     }
 
     async fn fetch_season_images(&self, id: i32, season: i32, lang: &str) -> Result<ImageResponse> {
         let url = format!(
-            "{TMDB_BASE}/tv/{id}/season/{season}/images?include_image_language={lang},null&api_key={}",
-            self.api_key
+            "{}/tv/{id}/season/{season}/images?include_image_language={lang},null&api_key={}",
+            self.base_url, self.api_key
         );
         self.get_json(&url).await
     }
@@ -92,29 +358,24 @@ impl TmdbClient {
 
 #[async_trait]
 impl TmdbApi for TmdbClient {
-    async fn search_movie(&self, query: &str) -> Result<i32> {
-        #[derive(Deserialize)]
-        struct SearchResult {
-            id: i32,
-        }
-        #[derive(Deserialize)]
-        struct SearchResponse {
-            results: Vec<SearchResult>,
+    async fn ping(&self) -> Result<()> {
+        let url = format!("{}/configuration?api_key={}", self.base_url, self.api_key);
+        let status = self.http.client().get(&url).send().await?.status();
+        if !status.is_success() {
+            return Err(anyhow!("{}", status.as_u16()));
         }
+        Ok(())
+    }
 
-        let url = format!(
-            "{TMDB_BASE}/search/movie?api_key={}&query={}&language=en-US",
-            self.api_key,
-            urlencoding::encode(query)
-        );
-        let data: SearchResponse = self.get_json(&url).await?;
-        data.results
-            .first()
-            .map(|r| r.id)
-            .ok_or_else(|| anyhow!("No TMDB movie found for '{}'", query))
+    async fn search_movie(&self, query: &str) -> Result<i32> {
+        self.search_with_year(SearchKind::Movie, query, None).await
     }
 
     async fn resolve_movie_id(&self, query: &str) -> Result<i32> {
+        self.resolve_movie_id_with_year(query, None).await
+    }
+
+    async fn resolve_movie_id_with_year(&self, query: &str, year: Option<i32>) -> Result<i32> {
         if let Some(id) = parse_tmdb_id(query) {
             return Ok(id);
         }
@@ -123,32 +384,18 @@ impl TmdbApi for TmdbClient {
                 return Ok(id);
             }
         }
-        self.search_movie(query).await
+        self.search_with_year(SearchKind::Movie, query, year).await
     }
 
     async fn search_tv(&self, query: &str) -> Result<i32> {
-        #[derive(Deserialize)]
-        struct SearchResult {
-            id: i32,
-        }
-        #[derive(Deserialize)]
-        struct SearchResponse {
-            results: Vec<SearchResult>,
-        }
-
-        let url = format!(
-            "{TMDB_BASE}/search/tv?api_key={}&query={}&language=en-US",
-            self.api_key,
-            urlencoding::encode(query)
-        );
-        let data: SearchResponse = self.get_json(&url).await?;
-        data.results
-            .first()
-            .map(|r| r.id)
-            .ok_or_else(|| anyhow!("No TMDB TV show found for '{}'", query))
+        self.search_with_year(SearchKind::Tv, query, None).await
     }
 
     async fn resolve_tv_id(&self, query: &str) -> Result<i32> {
+        self.resolve_tv_id_with_year(query, None).await
+    }
+
+    async fn resolve_tv_id_with_year(&self, query: &str, year: Option<i32>) -> Result<i32> {
         if let Some(id) = parse_tmdb_id(query) {
             return Ok(id);
         }
@@ -157,7 +404,7 @@ impl TmdbApi for TmdbClient {
                 return Ok(id);
             }
         }
-        self.search_tv(query).await
+        self.search_with_year(SearchKind::Tv, query, year).await
     }
 
     async fn lookup_imdb(&self, imdb_id: &str) -> Result<(Option<i32>, Option<i32>)> {
@@ -169,44 +416,61 @@ impl TmdbApi for TmdbClient {
         // If TMDB changes the response shape or an append isn't supported, fall back to the
         // previous multi-request approach (still parallelized).
         let appended = self.fetch_movie_appended(id).await.ok();
-        let (detail, credits, release_dates, videos, external_ids, images_opt) = if let Some(a) =
-            appended
-        {
-            (
-                a.detail,
-                a.credits,
-                a.release_dates,
-                a.videos,
-                a.external_ids,
-                a.images,
-            )
-        } else {
-            let url_detail = format!(
-                "{TMDB_BASE}/movie/{id}?language=en-US&api_key={}",
-                self.api_key
-            );
-            let url_credits = format!("{TMDB_BASE}/movie/{id}/credits?api_key={}", self.api_key);
-            let url_release_dates = format!(
-                "{TMDB_BASE}/movie/{id}/release_dates?api_key={}",
-                self.api_key
-            );
-            let url_videos = format!("{TMDB_BASE}/movie/{id}/videos?api_key={}", self.api_key);
-            let url_external_ids = format!(
-                "{TMDB_BASE}/movie/{id}/external_ids?api_key={}",
-                self.api_key
-            );
-
-            let (detail, credits, release_dates, videos, external_ids) = tokio::try_join!(
-                self.get_json::<MovieDetail>(&url_detail),
-                self.get_json::<Credits>(&url_credits),
-                self.get_json::<ReleaseDates>(&url_release_dates),
-                self.get_json::<Videos>(&url_videos),
-                self.get_json::<ExternalIds>(&url_external_ids),
-            )?;
-            (detail, credits, release_dates, videos, external_ids, None)
-        };
+        let (detail, credits, release_dates, videos, external_ids, images_opt, keywords, providers) =
+            if let Some(a) = appended {
+                (
+                    a.detail,
+                    a.credits,
+                    a.release_dates,
+                    a.videos,
+                    a.external_ids,
+                    a.images,
+                    a.keywords,
+                    a.watch_providers,
+                )
+            } else {
+                let url_detail = format!(
+                    "{}/movie/{id}?language=en-US&api_key={}",
+                    self.base_url, self.api_key
+                );
+                let url_credits = format!(
+                    "{}/movie/{id}/credits?api_key={}",
+                    self.base_url, self.api_key
+                );
+                let url_release_dates = format!(
+                    "{}/movie/{id}/release_dates?api_key={}",
+                    self.base_url, self.api_key
+                );
+                let url_videos = format!(
+                    "{}/movie/{id}/videos?api_key={}",
+                    self.base_url, self.api_key
+                );
+                let url_external_ids = format!(
+                    "{}/movie/{id}/external_ids?api_key={}",
+                    self.base_url, self.api_key
+                );
+
+                let (detail, credits, release_dates, videos, external_ids) = tokio::try_join!(
+                    self.get_json::<MovieDetail>(&url_detail),
+                    self.get_json::<Credits>(&url_credits),
+                    self.get_json::<ReleaseDates>(&url_release_dates),
+                    self.get_json::<Videos>(&url_videos),
+                    self.get_json::<ExternalIds>(&url_external_ids),
+                )?;
+                (
+                    detail,
+                    credits,
+                    release_dates,
+                    videos,
+                    external_ids,
+                    None,
+                    None,
+                    None,
+                )
+            };
 
         let content_rating = us_cert_from_release_dates(&release_dates);
+        let (watch_providers, watch_link) = region_providers(providers, &self.watch_region);
         let director = credits
             .crew
             .unwrap_or_default()
@@ -216,33 +480,31 @@ impl TmdbApi for TmdbClient {
             .collect::<Vec<_>>();
         let cast = top_names(&credits.cast, 10);
         let trailer = select_trailer(&videos);
-        let preferred_lang = if detail.original_language == "fr" {
-            Some("fr")
-        } else if detail.original_language == "es" {
-            Some("es")
-        } else {
-            None
-        };
+        let preferred_lang = original_poster_language(&detail.original_language);
 
+        let images = match (images_opt, preferred_lang) {
+            (Some(images), _) => Some(images),
+            (None, Some(lang)) => self.fetch_movie_images(id, lang).await.ok(),
+            (None, None) => None,
+        };
         let poster = match preferred_lang {
-            Some(lang) => {
-                let images = if images_opt.is_some() {
-                    images_opt
-                } else {
-                    self.fetch_movie_images(id, lang).await.ok()
-                };
-                select_poster(images.as_ref(), Some(lang)).or_else(|| {
-                    detail
-                        .poster_path
-                        .as_ref()
-                        .map(|p| format!("{POSTER_BASE}{p}"))
-                })
-            }
+            Some(lang) => select_poster(images.as_ref(), Some(lang)).or_else(|| {
+                detail
+                    .poster_path
+                    .as_ref()
+                    .map(|p| format!("{POSTER_BASE}{p}"))
+            }),
             None => detail
                 .poster_path
                 .as_ref()
                 .map(|p| format!("{POSTER_BASE}{p}")),
         };
+        let gallery = select_gallery(
+            images.as_ref(),
+            preferred_lang,
+            poster.as_deref(),
+            GALLERY_SIZE,
+        );
         let backdrop = detail
             .backdrop_path
             .as_ref()
@@ -260,7 +522,10 @@ impl TmdbApi for TmdbClient {
             .as_ref()
             .map(|id| format!("https://www.imdb.com/title/{id}"));
         let language = self.language_display_name(&detail.original_language).await;
-        let use_original = detail.original_language == "fr" || detail.original_language == "es";
+        let spoken_languages = self
+            .spoken_language_names(&detail.original_language, detail.spoken_languages.as_ref())
+            .await;
+        let use_original = preferred_lang.is_some();
         let name = if use_original {
             detail.original_title.clone()
         } else {
@@ -284,6 +549,7 @@ impl TmdbApi for TmdbClient {
             content_rating,
             country_of_origin: country,
             language,
+            spoken_languages,
             original_language: detail.original_language,
             release_date,
             year,
@@ -291,23 +557,31 @@ impl TmdbApi for TmdbClient {
             episodes: None,
             trailer,
             poster,
+            gallery,
             backdrop,
             imdb_page,
+            vote_average: voted(detail.vote_average),
+            vote_count: detail.vote_count.filter(|c| *c > 0),
+            collection: detail.belongs_to_collection.map(|c| c.name),
+            networks: Vec::new(),
+            keywords: keyword_names(keywords),
+            watch_providers,
+            watch_link,
         })
     }
 
     async fn fetch_tv_season(&self, id: i32, season: i32) -> Result<MediaData> {
         let url_season = format!(
-            "{TMDB_BASE}/tv/{id}/season/{season}?language=en-US&api_key={}",
-            self.api_key
+            "{}/tv/{id}/season/{season}?language=en-US&api_key={}",
+            self.base_url, self.api_key
         );
         let url_credits = format!(
-            "{TMDB_BASE}/tv/{id}/season/{season}/credits?api_key={}",
-            self.api_key
+            "{}/tv/{id}/season/{season}/credits?api_key={}",
+            self.base_url, self.api_key
         );
         let url_videos = format!(
-            "{TMDB_BASE}/tv/{id}/season/{season}/videos?api_key={}",
-            self.api_key
+            "{}/tv/{id}/season/{season}/videos?api_key={}",
+            self.base_url, self.api_key
         );
 
         let (show, season_detail, credits, season_videos) = tokio::try_join!(
@@ -323,18 +597,16 @@ impl TmdbApi for TmdbClient {
             content_ratings,
             videos: show_videos,
             images: show_images,
+            keywords,
+            watch_providers,
         } = show;
+        let (watch_providers, watch_link) = region_providers(watch_providers, &self.watch_region);
         let content_rating = us_rating(&content_ratings);
         let cast = top_names(&credits.cast, 10);
         let trailer = select_trailer(&season_videos).or_else(|| select_trailer(&show_videos));
-        let preferred_lang = if show_detail.original_language == "fr" {
-            Some("fr")
-        } else if show_detail.original_language == "es" {
-            Some("es")
-        } else {
-            None
-        };
+        let preferred_lang = original_poster_language(&show_detail.original_language);
 
+        let gallery_images = show_images.clone();
         let poster = match preferred_lang {
             Some(lang) => {
                 let season_images = self.fetch_season_images(id, season, lang).await.ok();
@@ -354,6 +626,12 @@ impl TmdbApi for TmdbClient {
                 .or(show_detail.poster_path.as_ref())
                 .map(|p| format!("{POSTER_BASE}{p}")),
         };
+        let gallery = select_gallery(
+            gallery_images.as_ref(),
+            preferred_lang,
+            poster.as_deref(),
+            GALLERY_SIZE,
+        );
         let backdrop = show_detail
             .backdrop_path
             .as_ref()
@@ -374,11 +652,17 @@ impl TmdbApi for TmdbClient {
             .unwrap_or_default();
         let episodes_count = season_detail.episodes.len();
         let runtime = average_episode_runtime(&season_detail, &show_detail);
+        let (vote_average, vote_count) = season_rating(&season_detail, &show_detail);
         let language = self
             .language_display_name(&show_detail.original_language)
             .await;
-        let use_original =
-            show_detail.original_language == "fr" || show_detail.original_language == "es";
+        let spoken_languages = self
+            .spoken_language_names(
+                &show_detail.original_language,
+                show_detail.spoken_languages.as_ref(),
+            )
+            .await;
+        let use_original = preferred_lang.is_some();
         let name = if use_original {
             show_detail.original_name.clone()
         } else {
@@ -406,6 +690,7 @@ impl TmdbApi for TmdbClient {
             content_rating,
             country_of_origin: country,
             language,
+            spoken_languages,
             original_language: show_detail.original_language,
             release_date: air_date,
             year,
@@ -413,8 +698,19 @@ impl TmdbApi for TmdbClient {
             episodes: Some(episodes_count),
             trailer,
             poster,
+            gallery,
             backdrop,
             imdb_page,
+            vote_average,
+            vote_count,
+            collection: None,
+            networks: show_detail
+                .networks
+                .map(|n| n.into_iter().map(|n| n.name).collect())
+                .unwrap_or_default(),
+            keywords: keyword_names(keywords),
+            watch_providers,
+            watch_link,
         })
     }
 }
@@ -432,8 +728,8 @@ impl TmdbClient {
         }
 
         let url = format!(
-            "{TMDB_BASE}/configuration/countries?language=en-US&api_key={}",
-            self.api_key
+            "{}/configuration/countries?language=en-US&api_key={}",
+            self.base_url, self.api_key
         );
         let items: Vec<CountryItem> = self.get_json(&url).await?;
         let map: HashMap<String, String> = items
@@ -444,15 +740,21 @@ impl TmdbClient {
         Ok(self.countries.get().expect("countries OnceCell is set"))
     }
 
+    /// Names from the shared table, so they match AniList's; TMDB's own list (fetched only
+    /// when needed) covers codes the table lacks. Unknown codes are kept as they are.
     async fn country_names(&self, codes: Vec<String>) -> Vec<String> {
-        let Ok(map) = self.get_country_map().await else {
-            return codes;
-        };
-
-        codes
-            .into_iter()
-            .map(|code| map.get(&code).cloned().unwrap_or(code))
-            .collect()
+        let mut names = Vec::with_capacity(codes.len());
+        for code in codes {
+            let name = match countries::country_name(&code) {
+                Some(name) => name.to_string(),
+                None => match self.get_country_map().await {
+                    Ok(map) => map.get(&code).cloned().unwrap_or(code),
+                    Err(_) => code,
+                },
+            };
+            names.push(name);
+        }
+        names
     }
 
     async fn get_language_map(&self) -> Result<&HashMap<String, String>> {
@@ -467,8 +769,8 @@ impl TmdbClient {
         }
 
         let url = format!(
-            "{TMDB_BASE}/configuration/languages?api_key={}",
-            self.api_key
+            "{}/configuration/languages?api_key={}",
+            self.base_url, self.api_key
         );
         let items: Vec<LanguageItem> = self.get_json(&url).await?;
         let map: HashMap<String, String> = items
@@ -480,64 +782,97 @@ impl TmdbClient {
         Ok(self.languages.get().expect("languages OnceCell is set"))
     }
 
+    /// Name from the shared table, with TMDB's own list (fetched only when needed) for codes
+    /// the table lacks, such as `cn` (Cantonese). Unknown codes are kept as they are.
     async fn language_display_name(&self, code: &str) -> Option<String> {
+        if let Some(name) = languages::language_name(code) {
+            return Some(name.to_string());
+        }
         if let Ok(map) = self.get_language_map().await {
             if let Some(name) = map.get(code) {
                 return Some(name.clone());
             }
         }
-        fallback_language_name(code)
+        Some(code.to_string())
+    }
+
+    /// Display names for every spoken language, original language first, without repeats.
+    async fn spoken_language_names(
+        &self,
+        original_language: &str,
+        spoken: Option<&Vec<SpokenLanguage>>,
+    ) -> Vec<String> {
+        let Some(spoken) = spoken.filter(|s| !s.is_empty()) else {
+            return Vec::new();
+        };
+        let mut codes = vec![original_language];
+        for language in spoken {
+            let code = language.iso_639_1.trim();
+            if !code.is_empty() && !codes.contains(&code) {
+                codes.push(code);
+            }
+        }
+        let mut names = Vec::with_capacity(codes.len());
+        for code in codes {
+            if let Some(name) = self.language_display_name(code).await {
+                if !names.contains(&name) {
+                    names.push(name);
+                }
+            }
+        }
+        names
     }
 
     async fn fetch_movie_appended(&self, id: i32) -> Result<MovieAppended> {
+        if let Some(cached) =
+            get_cached(&self.movie_cache, id, self.cache_ttl, &TMDB_MOVIE_CACHE).await
+        {
+            return Ok(cached);
+        }
         let url = format!(
-            "{TMDB_BASE}/movie/{id}?append_to_response=credits,release_dates,videos,external_ids,images&language=en-US&include_image_language=fr,es,null&api_key={}",
+            "{}/movie/{id}?append_to_response=credits,release_dates,videos,external_ids,images,keywords,watch/providers&language=en-US&include_image_language=fr,es,de,null&api_key={}",
+            self.base_url,
             self.api_key
         );
-        self.get_json(&url).await
+        let movie: MovieAppended = self.get_json(&url).await?;
+        put_cached(&self.movie_cache, id, movie.clone()).await;
+        Ok(movie)
     }
 
+    /// Cached per show id, so backfilling several seasons of one show fetches it once.
     async fn fetch_show_appended(&self, id: i32) -> Result<ShowAppended> {
+        if let Some(cached) =
+            get_cached(&self.show_cache, id, self.cache_ttl, &TMDB_SHOW_CACHE).await
+        {
+            return Ok(cached);
+        }
         let url = format!(
-            "{TMDB_BASE}/tv/{id}?append_to_response=external_ids,content_ratings,videos,images&language=en-US&include_image_language=fr,es,null&api_key={}",
+            "{}/tv/{id}?append_to_response=external_ids,content_ratings,videos,images,keywords,watch/providers&language=en-US&include_image_language=fr,es,de,null&api_key={}",
+            self.base_url,
             self.api_key
         );
-        self.get_json(&url).await
+        let show: ShowAppended = self.get_json(&url).await?;
+        put_cached(&self.show_cache, id, show.clone()).await;
+        Ok(show)
     }
 
     async fn get_json<T: for<'de> Deserialize<'de>>(&self, url: &str) -> Result<T> {
-        for attempt in 1..=MAX_RETRIES {
-            let res = self.client.get(url).send().await;
-            match res {
-                Ok(res) => {
-                    let status = res.status();
-                    if (status.as_u16() == 429 || status.is_server_error()) && attempt < MAX_RETRIES
-                    {
-                        let delay = retry_delay(attempt, res.headers().get("retry-after"));
-                        tokio::time::sleep(delay).await;
-                        continue;
-                    }
-                    let bytes = res.bytes().await.context("reading body failed")?;
-                    if !status.is_success() {
-                        return Err(anyhow!(
-                            "TMDB request failed (status {}) for {}: {}",
-                            status,
-                            url,
-                            String::from_utf8_lossy(&bytes)
-                        ));
-                    }
-                    return serde_json::from_slice(&bytes).context("JSON parse failed");
-                }
-                Err(e) => {
-                    if attempt < MAX_RETRIES && (e.is_timeout() || e.is_connect()) {
-                        tokio::time::sleep(retry_delay(attempt, None)).await;
-                        continue;
-                    }
-                    return Err(e).context("request failed");
-                }
-            }
+        let res = self.http.send(|| self.http.client().get(url)).await?;
+        let status = res.status();
+        let bytes = res.bytes().await.context("reading body failed")?;
+        if !status.is_success() {
+            return Err(UpstreamStatus::new(
+                status.as_u16(),
+                format!(
+                    "TMDB request failed (status {}) for {}: {}",
+                    status,
+                    url,
+                    String::from_utf8_lossy(&bytes)
+                ),
+            )
+            .into());
         }
-        unreachable!("loop returns on success/final error")
+        serde_json::from_slice(&bytes).context("JSON parse failed")
     }
 
     async fn find_imdb(&self, imdb_id: &str, media: &str) -> Result<Option<i32>> {
@@ -552,8 +887,8 @@ impl TmdbClient {
         }
 
         let url = format!(
-            "{TMDB_BASE}/find/{imdb_id}?external_source=imdb_id&language=en-US&api_key={}",
-            self.api_key
+            "{}/find/{imdb_id}?external_source=imdb_id&language=en-US&api_key={}",
+            self.base_url, self.api_key
         );
         let data: FindResponse = self.get_json(&url).await?;
         let id = match media {
@@ -576,8 +911,8 @@ impl TmdbClient {
         }
 
         let url = format!(
-            "{TMDB_BASE}/find/{imdb_id}?external_source=imdb_id&language=en-US&api_key={}",
-            self.api_key
+            "{}/find/{imdb_id}?external_source=imdb_id&language=en-US&api_key={}",
+            self.base_url, self.api_key
         );
         let data: FindResponse = self.get_json(&url).await?;
         let movie_id = data.movie_results.and_then(|mut v| v.pop()).map(|r| r.id);
@@ -586,36 +921,22 @@ impl TmdbClient {
     }
 }
 
-fn retry_delay(attempt: usize, retry_after: Option<&reqwest::header::HeaderValue>) -> Duration {
-    if let Some(v) = retry_after.and_then(|h| h.to_str().ok()) {
-        if let Ok(secs) = v.parse::<u64>() {
-            return Duration::from_secs(secs.min(30));
-        }
-    }
-    let base_ms = 200u64.saturating_mul(2u64.saturating_pow((attempt - 1) as u32));
-    let jitter_ms = jitter_ms();
-    Duration::from_millis((base_ms + jitter_ms).min(5_000))
-}
-
-fn jitter_ms() -> u64 {
-    use std::time::{SystemTime, UNIX_EPOCH};
-    let now = SystemTime::now()
-        .duration_since(UNIX_EPOCH)
-        .unwrap_or_default();
-    (now.subsec_millis() as u64) % 100
-}
-
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Clone, Deserialize)]
 struct Genre {
     name: String,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Clone, Deserialize)]
 struct ProductionCountry {
     iso_3166_1: String,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Clone, Deserialize)]
+struct SpokenLanguage {
+    iso_639_1: String,
+}
+
+#[derive(Debug, Clone, Deserialize)]
 struct MovieDetail {
     id: i32,
     title: String,
@@ -629,9 +950,20 @@ struct MovieDetail {
     poster_path: Option<String>,
     backdrop_path: Option<String>,
     genres: Option<Vec<Genre>>,
+    vote_average: Option<f64>,
+    vote_count: Option<u32>,
+    spoken_languages: Option<Vec<SpokenLanguage>>,
+    belongs_to_collection: Option<Collection>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+struct Collection {
+    #[allow(dead_code)]
+    id: i32,
+    name: String,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Clone, Deserialize)]
 struct ShowDetail {
     id: i32,
     name: String,
@@ -644,14 +976,89 @@ struct ShowDetail {
     genres: Option<Vec<Genre>>,
     episode_run_time: Option<Vec<i32>>,
     created_by: Option<Vec<Creator>>,
+    vote_average: Option<f64>,
+    vote_count: Option<u32>,
+    spoken_languages: Option<Vec<SpokenLanguage>>,
+    networks: Option<Vec<NetworkEntry>>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+struct NetworkEntry {
+    name: String,
+}
+
+/// Movies list their keywords under `keywords`, shows under `results`.
+#[derive(Debug, Clone, Default, Deserialize)]
+struct Keywords {
+    #[serde(default, alias = "results")]
+    keywords: Vec<Keyword>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+struct Keyword {
+    name: String,
+}
+
+fn keyword_names(keywords: Option<Keywords>) -> Vec<String> {
+    keywords
+        .map(|k| k.keywords.into_iter().map(|k| k.name).collect())
+        .unwrap_or_default()
+}
+
+/// `watch/providers`: where the title can be watched, per ISO 3166-1 country code.
+#[derive(Debug, Clone, Default, Deserialize)]
+struct WatchProviders {
+    #[serde(default)]
+    results: HashMap<String, RegionProviders>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+struct RegionProviders {
+    link: Option<String>,
+    /// Subscription services; `rent` and `buy` are ignored.
+    #[serde(default)]
+    flatrate: Vec<WatchProvider>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+struct WatchProvider {
+    provider_name: String,
+}
+
+/// Subscription services and JustWatch link for `region`; empty when TMDB has no data there.
+fn region_providers(
+    providers: Option<WatchProviders>,
+    region: &str,
+) -> (Vec<String>, Option<String>) {
+    match providers.and_then(|mut p| p.results.remove(region)) {
+        Some(region) => (
+            region
+                .flatrate
+                .into_iter()
+                .map(|p| p.provider_name)
+                .collect(),
+            region.link,
+        ),
+        None => (Vec::new(), None),
+    }
+}
+
+/// A two-letter country code, upper-cased.
+fn parse_watch_region(raw: &str) -> Result<String> {
+    let region = raw.trim();
+    if region.len() == 2 && region.chars().all(|c| c.is_ascii_alphabetic()) {
+        Ok(region.to_ascii_uppercase())
+    } else {
+        Err(anyhow!("expected a two-letter country code"))
+    }
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Clone, Deserialize)]
 struct Creator {
     name: String,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Clone, Deserialize)]
 struct SeasonDetail {
     #[allow(dead_code)]
     name: Option<String>,
@@ -659,69 +1066,71 @@ struct SeasonDetail {
     air_date: Option<String>,
     poster_path: Option<String>,
     episodes: Vec<Episode>,
+    vote_average: Option<f64>,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Clone, Deserialize)]
 struct Episode {
     runtime: Option<i32>,
+    vote_count: Option<u32>,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Clone, Deserialize)]
 struct Credits {
     cast: Vec<CastMember>,
     crew: Option<Vec<CrewMember>>,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Clone, Deserialize)]
 struct CastMember {
     name: String,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Clone, Deserialize)]
 struct CrewMember {
     job: Option<String>,
     name: String,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Clone, Deserialize)]
 struct ContentRatings {
     results: Vec<RatingEntry>,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Clone, Deserialize)]
 struct RatingEntry {
     iso_3166_1: String,
     rating: Option<String>,
     certification: Option<String>,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Clone, Deserialize)]
 struct ReleaseDates {
     results: Vec<ReleaseEntry>,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Clone, Deserialize)]
 struct ReleaseEntry {
     iso_3166_1: String,
     release_dates: Vec<ReleaseCert>,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Clone, Deserialize)]
 struct ReleaseCert {
     certification: String,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Clone, Deserialize)]
 struct ExternalIds {
     imdb_id: Option<String>,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Clone, Deserialize)]
 struct Videos {
     results: Vec<Video>,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Clone, Deserialize)]
 struct Video {
     site: String,
     #[serde(rename = "type")]
@@ -729,19 +1138,19 @@ struct Video {
     key: String,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Clone, Deserialize)]
 struct ImageResponse {
     #[serde(default)]
     posters: Vec<Image>,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Clone, Deserialize)]
 struct Image {
     file_path: String,
     iso_639_1: Option<String>,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Clone, Deserialize)]
 struct MovieAppended {
     #[serde(flatten)]
     detail: MovieDetail,
@@ -751,9 +1160,13 @@ struct MovieAppended {
     external_ids: ExternalIds,
     #[serde(default)]
     images: Option<ImageResponse>,
+    #[serde(default)]
+    keywords: Option<Keywords>,
+    #[serde(default, rename = "watch/providers")]
+    watch_providers: Option<WatchProviders>,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Clone, Deserialize)]
 struct ShowAppended {
     #[serde(flatten)]
     show: ShowDetail,
@@ -762,6 +1175,37 @@ struct ShowAppended {
     videos: Videos,
     #[serde(default)]
     images: Option<ImageResponse>,
+    #[serde(default)]
+    keywords: Option<Keywords>,
+    #[serde(default, rename = "watch/providers")]
+    watch_providers: Option<WatchProviders>,
+}
+
+async fn get_cached<T: Clone>(
+    cache: &Mutex<HashMap<i32, CacheEntry<T>>>,
+    id: i32,
+    ttl: Duration,
+    stats: &CacheCounters,
+) -> Option<T> {
+    let mut guard = cache.lock().await;
+    guard.retain(|_, v| v.inserted_at.elapsed() < ttl);
+    let cached = guard.get(&id).map(|e| e.value.clone());
+    stats.record(cached.is_some());
+    cached
+}
+
+async fn put_cached<T>(cache: &Mutex<HashMap<i32, CacheEntry<T>>>, id: i32, value: T) {
+    let mut guard = cache.lock().await;
+    if guard.len() > MAX_CACHE_ENTRIES {
+        guard.clear();
+    }
+    guard.insert(
+        id,
+        CacheEntry {
+            inserted_at: Instant::now(),
+            value,
+        },
+    );
 }
 
 pub fn parse_season_number(input: &str) -> Option<i32> {
@@ -774,20 +1218,104 @@ pub fn parse_season_number(input: &str) -> Option<i32> {
     input.trim().parse().ok()
 }
 
+/// `tmdb:603` or `#603`, or a bare number long enough not to be a title (see
+/// `titles::parse_bare_id`).
 pub fn parse_tmdb_id(input: &str) -> Option<i32> {
-    if input.chars().all(|c| c.is_ascii_digit()) {
-        return input.parse().ok();
+    match TitleId::parse(input) {
+        Some(TitleId::Tmdb(id)) => Some(id),
+        Some(_) => None,
+        None => parse_bare_id(input),
     }
-    None
 }
 
-pub fn parse_imdb_id(input: &str) -> Option<String> {
-    let lower = input.trim().to_lowercase();
-    if lower.starts_with("tt") && lower.len() > 2 && lower[2..].chars().all(|c| c.is_ascii_digit())
+/// One `/search/movie` or `/search/tv` result; TV results name their fields differently.
+#[derive(Debug, Clone, Default, Deserialize)]
+struct SearchResult {
+    id: i32,
+    #[serde(default, alias = "name")]
+    title: Option<String>,
+    #[serde(default, alias = "original_name")]
+    original_title: Option<String>,
+    #[serde(default, alias = "first_air_date")]
+    release_date: Option<String>,
+    #[serde(default)]
+    popularity: Option<f64>,
+    #[serde(default)]
+    vote_count: Option<u32>,
+}
+
+/// How well a result's title or original title matches the query: exact beats prefix beats
+/// contains, and an unrelated title scores 0.
+fn score_search_result(query_key: &str, result: &SearchResult) -> i32 {
+    let mut best = 0;
+    for title in [&result.title, &result.original_title]
+        .into_iter()
+        .flatten()
     {
-        return Some(lower);
+        let key = normalize_title_key(title);
+        let score = if key == query_key {
+            100
+        } else if key.starts_with(query_key) {
+            70
+        } else if key.contains(query_key) {
+            40
+        } else {
+            0
+        };
+        best = best.max(score);
+    }
+    best
+}
+
+/// The result whose title best matches `query`, with its score. Ties go to the more popular
+/// result (then the more voted one), and remaining ties to TMDB's own order, so an obscure
+/// short that happens to rank first no longer wins over the well-known film of the same name.
+fn pick_search_result<'a>(
+    query: &str,
+    results: &'a [SearchResult],
+) -> Option<(&'a SearchResult, i32)> {
+    let query_key = normalize_title_key(query);
+    let mut best: Option<(&SearchResult, i32)> = None;
+    for result in results {
+        let score = score_search_result(&query_key, result);
+        let better = match best {
+            None => true,
+            Some((current, current_score)) => {
+                let popularity = |r: &SearchResult| r.popularity.unwrap_or(0.0);
+                score > current_score
+                    || (score == current_score
+                        && (popularity(result) > popularity(current)
+                            || (popularity(result) == popularity(current)
+                                && result.vote_count > current.vote_count)))
+            }
+        };
+        if better {
+            best = Some((result, score));
+        }
+    }
+    best
+}
+
+/// Splits a trailing year hint off a title: `"Dune (2021)"` is `("Dune", 2021)`. `None` when
+/// the title doesn't end in a parenthesized year (1870-2100) or nothing is left before it.
+pub fn split_year_hint(title: &str) -> Option<(String, i32)> {
+    let rest = title.trim().strip_suffix(')')?;
+    let (query, year) = rest.rsplit_once('(')?;
+    let year = year.trim();
+    if year.len() != 4 || !year.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+    let year: i32 = year.parse().ok().filter(|y| (1870..=2100).contains(y))?;
+    let query = query.trim();
+    (!query.is_empty()).then(|| (query.to_string(), year))
+}
+
+/// `tt0133093`, `imdb:tt0133093` or an IMDb title URL, lower-cased.
+pub fn parse_imdb_id(input: &str) -> Option<String> {
+    match (TitleId::parse(input), parse_media_url(input)) {
+        (Some(TitleId::Imdb(id)), _) | (_, Some(MediaUrl::Imdb(id))) => Some(id),
+        _ => None,
     }
-    None
 }
 
 fn us_cert_from_release_dates(data: &ReleaseDates) -> Option<String> {
@@ -833,6 +1361,11 @@ fn origin_country(
         .unwrap_or_default()
 }
 
+/// TMDB reports `0` for titles nobody has voted on; that isn't a score.
+fn voted(vote_average: Option<f64>) -> Option<f64> {
+    vote_average.filter(|v| *v > 0.0)
+}
+
 fn extract_year(date: &str) -> Option<String> {
     date.split('-').next().map(|s| s.to_string())
 }
@@ -851,6 +1384,18 @@ fn select_trailer(videos: &Videos) -> Option<String> {
         .map(|v| format!("https://www.youtube.com/watch?v={}", v.key))
 }
 
+/// The season's average with its episodes' vote total (seasons carry no count of their own),
+/// or the show's average and count when the season has no votes; never one of each.
+fn season_rating(season: &SeasonDetail, show: &ShowDetail) -> (Option<f64>, Option<u32>) {
+    match voted(season.vote_average) {
+        Some(average) => {
+            let votes: u32 = season.episodes.iter().filter_map(|e| e.vote_count).sum();
+            (Some(average), Some(votes).filter(|c| *c > 0))
+        }
+        None => (voted(show.vote_average), show.vote_count.filter(|c| *c > 0)),
+    }
+}
+
 fn average_episode_runtime(season: &SeasonDetail, show: &ShowDetail) -> Option<f32> {
     let runtimes: Vec<i32> = season.episodes.iter().filter_map(|e| e.runtime).collect();
     if !runtimes.is_empty() {
@@ -863,41 +1408,52 @@ fn average_episode_runtime(season: &SeasonDetail, show: &ShowDetail) -> Option<f
         .map(|r| r as f32)
 }
 
-fn fallback_language_name(code: &str) -> Option<String> {
-    let name = match code {
-        "en" => "English",
-        "fr" => "French",
-        "es" => "Spanish",
-        "de" => "German",
-        "it" => "Italian",
-        "pt" => "Portuguese",
-        "ru" => "Russian",
-        "ja" => "Japanese",
-        "ko" => "Korean",
-        "zh" => "Chinese",
-        "ar" => "Arabic",
-        "hi" => "Hindi",
-        "sv" => "Swedish",
-        "da" => "Danish",
-        "no" => "Norwegian",
-        "fi" => "Finnish",
-        "nl" => "Dutch",
-        "pl" => "Polish",
-        "tr" => "Turkish",
-        "cs" => "Czech",
-        "el" => "Greek",
-        "he" => "Hebrew",
-        "id" => "Indonesian",
-        "ms" => "Malay",
-        "th" => "Thai",
-        "vi" => "Vietnamese",
-        "ro" => "Romanian",
-        "hu" => "Hungarian",
-        "uk" => "Ukrainian",
-        "fa" => "Persian",
-        _ => return Some(code.to_string()),
+/// Up to `limit` distinct posters other than `primary`: the preferred language first, then
+/// language-neutral posters, each group in TMDB's order. Posters in any other language are
+/// left out, so an English title's gallery doesn't fill up with the fr/es/de posters the
+/// appended request fetches for other titles.
+fn select_gallery(
+    images: Option<&ImageResponse>,
+    preferred_lang: Option<&str>,
+    primary: Option<&str>,
+    limit: usize,
+) -> Vec<String> {
+    let Some(images) = images else {
+        return Vec::new();
+    };
+    let rank = |p: &Image| match (p.iso_639_1.as_deref(), preferred_lang) {
+        (Some(lang), Some(preferred)) if lang == preferred => Some(0),
+        (None, _) => Some(1),
+        _ => None,
     };
-    Some(name.to_string())
+    let mut posters: Vec<(u8, &Image)> = images
+        .posters
+        .iter()
+        .filter_map(|p| Some((rank(p)?, p)))
+        .collect();
+    posters.sort_by_key(|(rank, _)| *rank);
+
+    let mut out: Vec<String> = Vec::new();
+    for (_, poster) in posters {
+        let url = format!("{POSTER_BASE}{}", poster.file_path);
+        if Some(url.as_str()) == primary || out.contains(&url) {
+            continue;
+        }
+        out.push(url);
+        if out.len() == limit {
+            break;
+        }
+    }
+    out
+}
+
+/// The original language when it is one whose posters and titles are preferred over TMDB's
+/// English ones.
+fn original_poster_language(original_language: &str) -> Option<&'static str> {
+    ORIGINAL_LANGUAGES
+        .iter()
+        .copied()
+        .find(|lang| *lang == original_language)
 }
 
 fn select_poster(images: Option<&ImageResponse>, preferred_lang: Option<&str>) -> Option<String> {
@@ -913,3 +1469,427 @@ fn select_poster(images: Option<&ImageResponse>, preferred_lang: Option<&str>) -
         .or(fallback)
         .map(|p| format!("{POSTER_BASE}{p}"))
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn result(id: i32, title: &str, popularity: f64, vote_count: u32) -> SearchResult {
+        SearchResult {
+            id,
+            title: Some(title.to_string()),
+            popularity: Some(popularity),
+            vote_count: Some(vote_count),
+            ..SearchResult::default()
+        }
+    }
+
+    #[test]
+    fn search_picks_the_closest_title_then_the_most_popular() {
+        // An obscure short ranked first loses to the popular film with the same title.
+        let results = [
+            result(1, "Alien", 0.6, 3),
+            result(2, "Alien", 45.0, 14_000),
+            result(3, "Aliens", 60.0, 9_000),
+        ];
+        let (best, score) = pick_search_result("alien", &results).unwrap();
+        assert_eq!((best.id, score), (2, 100));
+
+        // Exact beats prefix beats contains, whatever the popularity.
+        let results = [
+            result(1, "The Thing from Another World", 90.0, 900),
+            result(2, "The Thing Called Love", 50.0, 500),
+            result(3, "The Thing", 10.0, 100),
+        ];
+        assert_eq!(pick_search_result("The Thing", &results).unwrap().0.id, 3);
+        let results = [
+            result(1, "Return of the Thing", 90.0, 900),
+            result(2, "The Thing Called Love", 5.0, 50),
+        ];
+        assert_eq!(pick_search_result("the thing", &results).unwrap().0.id, 2);
+
+        // Punctuation and case don't matter, and the original title counts too.
+        let results = [
+            result(1, "Spiderman Returns", 10.0, 10),
+            SearchResult {
+                original_title: Some("Spider-Man: No Way Home".to_string()),
+                ..result(2, "Spider-Man 3", 1.0, 1)
+            },
+        ];
+        let (best, score) = pick_search_result("spider man  no way home", &results).unwrap();
+        assert_eq!((best.id, score), (2, 100));
+
+        // Ties all the way down keep TMDB's order; nothing to pick from is `None`.
+        let results = [result(7, "Dune", 1.0, 1), result(8, "Dune", 1.0, 1)];
+        assert_eq!(pick_search_result("Dune", &results).unwrap().0.id, 7);
+        assert!(pick_search_result("Dune", &[]).is_none());
+    }
+
+    #[test]
+    fn tv_search_results_read_name_and_first_air_date() {
+        let result: SearchResult = serde_json::from_value(serde_json::json!({
+            "id": 1399,
+            "name": "Game of Thrones",
+            "original_name": "Game of Thrones",
+            "first_air_date": "2011-04-17",
+            "popularity": 300.5,
+            "vote_count": 24000,
+        }))
+        .unwrap();
+        assert_eq!(result.title.as_deref(), Some("Game of Thrones"));
+        assert_eq!(result.release_date.as_deref(), Some("2011-04-17"));
+        assert_eq!(result.vote_count, Some(24000));
+    }
+
+    #[test]
+    fn tmdb_ids_are_marked_or_long_enough() {
+        assert_eq!(parse_tmdb_id("#12345"), Some(12345));
+        assert_eq!(parse_tmdb_id("#12345 "), Some(12345));
+        assert_eq!(parse_tmdb_id("#603"), Some(603));
+        assert_eq!(parse_tmdb_id("12345"), Some(12345));
+        assert_eq!(parse_tmdb_id("tmdb:603"), Some(603));
+        assert_eq!(parse_tmdb_id("#abc"), None);
+        assert_eq!(parse_tmdb_id(""), None);
+        assert_eq!(parse_tmdb_id("1917"), None);
+        assert_eq!(parse_tmdb_id("anilist:21"), None);
+    }
+
+    #[test]
+    fn year_hints_are_split_off_the_title() {
+        assert_eq!(
+            split_year_hint("Dune (2021)"),
+            Some(("Dune".to_string(), 2021))
+        );
+        assert_eq!(
+            split_year_hint(" The Office ( 2001 ) "),
+            Some(("The Office".to_string(), 2001))
+        );
+        assert_eq!(
+            split_year_hint("Blade Runner (1982) (2007)"),
+            Some(("Blade Runner (1982)".to_string(), 2007))
+        );
+        for title in [
+            "Dune",
+            "(2021)",
+            "Dune (21)",
+            "Dune (20211)",
+            "Dune (1066)",
+            "1917",
+            "Dune (Part Two)",
+        ] {
+            assert_eq!(split_year_hint(title), None, "{title}");
+        }
+    }
+
+    #[tokio::test]
+    async fn year_hints_narrow_the_search_and_fall_back_without_a_match() {
+        use wiremock::matchers::{method, path, query_param, query_param_is_missing};
+        use wiremock::{Mock, MockServer, ResponseTemplate};
+
+        let server = MockServer::start().await;
+        let results = |id: i32| {
+            ResponseTemplate::new(200)
+                .set_body_json(serde_json::json!({ "results": [{ "id": id }] }))
+        };
+        Mock::given(method("GET"))
+            .and(path("/search/movie"))
+            .and(query_param("year", "2021"))
+            .respond_with(results(438631))
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/search/movie"))
+            .and(query_param("year", "1999"))
+            .respond_with(
+                ResponseTemplate::new(200).set_body_json(serde_json::json!({ "results": [] })),
+            )
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/search/movie"))
+            .and(query_param_is_missing("year"))
+            .respond_with(results(841))
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/search/tv"))
+            .and(query_param("year", "2001"))
+            .respond_with(results(2996))
+            .mount(&server)
+            .await;
+
+        let client = TmdbClient::new("key".to_string())
+            .unwrap()
+            .with_base_url(server.uri());
+        let movie = |year| client.resolve_movie_id_with_year("Dune", year);
+        assert_eq!(movie(Some(2021)).await.unwrap(), 438631);
+        assert_eq!(movie(None).await.unwrap(), 841);
+        assert_eq!(movie(Some(1999)).await.unwrap(), 841);
+        let show = client.resolve_tv_id_with_year("The Office", Some(2001));
+        assert_eq!(show.await.unwrap(), 2996);
+    }
+
+    #[tokio::test]
+    async fn only_explicit_or_long_ids_skip_the_search() {
+        use wiremock::matchers::{method, path, query_param};
+        use wiremock::{Mock, MockServer, ResponseTemplate};
+
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/search/movie"))
+            .and(query_param("query", "1917"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+                "results": [{ "id": 530915, "title": "1917", "popularity": 20.0 }]
+            })))
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/find/tt0133093"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+                "movie_results": [{ "id": 603 }],
+                "tv_results": []
+            })))
+            .mount(&server)
+            .await;
+
+        let client = TmdbClient::new("key".to_string())
+            .unwrap()
+            .with_base_url(server.uri());
+        assert_eq!(client.resolve_movie_id("tmdb:603").await.unwrap(), 603);
+        assert_eq!(client.resolve_tv_id("438631").await.unwrap(), 438631);
+        assert!(server.received_requests().await.unwrap().is_empty());
+
+        assert_eq!(client.resolve_movie_id("1917").await.unwrap(), 530915);
+        let matrix = client.resolve_movie_id("imdb:tt0133093").await.unwrap();
+        assert_eq!(matrix, 603);
+    }
+
+    #[test]
+    fn search_cache_evicts_the_least_recently_used_entry() {
+        let ttl = Duration::from_secs(60);
+        let mut cache = SearchCache::new(2);
+        cache.insert(search_cache_key("movie", "Dune"), 1);
+        cache.insert(search_cache_key("tv", "Dune"), 2);
+        // Reading the movie entry makes the TV entry the oldest.
+        assert_eq!(
+            cache.get(&search_cache_key("movie", " dune "), ttl),
+            Some(1)
+        );
+        cache.insert(search_cache_key("movie", "Arrival"), 3);
+        assert_eq!(cache.get("tv:dune", ttl), None);
+        assert_eq!(cache.get("movie:dune", ttl), Some(1));
+        assert_eq!(cache.get("movie:arrival", ttl), Some(3));
+        assert_eq!(cache.get("movie:arrival", Duration::ZERO), None);
+    }
+
+    fn image(path: &str, lang: Option<&str>) -> Image {
+        Image {
+            file_path: path.to_string(),
+            iso_639_1: lang.map(str::to_string),
+        }
+    }
+
+    fn urls(paths: &[&str]) -> Vec<String> {
+        paths.iter().map(|p| format!("{POSTER_BASE}{p}")).collect()
+    }
+
+    #[test]
+    fn gallery_prefers_language_then_neutral_and_skips_the_rest() {
+        let images = ImageResponse {
+            posters: vec![
+                image("/en.jpg", Some("en")),
+                image("/null1.jpg", None),
+                image("/fr1.jpg", Some("fr")),
+                image("/fr2.jpg", Some("fr")),
+                image("/null2.jpg", None),
+            ],
+        };
+        let primary = format!("{POSTER_BASE}/fr1.jpg");
+        assert_eq!(
+            select_gallery(Some(&images), Some("fr"), Some(&primary), 4),
+            urls(&["/fr2.jpg", "/null1.jpg", "/null2.jpg"])
+        );
+        assert_eq!(
+            select_gallery(Some(&images), None, None, 2),
+            urls(&["/null1.jpg", "/null2.jpg"])
+        );
+    }
+
+    #[test]
+    fn english_titles_get_only_language_neutral_gallery_posters() {
+        // What the appended request returns for an English title: no preferred language.
+        let images = ImageResponse {
+            posters: vec![
+                image("/fr.jpg", Some("fr")),
+                image("/null1.jpg", None),
+                image("/es.jpg", Some("es")),
+                image("/de.jpg", Some("de")),
+                image("/null2.jpg", None),
+            ],
+        };
+        assert_eq!(
+            select_gallery(Some(&images), None, None, 4),
+            urls(&["/null1.jpg", "/null2.jpg"])
+        );
+    }
+
+    #[test]
+    fn gallery_drops_duplicate_paths() {
+        let images = ImageResponse {
+            posters: vec![
+                image("/a.jpg", None),
+                image("/a.jpg", None),
+                image("/b.jpg", Some("es")),
+            ],
+        };
+        assert_eq!(
+            select_gallery(Some(&images), Some("es"), None, 4),
+            urls(&["/b.jpg", "/a.jpg"])
+        );
+        assert!(select_gallery(None, Some("es"), None, 4).is_empty());
+    }
+
+    #[tokio::test]
+    async fn country_codes_become_names_from_the_shared_table() {
+        // Nothing listens there: only the unknown code needs TMDB's list, and it stays a code.
+        let client = TmdbClient::new("key".to_string())
+            .unwrap()
+            .with_base_url("http://127.0.0.1:9");
+        let codes = ["US", "GB", "KR", "ZZ"].map(String::from).to_vec();
+        assert_eq!(
+            client.country_names(codes).await,
+            ["United States", "United Kingdom", "South Korea", "ZZ"]
+        );
+    }
+
+    #[tokio::test]
+    async fn german_films_get_the_german_poster_and_original_title() {
+        use wiremock::matchers::{method, path, query_param};
+        use wiremock::{Mock, MockServer, ResponseTemplate};
+
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/movie/42"))
+            .and(query_param("include_image_language", "fr,es,de,null"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+                "id": 42,
+                "title": "The Lives of Others",
+                "original_title": "Das Leben der Anderen",
+                "overview": "East Berlin, 1984.",
+                "release_date": "2006-03-23",
+                "runtime": 137.0,
+                "original_language": "de",
+                "origin_country": ["DE"],
+                "poster_path": "/en.jpg",
+                "vote_count": 0,
+                "belongs_to_collection": {"id": 7, "name": "Stasi Collection"},
+                "credits": {"cast": [], "crew": []},
+                "release_dates": {"results": []},
+                "videos": {"results": []},
+                "external_ids": {"imdb_id": "tt0405094"},
+                "keywords": {"keywords": [{"id": 1, "name": "stasi"}, {"id": 2, "name": "surveillance"}]},
+                "watch/providers": {"results": {"US": {
+                    "link": "https://www.themoviedb.org/movie/582/watch?locale=US",
+                    "flatrate": [{"provider_id": 386, "provider_name": "Peacock"}],
+                }}},
+                "images": {"posters": [
+                    {"file_path": "/en.jpg", "iso_639_1": "en"},
+                    {"file_path": "/de.jpg", "iso_639_1": "de"},
+                ]},
+            })))
+            .mount(&server)
+            .await;
+
+        let client = TmdbClient::new("key".to_string())
+            .unwrap()
+            .with_base_url(server.uri());
+        let movie = client.fetch_movie(42).await.unwrap();
+        assert_eq!(
+            movie.poster.as_deref(),
+            Some(&*format!("{POSTER_BASE}/de.jpg"))
+        );
+        assert_eq!(movie.name, "Das Leben der Anderen");
+        assert_eq!(movie.eng_name.as_deref(), Some("The Lives of Others"));
+        assert_eq!(movie.language.as_deref(), Some("German"));
+        assert_eq!(movie.collection.as_deref(), Some("Stasi Collection"));
+        assert_eq!(movie.keywords, ["stasi", "surveillance"]);
+        assert_eq!(movie.watch_providers, ["Peacock"]);
+        assert_eq!(movie.vote_count, None);
+        assert!(movie.watch_link.is_some());
+    }
+
+    #[test]
+    fn season_ratings_take_the_average_and_the_count_from_the_same_source() {
+        let show: ShowDetail = serde_json::from_value(serde_json::json!({
+            "id": 1, "name": "Show", "original_name": "Show", "overview": "",
+            "original_language": "en", "origin_country": [],
+            "vote_average": 8.1, "vote_count": 5000,
+        }))
+        .unwrap();
+        let season = |average: f64| -> SeasonDetail {
+            serde_json::from_value(serde_json::json!({
+                "overview": "",
+                "vote_average": average,
+                "episodes": [{"vote_count": 12}, {"vote_count": 30}, {}],
+            }))
+            .unwrap()
+        };
+        assert_eq!(season_rating(&season(7.4), &show), (Some(7.4), Some(42)));
+        assert_eq!(season_rating(&season(0.0), &show), (Some(8.1), Some(5000)));
+    }
+
+    #[test]
+    fn keywords_read_the_movie_and_the_tv_shapes() {
+        let movie: Keywords =
+            serde_json::from_value(serde_json::json!({"keywords": [{"id": 1, "name": "heist"}]}))
+                .unwrap();
+        let tv: Keywords =
+            serde_json::from_value(serde_json::json!({"results": [{"id": 2, "name": "sitcom"}]}))
+                .unwrap();
+        assert_eq!(keyword_names(Some(movie)), ["heist"]);
+        assert_eq!(keyword_names(Some(tv)), ["sitcom"]);
+        assert!(keyword_names(None).is_empty());
+    }
+
+    #[test]
+    fn watch_providers_keep_the_region_subscriptions() {
+        let providers: WatchProviders = serde_json::from_value(serde_json::json!({
+            "id": 550,
+            "results": {
+                "US": {
+                    "link": "https://www.themoviedb.org/movie/550-fight-club/watch?locale=US",
+                    "flatrate": [
+                        {"logo_path": "/n.jpg", "provider_id": 8, "provider_name": "Netflix", "display_priority": 1},
+                        {"logo_path": "/h.jpg", "provider_id": 15, "provider_name": "Hulu", "display_priority": 4},
+                    ],
+                    "rent": [{"provider_id": 2, "provider_name": "Apple TV", "display_priority": 2}],
+                },
+                "FR": {
+                    "link": "https://www.themoviedb.org/movie/550-fight-club/watch?locale=FR",
+                    "buy": [{"provider_id": 2, "provider_name": "Apple TV", "display_priority": 2}],
+                },
+            },
+        }))
+        .unwrap();
+
+        let (names, link) = region_providers(Some(providers.clone()), "US");
+        assert_eq!(names, ["Netflix", "Hulu"]);
+        assert_eq!(
+            link.as_deref(),
+            Some("https://www.themoviedb.org/movie/550-fight-club/watch?locale=US")
+        );
+        let (names, link) = region_providers(Some(providers.clone()), "FR");
+        assert!(names.is_empty());
+        assert!(link.is_some());
+        assert_eq!(region_providers(Some(providers), "DE"), (Vec::new(), None));
+        assert_eq!(region_providers(None, "US"), (Vec::new(), None));
+    }
+
+    #[test]
+    fn watch_regions_are_two_letter_country_codes() {
+        assert_eq!(parse_watch_region(" gb ").unwrap(), "GB");
+        for raw in ["USA", "U", "1A"] {
+            assert!(parse_watch_region(raw).is_err(), "{raw}");
+        }
+    }
+}
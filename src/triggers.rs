@@ -0,0 +1,250 @@
+//! Title suffixes that arm a page for enrichment (`;` TMDB, `=` AniList anime, `~` AniList manga).
+use crate::anilist::AniListMediaType;
+use anyhow::Result;
+use std::env;
+
+pub const DEFAULT_TMDB_SUFFIX: &str = ";";
+pub const DEFAULT_ANILIST_SUFFIX: &str = "=";
+pub const DEFAULT_MANGA_SUFFIX: &str = "~";
+/// `Type` select values that send a `;`-armed page to AniList anime.
+pub const DEFAULT_ANIME_TYPE_VALUES: &str = "anime";
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Trigger {
+    Tmdb,
+    AniList(AniListMediaType),
+}
+
+#[derive(Clone, Debug)]
+pub struct TriggerConfig {
+    pub tmdb: String,
+    pub anilist_anime: String,
+    /// `None` disables the manga trigger.
+    pub anilist_manga: Option<String>,
+    /// Lowercased `Type` values routed to AniList anime even with the TMDB trigger.
+    pub anime_types: Vec<String>,
+}
+
+impl Default for TriggerConfig {
+    fn default() -> Self {
+        Self {
+            tmdb: DEFAULT_TMDB_SUFFIX.to_string(),
+            anilist_anime: DEFAULT_ANILIST_SUFFIX.to_string(),
+            anilist_manga: Some(DEFAULT_MANGA_SUFFIX.to_string()),
+            anime_types: parse_type_values(DEFAULT_ANIME_TYPE_VALUES),
+        }
+    }
+}
+
+impl TriggerConfig {
+    /// Builds a config, rejecting empty TMDB/anime suffixes and suffixes that overlap (one
+    /// ends with another), since those would make the trigger ambiguous. An empty manga
+    /// suffix disables the manga trigger.
+    pub fn new(tmdb: &str, anilist_anime: &str, anilist_manga: &str) -> Result<Self> {
+        let (tmdb, anime, manga) = (tmdb.trim(), anilist_anime.trim(), anilist_manga.trim());
+        for (name, value) in [("TMDB trigger", tmdb), ("AniList trigger", anime)] {
+            if value.is_empty() {
+                anyhow::bail!("{} must not be empty", name);
+            }
+        }
+        let mut suffixes = vec![("TMDB trigger", tmdb), ("AniList trigger", anime)];
+        if !manga.is_empty() {
+            suffixes.push(("manga trigger", manga));
+        }
+        for (i, (a_name, a)) in suffixes.iter().enumerate() {
+            for (b_name, b) in suffixes.iter().skip(i + 1) {
+                if a.ends_with(b) || b.ends_with(a) {
+                    anyhow::bail!("{} ({:?}) overlaps with {} ({:?})", a_name, a, b_name, b);
+                }
+            }
+        }
+        Ok(Self {
+            tmdb: tmdb.to_string(),
+            anilist_anime: anime.to_string(),
+            anilist_manga: (!manga.is_empty()).then(|| manga.to_string()),
+            anime_types: parse_type_values(DEFAULT_ANIME_TYPE_VALUES),
+        })
+    }
+
+    /// Replaces the anime `Type` values with a comma-separated list (case-insensitive); an
+    /// empty list turns Type-based routing off.
+    pub fn with_anime_types(mut self, values: &str) -> Self {
+        self.anime_types = parse_type_values(values);
+        self
+    }
+
+    /// Reads `CINELINK_TMDB_TRIGGER`, `CINELINK_ANILIST_TRIGGER` and `CINELINK_MANGA_TRIGGER`
+    /// (the older `TRIGGER_*_SUFFIX` names are still accepted) and
+    /// `CINELINK_ANIME_TYPE_VALUES`, falling back to the defaults for unset variables.
+    pub fn from_env() -> Result<Self> {
+        let read = |name: &str, legacy: &str, default: &str| {
+            env::var(name)
+                .or_else(|_| env::var(legacy))
+                .unwrap_or_else(|_| default.to_string())
+        };
+        let config = Self::new(
+            &read(
+                "CINELINK_TMDB_TRIGGER",
+                "TRIGGER_TMDB_SUFFIX",
+                DEFAULT_TMDB_SUFFIX,
+            ),
+            &read(
+                "CINELINK_ANILIST_TRIGGER",
+                "TRIGGER_ANILIST_SUFFIX",
+                DEFAULT_ANILIST_SUFFIX,
+            ),
+            &read(
+                "CINELINK_MANGA_TRIGGER",
+                "TRIGGER_MANGA_SUFFIX",
+                DEFAULT_MANGA_SUFFIX,
+            ),
+        )?;
+        let anime_types = env::var("CINELINK_ANIME_TYPE_VALUES")
+            .unwrap_or_else(|_| DEFAULT_ANIME_TYPE_VALUES.to_string());
+        Ok(config.with_anime_types(&anime_types))
+    }
+
+    /// One-line summary for the startup log.
+    pub fn describe(&self) -> String {
+        format!(
+            "TMDB {:?}, AniList anime {:?} (and Type {:?}), AniList manga {}",
+            self.tmdb,
+            self.anilist_anime,
+            self.anime_types,
+            self.anilist_manga
+                .as_deref()
+                .map(|s| format!("{:?}", s))
+                .unwrap_or_else(|| "disabled".to_string())
+        )
+    }
+
+    /// Returns the trigger and the title with the full suffix stripped and trimmed.
+    pub fn match_title(&self, raw_title: &str) -> Option<(Trigger, String)> {
+        let title = raw_title.trim_end();
+        let candidates = [
+            (Trigger::Tmdb, Some(self.tmdb.as_str())),
+            (
+                Trigger::AniList(AniListMediaType::Anime),
+                Some(self.anilist_anime.as_str()),
+            ),
+            (
+                Trigger::AniList(AniListMediaType::Manga),
+                self.anilist_manga.as_deref(),
+            ),
+        ];
+        candidates.into_iter().find_map(|(trigger, suffix)| {
+            title
+                .strip_suffix(suffix?)
+                .map(|rest| (trigger, rest.trim().to_string()))
+        })
+    }
+
+    /// Like `match_title`, also stripping a second copy of the suffix (`;;`, `==`): typing
+    /// the trigger twice forces a page that already has an `ID` to be enriched again.
+    pub fn match_title_forced(&self, raw_title: &str) -> Option<(Trigger, String, bool)> {
+        let (trigger, rest) = self.match_title(raw_title)?;
+        match self.suffix(trigger).and_then(|s| rest.strip_suffix(s)) {
+            Some(query) => Some((trigger, query.trim().to_string(), true)),
+            None => Some((trigger, rest, false)),
+        }
+    }
+
+    fn suffix(&self, trigger: Trigger) -> Option<&str> {
+        match trigger {
+            Trigger::Tmdb => Some(&self.tmdb),
+            Trigger::AniList(AniListMediaType::Anime) => Some(&self.anilist_anime),
+            Trigger::AniList(AniListMediaType::Manga) => self.anilist_manga.as_deref(),
+        }
+    }
+
+    pub fn is_tmdb_armed(&self, raw_title: &str) -> bool {
+        raw_title.trim_end().ends_with(&self.tmdb)
+    }
+
+    pub fn is_anime_type(&self, type_value: &str) -> bool {
+        let type_value = type_value.trim().to_lowercase();
+        self.anime_types.contains(&type_value)
+    }
+}
+
+fn parse_type_values(raw: &str) -> Vec<String> {
+    raw.split(',')
+        .map(|v| v.trim().to_lowercase())
+        .filter(|v| !v.is_empty())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn strips_full_suffix_and_trims() {
+        let config = TriggerConfig::new(";;", "=", "~").unwrap();
+        assert_eq!(
+            config.match_title("Movie Title ;; "),
+            Some((Trigger::Tmdb, "Movie Title".to_string()))
+        );
+        assert_eq!(
+            config.match_title("Query ="),
+            Some((
+                Trigger::AniList(AniListMediaType::Anime),
+                "Query".to_string()
+            ))
+        );
+        assert_eq!(config.match_title("Movie Title ;"), None);
+    }
+
+    #[test]
+    fn doubled_suffix_forces_and_is_stripped() {
+        let config = TriggerConfig::default();
+        assert_eq!(
+            config.match_title_forced("Movie Title ;;"),
+            Some((Trigger::Tmdb, "Movie Title".to_string(), true))
+        );
+        assert_eq!(
+            config.match_title_forced("Frieren = ="),
+            Some((
+                Trigger::AniList(AniListMediaType::Anime),
+                "Frieren".to_string(),
+                true
+            ))
+        );
+        assert_eq!(
+            config.match_title_forced("Movie Title ;"),
+            Some((Trigger::Tmdb, "Movie Title".to_string(), false))
+        );
+    }
+
+    #[test]
+    fn defaults_match_legacy_suffixes() {
+        let config = TriggerConfig::default();
+        assert_eq!(
+            config.match_title("Manga~").map(|(t, _)| t),
+            Some(Trigger::AniList(AniListMediaType::Manga))
+        );
+        assert!(config.is_tmdb_armed("Title ;"));
+        assert!(!config.is_tmdb_armed("Title"));
+    }
+
+    #[test]
+    fn empty_manga_suffix_disables_manga_trigger() {
+        let config = TriggerConfig::new(";", "=", "").unwrap();
+        assert_eq!(config.anilist_manga, None);
+        assert_eq!(config.match_title("Berserk~"), None);
+        assert!(config.describe().ends_with("AniList manga disabled"));
+    }
+
+    #[test]
+    fn anime_type_values_are_case_insensitive_and_configurable() {
+        let config = TriggerConfig::default();
+        assert!(config.is_anime_type(" Anime "));
+        assert!(!config.is_anime_type("TV"));
+        let config = config.with_anime_types("Anime, Donghua ,");
+        assert_eq!(config.anime_types, vec!["anime", "donghua"]);
+        assert!(config.is_anime_type("donghua"));
+        assert!(!TriggerConfig::default()
+            .with_anime_types("")
+            .is_anime_type("Anime"));
+    }
+}
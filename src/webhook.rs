@@ -0,0 +1,260 @@
+//! Typed Notion webhook envelope, and the decision whether an event needs a page job.
+//!
+//! Every field is optional: Notion adds keys over time and test payloads omit most of them,
+//! so a missing field means "not this kind of event" rather than a rejected request.
+use serde::Deserialize;
+
+/// Raw id Notion sometimes reports for the "Season" property instead of its name.
+const SEASON_PROPERTY_ID: &str = "Siv%5D";
+
+#[derive(Clone, Debug, Default, Deserialize)]
+pub struct WebhookEvent {
+    #[serde(default)]
+    pub id: Option<String>,
+    #[serde(default)]
+    pub timestamp: Option<String>,
+    #[serde(rename = "type", default)]
+    pub event_type: Option<String>,
+    /// One entity, or several when Notion batches pages into one event.
+    #[serde(default)]
+    pub entity: Option<Entities>,
+    #[serde(default)]
+    pub data: Option<EventData>,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+#[serde(untagged)]
+pub enum Entities {
+    One(Entity),
+    Many(Vec<Entity>),
+}
+
+impl Entities {
+    fn as_slice(&self) -> &[Entity] {
+        match self {
+            Entities::One(entity) => std::slice::from_ref(entity),
+            Entities::Many(entities) => entities,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Default, Deserialize)]
+pub struct Entity {
+    #[serde(default)]
+    pub id: Option<String>,
+    #[serde(rename = "type", default)]
+    pub entity_type: Option<String>,
+}
+
+#[derive(Clone, Debug, Default, Deserialize)]
+pub struct EventData {
+    /// Property ids or names, percent-encoded.
+    #[serde(default)]
+    pub updated_properties: Vec<String>,
+    #[serde(default)]
+    pub parent: Option<Entity>,
+    /// Further pages the event applies to, as ids or entities.
+    #[serde(default)]
+    pub pages: Vec<PageRef>,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+#[serde(untagged)]
+pub enum PageRef {
+    Id(String),
+    Entity(Entity),
+}
+
+impl PageRef {
+    fn id(&self) -> Option<&str> {
+        match self {
+            PageRef::Id(id) => Some(id),
+            PageRef::Entity(entity) => entity.id.as_deref(),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum EventKind {
+    PageCreated,
+    PropertiesUpdated,
+    /// The database (or data source) schema changed; the cached schema must be reloaded.
+    SchemaUpdated,
+    Other,
+}
+
+/// Why an event needs a page job.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PageTrigger {
+    /// New pages arrive already titled (e.g. "Dune ;"), so they only ever emit `page.created`.
+    Created,
+    /// The title or season changed.
+    TitleOrSeason,
+}
+
+impl WebhookEvent {
+    pub fn kind(&self) -> EventKind {
+        match self.event_type.as_deref() {
+            Some("page.created") => EventKind::PageCreated,
+            Some("page.properties_updated") => EventKind::PropertiesUpdated,
+            Some("database.schema_updated" | "data_source.schema_updated") => {
+                EventKind::SchemaUpdated
+            }
+            _ => EventKind::Other,
+        }
+    }
+
+    /// Every page the event names: its entities, then `data.pages`, without repeats.
+    pub fn page_ids(&self) -> Vec<&str> {
+        let entities = self
+            .entity
+            .as_ref()
+            .map(Entities::as_slice)
+            .unwrap_or_default();
+        let pages = self
+            .data
+            .as_ref()
+            .map(|d| d.pages.as_slice())
+            .unwrap_or_default();
+        let mut ids: Vec<&str> = Vec::new();
+        let candidates = entities
+            .iter()
+            .filter_map(|e| e.id.as_deref())
+            .chain(pages.iter().filter_map(PageRef::id));
+        for id in candidates {
+            if !id.is_empty() && !ids.contains(&id) {
+                ids.push(id);
+            }
+        }
+        ids
+    }
+
+    fn updated_properties(&self) -> &[String] {
+        self.data
+            .as_ref()
+            .map(|d| d.updated_properties.as_slice())
+            .unwrap_or_default()
+    }
+}
+
+/// Whether `event` should (re-)enrich its page: a created page, or an update that touches
+/// the title or season, by name or id, percent-encoded or not.
+pub fn should_process(event: &WebhookEvent) -> Option<PageTrigger> {
+    match event.kind() {
+        EventKind::PageCreated => Some(PageTrigger::Created),
+        EventKind::PropertiesUpdated => event
+            .updated_properties()
+            .iter()
+            .any(|raw| is_title_or_season(raw))
+            .then_some(PageTrigger::TitleOrSeason),
+        EventKind::SchemaUpdated | EventKind::Other => None,
+    }
+}
+
+fn is_title_or_season(raw: &str) -> bool {
+    if raw == SEASON_PROPERTY_ID {
+        return true;
+    }
+    let decoded = urlencoding::decode(raw)
+        .map(|d| d.into_owned())
+        .unwrap_or_else(|_| raw.to_string());
+    let lower = decoded.to_lowercase();
+    lower == "title" || lower == "season"
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn event(payload: serde_json::Value) -> WebhookEvent {
+        serde_json::from_value(payload).unwrap()
+    }
+
+    fn updated(properties: &[&str]) -> WebhookEvent {
+        event(json!({
+            "id": "evt-1",
+            "timestamp": "2025-01-01T00:00:00.000Z",
+            "type": "page.properties_updated",
+            "entity": { "id": "page-1", "type": "page" },
+            "data": {
+                "updated_properties": properties,
+                "parent": { "id": "db-1", "type": "database" },
+            },
+        }))
+    }
+
+    #[test]
+    fn parses_the_envelope() {
+        let e = updated(&["title"]);
+        assert_eq!(e.id.as_deref(), Some("evt-1"));
+        assert_eq!(e.page_ids(), vec!["page-1"]);
+        assert_eq!(e.kind(), EventKind::PropertiesUpdated);
+        let parent = e.data.unwrap().parent.unwrap();
+        assert_eq!(parent.entity_type.as_deref(), Some("database"));
+    }
+
+    #[test]
+    fn batched_events_name_every_page_once() {
+        let e = event(json!({
+            "type": "page.properties_updated",
+            "entity": [{ "id": "page-1", "type": "page" }, { "id": "page-2" }, {}],
+            "data": {
+                "updated_properties": ["title"],
+                "pages": ["page-2", { "id": "page-3", "type": "page" }, ""],
+            },
+        }));
+        assert_eq!(e.page_ids(), vec!["page-1", "page-2", "page-3"]);
+        assert_eq!(should_process(&e), Some(PageTrigger::TitleOrSeason));
+
+        let e = event(json!({ "type": "page.created", "entity": [] }));
+        assert!(e.page_ids().is_empty());
+    }
+
+    #[test]
+    fn title_and_season_updates_trigger_by_name_or_id() {
+        for properties in [
+            &["title"][..],
+            &["Season"],
+            &["%53eason"],
+            &["abc", "TITLE"],
+            &["Siv%5D"],
+        ] {
+            assert_eq!(
+                should_process(&updated(properties)),
+                Some(PageTrigger::TitleOrSeason),
+                "{properties:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn other_updates_do_not_trigger() {
+        assert_eq!(should_process(&updated(&["abc", "Genre"])), None);
+        assert_eq!(should_process(&updated(&["%ZZ"])), None);
+        assert_eq!(should_process(&updated(&[])), None);
+    }
+
+    #[test]
+    fn created_pages_trigger_whatever_changed() {
+        let e = event(json!({ "type": "page.created", "entity": { "id": "page-1" } }));
+        assert_eq!(should_process(&e), Some(PageTrigger::Created));
+    }
+
+    #[test]
+    fn missing_fields_mean_no_trigger() {
+        let e = event(json!({}));
+        assert_eq!(e.kind(), EventKind::Other);
+        assert!(e.page_ids().is_empty());
+        assert_eq!(should_process(&e), None);
+
+        let e = event(json!({ "type": "page.properties_updated" }));
+        assert_eq!(should_process(&e), None);
+        let e = event(json!({ "type": "page.properties_updated", "data": {} }));
+        assert_eq!(should_process(&e), None);
+
+        let e = event(json!({ "type": "data_source.schema_updated", "extra": 1 }));
+        assert_eq!(e.kind(), EventKind::SchemaUpdated);
+        assert_eq!(should_process(&e), None);
+    }
+}
@@ -0,0 +1,23 @@
+// Runs `examples/local_demo.rs` so the demo can't silently rot.
+#[allow(dead_code)]
+#[path = "../examples/local_demo.rs"]
+mod local_demo;
+
+#[tokio::test]
+async fn local_demo_enriches_the_mock_notion_page() {
+    let update = local_demo::run_demo().await.expect("demo runs");
+    let props = &update["properties"];
+    assert_eq!(
+        props["Name"]["title"][0]["text"]["content"],
+        "Dune: Part Two"
+    );
+    assert_eq!(props["ID"]["number"], 693134.0);
+    assert_eq!(
+        props["Director"]["rich_text"][0]["text"]["content"],
+        "Denis Villeneuve"
+    );
+    assert_eq!(
+        update["cover"]["external"]["url"],
+        "https://image.tmdb.org/t/p/original/backdrop.jpg"
+    );
+}
@@ -1,34 +1,86 @@
 use axum::body::Body;
+use axum::extract::ConnectInfo;
 use axum::http::{Request, StatusCode};
 use axum::Router;
 use chrono::Utc;
 use cinelink::anilist::{AniListApi, AniListMapped};
-use cinelink::app::{build_router, AppState};
-use cinelink::notion::{NotionApi, PropertySchema, PropertyType, NOTION_VERSION};
-use cinelink::tmdb::{MediaData, TmdbApi};
+use cinelink::app::{
+    build_router, drain_jobs, process_page_backfill_movie, process_page_backfill_tv,
+    spawn_job_workers, spawn_retry_worker, AppState, INSECURE_DISABLED_SECRET,
+    SIGNATURE_SKIPPED_HEADER, WAIT_HEADER,
+};
+use cinelink::backfill::{
+    run_backfill, run_backfill_logged, BackfillKind, BackfillOptions, BackfillProgress, ResultLog,
+};
+use cinelink::budget::{self, Provider, DEFAULT_REQUEST_BUDGET};
+use cinelink::config::{AppConfig, OverwriteMode};
+use cinelink::dedupe_store::DedupeStore;
+use cinelink::duplicates::DuplicateIndex;
+use cinelink::errors::UpstreamStatus;
+use cinelink::failures::FailureLog;
+use cinelink::metrics::Metrics;
+use cinelink::negative_cache::NegativeCache;
+use cinelink::notify::ErrorNotifier;
+use cinelink::notion::{
+    all_pages, DatabaseQueryResponse, NotionApi, PropertySchema, PropertyType, SharedSchema,
+    NOTION_VERSION,
+};
+use cinelink::retry::RetryQueue;
+use cinelink::tmdb::{self, MediaData, TmdbApi};
+use cinelink::triggers::TriggerConfig;
+use futures_util::TryStreamExt;
 use hmac::{Hmac, Mac};
 use serde_json::{json, Map, Value};
 use sha2::Sha256;
 use std::collections::HashMap;
+use std::net::SocketAddr;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
 use std::sync::{Arc, Mutex};
 use std::time::Duration;
 use tower::util::ServiceExt;
 
 const WEBHOOK_SECRET: &str = "test-secret";
+const ADMIN_KEY: &str = "test-admin-key";
+
+type RecordedUpdate = (String, Map<String, Value>, Option<Value>, Option<Value>);
 
 struct FakeNotion {
-    schema: PropertySchema,
+    schema: Mutex<PropertySchema>,
+    schema_error: Option<&'static str>,
+    schema_fetches: std::sync::atomic::AtomicUsize,
+    /// `fetch_page` answers 503 this many times before serving pages.
+    fetch_failures: std::sync::atomic::AtomicUsize,
+    /// Added latency on every `fetch_page`.
+    fetch_delay: Duration,
     pages: Mutex<HashMap<String, Value>>,
-    updates: Mutex<Vec<(String, Map<String, Value>, Option<Value>, Option<Value>)>>,
+    updates: Mutex<Vec<RecordedUpdate>>,
+    comments: Mutex<Vec<(String, String)>>,
+    /// Page body blocks by page id.
+    blocks: Mutex<HashMap<String, Vec<Value>>>,
 }
 
 #[async_trait::async_trait]
 impl NotionApi for FakeNotion {
     async fn fetch_property_schema(&self) -> anyhow::Result<PropertySchema> {
-        Ok(self.schema.clone())
+        self.schema_fetches
+            .fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+        if let Some(err) = self.schema_error {
+            anyhow::bail!(err);
+        }
+        Ok(self.schema.lock().unwrap().clone())
     }
 
     async fn fetch_page(&self, page_id: &str) -> anyhow::Result<Value> {
+        budget::charge(Provider::Notion)?;
+        tokio::time::sleep(self.fetch_delay).await;
+        let failing = self.fetch_failures.fetch_update(
+            std::sync::atomic::Ordering::SeqCst,
+            std::sync::atomic::Ordering::SeqCst,
+            |n| n.checked_sub(1),
+        );
+        if failing.is_ok() {
+            return Err(UpstreamStatus::new(503, "Notion page request failed (status 503)").into());
+        }
         self.pages
             .lock()
             .unwrap()
@@ -44,12 +96,77 @@ impl NotionApi for FakeNotion {
         _icon: Option<Value>,
         _cover: Option<Value>,
     ) -> anyhow::Result<()> {
+        budget::charge(Provider::Notion)?;
         self.updates
             .lock()
             .unwrap()
             .push((page_id.to_string(), properties, _icon, _cover));
         Ok(())
     }
+
+    async fn query_pages_by_number(
+        &self,
+        property: &str,
+        value: f64,
+    ) -> anyhow::Result<Vec<Value>> {
+        budget::charge(Provider::Notion)?;
+        Ok(self
+            .pages
+            .lock()
+            .unwrap()
+            .values()
+            .filter(|page| {
+                page.get("properties")
+                    .and_then(|p| p.get(property))
+                    .and_then(|p| p.get("number"))
+                    .and_then(|v| v.as_f64())
+                    == Some(value)
+            })
+            .cloned()
+            .collect())
+    }
+
+    async fn add_comment(&self, page_id: &str, text: &str) -> anyhow::Result<()> {
+        budget::charge(Provider::Notion)?;
+        self.comments
+            .lock()
+            .unwrap()
+            .push((page_id.to_string(), text.to_string()));
+        Ok(())
+    }
+
+    async fn append_blocks(&self, page_id: &str, blocks: Vec<Value>) -> anyhow::Result<()> {
+        budget::charge(Provider::Notion)?;
+        self.blocks
+            .lock()
+            .unwrap()
+            .entry(page_id.to_string())
+            .or_default()
+            .extend(blocks);
+        Ok(())
+    }
+
+    async fn clear_blocks(&self, page_id: &str) -> anyhow::Result<()> {
+        budget::charge(Provider::Notion)?;
+        self.blocks.lock().unwrap().remove(page_id);
+        Ok(())
+    }
+
+    async fn list_pages(
+        &self,
+        start_cursor: Option<&str>,
+        page_size: usize,
+    ) -> anyhow::Result<DatabaseQueryResponse> {
+        let mut pages: Vec<Value> = self.pages.lock().unwrap().values().cloned().collect();
+        pages.sort_by_key(|page| page["id"].as_str().unwrap_or_default().to_string());
+        let start = start_cursor.and_then(|c| c.parse().ok()).unwrap_or(0);
+        let end = (start + page_size).min(pages.len());
+        Ok(DatabaseQueryResponse {
+            results: pages[start..end].to_vec(),
+            has_more: end < pages.len(),
+            next_cursor: (end < pages.len()).then(|| end.to_string()),
+        })
+    }
 }
 
 struct FakeTmdb {
@@ -57,27 +174,74 @@ struct FakeTmdb {
     tv: MediaData,
 }
 
+/// FakeTmdb matches no title starting with "wip"; `UNMATCHED_SEARCHES` counts its searches
+/// for `UNMATCHED_TITLE`.
+const UNMATCHED_TITLE: &str = "wip notes";
+static UNMATCHED_SEARCHES: AtomicUsize = AtomicUsize::new(0);
+/// FakeTmdb answers the first fetch of this movie id with a 503.
+const FLAKY_MOVIE_ID: i32 = 303;
+static FLAKY_MOVIE_FAILED: AtomicBool = AtomicBool::new(false);
+/// FakeAniList answers every resolution of this title with a 503; `OUTAGE_LOOKUPS` counts
+/// those attempts and `OUTAGE_TMDB_SEARCHES` any TMDB search for it.
+const ANILIST_OUTAGE_TITLE: &str = "Outage Anime";
+static OUTAGE_LOOKUPS: AtomicUsize = AtomicUsize::new(0);
+static OUTAGE_TMDB_SEARCHES: AtomicUsize = AtomicUsize::new(0);
+/// Every `(query, year)` FakeTmdb was asked to resolve a movie with.
+static MOVIE_YEAR_HINTS: Mutex<Vec<(String, Option<i32>)>> = Mutex::new(Vec::new());
+
 #[async_trait::async_trait]
 impl TmdbApi for FakeTmdb {
-    async fn search_movie(&self, _query: &str) -> anyhow::Result<i32> {
+    async fn search_movie(&self, query: &str) -> anyhow::Result<i32> {
+        budget::charge(Provider::Tmdb)?;
+        if query == ANILIST_OUTAGE_TITLE {
+            OUTAGE_TMDB_SEARCHES.fetch_add(1, Ordering::SeqCst);
+        }
+        if query.starts_with("wip") {
+            if query == UNMATCHED_TITLE {
+                UNMATCHED_SEARCHES.fetch_add(1, Ordering::SeqCst);
+            }
+            anyhow::bail!("No TMDB movie found for '{query}'");
+        }
         Ok(self.movie.id)
     }
-    async fn search_tv(&self, _query: &str) -> anyhow::Result<i32> {
+    async fn search_tv(&self, query: &str) -> anyhow::Result<i32> {
+        budget::charge(Provider::Tmdb)?;
+        if query == ANILIST_OUTAGE_TITLE {
+            OUTAGE_TMDB_SEARCHES.fetch_add(1, Ordering::SeqCst);
+        }
         Ok(self.tv.id)
     }
     async fn resolve_movie_id(&self, query: &str) -> anyhow::Result<i32> {
+        budget::charge(Provider::Tmdb)?;
+        // Like TmdbClient; any other id would fail `fetch_movie`.
+        if let Some(id) = tmdb::parse_tmdb_id(query) {
+            return Ok(id);
+        }
         if query == "tt12345" {
             return Ok(self.movie.id);
         }
         self.search_movie(query).await
     }
     async fn resolve_tv_id(&self, query: &str) -> anyhow::Result<i32> {
+        budget::charge(Provider::Tmdb)?;
         if query == "tt99999" {
             return Ok(self.tv.id);
         }
         self.search_tv(query).await
     }
+    async fn resolve_movie_id_with_year(
+        &self,
+        query: &str,
+        year: Option<i32>,
+    ) -> anyhow::Result<i32> {
+        MOVIE_YEAR_HINTS
+            .lock()
+            .unwrap()
+            .push((query.to_string(), year));
+        self.resolve_movie_id(query).await
+    }
     async fn lookup_imdb(&self, imdb_id: &str) -> anyhow::Result<(Option<i32>, Option<i32>)> {
+        budget::charge(Provider::Tmdb)?;
         match imdb_id {
             "tt12345" => Ok((Some(self.movie.id), None)),
             "tt99999" => Ok((None, Some(self.tv.id))),
@@ -85,10 +249,15 @@ impl TmdbApi for FakeTmdb {
         }
     }
     async fn fetch_movie(&self, id: i32) -> anyhow::Result<MediaData> {
+        budget::charge(Provider::Tmdb)?;
         assert_eq!(id, self.movie.id);
+        if id == FLAKY_MOVIE_ID && !FLAKY_MOVIE_FAILED.swap(true, Ordering::SeqCst) {
+            return Err(UpstreamStatus::new(503, "TMDB request failed (status 503)").into());
+        }
         Ok(self.movie.clone())
     }
     async fn fetch_tv_season(&self, id: i32, _season: i32) -> anyhow::Result<MediaData> {
+        budget::charge(Provider::Tmdb)?;
         assert_eq!(id, self.tv.id);
         Ok(self.tv.clone())
     }
@@ -97,19 +266,70 @@ impl TmdbApi for FakeTmdb {
 struct FakeAniList {
     resolved_id: i32,
     anime: AniListMapped,
+    manga: AniListMapped,
 }
 
 #[async_trait::async_trait]
 impl AniListApi for FakeAniList {
-    async fn resolve_anime_id(&self, _query: &str, season: Option<i32>) -> anyhow::Result<i32> {
+    async fn resolve_anime_id(&self, query: &str, season: Option<i32>) -> anyhow::Result<i32> {
+        budget::charge(Provider::AniList)?;
         assert_eq!(season, Some(2));
+        if query == ANILIST_OUTAGE_TITLE {
+            OUTAGE_LOOKUPS.fetch_add(1, Ordering::SeqCst);
+            return Err(UpstreamStatus::new(503, "AniList request failed (status 503)").into());
+        }
         Ok(self.resolved_id)
     }
 
     async fn fetch_anime(&self, id: i32) -> anyhow::Result<AniListMapped> {
+        budget::charge(Provider::AniList)?;
         assert_eq!(id, self.resolved_id);
         Ok(self.anime.clone())
     }
+
+    async fn resolve_manga_id(&self, _query: &str, season: Option<i32>) -> anyhow::Result<i32> {
+        budget::charge(Provider::AniList)?;
+        assert_eq!(season, None);
+        Ok(self.manga.id)
+    }
+
+    async fn fetch_manga(&self, id: i32) -> anyhow::Result<AniListMapped> {
+        budget::charge(Provider::AniList)?;
+        assert_eq!(id, self.manga.id);
+        Ok(self.manga.clone())
+    }
+}
+
+fn anilist_manga() -> AniListMapped {
+    AniListMapped {
+        id: 30013,
+        id_mal: None,
+        name: "AniList Manga".to_string(),
+        eng_name: None,
+        original_title: Some("AniList Manga Romaji".to_string()),
+        native_title: None,
+        synopsis: Some("Manga synopsis".to_string()),
+        genres: vec!["Adventure".to_string(), "manga".to_string()],
+        cast: vec!["Character A".to_string()],
+        director: vec![],
+        is_adult: false,
+        content_rating: "All Audiences".to_string(),
+        country_of_origin: Some("Japan".to_string()),
+        language: Some("Japanese".to_string()),
+        release_date: Some("1997-07-22".to_string()),
+        year: Some("1997".to_string()),
+        runtime_minutes: None,
+        episodes: None,
+        chapters: Some(120),
+        volumes: Some(12),
+        trailer: None,
+        poster: Some("https://anilist/manga.png".to_string()),
+        backdrop: None,
+        imdb_page: Some("https://anilist.co/manga/30013".to_string()),
+        mean_score: None,
+        average_score: None,
+        popularity: None,
+    }
 }
 
 fn base_schema() -> PropertySchema {
@@ -117,6 +337,7 @@ fn base_schema() -> PropertySchema {
     types.insert("Name".to_string(), PropertyType::Title);
     types.insert("Eng Name".to_string(), PropertyType::RichText);
     types.insert("Original Title".to_string(), PropertyType::RichText);
+    types.insert("Native Title".to_string(), PropertyType::RichText);
     types.insert("Synopsis".to_string(), PropertyType::RichText);
     types.insert("Genre".to_string(), PropertyType::MultiSelect);
     types.insert("Cast".to_string(), PropertyType::RichText);
@@ -128,12 +349,20 @@ fn base_schema() -> PropertySchema {
     types.insert("Year".to_string(), PropertyType::RichText);
     types.insert("Runtime".to_string(), PropertyType::Number);
     types.insert("Episodes".to_string(), PropertyType::Number);
+    types.insert("Chapters".to_string(), PropertyType::Number);
     types.insert("Trailer".to_string(), PropertyType::Url);
     types.insert("IMG".to_string(), PropertyType::Files);
+    types.insert("Gallery".to_string(), PropertyType::Files);
     types.insert("IMDb Page".to_string(), PropertyType::Url);
     types.insert("ID".to_string(), PropertyType::Number);
     types.insert("Season".to_string(), PropertyType::Select);
     types.insert("Type".to_string(), PropertyType::Select);
+    types.insert("Last Synced".to_string(), PropertyType::Date);
+    types.insert("Source".to_string(), PropertyType::Select);
+    types.insert("Languages".to_string(), PropertyType::MultiSelect);
+    types.insert("Score".to_string(), PropertyType::Number);
+    types.insert("Adult".to_string(), PropertyType::Checkbox);
+    types.insert("Collection".to_string(), PropertyType::Select);
     PropertySchema {
         types,
         title_property: Some("Name".to_string()),
@@ -191,6 +420,7 @@ fn tmdb_movie() -> MediaData {
         content_rating: Some("PG-13".to_string()),
         country_of_origin: vec!["US".to_string()],
         language: Some("English".to_string()),
+        spoken_languages: vec!["English".to_string(), "French".to_string()],
         original_language: "en".to_string(),
         release_date: Some("2024-01-01".to_string()),
         year: Some("2024".to_string()),
@@ -198,8 +428,19 @@ fn tmdb_movie() -> MediaData {
         episodes: None,
         trailer: Some("https://youtube.com/movie".to_string()),
         poster: Some("https://image.tmdb.org/movie.jpg".to_string()),
+        gallery: vec![
+            "https://image.tmdb.org/alt1.jpg".to_string(),
+            "https://image.tmdb.org/alt2.jpg".to_string(),
+        ],
         backdrop: None,
         imdb_page: Some("https://imdb.com/title/tt123".to_string()),
+        vote_average: Some(7.4),
+        vote_count: Some(1_234),
+        collection: None,
+        networks: Vec::new(),
+        keywords: Vec::new(),
+        watch_providers: Vec::new(),
+        watch_link: None,
     }
 }
 
@@ -216,6 +457,7 @@ fn tmdb_tv() -> MediaData {
         content_rating: Some("TV-MA".to_string()),
         country_of_origin: vec!["US".to_string()],
         language: Some("English".to_string()),
+        spoken_languages: vec![],
         original_language: "en".to_string(),
         release_date: Some("2025-02-02".to_string()),
         year: Some("2025".to_string()),
@@ -223,22 +465,119 @@ fn tmdb_tv() -> MediaData {
         episodes: Some(8),
         trailer: Some("https://youtube.com/show".to_string()),
         poster: Some("https://image.tmdb.org/show.jpg".to_string()),
+        gallery: vec![],
         backdrop: None,
         imdb_page: Some("https://imdb.com/title/tt456".to_string()),
+        vote_average: None,
+        vote_count: None,
+        collection: None,
+        networks: Vec::new(),
+        keywords: Vec::new(),
+        watch_providers: Vec::new(),
+        watch_link: None,
     }
 }
 
 fn app_with_mocks(page: Value, tmdb: FakeTmdb) -> (Router, Arc<FakeNotion>) {
+    app_with_pages(vec![page], tmdb)
+}
+
+fn app_with_pages(pages: Vec<Value>, tmdb: FakeTmdb) -> (Router, Arc<FakeNotion>) {
+    app_with_options(pages, tmdb, AppOptions::default())
+}
+
+struct AppOptions {
+    triggers: TriggerConfig,
+    request_budget: u32,
+    notion_schema_error: Option<&'static str>,
+    capture_dir: Option<std::path::PathBuf>,
+    notion_fetch_failures: usize,
+    notion_fetch_delay: Duration,
+    sync_timeout_secs: u64,
+    retry_max_attempts: u32,
+    retry_base_delay: Duration,
+    dedupe_store: Option<std::path::PathBuf>,
+    signing_secret: &'static str,
+    negative_cache_ttl_secs: u64,
+    dry_run: bool,
+    error_webhook_url: Option<String>,
+    overwrite_mode: OverwriteMode,
+    synopsis_as_body: bool,
+    job_queue_capacity: usize,
+    webhook_allowed_cidrs: Vec<ipnet::IpNet>,
+    trusted_proxies: Vec<ipnet::IpNet>,
+    max_keywords: usize,
+}
+
+impl Default for AppOptions {
+    fn default() -> Self {
+        Self {
+            triggers: TriggerConfig::default(),
+            request_budget: DEFAULT_REQUEST_BUDGET,
+            notion_schema_error: None,
+            capture_dir: None,
+            notion_fetch_failures: 0,
+            notion_fetch_delay: Duration::ZERO,
+            sync_timeout_secs: 30,
+            retry_max_attempts: 4,
+            retry_base_delay: Duration::from_millis(10),
+            dedupe_store: None,
+            signing_secret: WEBHOOK_SECRET,
+            negative_cache_ttl_secs: 600,
+            dry_run: false,
+            error_webhook_url: None,
+            overwrite_mode: OverwriteMode::Always,
+            synopsis_as_body: false,
+            job_queue_capacity: cinelink::queue::DEFAULT_JOB_QUEUE_CAPACITY,
+            webhook_allowed_cidrs: Vec::new(),
+            trusted_proxies: Vec::new(),
+            max_keywords: 10,
+        }
+    }
+}
+
+fn app_with_options(
+    pages: Vec<Value>,
+    tmdb: FakeTmdb,
+    options: AppOptions,
+) -> (Router, Arc<FakeNotion>) {
+    let (state, notion) = state_with_options(pages, tmdb, options);
+    spawn_retry_worker(&state);
+    spawn_job_workers(&state);
+    (build_router(state), notion)
+}
+
+fn state_with_options(
+    pages: Vec<Value>,
+    tmdb: FakeTmdb,
+    options: AppOptions,
+) -> (AppState, Arc<FakeNotion>) {
     let schema = base_schema();
     let notion = Arc::new(FakeNotion {
-        schema: schema.clone(),
-        pages: Mutex::new(HashMap::from([(
-            page.get("id").unwrap().as_str().unwrap().to_string(),
-            page,
-        )])),
+        schema: Mutex::new(schema.clone()),
+        schema_error: options.notion_schema_error,
+        schema_fetches: std::sync::atomic::AtomicUsize::new(0),
+        fetch_failures: std::sync::atomic::AtomicUsize::new(options.notion_fetch_failures),
+        fetch_delay: options.notion_fetch_delay,
+        pages: Mutex::new(
+            pages
+                .into_iter()
+                .map(|page| (page.get("id").unwrap().as_str().unwrap().to_string(), page))
+                .collect(),
+        ),
         updates: Mutex::new(Vec::new()),
+        comments: Mutex::new(Vec::new()),
+        blocks: Mutex::new(HashMap::new()),
     });
 
+    let (dedupe_store, recent_events) = match options.dedupe_store {
+        Some(path) => {
+            let (store, events) = DedupeStore::open(path, 600, Utc::now().timestamp());
+            (Some(Arc::new(store)), events)
+        }
+        None => (None, HashMap::new()),
+    };
+    let metrics = Arc::new(Metrics::new());
     let state = AppState {
         notion: notion.clone(),
         tmdb: Arc::new(tmdb),
@@ -250,6 +589,7 @@ fn app_with_mocks(page: Value, tmdb: FakeTmdb) -> (Router, Arc<FakeNotion>) {
                 name: "AniList English Season 2".to_string(),
                 eng_name: None,
                 original_title: Some("AniList Romaji Season 2".to_string()),
+                native_title: Some("アニリスト".to_string()),
                 synopsis: Some("AniList synopsis".to_string()),
                 genres: vec!["Action".to_string()],
                 cast: vec!["Cast A".to_string()],
@@ -262,33 +602,75 @@ fn app_with_mocks(page: Value, tmdb: FakeTmdb) -> (Router, Arc<FakeNotion>) {
                 year: Some("2025".to_string()),
                 runtime_minutes: Some(24.0),
                 episodes: Some(13),
+                chapters: None,
+                volumes: None,
                 trailer: Some("https://youtube.com/anime".to_string()),
                 poster: Some("https://anilist/poster.png".to_string()),
                 backdrop: Some("https://anilist/backdrop.jpg".to_string()),
                 imdb_page: Some("https://anilist.co/anime/176496".to_string()),
+                mean_score: Some(82.0),
+                average_score: Some(81.0),
+                popularity: Some(154_000.0),
             },
+            manga: anilist_manga(),
         }),
         title_property: "Name".to_string(),
-        schema: Arc::new(schema),
-        signing_secret: WEBHOOK_SECRET.to_string(),
+        triggers: Arc::new(options.triggers),
+        config: Arc::new(AppConfig {
+            request_budget: options.request_budget,
+            retry_max_attempts: options.retry_max_attempts,
+            sync_timeout_secs: options.sync_timeout_secs,
+            negative_cache_ttl_secs: options.negative_cache_ttl_secs,
+            dry_run: options.dry_run,
+            overwrite_mode: options.overwrite_mode,
+            synopsis_as_body: options.synopsis_as_body,
+            job_queue_capacity: options.job_queue_capacity,
+            webhook_allowed_cidrs: options.webhook_allowed_cidrs,
+            trusted_proxies: options.trusted_proxies,
+            max_keywords: options.max_keywords,
+            ..AppConfig::default()
+        }),
+        schema: Arc::new(SharedSchema::new(schema)),
+        signing_secret: options.signing_secret.to_string(),
+        secondary_signing_secret: None,
         rate_limits: Arc::new(tokio::sync::Mutex::new(std::collections::HashMap::new())),
-        global_limit: Arc::new(tokio::sync::Mutex::new(cinelink::app::WindowCounter {
-            window: 0,
-            count: 0,
-        })),
-        recent_events: Arc::new(tokio::sync::Mutex::new(std::collections::HashMap::new())),
+        global_limit: Arc::new(tokio::sync::Mutex::new(
+            cinelink::rate_limit::TokenBucket::default(),
+        )),
+        shape_warning_limit: Arc::new(tokio::sync::Mutex::new(
+            cinelink::rate_limit::TokenBucket::default(),
+        )),
+        recent_events: Arc::new(tokio::sync::Mutex::new(recent_events)),
+        dedupe_store,
         processing_sem: Arc::new(tokio::sync::Semaphore::new(8)),
+        duplicates: Arc::new(DuplicateIndex::new()),
+        negative_lookups: Arc::new(NegativeCache::new(Duration::from_secs(
+            options.negative_cache_ttl_secs,
+        ))),
+        metrics: metrics.clone(),
+        readiness: Arc::new(tokio::sync::Mutex::new(None)),
+        admin_key: Some(ADMIN_KEY.to_string()),
+        capture_dir: options.capture_dir,
+        failures: Arc::new(FailureLog::new()),
+        retry: Arc::new(RetryQueue::new(
+            options.retry_max_attempts,
+            options.retry_base_delay,
+            metrics,
+        )),
+        verification_token: Arc::new(tokio::sync::Mutex::new(None)),
+        backfill: Arc::new(BackfillProgress::default()),
+        error_notifier: options
+            .error_webhook_url
+            .map(|url| Arc::new(ErrorNotifier::new(url).unwrap())),
+        in_flight_pages: Arc::new(std::sync::Mutex::new(HashMap::new())),
+        jobs: tokio_util::task::TaskTracker::new(),
+        job_queue: Arc::new(cinelink::queue::JobQueue::new(options.job_queue_capacity)),
     };
-
-    (build_router(state), notion)
+    (state, notion)
 }
 
 fn webhook_payload(updated: &[&str], page_id: &str) -> String {
-    let id = format!(
-        "evt-{}-{}",
-        page_id,
-        updated.iter().copied().collect::<Vec<_>>().join(",")
-    );
+    let id = format!("evt-{}-{}", page_id, updated.to_vec().join(","));
     json!({
         "id": id,
         "timestamp": Utc::now().to_rfc3339(),
@@ -403,6 +785,23 @@ async fn updates_movie_when_title_has_semicolon() {
         .and_then(|e| e.get("url"))
         .and_then(|u| u.as_str());
     assert!(cover_url.is_none()); // movie fixture has no backdrop
+
+    // IMG stays the single primary poster; alternates go to Gallery.
+    let img = props["IMG"]["files"].as_array().unwrap();
+    assert_eq!(img.len(), 1);
+    let gallery = props["Gallery"]["files"].as_array().unwrap();
+    assert_eq!(gallery.len(), movie.gallery.len());
+    assert_eq!(gallery[0]["external"]["url"], json!(movie.gallery[0]));
+
+    assert_eq!(props["Source"], json!({ "select": { "name": "TMDB" } }));
+    assert_eq!(props["Score"], json!({ "number": 7.4 }));
+    assert_eq!(
+        props["Languages"],
+        json!({ "multi_select": [{ "name": "English" }, { "name": "French" }] })
+    );
+    let synced = props["Last Synced"]["date"]["start"].as_str().unwrap();
+    let synced = chrono::DateTime::parse_from_rfc3339(synced).unwrap();
+    assert!((Utc::now() - synced.with_timezone(&Utc)).num_seconds() < 60);
 }
 
 #[tokio::test]
@@ -473,6 +872,62 @@ async fn updates_tv_with_season() {
     assert!(cover_url.is_none()); // tv fixture has no backdrop
 }
 
+#[tokio::test]
+async fn season_stored_as_a_number_is_read() {
+    // FakeAniList only resolves season 2, so a missed season fails the job.
+    let mut page = make_page("Ani Query=", "tv", None);
+    page["properties"]["Season"] = json!({ "type": "number", "number": 2.0 });
+    let (app, notion) = app_with_mocks(
+        page,
+        FakeTmdb {
+            movie: tmdb_movie(),
+            tv: tmdb_tv(),
+        },
+    );
+
+    let res = app
+        .oneshot(signed_request(webhook_payload(&["title"], "page-1")))
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::OK);
+
+    wait_for_update_count(&notion, 1).await;
+    let updates = notion.updates.lock().unwrap();
+    let (_, props, _, _) = &updates[0];
+    assert_eq!(
+        props["Name"]["title"][0]["text"]["content"],
+        "AniList English"
+    );
+}
+
+#[tokio::test]
+async fn year_hints_reach_the_movie_resolver() {
+    let mut dated = make_page("Dated Movie ;", "Movie", None);
+    dated["id"] = json!("page-dated");
+    dated["properties"]["Release Date"] = json!({ "date": { "start": "1984-12-14" } });
+    let (app, notion) = app_with_pages(
+        vec![make_page("Year Hint Movie (2021) ;", "Movie", None), dated],
+        FakeTmdb {
+            movie: tmdb_movie(),
+            tv: tmdb_tv(),
+        },
+    );
+
+    for page_id in ["page-1", "page-dated"] {
+        let res = app
+            .clone()
+            .oneshot(signed_request(webhook_payload(&["title"], page_id)))
+            .await
+            .unwrap();
+        assert_eq!(res.status(), StatusCode::OK);
+    }
+    wait_for_update_count(&notion, 2).await;
+
+    let hints = MOVIE_YEAR_HINTS.lock().unwrap();
+    assert!(hints.contains(&("Year Hint Movie".to_string(), Some(2021))));
+    assert!(hints.contains(&("Dated Movie".to_string(), Some(1984))));
+}
+
 #[tokio::test]
 async fn resolves_imdb_id_for_movie() {
     let page = make_page("tt12345 ;", "Movie", None);
@@ -585,6 +1040,14 @@ async fn updates_anime_when_title_has_equals() {
         .and_then(|t| t.get("content"))
         .and_then(|v| v.as_str());
     assert_eq!(original, Some("AniList Romaji"));
+    assert_eq!(
+        props["Native Title"]["rich_text"][0]["text"]["content"],
+        "アニリスト"
+    );
+    assert_eq!(props["Source"], json!({ "select": { "name": "AniList" } }));
+    assert_eq!(props["Score"], json!({ "number": 82.0 }));
+    assert_eq!(props["Adult"], json!({ "checkbox": false }));
+    assert!(props["Last Synced"]["date"]["start"].is_string());
 
     let eng_name = props
         .get("Eng Name")
@@ -637,6 +1100,7 @@ async fn uses_original_title_for_french_with_eng_name_set() {
         content_rating: None,
         country_of_origin: vec![],
         language: Some("French".to_string()),
+        spoken_languages: vec![],
         original_language: "fr".to_string(),
         release_date: None,
         year: None,
@@ -644,8 +1108,16 @@ async fn uses_original_title_for_french_with_eng_name_set() {
         episodes: None,
         trailer: None,
         poster: None,
+        gallery: vec![],
         backdrop: None,
         imdb_page: None,
+        vote_average: None,
+        vote_count: None,
+        collection: None,
+        networks: Vec::new(),
+        keywords: Vec::new(),
+        watch_providers: Vec::new(),
+        watch_link: None,
     };
     let french_media_with_titles = MediaData {
         name: "Titre original".to_string(),
@@ -708,6 +1180,7 @@ async fn sets_original_title_when_using_english_title() {
         content_rating: None,
         country_of_origin: vec![],
         language: Some("Japanese".to_string()),
+        spoken_languages: vec![],
         original_language: "ja".to_string(),
         release_date: None,
         year: None,
@@ -715,8 +1188,16 @@ async fn sets_original_title_when_using_english_title() {
         episodes: None,
         trailer: None,
         poster: None,
+        gallery: vec![],
         backdrop: None,
         imdb_page: None,
+        vote_average: None,
+        vote_count: None,
+        collection: None,
+        networks: Vec::new(),
+        keywords: Vec::new(),
+        watch_providers: Vec::new(),
+        watch_link: None,
     };
 
     let page = make_page("Spirited Away ;", "Movie", None);
@@ -746,3 +1227,3229 @@ async fn sets_original_title_when_using_english_title() {
         .and_then(|s| s.as_str());
     assert_eq!(original, Some("千と千尋の神隠し"));
 }
+
+#[tokio::test]
+async fn updates_manga_when_title_has_tilde() {
+    let page = make_page("Manga Query~", "Manga", Some("Season 2"));
+    let manga = anilist_manga();
+    let (app, notion) = app_with_mocks(
+        page,
+        FakeTmdb {
+            movie: tmdb_movie(),
+            tv: tmdb_tv(),
+        },
+    );
+
+    let payload = webhook_payload(&["title"], "page-1");
+    let res = app.oneshot(signed_request(payload)).await.unwrap();
+    assert_eq!(res.status(), StatusCode::OK);
+
+    wait_for_update_count(&notion, 1).await;
+    let updates = notion.updates.lock().unwrap();
+    assert_eq!(updates.len(), 1);
+    let (_id, props, icon, _cover) = &updates[0];
+
+    let name = props
+        .get("Name")
+        .and_then(|p| p.get("title"))
+        .and_then(|t| t.as_array())
+        .and_then(|a| a.first())
+        .and_then(|v| v.get("text"))
+        .and_then(|t| t.get("content"))
+        .and_then(|s| s.as_str());
+    assert_eq!(name, Some(manga.name.as_str()));
+
+    let id = props
+        .get("ID")
+        .and_then(|v| v.get("number"))
+        .and_then(|v| v.as_f64());
+    assert_eq!(id, Some(manga.id as f64));
+
+    let genre_names = props
+        .get("Genre")
+        .and_then(|v| v.get("multi_select"))
+        .and_then(|v| v.as_array())
+        .map(|arr| {
+            arr.iter()
+                .filter_map(|v| v.get("name").and_then(|n| n.as_str()).map(str::to_string))
+                .collect::<Vec<_>>()
+        })
+        .unwrap_or_default();
+    assert_eq!(
+        genre_names,
+        vec!["Manga".to_string(), "Adventure".to_string()]
+    );
+
+    // Manga have no episodes/runtime, so those properties are left alone.
+    assert!(props.get("Episodes").is_none());
+    assert!(props.get("Runtime").is_none());
+
+    // Chapters is in the schema; Volumes isn't, so it must not be written.
+    assert_eq!(
+        props
+            .get("Chapters")
+            .and_then(|v| v.get("number"))
+            .and_then(|v| v.as_f64()),
+        Some(120.0)
+    );
+    assert!(props.get("Volumes").is_none());
+
+    let icon_url = icon
+        .as_ref()
+        .and_then(|i| i.get("external"))
+        .and_then(|e| e.get("url"))
+        .and_then(|u| u.as_str());
+    assert_eq!(icon_url, manga.poster.as_deref());
+}
+
+#[tokio::test]
+async fn custom_multi_char_suffix_triggers_and_is_stripped() {
+    let triggers = TriggerConfig::new(";;", "==", "~~").unwrap();
+    let (app, notion) = app_with_options(
+        vec![make_page("Movie Title ;; ", "Movie", None)],
+        FakeTmdb {
+            movie: tmdb_movie(),
+            tv: tmdb_tv(),
+        },
+        AppOptions {
+            triggers,
+            ..Default::default()
+        },
+    );
+
+    let payload = webhook_payload(&["title"], "page-1");
+    let res = app.oneshot(signed_request(payload)).await.unwrap();
+    assert_eq!(res.status(), StatusCode::OK);
+
+    wait_for_update_count(&notion, 1).await;
+    let updates = notion.updates.lock().unwrap();
+    let props = &updates[0].1;
+    assert_eq!(
+        props["Name"]["title"][0]["text"]["content"],
+        json!("TMDB Movie")
+    );
+}
+
+async fn updated_id_for(page: Value, triggers: TriggerConfig) -> Value {
+    let (app, notion) = app_with_options(
+        vec![page],
+        FakeTmdb {
+            movie: tmdb_movie(),
+            tv: tmdb_tv(),
+        },
+        AppOptions {
+            triggers,
+            ..Default::default()
+        },
+    );
+    let payload = webhook_payload(&["title"], "page-1");
+    let res = app.oneshot(signed_request(payload)).await.unwrap();
+    assert_eq!(res.status(), StatusCode::OK);
+    wait_for_update_count(&notion, 1).await;
+    let updates = notion.updates.lock().unwrap();
+    updates[0].1["ID"]["number"].clone()
+}
+
+#[tokio::test]
+async fn anime_type_with_tmdb_trigger_routes_to_anilist() {
+    let page = make_page("Ani Query ;", "Anime", Some("Season 2"));
+    let id = updated_id_for(page, TriggerConfig::default()).await;
+    assert_eq!(id, json!(176496.0));
+}
+
+#[tokio::test]
+async fn anilist_outages_requeue_anime_pages_instead_of_falling_back_to_tmdb() {
+    let (app, notion) = app_with_options(
+        vec![make_page(
+            &format!("{ANILIST_OUTAGE_TITLE} ;"),
+            "Anime",
+            Some("Season 2"),
+        )],
+        FakeTmdb {
+            movie: tmdb_movie(),
+            tv: tmdb_tv(),
+        },
+        AppOptions {
+            retry_max_attempts: 3,
+            ..AppOptions::default()
+        },
+    );
+
+    app.clone()
+        .oneshot(signed_request(webhook_payload(&["title"], "page-1")))
+        .await
+        .unwrap();
+    // Every attempt goes back to the retry queue until the attempts run out.
+    let deadline = tokio::time::Instant::now() + Duration::from_secs(2);
+    while OUTAGE_LOOKUPS.load(Ordering::SeqCst) < 3 {
+        assert!(
+            tokio::time::Instant::now() < deadline,
+            "job was not retried"
+        );
+        tokio::time::sleep(Duration::from_millis(10)).await;
+    }
+    tokio::time::sleep(Duration::from_millis(100)).await;
+    assert_eq!(OUTAGE_LOOKUPS.load(Ordering::SeqCst), 3);
+    assert_eq!(OUTAGE_TMDB_SEARCHES.load(Ordering::SeqCst), 0);
+    assert!(fetch_metrics(&app)
+        .await
+        .contains("cinelink_anilist_errors_total 3\n"));
+    assert_no_updates(&notion).await;
+}
+
+#[tokio::test]
+async fn prefixed_ids_pick_the_provider_whatever_the_trigger() {
+    let page = make_page("anilist:176496 ;", "Movie", Some("Season 2"));
+    let id = updated_id_for(page, TriggerConfig::default()).await;
+    assert_eq!(id, json!(176496.0));
+
+    let page = make_page("tmdb:101=", "Movie", None);
+    let id = updated_id_for(page, TriggerConfig::default()).await;
+    assert_eq!(id, json!(101.0));
+
+    let page = make_page("#101 ;", "Movie", None);
+    let id = updated_id_for(page, TriggerConfig::default()).await;
+    assert_eq!(id, json!(101.0));
+
+    // An explicit IMDb id stays on TMDB even when the Type says anime.
+    let page = make_page("imdb:tt12345 ;", "Anime", None);
+    let id = updated_id_for(page, TriggerConfig::default()).await;
+    assert_eq!(id, json!(101.0));
+}
+
+#[tokio::test]
+async fn pasted_urls_override_the_page_type_and_season() {
+    // A show URL with its season works on a page typed as a movie, without a Season.
+    let page = make_page(
+        "https://www.themoviedb.org/tv/202-tmdb-show/season/2 ;",
+        "Movie",
+        None,
+    );
+    let id = updated_id_for(page, TriggerConfig::default()).await;
+    assert_eq!(id, json!(202.0));
+
+    let page = make_page("https://www.themoviedb.org/movie/101 ;", "TV Series", None);
+    let id = updated_id_for(page, TriggerConfig::default()).await;
+    assert_eq!(id, json!(101.0));
+
+    let page = make_page("https://www.imdb.com/title/tt12345/ ;", "Movie", None);
+    let id = updated_id_for(page, TriggerConfig::default()).await;
+    assert_eq!(id, json!(101.0));
+
+    // An AniList URL takes the AniList path even behind the TMDB suffix.
+    let page = make_page(
+        "https://anilist.co/anime/176496/ ;",
+        "Movie",
+        Some("Season 2"),
+    );
+    let id = updated_id_for(page, TriggerConfig::default()).await;
+    assert_eq!(id, json!(176496.0));
+}
+
+#[tokio::test]
+async fn numeric_titles_are_searched_rather_than_taken_as_ids() {
+    // Read as TMDB id 1917, the title would fetch a movie FakeTmdb doesn't have.
+    let page = make_page("1917 ;", "Movie", None);
+    let id = updated_id_for(page, TriggerConfig::default()).await;
+    assert_eq!(id, json!(101.0));
+}
+
+#[tokio::test]
+async fn anime_type_values_are_configurable() {
+    let triggers = TriggerConfig::default().with_anime_types("Donghua");
+    let page = make_page("Ani Query ;", "Donghua", Some("Season 2"));
+    assert_eq!(
+        updated_id_for(page, triggers.clone()).await,
+        json!(176496.0)
+    );
+
+    // "Anime" is no longer an anime value, so the page stays on TMDB.
+    let page = make_page("Movie Title ;", "Anime", None);
+    assert_eq!(updated_id_for(page, triggers).await, json!(101.0));
+}
+
+#[tokio::test]
+async fn default_suffix_is_ignored_when_custom_suffix_configured() {
+    let triggers = TriggerConfig::new(";;", "=", "~").unwrap();
+    let (app, notion) = app_with_options(
+        vec![make_page("Movie Title; part one;", "Movie", None)],
+        FakeTmdb {
+            movie: tmdb_movie(),
+            tv: tmdb_tv(),
+        },
+        AppOptions {
+            triggers,
+            ..Default::default()
+        },
+    );
+
+    let payload = webhook_payload(&["title"], "page-1");
+    let res = app.oneshot(signed_request(payload)).await.unwrap();
+    assert_eq!(res.status(), StatusCode::OK);
+
+    tokio::time::sleep(Duration::from_millis(50)).await;
+    assert!(notion.updates.lock().unwrap().is_empty());
+}
+
+#[tokio::test]
+async fn custom_anilist_suffix_routes_to_anilist() {
+    let triggers = TriggerConfig::new(";;", "!a", "!m").unwrap();
+    let (app, notion) = app_with_options(
+        vec![make_page("Ani Query !a", "tv", Some("Season 2"))],
+        FakeTmdb {
+            movie: tmdb_movie(),
+            tv: tmdb_tv(),
+        },
+        AppOptions {
+            triggers,
+            ..Default::default()
+        },
+    );
+
+    let payload = webhook_payload(&["title"], "page-1");
+    let res = app.oneshot(signed_request(payload)).await.unwrap();
+    assert_eq!(res.status(), StatusCode::OK);
+
+    wait_for_update_count(&notion, 1).await;
+    let updates = notion.updates.lock().unwrap();
+    assert_eq!(updates[0].1["ID"]["number"], json!(176496.0));
+}
+
+#[test]
+fn trigger_config_rejects_empty_and_overlapping_suffixes() {
+    assert!(TriggerConfig::new("", "=", "~").is_err());
+    assert!(TriggerConfig::new(";", ";", "~").is_err());
+    assert!(TriggerConfig::new(";", ";;", "~").is_err());
+    assert!(TriggerConfig::new("x;", ";", "~").is_err());
+    assert!(TriggerConfig::new(";;", "==", "~~").is_ok());
+}
+
+#[tokio::test]
+async fn aborts_when_request_budget_is_exceeded() {
+    let (app, notion) = app_with_options(
+        vec![make_page("Movie Title ;", "Movie", None)],
+        FakeTmdb {
+            movie: tmdb_movie(),
+            tv: tmdb_tv(),
+        },
+        AppOptions {
+            request_budget: 3,
+            ..Default::default()
+        },
+    );
+
+    let payload = webhook_payload(&["title"], "page-1");
+    let res = app.oneshot(signed_request(payload)).await.unwrap();
+    assert_eq!(res.status(), StatusCode::OK);
+
+    // fetch_page + resolve + search fit; fetching details, the update and the error title don't.
+    assert_no_updates(&notion).await;
+}
+
+#[tokio::test]
+async fn updates_when_within_request_budget() {
+    // fetch_page, resolve_movie_id, search_movie, fetch_movie, update_page, duplicate query.
+    let (app, notion) = app_with_options(
+        vec![make_page("Movie Title ;", "Movie", None)],
+        FakeTmdb {
+            movie: tmdb_movie(),
+            tv: tmdb_tv(),
+        },
+        AppOptions {
+            request_budget: 6,
+            ..Default::default()
+        },
+    );
+
+    let payload = webhook_payload(&["title"], "page-1");
+    let res = app.oneshot(signed_request(payload)).await.unwrap();
+    assert_eq!(res.status(), StatusCode::OK);
+
+    wait_for_update_count(&notion, 1).await;
+}
+
+async fn fetch_metrics(app: &Router) -> String {
+    let res = app
+        .clone()
+        .oneshot(Request::get("/metrics").body(Body::empty()).unwrap())
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::OK);
+    let body = axum::body::to_bytes(res.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    String::from_utf8(body.to_vec()).unwrap()
+}
+
+#[tokio::test]
+async fn metrics_count_accepted_rejected_and_updated_pages() {
+    let (app, notion) = app_with_mocks(
+        make_page("Movie Title ;", "Movie", None),
+        FakeTmdb {
+            movie: tmdb_movie(),
+            tv: tmdb_tv(),
+        },
+    );
+
+    let payload = webhook_payload(&["title"], "page-1");
+    let res = app
+        .clone()
+        .oneshot(signed_request(payload.clone()))
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::OK);
+    wait_for_update_count(&notion, 1).await;
+
+    // Same event id again: deduped.
+    app.clone().oneshot(signed_request(payload)).await.unwrap();
+
+    let bad = Request::post("/")
+        .header("content-type", "application/json")
+        .header("x-notion-signature", "sha256=00")
+        .body(Body::from("{}"))
+        .unwrap();
+    app.clone().oneshot(bad).await.unwrap();
+
+    // Correctly signed, but an hour old.
+    let body = "{}";
+    let timestamp = (Utc::now().timestamp() - 3600).to_string();
+    let mut mac = Hmac::<Sha256>::new_from_slice(WEBHOOK_SECRET.as_bytes()).unwrap();
+    mac.update(format!("{timestamp}.{body}").as_bytes());
+    let stale = Request::post("/")
+        .header("content-type", "application/json")
+        .header(
+            "x-notion-signature",
+            format!("sha256={}", hex::encode(mac.finalize().into_bytes())),
+        )
+        .header("x-notion-signature-timestamp", timestamp)
+        .body(Body::from(body))
+        .unwrap();
+    app.clone().oneshot(stale).await.unwrap();
+
+    let deadline = tokio::time::Instant::now() + Duration::from_secs(2);
+    let text = loop {
+        let text = fetch_metrics(&app).await;
+        if text.contains("cinelink_pages_updated_total 1\n") {
+            break text;
+        }
+        assert!(
+            tokio::time::Instant::now() < deadline,
+            "timed out waiting for pages_updated: {text}"
+        );
+        tokio::task::yield_now().await;
+    };
+    assert!(text.contains("cinelink_webhooks_received_total 4\n"));
+    assert!(text.contains("cinelink_webhooks_deduped_total 1\n"));
+    assert!(text.contains("cinelink_signature_failures_total 1\n"));
+    assert!(text.contains("cinelink_webhooks_rejected_total{reason=\"dedupe\"} 1\n"));
+    assert!(text.contains("cinelink_webhooks_rejected_total{reason=\"signature\"} 1\n"));
+    assert!(text.contains("cinelink_webhooks_rejected_total{reason=\"stale\"} 1\n"));
+    assert!(text.contains("cinelink_webhooks_rejected_total{reason=\"rate_limit\"} 0\n"));
+    assert!(text.contains("cinelink_pages_no_match_total 0\n"));
+    assert!(text.contains("cinelink_active_jobs 0\n"));
+    assert!(text.contains("cinelink_page_processing_seconds_count 1\n"));
+}
+
+#[derive(Clone, Default)]
+struct CapturedLogs(Arc<Mutex<Vec<u8>>>);
+
+impl std::io::Write for CapturedLogs {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.0.lock().unwrap().extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+impl CapturedLogs {
+    fn text(&self) -> String {
+        String::from_utf8_lossy(&self.0.lock().unwrap()).into_owned()
+    }
+}
+
+#[tokio::test]
+async fn unknown_payload_shape_is_logged_counted_and_still_processed() {
+    let logs = CapturedLogs::default();
+    let writer = logs.clone();
+    let subscriber = tracing_subscriber::fmt()
+        .with_writer(move || writer.clone())
+        .with_ansi(false)
+        .finish();
+    let _guard = tracing::subscriber::set_default(subscriber);
+
+    let (app, notion) = app_with_mocks(
+        make_page("Movie Title ;", "Movie", None),
+        FakeTmdb {
+            movie: tmdb_movie(),
+            tv: tmdb_tv(),
+        },
+    );
+
+    let mut payload: Value = serde_json::from_str(&webhook_payload(&["title"], "page-1")).unwrap();
+    payload["version"] = json!("2");
+    payload["delivery"] = json!({ "mode": "batched" });
+    let res = app
+        .clone()
+        .oneshot(signed_request(payload.to_string()))
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::OK);
+    wait_for_update_count(&notion, 1).await;
+
+    let text = logs.text();
+    assert!(text.contains("Webhook payload deviates from the known shape"));
+    assert!(text.contains("event_type=Some(\"page.properties_updated\")"));
+    assert!(text.contains("version=Some(\"2\")"));
+    assert!(text.contains("unexpected_keys=[\"delivery\", \"version\"]"));
+
+    let res = app
+        .oneshot(Request::get("/status").body(Body::empty()).unwrap())
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::OK);
+    let body = axum::body::to_bytes(res.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    let status: Value = serde_json::from_slice(&body).unwrap();
+    assert_eq!(status["unknown_shape_events"], json!(1));
+}
+
+#[tokio::test]
+async fn known_payload_shape_is_not_counted() {
+    let (app, notion) = app_with_mocks(
+        make_page("Movie Title ;", "Movie", None),
+        FakeTmdb {
+            movie: tmdb_movie(),
+            tv: tmdb_tv(),
+        },
+    );
+    let res = app
+        .clone()
+        .oneshot(signed_request(webhook_payload(&["title"], "page-1")))
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::OK);
+    wait_for_update_count(&notion, 1).await;
+
+    let res = app
+        .oneshot(Request::get("/status").body(Body::empty()).unwrap())
+        .await
+        .unwrap();
+    let body = axum::body::to_bytes(res.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    let status: Value = serde_json::from_slice(&body).unwrap();
+    assert_eq!(status["unknown_shape_events"], json!(0));
+}
+
+fn page_created_payload(page_id: &str) -> String {
+    json!({
+        "id": format!("evt-created-{}", page_id),
+        "timestamp": Utc::now().to_rfc3339(),
+        "type": "page.created",
+        "entity": { "id": page_id, "type": "page" },
+        "data": { "parent": { "id": "db-1", "type": "database" } }
+    })
+    .to_string()
+}
+
+#[tokio::test]
+async fn page_created_with_suffix_is_enriched_once() {
+    let (app, notion) = app_with_mocks(
+        make_page("Movie Title ;", "Movie", None),
+        FakeTmdb {
+            movie: tmdb_movie(),
+            tv: tmdb_tv(),
+        },
+    );
+
+    let payload = page_created_payload("page-1");
+    let res = app
+        .clone()
+        .oneshot(signed_request(payload.clone()))
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::OK);
+    // A redelivery of the same event is deduped.
+    let res = app.oneshot(signed_request(payload)).await.unwrap();
+    assert_eq!(res.status(), StatusCode::OK);
+
+    wait_for_update_count(&notion, 1).await;
+    tokio::time::sleep(Duration::from_millis(50)).await;
+    assert_eq!(notion.updates.lock().unwrap().len(), 1);
+}
+
+#[tokio::test]
+async fn page_created_without_suffix_is_ignored() {
+    let (app, notion) = app_with_mocks(
+        make_page("Movie Title", "Movie", None),
+        FakeTmdb {
+            movie: tmdb_movie(),
+            tv: tmdb_tv(),
+        },
+    );
+
+    let res = app
+        .oneshot(signed_request(page_created_payload("page-1")))
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::OK);
+    assert_no_updates(&notion).await;
+}
+
+async fn get_json(app: &Router, uri: &str) -> (StatusCode, Value) {
+    let res = app
+        .clone()
+        .oneshot(Request::get(uri).body(Body::empty()).unwrap())
+        .await
+        .unwrap();
+    let status = res.status();
+    let body = axum::body::to_bytes(res.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    (status, serde_json::from_slice(&body).unwrap())
+}
+
+#[tokio::test]
+async fn readiness_reports_ok_when_dependencies_respond() {
+    let (app, _notion) = app_with_mocks(
+        make_page("Movie Title", "Movie", None),
+        FakeTmdb {
+            movie: tmdb_movie(),
+            tv: tmdb_tv(),
+        },
+    );
+    let (status, body) = get_json(&app, "/health/ready").await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(
+        body,
+        json!({ "notion": "ok", "tmdb": "ok", "anilist": "ok" })
+    );
+}
+
+#[tokio::test]
+async fn readiness_fails_and_is_cached_when_notion_is_down() {
+    let (app, notion) = app_with_options(
+        vec![make_page("Movie Title", "Movie", None)],
+        FakeTmdb {
+            movie: tmdb_movie(),
+            tv: tmdb_tv(),
+        },
+        AppOptions {
+            notion_schema_error: Some("401 Unauthorized"),
+            ..Default::default()
+        },
+    );
+    let (status, body) = get_json(&app, "/health/ready").await;
+    assert_eq!(status, StatusCode::SERVICE_UNAVAILABLE);
+    assert_eq!(body["notion"], json!("error: 401 Unauthorized"));
+    assert_eq!(body["tmdb"], json!("ok"));
+
+    let (status, _) = get_json(&app, "/health/ready").await;
+    assert_eq!(status, StatusCode::SERVICE_UNAVAILABLE);
+    assert_eq!(
+        notion
+            .schema_fetches
+            .load(std::sync::atomic::Ordering::SeqCst),
+        1
+    );
+}
+
+#[tokio::test]
+async fn deep_health_checks_every_backend_on_each_request() {
+    let (app, _) = app_with_mocks(
+        make_page("Movie Title", "Movie", None),
+        FakeTmdb {
+            movie: tmdb_movie(),
+            tv: tmdb_tv(),
+        },
+    );
+    let (status, body) = get_json(&app, "/health/deep").await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(
+        body,
+        json!({ "notion": "ok", "tmdb": "ok", "anilist": "ok" })
+    );
+
+    let (app, notion) = app_with_options(
+        vec![make_page("Movie Title", "Movie", None)],
+        FakeTmdb {
+            movie: tmdb_movie(),
+            tv: tmdb_tv(),
+        },
+        AppOptions {
+            notion_schema_error: Some("401 Unauthorized"),
+            ..Default::default()
+        },
+    );
+    for _ in 0..2 {
+        let (status, body) = get_json(&app, "/health/deep").await;
+        assert_eq!(status, StatusCode::SERVICE_UNAVAILABLE);
+        assert_eq!(body["notion"], json!("error: 401 Unauthorized"));
+        assert_eq!(body["tmdb"], json!("ok"));
+        assert_eq!(body["failed"], json!(["notion"]));
+    }
+    assert_eq!(
+        notion
+            .schema_fetches
+            .load(std::sync::atomic::Ordering::SeqCst),
+        2
+    );
+}
+
+fn enriched_page(page_id: &str, title: &str, type_select: &str, id: i32, link: &str) -> Value {
+    let mut page = make_page(title, type_select, None);
+    page["id"] = json!(page_id);
+    page["properties"]["ID"] = json!({ "number": id });
+    page["properties"]["IMDb Page"] = json!({ "url": link });
+    page
+}
+
+#[tokio::test]
+async fn retriggering_an_enriched_page_is_skipped_unless_forced() {
+    let link = "https://imdb.com/title/tt123";
+    let (app, notion) = app_with_pages(
+        vec![
+            enriched_page("page-1", "Movie Title ;", "Movie", 7, link),
+            enriched_page("page-2", "Movie Title ;;", "Movie", 7, link),
+        ],
+        FakeTmdb {
+            movie: tmdb_movie(),
+            tv: tmdb_tv(),
+        },
+    );
+
+    let (status, body) = post_admin(
+        &app,
+        "/admin/process",
+        Some(ADMIN_KEY),
+        json!({ "page_id": "page-1" }),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["updated"], false);
+    assert!(notion.updates.lock().unwrap().is_empty());
+
+    // A doubled suffix re-enriches, searching without either copy of it.
+    let (_, body) = post_admin(
+        &app,
+        "/admin/process",
+        Some(ADMIN_KEY),
+        json!({ "page_id": "page-2" }),
+    )
+    .await;
+    assert_eq!(body["updated"], true);
+    assert_eq!(body["id"], 101);
+
+    let (_, body) = post_admin(
+        &app,
+        "/admin/process",
+        Some(ADMIN_KEY),
+        json!({ "page_id": "page-1", "force": true }),
+    )
+    .await;
+    assert_eq!(body["updated"], true);
+    let updates = notion.updates.lock().unwrap();
+    let ids: Vec<&str> = updates.iter().map(|u| u.0.as_str()).collect();
+    assert_eq!(ids, vec!["page-2", "page-1"]);
+}
+
+#[tokio::test]
+async fn failed_enrichments_are_posted_to_the_error_webhook() {
+    let alerts = wiremock::MockServer::start().await;
+    wiremock::Mock::given(wiremock::matchers::method("POST"))
+        .respond_with(wiremock::ResponseTemplate::new(204))
+        .mount(&alerts)
+        .await;
+    let (app, notion) = app_with_options(
+        vec![make_page("wip alert ;", "Movie", None)],
+        FakeTmdb {
+            movie: tmdb_movie(),
+            tv: tmdb_tv(),
+        },
+        AppOptions {
+            notion_fetch_failures: 1,
+            retry_max_attempts: 1,
+            error_webhook_url: Some(alerts.uri()),
+            ..AppOptions::default()
+        },
+    );
+
+    // The page can't be read the first time, then matches nothing.
+    for expected in [StatusCode::BAD_GATEWAY, StatusCode::OK] {
+        let (status, _) = post_admin(
+            &app,
+            "/admin/process",
+            Some(ADMIN_KEY),
+            json!({ "page_id": "page-1" }),
+        )
+        .await;
+        assert_eq!(status, expected);
+    }
+
+    let mut received = Vec::new();
+    for _ in 0..100 {
+        received = alerts.received_requests().await.unwrap();
+        if received.len() == 2 {
+            break;
+        }
+        tokio::time::sleep(Duration::from_millis(20)).await;
+    }
+    let mut bodies: Vec<Value> = received
+        .iter()
+        .map(|r| serde_json::from_slice(&r.body).unwrap())
+        .collect();
+    bodies.sort_by_key(|b| b["title"].is_null());
+    assert_eq!(bodies.len(), 2);
+    assert_eq!(bodies[0]["page_id"], "page-1");
+    assert_eq!(
+        bodies[0]["title"],
+        json!(format!(
+            "wip alert ; | {}",
+            bodies[0]["error"].as_str().unwrap()
+        ))
+    );
+    assert_eq!(notion.updates.lock().unwrap().len(), 1);
+    assert_eq!(bodies[1]["title"], Value::Null);
+    assert!(bodies[1]["error"].as_str().unwrap().contains("503"));
+}
+
+#[tokio::test]
+async fn webhook_jobs_alert_once_when_their_retries_run_out() {
+    for (failures, attempts, expected_alerts) in [(2, 3, 0), (3, 3, 1)] {
+        let alerts = wiremock::MockServer::start().await;
+        wiremock::Mock::given(wiremock::matchers::method("POST"))
+            .respond_with(wiremock::ResponseTemplate::new(204))
+            .mount(&alerts)
+            .await;
+        let (app, notion) = app_with_options(
+            vec![make_page("Movie Title ;", "Movie", None)],
+            FakeTmdb {
+                movie: tmdb_movie(),
+                tv: tmdb_tv(),
+            },
+            AppOptions {
+                notion_fetch_failures: failures,
+                retry_max_attempts: attempts,
+                error_webhook_url: Some(alerts.uri()),
+                ..AppOptions::default()
+            },
+        );
+
+        app.clone()
+            .oneshot(signed_request(webhook_payload(&["title"], "page-1")))
+            .await
+            .unwrap();
+        let deadline = tokio::time::Instant::now() + Duration::from_secs(2);
+        while !fetch_metrics(&app)
+            .await
+            .contains(&format!("cinelink_notion_errors_total {failures}\n"))
+        {
+            assert!(tokio::time::Instant::now() < deadline, "attempts never ran");
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+        tokio::time::sleep(Duration::from_millis(100)).await;
+
+        let received = alerts.received_requests().await.unwrap();
+        assert_eq!(received.len(), expected_alerts, "{failures} failures");
+        assert_eq!(
+            notion.updates.lock().unwrap().len(),
+            1 - expected_alerts,
+            "{failures} failures"
+        );
+    }
+}
+
+#[tokio::test]
+async fn fill_empty_keeps_existing_values_but_still_sets_title_and_id() {
+    let mut page = make_page("Movie Title ;", "Movie", None);
+    page["properties"]["Synopsis"] = json!({ "rich_text": [{ "plain_text": "My own notes" }] });
+    page["properties"]["Director"] = json!({ "rich_text": [] });
+    page["icon"] = json!({ "type": "emoji", "emoji": "🎬" });
+    let (app, notion) = app_with_options(
+        vec![page],
+        FakeTmdb {
+            movie: tmdb_movie(),
+            tv: tmdb_tv(),
+        },
+        AppOptions {
+            overwrite_mode: OverwriteMode::FillEmpty,
+            ..AppOptions::default()
+        },
+    );
+
+    let (_, body) = post_admin(
+        &app,
+        "/admin/process",
+        Some(ADMIN_KEY),
+        json!({ "page_id": "page-1" }),
+    )
+    .await;
+    assert_eq!(body["updated"], true);
+    let updates = notion.updates.lock().unwrap();
+    // The existing emoji icon stays; TMDB has no backdrop for a cover anyway.
+    let (_, props, icon, cover) = &updates[0];
+    assert!(!props.contains_key("Synopsis"));
+    assert!(props.contains_key("Director"));
+    assert!(props.contains_key("Name"));
+    assert_eq!(props["ID"], json!({ "number": 101.0 }));
+    assert_eq!(*icon, None);
+    assert_eq!(*cover, None);
+}
+
+#[tokio::test]
+async fn sync_status_properties_take_the_error_instead_of_the_title() {
+    let with_status = |title: &str| {
+        let mut page = make_page(title, "Movie", None);
+        page["properties"]["Sync Status"] = json!({ "type": "select", "select": null });
+        page["properties"]["Sync Error"] = json!({ "type": "rich_text", "rich_text": [] });
+        page
+    };
+    let mut ok_page = with_status("Movie Title ;");
+    ok_page["id"] = json!("page-2");
+    let (app, notion) = app_with_pages(
+        vec![with_status("wip status ;"), ok_page],
+        FakeTmdb {
+            movie: tmdb_movie(),
+            tv: tmdb_tv(),
+        },
+    );
+
+    for page_id in ["page-1", "page-2"] {
+        post_admin(
+            &app,
+            "/admin/process",
+            Some(ADMIN_KEY),
+            json!({ "page_id": page_id }),
+        )
+        .await;
+    }
+    let updates = notion.updates.lock().unwrap();
+    let (_, failed, _, _) = &updates[0];
+    assert_eq!(failed["Name"]["title"][0]["text"]["content"], "wip status");
+    assert_eq!(
+        failed["Sync Status"],
+        json!({ "select": { "name": "Error" } })
+    );
+    assert_eq!(
+        failed["Sync Error"]["rich_text"][0]["text"]["content"],
+        "No TMDB movie match"
+    );
+    let (_, matched, _, _) = &updates[1];
+    assert_eq!(
+        matched["Sync Status"],
+        json!({ "select": { "name": "OK" } })
+    );
+    assert_eq!(matched["Sync Error"], json!({ "rich_text": [] }));
+}
+
+#[tokio::test]
+async fn multi_select_cast_names_are_sanitized_for_notion() {
+    let movie = MediaData {
+        cast: vec![
+            "Monkey D., Luffy".to_string(),
+            "monkey d. luffy".to_string(),
+            " ".to_string(),
+            "Nami".to_string(),
+        ],
+        ..tmdb_movie()
+    };
+    let (state, notion) = state_with_options(
+        vec![make_page("Movie Title ;", "Movie", None)],
+        FakeTmdb {
+            movie,
+            tv: tmdb_tv(),
+        },
+        AppOptions::default(),
+    );
+    let mut schema = base_schema();
+    schema
+        .types
+        .insert("Cast".to_string(), PropertyType::MultiSelect);
+    state.schema.replace(schema);
+    let app = build_router(state);
+
+    let (_, body) = post_admin(
+        &app,
+        "/admin/process",
+        Some(ADMIN_KEY),
+        json!({ "page_id": "page-1" }),
+    )
+    .await;
+    assert_eq!(body["updated"], true);
+    let updates = notion.updates.lock().unwrap();
+    assert_eq!(
+        updates[0].1["Cast"],
+        json!({ "multi_select": [{ "name": "Monkey D. Luffy" }, { "name": "Nami" }] })
+    );
+}
+
+#[tokio::test]
+async fn people_properties_are_left_out_of_the_update() {
+    let (state, notion) = state_with_options(
+        vec![make_page("Movie Title ;", "Movie", None)],
+        FakeTmdb {
+            movie: tmdb_movie(),
+            tv: tmdb_tv(),
+        },
+        AppOptions::default(),
+    );
+    let mut schema = base_schema();
+    schema
+        .types
+        .insert("Director".to_string(), PropertyType::People);
+    state.schema.replace(schema);
+    let app = build_router(state);
+
+    let (_, body) = post_admin(
+        &app,
+        "/admin/process",
+        Some(ADMIN_KEY),
+        json!({ "page_id": "page-1" }),
+    )
+    .await;
+    assert_eq!(body["updated"], true);
+    let updates = notion.updates.lock().unwrap();
+    assert!(!updates[0].1.contains_key("Director"));
+    assert!(!updates[0].1.contains_key("Adult"));
+    assert_eq!(
+        updates[0].1["Cast"]["rich_text"][0]["text"]["content"],
+        "Actor A"
+    );
+}
+
+#[tokio::test]
+async fn missing_trailer_clears_a_stale_one_unless_filling_empty_properties() {
+    for (mode, expected) in [
+        (OverwriteMode::Always, Some(json!({ "url": null }))),
+        (OverwriteMode::FillEmpty, None),
+    ] {
+        // The manga fixture has no trailer; the page still has one from an earlier match.
+        let mut page = make_page("Manga Query~", "Manga", None);
+        page["properties"]["Trailer"] = json!({ "type": "url", "url": "https://youtube.com/old" });
+        let (app, notion) = app_with_options(
+            vec![page],
+            FakeTmdb {
+                movie: tmdb_movie(),
+                tv: tmdb_tv(),
+            },
+            AppOptions {
+                overwrite_mode: mode,
+                ..AppOptions::default()
+            },
+        );
+
+        let (_, body) = post_admin(
+            &app,
+            "/admin/process",
+            Some(ADMIN_KEY),
+            json!({ "page_id": "page-1" }),
+        )
+        .await;
+        assert_eq!(body["updated"], true);
+        let updates = notion.updates.lock().unwrap();
+        assert_eq!(updates[0].1.get("Trailer").cloned(), expected, "{mode:?}");
+    }
+}
+
+#[tokio::test]
+async fn optional_values_tmdb_no_longer_has_are_cleared() {
+    let movie = MediaData {
+        content_rating: None,
+        language: None,
+        year: None,
+        runtime_minutes: None,
+        poster: None,
+        vote_average: None,
+        ..tmdb_movie()
+    };
+    let (app, notion) = app_with_mocks(
+        make_page("Movie Title ;", "Movie", None),
+        FakeTmdb {
+            movie,
+            tv: tmdb_tv(),
+        },
+    );
+    let res = app
+        .oneshot(signed_request(webhook_payload(&["title"], "page-1")))
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::OK);
+    wait_for_update_count(&notion, 1).await;
+
+    let updates = notion.updates.lock().unwrap();
+    let props = &updates[0].1;
+    assert_eq!(props["Content Rating"], json!({ "select": null }));
+    assert_eq!(props["Language"], json!({ "select": null }));
+    assert_eq!(props["Year"], json!({ "rich_text": [] }));
+    assert_eq!(props["Runtime"], json!({ "number": null }));
+    assert_eq!(props["IMG"], json!({ "files": [] }));
+    assert_eq!(props["Score"], json!({ "number": null }));
+}
+
+#[tokio::test]
+async fn multi_select_country_of_origin_gets_one_option_per_country() {
+    let movie = MediaData {
+        country_of_origin: cinelink::countries::country_names(&["US", "GB", "KR"]),
+        ..tmdb_movie()
+    };
+    let (state, notion) = state_with_options(
+        vec![make_page("Movie Title ;", "Movie", None)],
+        FakeTmdb {
+            movie,
+            tv: tmdb_tv(),
+        },
+        AppOptions::default(),
+    );
+    let mut schema = base_schema();
+    schema
+        .types
+        .insert("Country of origin".to_string(), PropertyType::MultiSelect);
+    state.schema.replace(schema);
+    let app = build_router(state);
+
+    post_admin(
+        &app,
+        "/admin/process",
+        Some(ADMIN_KEY),
+        json!({ "page_id": "page-1" }),
+    )
+    .await;
+    let updates = notion.updates.lock().unwrap();
+    assert_eq!(
+        updates[0].1["Country of origin"],
+        json!({ "multi_select": [
+            { "name": "United States" },
+            { "name": "United Kingdom" },
+            { "name": "South Korea" }
+        ] })
+    );
+}
+
+#[tokio::test]
+async fn movie_collection_is_written_only_when_the_database_has_the_property() {
+    let in_collection = MediaData {
+        collection: Some("The Lord of the Rings Collection".to_string()),
+        ..tmdb_movie()
+    };
+    let cases = [
+        (
+            in_collection.clone(),
+            true,
+            Some(json!({ "select": { "name": "The Lord of the Rings Collection" } })),
+        ),
+        // A standalone movie clears whatever collection the page had.
+        (tmdb_movie(), true, Some(json!({ "select": null }))),
+        (in_collection, false, None),
+    ];
+    for (movie, with_property, expected) in cases {
+        let (state, notion) = state_with_options(
+            vec![make_page("Movie Title ;", "Movie", None)],
+            FakeTmdb {
+                movie,
+                tv: tmdb_tv(),
+            },
+            AppOptions::default(),
+        );
+        if !with_property {
+            let mut schema = base_schema();
+            schema.types.remove("Collection");
+            state.schema.replace(schema);
+        }
+        let app = build_router(state);
+
+        post_admin(
+            &app,
+            "/admin/process",
+            Some(ADMIN_KEY),
+            json!({ "page_id": "page-1" }),
+        )
+        .await;
+        let updates = notion.updates.lock().unwrap();
+        assert_eq!(updates[0].1.get("Collection").cloned(), expected);
+    }
+}
+
+#[tokio::test]
+async fn show_networks_are_written_when_the_page_has_the_property() {
+    let tv = MediaData {
+        networks: vec!["BBC Two".to_string(), "BBC Three".to_string()],
+        ..tmdb_tv()
+    };
+    let mut page = make_page("Show Title ;", "TV", Some("Season 1"));
+    page["properties"]["Network"] = json!({ "type": "multi_select", "multi_select": [] });
+    let (app, notion) = app_with_mocks(
+        page,
+        FakeTmdb {
+            movie: tmdb_movie(),
+            tv,
+        },
+    );
+
+    post_admin(
+        &app,
+        "/admin/process",
+        Some(ADMIN_KEY),
+        json!({ "page_id": "page-1" }),
+    )
+    .await;
+    let updates = notion.updates.lock().unwrap();
+    assert_eq!(
+        updates[0].1["Network"],
+        json!({ "multi_select": [{ "name": "BBC Two" }, { "name": "BBC Three" }] })
+    );
+}
+
+#[tokio::test]
+async fn keywords_are_capped_and_written_when_the_page_has_the_property() {
+    let movie = MediaData {
+        keywords: ["heist", "sequel", "time travel"]
+            .map(String::from)
+            .to_vec(),
+        ..tmdb_movie()
+    };
+    for (with_property, expected) in [
+        (
+            true,
+            Some(json!({ "multi_select": [{ "name": "heist" }, { "name": "sequel" }] })),
+        ),
+        (false, None),
+    ] {
+        let mut page = make_page("Movie Title ;", "Movie", None);
+        if with_property {
+            page["properties"]["Keywords"] = json!({ "type": "multi_select", "multi_select": [] });
+        }
+        let (state, notion) = state_with_options(
+            vec![page],
+            FakeTmdb {
+                movie: movie.clone(),
+                tv: tmdb_tv(),
+            },
+            AppOptions {
+                max_keywords: 2,
+                ..AppOptions::default()
+            },
+        );
+        let app = build_router(state);
+
+        post_admin(
+            &app,
+            "/admin/process",
+            Some(ADMIN_KEY),
+            json!({ "page_id": "page-1" }),
+        )
+        .await;
+        let updates = notion.updates.lock().unwrap();
+        assert_eq!(updates[0].1.get("Keywords").cloned(), expected);
+    }
+}
+
+#[tokio::test]
+async fn watch_providers_are_written_only_when_the_region_has_data() {
+    let available = MediaData {
+        watch_providers: vec!["Netflix".to_string(), "Hulu".to_string()],
+        watch_link: Some("https://www.themoviedb.org/movie/101/watch?locale=US".to_string()),
+        ..tmdb_movie()
+    };
+    for movie in [available, tmdb_movie()] {
+        let mut page = make_page("Movie Title ;", "Movie", None);
+        page["properties"]["Available on"] = json!({ "type": "multi_select", "multi_select": [] });
+        page["properties"]["Where to Watch"] = json!({ "type": "url", "url": null });
+        let (app, notion) = app_with_mocks(
+            page,
+            FakeTmdb {
+                movie: movie.clone(),
+                tv: tmdb_tv(),
+            },
+        );
+
+        post_admin(
+            &app,
+            "/admin/process",
+            Some(ADMIN_KEY),
+            json!({ "page_id": "page-1" }),
+        )
+        .await;
+        let updates = notion.updates.lock().unwrap();
+        let update = &updates[0].1;
+        if movie.watch_providers.is_empty() {
+            assert!(update.get("Available on").is_none());
+            assert!(update.get("Where to Watch").is_none());
+        } else {
+            assert_eq!(
+                update["Available on"],
+                json!({ "multi_select": [{ "name": "Netflix" }, { "name": "Hulu" }] })
+            );
+            assert_eq!(
+                update["Where to Watch"],
+                json!({ "url": "https://www.themoviedb.org/movie/101/watch?locale=US" })
+            );
+        }
+    }
+}
+
+#[tokio::test]
+async fn tmdb_ratings_are_rounded_and_skipped_without_votes() {
+    let rated = MediaData {
+        vote_average: Some(7.456),
+        ..tmdb_movie()
+    };
+    let unrated = MediaData {
+        vote_count: None,
+        ..tmdb_movie()
+    };
+    for (movie, rating, votes) in [
+        (
+            rated,
+            Some(json!({ "number": 7.5 })),
+            Some(json!({ "number": 1234.0 })),
+        ),
+        (unrated, None, None),
+    ] {
+        let mut page = make_page("Movie Title ;", "Movie", None);
+        page["properties"]["TMDB Rating"] = json!({ "type": "number", "number": null });
+        page["properties"]["TMDB Votes"] = json!({ "type": "number", "number": null });
+        let (app, notion) = app_with_mocks(
+            page,
+            FakeTmdb {
+                movie,
+                tv: tmdb_tv(),
+            },
+        );
+
+        post_admin(
+            &app,
+            "/admin/process",
+            Some(ADMIN_KEY),
+            json!({ "page_id": "page-1" }),
+        )
+        .await;
+        let updates = notion.updates.lock().unwrap();
+        assert_eq!(updates[0].1.get("TMDB Rating").cloned(), rating);
+        assert_eq!(updates[0].1.get("TMDB Votes").cloned(), votes);
+    }
+}
+
+#[tokio::test]
+async fn anilist_scores_prefer_their_own_property_over_the_tmdb_rating() {
+    for (property, expected) in [
+        ("AniList Score", json!({ "number": 81.0 })),
+        ("TMDB Rating", json!({ "number": 8.1 })),
+    ] {
+        let mut page = make_page("Ani Query=", "tv", Some("Season 2"));
+        page["properties"]["TMDB Rating"] = json!({ "type": "number", "number": null });
+        if property == "AniList Score" {
+            page["properties"]["AniList Score"] = json!({ "type": "number", "number": null });
+        }
+        let (app, notion) = app_with_mocks(
+            page,
+            FakeTmdb {
+                movie: tmdb_movie(),
+                tv: tmdb_tv(),
+            },
+        );
+
+        post_admin(
+            &app,
+            "/admin/process",
+            Some(ADMIN_KEY),
+            json!({ "page_id": "page-1" }),
+        )
+        .await;
+        let updates = notion.updates.lock().unwrap();
+        let props = &updates[0].1;
+        assert_eq!(props[property], expected);
+        if property == "AniList Score" {
+            assert!(props.get("TMDB Rating").is_none());
+        }
+    }
+}
+
+#[tokio::test]
+async fn comments_when_another_page_has_the_same_tmdb_id() {
+    let movie = tmdb_movie();
+    let existing = enriched_page(
+        "page-2",
+        "Existing Movie",
+        "Movie",
+        movie.id,
+        "https://imdb.com/title/tt123",
+    );
+    let (app, notion) = app_with_pages(
+        vec![make_page("Movie Title ;", "Movie", None), existing],
+        FakeTmdb {
+            movie,
+            tv: tmdb_tv(),
+        },
+    );
+
+    let payload = webhook_payload(&["title"], "page-1");
+    let res = app.oneshot(signed_request(payload)).await.unwrap();
+    assert_eq!(res.status(), StatusCode::OK);
+
+    wait_for_update_count(&notion, 1).await;
+    let deadline = tokio::time::Instant::now() + Duration::from_secs(2);
+    while notion.comments.lock().unwrap().is_empty() {
+        assert!(
+            tokio::time::Instant::now() < deadline,
+            "timed out waiting for duplicate comment"
+        );
+        tokio::task::yield_now().await;
+    }
+    let comments = notion.comments.lock().unwrap();
+    assert_eq!(comments.len(), 1);
+    assert_eq!(comments[0].0, "page-1");
+    assert!(comments[0].1.contains("Existing Movie"));
+}
+
+#[tokio::test]
+async fn same_id_from_a_different_provider_is_not_a_duplicate() {
+    let movie = tmdb_movie();
+    // An AniList page that happens to share the numeric id.
+    let existing = enriched_page(
+        "page-2",
+        "Some Anime",
+        "TV",
+        movie.id,
+        "https://anilist.co/anime/101",
+    );
+    let (app, notion) = app_with_pages(
+        vec![make_page("Movie Title ;", "Movie", None), existing],
+        FakeTmdb {
+            movie,
+            tv: tmdb_tv(),
+        },
+    );
+
+    let payload = webhook_payload(&["title"], "page-1");
+    let res = app.oneshot(signed_request(payload)).await.unwrap();
+    assert_eq!(res.status(), StatusCode::OK);
+
+    wait_for_update_count(&notion, 1).await;
+    tokio::time::sleep(Duration::from_millis(50)).await;
+    assert!(notion.comments.lock().unwrap().is_empty());
+}
+
+/// `req` as if it arrived over a connection from `ip`.
+fn from_peer(mut req: Request<Body>, ip: &str) -> Request<Body> {
+    let addr = SocketAddr::new(ip.parse().unwrap(), 40_000);
+    req.extensions_mut().insert(ConnectInfo(addr));
+    req
+}
+
+fn unsigned_request_from(ip: &str) -> Request<Body> {
+    let req = Request::post("/")
+        .header("content-type", "application/json")
+        .body(Body::from("{}"))
+        .expect("failed to build request");
+    from_peer(req, ip)
+}
+
+#[tokio::test]
+async fn webhooks_from_outside_the_allowlist_are_forbidden() {
+    let (app, notion) = app_with_options(
+        vec![make_page("Movie Title ;", "Movie", None)],
+        FakeTmdb {
+            movie: tmdb_movie(),
+            tv: tmdb_tv(),
+        },
+        AppOptions {
+            webhook_allowed_cidrs: vec![
+                "10.0.0.0/8".parse().unwrap(),
+                "2001:db8::/32".parse().unwrap(),
+            ],
+            ..AppOptions::default()
+        },
+    );
+    let from = |ip: Option<&str>, updated: &str| {
+        let req = signed_request(webhook_payload(&[updated], "page-1"));
+        match ip {
+            Some(ip) => from_peer(req, ip),
+            None => req,
+        }
+    };
+
+    // Rejected before the signature or body is looked at.
+    for ip in ["203.0.113.5", "2001:db9::1"] {
+        let res = app
+            .clone()
+            .oneshot(unsigned_request_from(ip))
+            .await
+            .unwrap();
+        assert_eq!(res.status(), StatusCode::FORBIDDEN, "{ip}");
+    }
+    let res = app.clone().oneshot(from(None, "title")).await.unwrap();
+    assert_eq!(res.status(), StatusCode::FORBIDDEN);
+    assert_no_updates(&notion).await;
+
+    let res = app
+        .clone()
+        .oneshot(from(Some("10.20.30.40"), "title"))
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::OK);
+    wait_for_update_count(&notion, 1).await;
+
+    let res = app
+        .clone()
+        .oneshot(from(Some("2001:db8::7"), "Title"))
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::OK);
+
+    let text = fetch_metrics(&app).await;
+    assert!(text.contains("cinelink_webhooks_rejected_total{reason=\"ip_allowlist\"} 3\n"));
+    assert!(text.contains("cinelink_webhooks_rejected_total{reason=\"signature\"} 0\n"));
+}
+
+#[tokio::test]
+async fn forwarding_headers_only_count_from_trusted_proxies() {
+    let (app, notion) = app_with_options(
+        vec![make_page("Movie Title ;", "Movie", None)],
+        FakeTmdb {
+            movie: tmdb_movie(),
+            tv: tmdb_tv(),
+        },
+        AppOptions {
+            webhook_allowed_cidrs: vec!["10.0.0.0/8".parse().unwrap()],
+            trusted_proxies: vec!["192.0.2.1/32".parse().unwrap()],
+            ..AppOptions::default()
+        },
+    );
+    let forwarded = |peer: &str, header: &'static str| {
+        let mut req = signed_request(webhook_payload(&["title"], "page-1"));
+        req.headers_mut()
+            .insert(header, "10.0.0.1".parse().unwrap());
+        from_peer(req, peer)
+    };
+
+    // A client claiming an allowed address is still judged by its own.
+    for header in ["cf-connecting-ip", "x-real-ip", "x-forwarded-for"] {
+        let res = app
+            .clone()
+            .oneshot(forwarded("203.0.113.5", header))
+            .await
+            .unwrap();
+        assert_eq!(res.status(), StatusCode::FORBIDDEN, "{header}");
+    }
+    // The proxy itself is not allowed, but the client it names is.
+    let res = app
+        .clone()
+        .oneshot(from_peer(
+            signed_request(webhook_payload(&["title"], "page-1")),
+            "192.0.2.1",
+        ))
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::FORBIDDEN);
+    assert_no_updates(&notion).await;
+
+    let res = app
+        .clone()
+        .oneshot(forwarded("192.0.2.1", "x-forwarded-for"))
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::OK);
+    wait_for_update_count(&notion, 1).await;
+}
+
+fn rate_limit_header(res: &axum::response::Response, name: &str) -> Option<u64> {
+    res.headers()
+        .get(name)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.parse().ok())
+}
+
+async fn assert_rate_limited(res: axum::response::Response, expected_limit: &str) {
+    assert_eq!(res.status(), StatusCode::TOO_MANY_REQUESTS);
+    let retry_after: u64 = res
+        .headers()
+        .get("retry-after")
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.parse().ok())
+        .expect("retry-after header");
+    assert!((1..=60).contains(&retry_after));
+    let body = axum::body::to_bytes(res.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    let json: Value = serde_json::from_slice(&body).unwrap();
+    assert_eq!(
+        json.get("limit").and_then(|v| v.as_str()),
+        Some(expected_limit)
+    );
+    assert_eq!(
+        json.get("retry_after_secs").and_then(|v| v.as_u64()),
+        Some(retry_after)
+    );
+}
+
+#[tokio::test]
+async fn per_ip_rate_limit_returns_retry_after() {
+    let page = make_page("Movie Title", "Movie", None);
+    let (app, _notion) = app_with_mocks(
+        page,
+        FakeTmdb {
+            movie: tmdb_movie(),
+            tv: tmdb_tv(),
+        },
+    );
+
+    // 60/min plus a burst of 10 are allowed.
+    for sent in 1..=70 {
+        let res = app
+            .clone()
+            .oneshot(unsigned_request_from("10.0.0.1"))
+            .await
+            .unwrap();
+        assert_ne!(res.status(), StatusCode::TOO_MANY_REQUESTS);
+        assert_eq!(rate_limit_header(&res, "x-ratelimit-limit"), Some(70));
+        assert_eq!(
+            rate_limit_header(&res, "x-ratelimit-remaining"),
+            Some(70 - sent)
+        );
+    }
+    let res = app
+        .clone()
+        .oneshot(unsigned_request_from("10.0.0.1"))
+        .await
+        .unwrap();
+    assert_eq!(rate_limit_header(&res, "x-ratelimit-remaining"), Some(0));
+    assert_rate_limited(res, "per_ip").await;
+
+    // Other clients are unaffected by the per-IP limiter.
+    let res = app
+        .oneshot(unsigned_request_from("10.0.0.2"))
+        .await
+        .unwrap();
+    assert_ne!(res.status(), StatusCode::TOO_MANY_REQUESTS);
+}
+
+#[tokio::test]
+async fn global_rate_limit_returns_retry_after() {
+    let page = make_page("Movie Title", "Movie", None);
+    let (app, _notion) = app_with_mocks(
+        page,
+        FakeTmdb {
+            movie: tmdb_movie(),
+            tv: tmdb_tv(),
+        },
+    );
+
+    // 200/min plus a burst of 20 are allowed across all clients.
+    for i in 0..220 {
+        let ip = format!("10.1.{}.{}", i / 200, i % 200);
+        let res = app
+            .clone()
+            .oneshot(unsigned_request_from(&ip))
+            .await
+            .unwrap();
+        assert_ne!(res.status(), StatusCode::TOO_MANY_REQUESTS);
+    }
+    let res = app
+        .clone()
+        .oneshot(unsigned_request_from("10.2.0.1"))
+        .await
+        .unwrap();
+    assert_rate_limited(res, "global").await;
+
+    // Admin lookups share the global bucket but are counted apart from webhooks.
+    let (status, _) = post_admin(
+        &app,
+        "/search",
+        Some(ADMIN_KEY),
+        json!({ "query": "Movie Title", "source": "tmdb_movie" }),
+    )
+    .await;
+    assert_eq!(status, StatusCode::TOO_MANY_REQUESTS);
+    let text = fetch_metrics(&app).await;
+    assert!(text.contains("cinelink_webhooks_rejected_total{reason=\"rate_limit\"} 1\n"));
+    assert!(text.contains("cinelink_admin_rate_limited_total 1\n"));
+}
+
+async fn admin_replay(app: &Router, token: Option<&str>, body: Value) -> (StatusCode, Value) {
+    post_admin(app, "/admin/replay", token, body).await
+}
+
+async fn post_admin(
+    app: &Router,
+    uri: &str,
+    token: Option<&str>,
+    body: Value,
+) -> (StatusCode, Value) {
+    let mut req = Request::post(uri).header("content-type", "application/json");
+    if let Some(token) = token {
+        req = req.header("authorization", format!("Bearer {token}"));
+    }
+    let res = app
+        .clone()
+        .oneshot(req.body(Body::from(body.to_string())).unwrap())
+        .await
+        .unwrap();
+    let status = res.status();
+    let body = axum::body::to_bytes(res.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    (status, serde_json::from_slice(&body).unwrap_or(Value::Null))
+}
+
+#[tokio::test]
+async fn every_management_route_requires_the_admin_key() {
+    let (app, _notion) = app_with_mocks(
+        make_page("Movie Title ;", "Movie", None),
+        FakeTmdb {
+            movie: tmdb_movie(),
+            tv: tmdb_tv(),
+        },
+    );
+    let routes = [
+        ("GET", "/verification"),
+        ("POST", "/enrich"),
+        ("POST", "/search"),
+        ("POST", "/admin/replay"),
+        ("POST", "/admin/process"),
+        ("POST", "/admin/reload-schema"),
+        ("POST", "/admin/backfill"),
+        ("GET", "/admin/backfill/status"),
+    ];
+    for (method, uri) in routes {
+        for token in [None, Some("wrong")] {
+            let mut req = Request::builder().method(method).uri(uri);
+            if let Some(token) = token {
+                req = req.header("authorization", format!("Bearer {token}"));
+            }
+            // The key is checked before the (invalid) body is read.
+            let req = req.body(Body::from("not json")).unwrap();
+            let res = app.clone().oneshot(req).await.unwrap();
+            assert_eq!(res.status(), StatusCode::UNAUTHORIZED, "{method} {uri}");
+        }
+    }
+
+    for uri in ["/health", "/health/deep"] {
+        let req = Request::get(uri).body(Body::empty()).unwrap();
+        let res = app.clone().oneshot(req).await.unwrap();
+        assert_eq!(res.status(), StatusCode::OK, "{uri}");
+    }
+    let payload = webhook_payload(&["title"], "page-1");
+    let res = app.clone().oneshot(signed_request(payload)).await.unwrap();
+    assert_eq!(res.status(), StatusCode::OK);
+}
+
+async fn get_admin(app: &Router, uri: &str) -> (StatusCode, Value) {
+    let req = Request::get(uri).header("authorization", format!("Bearer {ADMIN_KEY}"));
+    let res = app
+        .clone()
+        .oneshot(req.body(Body::empty()).unwrap())
+        .await
+        .unwrap();
+    let status = res.status();
+    let body = axum::body::to_bytes(res.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    (status, serde_json::from_slice(&body).unwrap_or(Value::Null))
+}
+
+#[tokio::test]
+async fn replays_a_failed_page_by_failure_id() {
+    let (app, notion) = app_with_pages(
+        Vec::new(),
+        FakeTmdb {
+            movie: tmdb_movie(),
+            tv: tmdb_tv(),
+        },
+    );
+
+    // The page isn't readable yet, so the first run fails and lands in the failure log.
+    let res = app
+        .clone()
+        .oneshot(signed_request(webhook_payload(&["title"], "page-1")))
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::OK);
+    let deadline = tokio::time::Instant::now() + Duration::from_secs(2);
+    while !fetch_metrics(&app)
+        .await
+        .contains("cinelink_notion_errors_total 1\n")
+    {
+        assert!(
+            tokio::time::Instant::now() < deadline,
+            "page job never failed"
+        );
+        tokio::task::yield_now().await;
+    }
+
+    notion.pages.lock().unwrap().insert(
+        "page-1".to_string(),
+        make_page("Movie Title ;", "Movie", None),
+    );
+    let (status, body) = loop {
+        let (status, body) = admin_replay(&app, Some(ADMIN_KEY), json!({ "failure_id": 1 })).await;
+        if status != StatusCode::NOT_FOUND {
+            break (status, body);
+        }
+        assert!(
+            tokio::time::Instant::now() < deadline,
+            "failure never recorded"
+        );
+        tokio::task::yield_now().await;
+    };
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body, json!({ "page_id": "page-1", "updated": true }));
+    assert_eq!(notion.updates.lock().unwrap().len(), 1);
+}
+
+#[tokio::test]
+async fn replay_rejects_bad_tokens_and_path_traversal() {
+    let (app, notion) = app_with_options(
+        vec![make_page("Movie Title ;", "Movie", None)],
+        FakeTmdb {
+            movie: tmdb_movie(),
+            tv: tmdb_tv(),
+        },
+        AppOptions {
+            capture_dir: Some(std::env::temp_dir()),
+            ..Default::default()
+        },
+    );
+
+    let (status, _) = admin_replay(&app, None, json!({ "failure_id": 1 })).await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+    let (status, _) = admin_replay(&app, Some("wrong"), json!({ "failure_id": 1 })).await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+
+    for name in ["../etc/passwd", "/etc/passwd", ".hidden"] {
+        let (status, body) = admin_replay(&app, Some(ADMIN_KEY), json!({ "capture": name })).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST, "{name}");
+        assert_eq!(body["error"], "invalid capture file name");
+    }
+    let (status, _) = admin_replay(&app, Some(ADMIN_KEY), json!({ "failure_id": 42 })).await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+    assert_no_updates(&notion).await;
+}
+
+fn retry_app(notion_fetch_failures: usize, retry_max_attempts: u32) -> (Router, Arc<FakeNotion>) {
+    app_with_options(
+        vec![make_page("Movie Title ;", "Movie", None)],
+        FakeTmdb {
+            movie: tmdb_movie(),
+            tv: tmdb_tv(),
+        },
+        AppOptions {
+            notion_fetch_failures,
+            retry_max_attempts,
+            ..Default::default()
+        },
+    )
+}
+
+#[tokio::test]
+async fn transient_failures_are_retried_until_the_page_updates() {
+    let (app, notion) = retry_app(2, 4);
+
+    let res = app
+        .clone()
+        .oneshot(signed_request(webhook_payload(&["title"], "page-1")))
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::OK);
+
+    wait_for_update_count(&notion, 1).await;
+    let text = fetch_metrics(&app).await;
+    assert!(text.contains("cinelink_notion_errors_total 2\n"));
+    assert!(text.contains("cinelink_retry_queue_depth 0\n"));
+}
+
+#[tokio::test]
+async fn tmdb_outages_are_retried_instead_of_marking_the_title() {
+    let movie = MediaData {
+        id: FLAKY_MOVIE_ID,
+        ..tmdb_movie()
+    };
+    let (app, notion) = app_with_mocks(
+        make_page("Movie Title ;", "Movie", None),
+        FakeTmdb {
+            movie,
+            tv: tmdb_tv(),
+        },
+    );
+
+    app.clone()
+        .oneshot(signed_request(webhook_payload(&["title"], "page-1")))
+        .await
+        .unwrap();
+
+    wait_for_update_count(&notion, 1).await;
+    let updates = notion.updates.lock().unwrap();
+    assert_eq!(updates.len(), 1);
+    assert_eq!(
+        updates[0].1["Name"]["title"][0]["text"]["content"],
+        "TMDB Movie"
+    );
+    assert!(FLAKY_MOVIE_FAILED.load(Ordering::SeqCst));
+}
+
+#[tokio::test]
+async fn retries_stop_at_the_max_attempt_count() {
+    let (app, notion) = retry_app(2, 2);
+
+    app.clone()
+        .oneshot(signed_request(webhook_payload(&["title"], "page-1")))
+        .await
+        .unwrap();
+
+    tokio::time::sleep(Duration::from_millis(150)).await;
+    assert!(notion.updates.lock().unwrap().is_empty());
+    assert!(fetch_metrics(&app)
+        .await
+        .contains("cinelink_notion_errors_total 2\n"));
+}
+
+#[tokio::test]
+async fn retries_wait_for_a_job_already_running_on_the_page() {
+    let (app, notion) = app_with_options(
+        vec![make_page("Movie Title ;", "Movie", None)],
+        FakeTmdb {
+            movie: tmdb_movie(),
+            tv: tmdb_tv(),
+        },
+        AppOptions {
+            notion_fetch_failures: 1,
+            notion_fetch_delay: Duration::from_millis(100),
+            retry_base_delay: Duration::from_millis(200),
+            ..Default::default()
+        },
+    );
+
+    // The first job fails at 100ms, so its retry is due at 300ms; a fresh event's job runs
+    // from 250ms to 350ms and covers the retry.
+    app.clone()
+        .oneshot(signed_request(webhook_payload(&["title"], "page-1")))
+        .await
+        .unwrap();
+    tokio::time::sleep(Duration::from_millis(250)).await;
+    app.clone()
+        .oneshot(signed_request(webhook_payload(&["Season"], "page-1")))
+        .await
+        .unwrap();
+
+    wait_for_update_count(&notion, 1).await;
+    tokio::time::sleep(Duration::from_millis(300)).await;
+    assert_eq!(notion.updates.lock().unwrap().len(), 1);
+}
+
+#[tokio::test]
+async fn permanent_failures_are_not_retried() {
+    let (app, notion) = app_with_pages(
+        Vec::new(),
+        FakeTmdb {
+            movie: tmdb_movie(),
+            tv: tmdb_tv(),
+        },
+    );
+
+    app.clone()
+        .oneshot(signed_request(webhook_payload(&["title"], "page-1")))
+        .await
+        .unwrap();
+    let deadline = tokio::time::Instant::now() + Duration::from_secs(2);
+    while !fetch_metrics(&app)
+        .await
+        .contains("cinelink_notion_errors_total 1\n")
+    {
+        assert!(
+            tokio::time::Instant::now() < deadline,
+            "page job never failed"
+        );
+        tokio::task::yield_now().await;
+    }
+
+    // A retry would now succeed, so any update means the missing page was retried.
+    notion.pages.lock().unwrap().insert(
+        "page-1".to_string(),
+        make_page("Movie Title ;", "Movie", None),
+    );
+    tokio::time::sleep(Duration::from_millis(100)).await;
+    assert!(notion.updates.lock().unwrap().is_empty());
+}
+
+#[tokio::test]
+async fn dedupe_survives_a_restart_with_a_store_file() {
+    let path = std::env::temp_dir().join(format!("cinelink-restart-{}.ndjson", std::process::id()));
+    let _ = std::fs::remove_file(&path);
+    let start = || {
+        app_with_options(
+            vec![make_page("Movie Title ;", "Movie", None)],
+            FakeTmdb {
+                movie: tmdb_movie(),
+                tv: tmdb_tv(),
+            },
+            AppOptions {
+                dedupe_store: Some(path.clone()),
+                ..Default::default()
+            },
+        )
+    };
+    let payload = webhook_payload(&["title"], "page-1");
+
+    let (app, notion) = start();
+    app.oneshot(signed_request(payload.clone())).await.unwrap();
+    wait_for_update_count(&notion, 1).await;
+    // The store is written in the background.
+    let deadline = tokio::time::Instant::now() + Duration::from_secs(2);
+    while !std::fs::read_to_string(&path).is_ok_and(|s| !s.is_empty()) {
+        assert!(
+            tokio::time::Instant::now() < deadline,
+            "store never written"
+        );
+        tokio::time::sleep(Duration::from_millis(5)).await;
+    }
+
+    // A fresh process sharing the store ignores Notion's redelivery of the same event.
+    let (app, notion) = start();
+    let res = app.oneshot(signed_request(payload)).await.unwrap();
+    assert_eq!(res.status(), StatusCode::OK);
+    assert_no_updates(&notion).await;
+    let _ = std::fs::remove_file(&path);
+}
+
+#[tokio::test]
+async fn verification_request_is_acknowledged_without_a_signature() {
+    let (app, notion) = app_with_options(
+        vec![make_page("Movie Title ;", "Movie", None)],
+        FakeTmdb {
+            movie: tmdb_movie(),
+            tv: tmdb_tv(),
+        },
+        AppOptions {
+            signing_secret: "",
+            ..AppOptions::default()
+        },
+    );
+    let (status, _) = get_admin(&app, "/verification").await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+
+    let verify = |token: &str| {
+        Request::post("/")
+            .header("content-type", "application/json")
+            .body(Body::from(
+                json!({ "verification_token": token }).to_string(),
+            ))
+            .unwrap()
+    };
+    let res = app
+        .clone()
+        .oneshot(verify("secret_tMrlL1qK5vuQAh1b6cZGhFChZTSYJlce98V0pYn7yBl"))
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::OK);
+    // A later token, from anyone, doesn't replace the first.
+    let res = app
+        .clone()
+        .oneshot(verify("secret_attacker_chosen_value_0000000000000000000"))
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::OK);
+    assert_no_updates(&notion).await;
+
+    let (status, body) = get_admin(&app, "/verification").await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(
+        body["verification_token"],
+        "secret_tMrlL1qK5vuQAh1b6cZGhFChZTSYJlce98V0pYn7yBl"
+    );
+    let res = app
+        .oneshot(Request::get("/verification").body(Body::empty()).unwrap())
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::UNAUTHORIZED);
+}
+
+#[tokio::test]
+async fn verification_tokens_are_ignored_once_a_secret_is_set() {
+    let (app, notion) = app_with_mocks(
+        make_page("Movie Title ;", "Movie", None),
+        FakeTmdb {
+            movie: tmdb_movie(),
+            tv: tmdb_tv(),
+        },
+    );
+
+    let body = json!({ "verification_token": "secret_attacker_chosen_value_0000000000000000000" });
+    let res = app
+        .clone()
+        .oneshot(
+            Request::post("/")
+                .header("content-type", "application/json")
+                .body(Body::from(body.to_string()))
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::OK);
+    assert_no_updates(&notion).await;
+
+    let (status, _) = get_admin(&app, "/verification").await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+    assert!(fetch_metrics(&app)
+        .await
+        .contains("cinelink_webhooks_rejected_total{reason=\"signature\"} 1\n"));
+}
+
+#[tokio::test]
+async fn enrich_updates_a_page_without_a_trigger_suffix() {
+    let (app, notion) = app_with_mocks(
+        make_page("Movie Title", "Movie", Some("Season 2")),
+        FakeTmdb {
+            movie: tmdb_movie(),
+            tv: tmdb_tv(),
+        },
+    );
+
+    let (status, body) = post_admin(
+        &app,
+        "/enrich",
+        Some(ADMIN_KEY),
+        json!({ "page_id": "page-1", "source": "tmdb" }),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body, json!({ "updated": true, "title": "TMDB Movie" }));
+    assert_eq!(notion.updates.lock().unwrap().len(), 1);
+
+    // `anilist` routes the same untriggered title to AniList instead.
+    let (status, body) = post_admin(
+        &app,
+        "/enrich",
+        Some(ADMIN_KEY),
+        json!({ "page_id": "page-1", "source": "anilist" }),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["updated"], true);
+    let updates = notion.updates.lock().unwrap();
+    let (_, props, _, _) = updates.last().unwrap();
+    assert_eq!(props["ID"]["number"], 176496.0);
+}
+
+#[tokio::test]
+async fn enrich_requires_the_admin_key_and_a_valid_request() {
+    let (app, notion) = app_with_mocks(
+        make_page("Movie Title", "Movie", None),
+        FakeTmdb {
+            movie: tmdb_movie(),
+            tv: tmdb_tv(),
+        },
+    );
+    let request = json!({ "page_id": "page-1" });
+    let (status, _) = post_admin(&app, "/enrich", None, request.clone()).await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+    let (status, _) = post_admin(&app, "/enrich", Some("wrong"), request).await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+
+    for bad in [
+        json!({}),
+        json!({ "page_id": "" }),
+        json!({ "page_id": "page-1", "source": "imdb" }),
+    ] {
+        let (status, body) = post_admin(&app, "/enrich", Some(ADMIN_KEY), bad).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert!(body["error"].is_string());
+    }
+    assert_no_updates(&notion).await;
+}
+
+#[tokio::test]
+async fn search_returns_mapped_media_without_writing_to_notion() {
+    let (app, notion) = app_with_mocks(
+        make_page("Movie Title", "Movie", None),
+        FakeTmdb {
+            movie: tmdb_movie(),
+            tv: tmdb_tv(),
+        },
+    );
+    let request = json!({ "query": "Movie Title", "source": "tmdb_movie" });
+    let (status, _) = post_admin(&app, "/search", None, request.clone()).await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+
+    let (status, body) = post_admin(&app, "/search", Some(ADMIN_KEY), request).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["id"], 101);
+    assert_eq!(body["name"], "TMDB Movie");
+    assert_eq!(body["vote_average"], 7.4);
+
+    let (status, body) = post_admin(
+        &app,
+        "/search",
+        Some(ADMIN_KEY),
+        json!({ "query": "tt99999", "source": "tmdb_tv", "season": 2 }),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["id"], 202);
+
+    let (status, body) = post_admin(
+        &app,
+        "/search",
+        Some(ADMIN_KEY),
+        json!({ "query": "Frieren", "source": "anilist_anime", "season": 2 }),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["id"], 176496);
+    assert_eq!(body["native_title"], "アニリスト");
+
+    // Queries are read like page titles: a year hint, a pasted URL or a prefixed id.
+    let (status, _) = post_admin(
+        &app,
+        "/search",
+        Some(ADMIN_KEY),
+        json!({ "query": "Search Title (2019)", "source": "tmdb_movie" }),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    assert!(MOVIE_YEAR_HINTS
+        .lock()
+        .unwrap()
+        .contains(&("Search Title".to_string(), Some(2019))));
+    for (query, source, id) in [
+        (
+            "https://www.themoviedb.org/movie/101-tmdb-movie",
+            "anilist_anime",
+            101,
+        ),
+        (
+            "https://www.themoviedb.org/tv/202/season/2",
+            "tmdb_movie",
+            202,
+        ),
+        ("anilist:176496", "tmdb_movie", 176496),
+    ] {
+        let (status, body) = post_admin(
+            &app,
+            "/search",
+            Some(ADMIN_KEY),
+            json!({ "query": query, "source": source, "season": 2 }),
+        )
+        .await;
+        assert_eq!(status, StatusCode::OK, "{query}: {body}");
+        assert_eq!(body["id"], id, "{query}");
+    }
+
+    let (status, body) = post_admin(
+        &app,
+        "/search",
+        Some(ADMIN_KEY),
+        json!({ "query": "wip search", "source": "tmdb_movie" }),
+    )
+    .await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+    assert!(body["error"].as_str().unwrap().contains("No TMDB movie"));
+
+    for bad in [
+        json!({ "source": "tmdb_movie" }),
+        json!({ "query": "Movie Title", "source": "tmdb" }),
+        json!({ "query": "Movie Title", "source": "tmdb_tv", "season": -1 }),
+    ] {
+        let (status, body) = post_admin(&app, "/search", Some(ADMIN_KEY), bad).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert!(body["error"].is_string());
+    }
+    assert_no_updates(&notion).await;
+}
+
+fn unsigned_request(body: String) -> Request<Body> {
+    Request::post("/")
+        .header("content-type", "application/json")
+        .header("Notion-Version", NOTION_VERSION)
+        .body(Body::from(body))
+        .expect("failed to build request")
+}
+
+#[tokio::test]
+async fn insecure_disabled_secret_accepts_unsigned_webhooks() {
+    let (app, notion) = app_with_options(
+        vec![make_page("Movie Title ;", "Movie", None)],
+        FakeTmdb {
+            movie: tmdb_movie(),
+            tv: tmdb_tv(),
+        },
+        AppOptions {
+            signing_secret: INSECURE_DISABLED_SECRET,
+            ..Default::default()
+        },
+    );
+
+    let res = app
+        .oneshot(unsigned_request(webhook_payload(&["title"], "page-1")))
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::OK);
+    assert_eq!(res.headers()[SIGNATURE_SKIPPED_HEADER], "skipped");
+    wait_for_update_count(&notion, 1).await;
+}
+
+#[tokio::test]
+async fn unsigned_webhooks_are_ignored_with_a_real_secret() {
+    let (app, notion) = app_with_mocks(
+        make_page("Movie Title ;", "Movie", None),
+        FakeTmdb {
+            movie: tmdb_movie(),
+            tv: tmdb_tv(),
+        },
+    );
+
+    let res = app
+        .oneshot(unsigned_request(webhook_payload(&["title"], "page-1")))
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::OK);
+    assert!(res.headers().get(SIGNATURE_SKIPPED_HEADER).is_none());
+    assert_no_updates(&notion).await;
+}
+
+fn language_payload(notion: &FakeNotion) -> Value {
+    let updates = notion.updates.lock().unwrap();
+    updates.last().unwrap().1["Language"].clone()
+}
+
+#[tokio::test]
+async fn schema_changes_apply_after_a_reload() {
+    let (app, notion) = app_with_mocks(
+        make_page("Movie Title ;", "Movie", None),
+        FakeTmdb {
+            movie: tmdb_movie(),
+            tv: tmdb_tv(),
+        },
+    );
+    let send = |id: &str| {
+        let payload = json!({
+            "id": id,
+            "timestamp": Utc::now().to_rfc3339(),
+            "type": "page.properties_updated",
+            "entity": { "id": "page-1", "type": "page" },
+            "data": { "updated_properties": ["title"] }
+        });
+        app.clone().oneshot(signed_request(payload.to_string()))
+    };
+    send("evt-1").await.unwrap();
+    wait_for_update_count(&notion, 1).await;
+    assert_eq!(
+        language_payload(&notion),
+        json!({ "select": { "name": "English" } })
+    );
+
+    // "Language" becomes a text property; the cached schema still says select.
+    notion
+        .schema
+        .lock()
+        .unwrap()
+        .types
+        .insert("Language".to_string(), PropertyType::RichText);
+    send("evt-2").await.unwrap();
+    wait_for_update_count(&notion, 2).await;
+    assert!(language_payload(&notion).get("select").is_some());
+
+    let (status, _) = post_admin(&app, "/admin/reload-schema", None, json!({})).await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+    let (status, body) = post_admin(&app, "/admin/reload-schema", Some(ADMIN_KEY), json!({})).await;
+    assert_eq!(status, StatusCode::OK);
+    assert!(body["properties"]
+        .as_array()
+        .unwrap()
+        .contains(&json!("Language")));
+    send("evt-3").await.unwrap();
+    wait_for_update_count(&notion, 3).await;
+    assert_eq!(
+        language_payload(&notion)["rich_text"][0]["text"]["content"],
+        "English"
+    );
+}
+
+#[tokio::test]
+async fn schema_updated_events_reload_the_schema() {
+    let (app, notion) = app_with_mocks(
+        make_page("Movie Title ;", "Movie", None),
+        FakeTmdb {
+            movie: tmdb_movie(),
+            tv: tmdb_tv(),
+        },
+    );
+    for (id, event_type) in [
+        ("evt-db", "database.schema_updated"),
+        ("evt-ds", "data_source.schema_updated"),
+    ] {
+        let payload = json!({
+            "id": id,
+            "timestamp": Utc::now().to_rfc3339(),
+            "type": event_type,
+            "entity": { "id": "db-1", "type": "database" },
+            "data": {}
+        });
+        let res = app
+            .clone()
+            .oneshot(signed_request(payload.to_string()))
+            .await
+            .unwrap();
+        assert_eq!(res.status(), StatusCode::OK);
+    }
+    let deadline = tokio::time::Instant::now() + Duration::from_secs(2);
+    while notion
+        .schema_fetches
+        .load(std::sync::atomic::Ordering::SeqCst)
+        < 2
+    {
+        assert!(
+            tokio::time::Instant::now() < deadline,
+            "schema was not reloaded"
+        );
+        tokio::task::yield_now().await;
+    }
+    assert_no_updates(&notion).await;
+}
+
+#[tokio::test]
+async fn admin_process_reports_the_matched_id() {
+    let (app, notion) = app_with_mocks(
+        make_page("Movie Title", "Movie", None),
+        FakeTmdb {
+            movie: tmdb_movie(),
+            tv: tmdb_tv(),
+        },
+    );
+
+    // Without `force` the untriggered title is left alone.
+    let (status, body) = post_admin(
+        &app,
+        "/admin/process",
+        Some(ADMIN_KEY),
+        json!({ "page_id": "page-1" }),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["updated"], false);
+    assert_eq!(body["id"], Value::Null);
+    assert!(notion.updates.lock().unwrap().is_empty());
+
+    let (status, body) = post_admin(
+        &app,
+        "/admin/process",
+        Some(ADMIN_KEY),
+        json!({ "page_id": "page-1", "force": true }),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(
+        body,
+        json!({
+            "page_id": "page-1",
+            "updated": true,
+            "title": "TMDB Movie",
+            "provider": "tmdb",
+            "id": 101,
+        })
+    );
+    assert_eq!(notion.updates.lock().unwrap().len(), 1);
+
+    let (status, _) = post_admin(
+        &app,
+        "/admin/process",
+        None,
+        json!({ "page_id": "page-1", "force": true }),
+    )
+    .await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+    let (status, _) = post_admin(
+        &app,
+        "/admin/process",
+        Some(ADMIN_KEY),
+        json!({ "page_id": "page-1", "force": "yes" }),
+    )
+    .await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+}
+
+async fn send_waiting(app: Router, body: String) -> (StatusCode, Value) {
+    let mut req = signed_request(body);
+    req.headers_mut()
+        .insert(WAIT_HEADER, "true".parse().unwrap());
+    let res = app.oneshot(req).await.unwrap();
+    let status = res.status();
+    let body = axum::body::to_bytes(res.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    (status, serde_json::from_slice(&body).unwrap_or(Value::Null))
+}
+
+#[tokio::test]
+async fn wait_header_returns_the_page_outcome() {
+    let (app, notion) = app_with_mocks(
+        make_page("Movie Title ;", "Movie", None),
+        FakeTmdb {
+            movie: tmdb_movie(),
+            tv: tmdb_tv(),
+        },
+    );
+    let (status, body) = send_waiting(app, webhook_payload(&["title"], "page-1")).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(
+        body,
+        json!({
+            "page_id": "page-1",
+            "updated": true,
+            "title": "TMDB Movie",
+            "provider": "tmdb",
+            "id": 101,
+        })
+    );
+    assert_eq!(notion.updates.lock().unwrap().len(), 1);
+}
+
+#[tokio::test]
+async fn wait_header_times_out_while_the_job_continues() {
+    let (app, notion) = app_with_options(
+        vec![make_page("Movie Title ;", "Movie", None)],
+        FakeTmdb {
+            movie: tmdb_movie(),
+            tv: tmdb_tv(),
+        },
+        AppOptions {
+            notion_fetch_delay: Duration::from_millis(1500),
+            sync_timeout_secs: 1,
+            ..Default::default()
+        },
+    );
+    let (status, body) = send_waiting(app, webhook_payload(&["title"], "page-1")).await;
+    assert_eq!(status, StatusCode::GATEWAY_TIMEOUT);
+    assert_eq!(body["page_id"], "page-1");
+    assert!(notion.updates.lock().unwrap().is_empty());
+    wait_for_update_count(&notion, 1).await;
+}
+
+#[tokio::test]
+async fn shutdown_drains_running_jobs_and_refuses_new_webhooks() {
+    let (state, notion) = state_with_options(
+        vec![make_page("Movie Title ;", "Movie", None)],
+        FakeTmdb {
+            movie: tmdb_movie(),
+            tv: tmdb_tv(),
+        },
+        AppOptions {
+            notion_fetch_delay: Duration::from_millis(300),
+            ..Default::default()
+        },
+    );
+    spawn_job_workers(&state);
+    let jobs = state.jobs.clone();
+    let app = build_router(state);
+    let res = app
+        .clone()
+        .oneshot(signed_request(webhook_payload(&["title"], "page-1")))
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::OK);
+    assert!(notion.updates.lock().unwrap().is_empty());
+
+    assert_eq!(drain_jobs(&jobs, Duration::from_secs(5)).await, (1, 0));
+    assert_eq!(notion.updates.lock().unwrap().len(), 1);
+
+    let res = app
+        .oneshot(signed_request(webhook_payload(&["season"], "page-1")))
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::SERVICE_UNAVAILABLE);
+}
+
+#[tokio::test]
+async fn webhooks_are_refused_while_the_job_queue_is_full() {
+    let (state, notion) = state_with_options(
+        vec![make_page("Movie Title ;", "Movie", None)],
+        FakeTmdb {
+            movie: tmdb_movie(),
+            tv: tmdb_tv(),
+        },
+        AppOptions {
+            job_queue_capacity: 1,
+            ..Default::default()
+        },
+    );
+    // No workers yet: the first job waits in the queue and fills it.
+    let app = build_router(state.clone());
+    let res = app
+        .clone()
+        .oneshot(signed_request(webhook_payload(&["title"], "page-1")))
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::OK);
+
+    let refused = || signed_request(webhook_payload(&["season"], "page-1"));
+    let res = app.clone().oneshot(refused()).await.unwrap();
+    assert_eq!(res.status(), StatusCode::SERVICE_UNAVAILABLE);
+    assert_eq!(rate_limit_header(&res, "retry-after"), Some(10));
+
+    // The refused event wasn't remembered, so its redelivery goes through once there's room.
+    spawn_job_workers(&state);
+    wait_for_update_count(&notion, 1).await;
+    let res = app.oneshot(refused()).await.unwrap();
+    assert_eq!(res.status(), StatusCode::OK);
+}
+
+#[tokio::test]
+async fn shutdown_abandons_jobs_that_outlast_the_grace_period() {
+    let (state, notion) = state_with_options(
+        vec![make_page("Movie Title ;", "Movie", None)],
+        FakeTmdb {
+            movie: tmdb_movie(),
+            tv: tmdb_tv(),
+        },
+        AppOptions {
+            notion_fetch_delay: Duration::from_secs(5),
+            ..Default::default()
+        },
+    );
+    spawn_job_workers(&state);
+    let jobs = state.jobs.clone();
+    let res = build_router(state)
+        .oneshot(signed_request(webhook_payload(&["title"], "page-1")))
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::OK);
+
+    assert_eq!(drain_jobs(&jobs, Duration::from_millis(50)).await, (0, 1));
+    assert!(notion.updates.lock().unwrap().is_empty());
+}
+
+#[tokio::test]
+async fn movie_backfill_can_skip_pages_that_are_already_enriched() {
+    let mut fresh = make_page("Arrival", "Movie", None);
+    fresh["id"] = json!("page-fresh");
+    let mut enriched = enriched_page("page-enriched", "Dune", "Movie", 1, "https://imdb/dune");
+    enriched["properties"]["Director"] = json!({ "rich_text": [{ "plain_text": "Someone" }] });
+    let no_director = enriched_page("page-no-director", "Heat", "Movie", 2, "https://imdb/heat");
+    let mut show = make_page("Show", "TV Series", Some("Season 1"));
+    show["id"] = json!("page-show");
+    let (state, notion) = state_with_options(
+        vec![fresh, enriched, no_director, show],
+        FakeTmdb {
+            movie: tmdb_movie(),
+            tv: tmdb_tv(),
+        },
+        AppOptions::default(),
+    );
+
+    let first_time = BackfillOptions {
+        only_incomplete: true,
+        ..BackfillOptions::default()
+    };
+    for (page_id, expected) in [
+        ("page-fresh", true),
+        ("page-enriched", false),
+        ("page-no-director", true),
+        ("page-show", false),
+    ] {
+        let outcome = process_page_backfill_movie(&state, page_id, &first_time)
+            .await
+            .unwrap();
+        assert_eq!(outcome.updated, expected, "{page_id}");
+    }
+    assert_eq!(notion.updates.lock().unwrap().len(), 2);
+
+    // A forced refresh updates enriched movies too; TV pages stay with the TV backfill.
+    let force = BackfillOptions::default();
+    let run_movie = |page_id| process_page_backfill_movie(&state, page_id, &force);
+    assert!(run_movie("page-enriched").await.unwrap().updated);
+    assert!(!run_movie("page-show").await.unwrap().updated);
+    assert!(
+        process_page_backfill_tv(&state, "page-show", &force)
+            .await
+            .unwrap()
+            .updated
+    );
+    assert_eq!(notion.updates.lock().unwrap().len(), 4);
+}
+
+#[tokio::test]
+async fn backfill_misses_are_counted_without_marking_the_title() {
+    let mut missing = make_page("wip draft", "Movie", None);
+    missing["id"] = json!("page-missing");
+    let mut fresh = make_page("Arrival", "Movie", None);
+    fresh["id"] = json!("page-fresh");
+    let (app, notion) = app_with_pages(
+        vec![missing, fresh],
+        FakeTmdb {
+            movie: tmdb_movie(),
+            tv: tmdb_tv(),
+        },
+    );
+
+    let (status, _) = post_admin(
+        &app,
+        "/admin/backfill",
+        Some(ADMIN_KEY),
+        json!({ "kind": "sometimes" }),
+    )
+    .await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    let (status, _) = post_admin(
+        &app,
+        "/admin/backfill",
+        Some(ADMIN_KEY),
+        json!({ "kind": "movie", "only_incomplete": true }),
+    )
+    .await;
+    assert_eq!(status, StatusCode::ACCEPTED);
+
+    let status = wait_for_backfill(&app).await;
+    assert_eq!(status["candidates"], 2);
+    assert_eq!(status["updated"], 1);
+    assert_eq!(status["skipped"], json!({ "No TMDB movie match": 1 }));
+    let updates = notion.updates.lock().unwrap();
+    assert_eq!(updates.len(), 1);
+    assert_eq!(updates[0].0, "page-fresh");
+}
+
+#[tokio::test]
+async fn backfill_results_are_appended_to_a_jsonl_log() {
+    let path = std::env::temp_dir().join(format!("cinelink-backfill-{}.jsonl", std::process::id()));
+    let _ = std::fs::remove_file(&path);
+    let mut missing = make_page("wip draft", "Movie", None);
+    missing["id"] = json!("page-missing");
+    let mut fresh = make_page("Arrival", "Movie", None);
+    fresh["id"] = json!("page-fresh");
+    let (state, _notion) = state_with_options(
+        vec![missing, fresh],
+        FakeTmdb {
+            movie: tmdb_movie(),
+            tv: tmdb_tv(),
+        },
+        AppOptions::default(),
+    );
+    let options = BackfillOptions {
+        kind: BackfillKind::Movie,
+        ..BackfillOptions::default()
+    };
+    for _ in 0..2 {
+        let log = ResultLog::open(&path).unwrap();
+        let progress = BackfillProgress::default();
+        run_backfill_logged(&state, &options, &progress, Some(&log))
+            .await
+            .unwrap();
+        log.finish().await.unwrap();
+    }
+
+    let lines: Vec<Value> = std::fs::read_to_string(&path)
+        .unwrap()
+        .lines()
+        .map(|line| serde_json::from_str(line).unwrap())
+        .collect();
+    let _ = std::fs::remove_file(&path);
+    assert_eq!(lines.len(), 4, "both runs are kept");
+    let record = |page_id: &str| lines.iter().find(|l| l["page_id"] == page_id).unwrap();
+    assert_eq!(
+        *record("page-fresh"),
+        json!({
+            "page_id": "page-fresh",
+            "original_title": "Arrival",
+            "updated_title": "TMDB Movie",
+            "source": "tmdb",
+            "success": true,
+            "error": null,
+        })
+    );
+    let missing = record("page-missing");
+    assert_eq!(missing["success"], false);
+    assert_eq!(missing["error"], "No TMDB movie match");
+    assert_eq!(missing["updated_title"], "wip draft");
+}
+
+#[tokio::test]
+async fn unmatched_titles_are_not_searched_again_until_forced() {
+    let (app, notion) = app_with_mocks(
+        make_page(&format!("{UNMATCHED_TITLE};"), "Movie", None),
+        FakeTmdb {
+            movie: tmdb_movie(),
+            tv: tmdb_tv(),
+        },
+    );
+    let process = |force: bool| {
+        post_admin(
+            &app,
+            "/admin/process",
+            Some(ADMIN_KEY),
+            json!({ "page_id": "page-1", "force": force }),
+        )
+    };
+
+    for _ in 0..2 {
+        let (status, body) = process(false).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body["title"], "wip notes; | No TMDB movie match");
+    }
+    assert_eq!(UNMATCHED_SEARCHES.load(Ordering::SeqCst), 1);
+    assert_eq!(notion.updates.lock().unwrap().len(), 2);
+
+    let (status, body) = process(true).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["updated"], false);
+    assert_eq!(UNMATCHED_SEARCHES.load(Ordering::SeqCst), 2);
+}
+
+async fn wait_for_backfill(app: &Router) -> Value {
+    let deadline = tokio::time::Instant::now() + Duration::from_secs(5);
+    loop {
+        let (code, body) = get_admin(app, "/admin/backfill/status").await;
+        assert_eq!(code, StatusCode::OK);
+        if body["running"] == false {
+            return body;
+        }
+        assert!(
+            tokio::time::Instant::now() < deadline,
+            "backfill never finished"
+        );
+        tokio::time::sleep(Duration::from_millis(20)).await;
+    }
+}
+
+#[tokio::test]
+async fn admin_backfill_runs_once_and_reports_progress() {
+    let mut show = make_page("Show", "TV Series", Some("Season 1"));
+    show["id"] = json!("page-show");
+    let mut movie = make_page("Movie Title", "Movie", None);
+    movie["id"] = json!("page-movie");
+    let mut untitled = make_page("", "TV Series", Some("Season 1"));
+    untitled["id"] = json!("page-untitled");
+    let (app, notion) = app_with_options(
+        vec![show, movie, untitled],
+        FakeTmdb {
+            movie: tmdb_movie(),
+            tv: tmdb_tv(),
+        },
+        AppOptions {
+            notion_fetch_delay: Duration::from_millis(200),
+            ..AppOptions::default()
+        },
+    );
+
+    let (status, _) = post_admin(&app, "/admin/backfill", None, json!({})).await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+
+    let (status, body) = post_admin(&app, "/admin/backfill", Some(ADMIN_KEY), json!({})).await;
+    assert_eq!(status, StatusCode::ACCEPTED);
+    assert_eq!(body["running"], true);
+    let (status, _) = post_admin(&app, "/admin/backfill", Some(ADMIN_KEY), json!({})).await;
+    assert_eq!(status, StatusCode::CONFLICT);
+
+    let status = wait_for_backfill(&app).await;
+    assert_eq!(status["scanned"], 3);
+    assert_eq!(status["candidates"], 1);
+    assert_eq!(status["updated"], 1);
+    assert_eq!(status["errors"], 0);
+    assert_eq!(status["error"], Value::Null);
+    assert_eq!(notion.updates.lock().unwrap()[0].0, "page-show");
+
+    let (status, _) = post_admin(&app, "/admin/backfill", Some(ADMIN_KEY), json!({})).await;
+    assert_eq!(status, StatusCode::ACCEPTED);
+}
+
+#[tokio::test]
+async fn simultaneous_events_for_a_page_run_one_job() {
+    let (app, notion) = app_with_options(
+        vec![make_page("Movie Title ;", "Movie", None)],
+        FakeTmdb {
+            movie: tmdb_movie(),
+            tv: tmdb_tv(),
+        },
+        AppOptions {
+            notion_fetch_delay: Duration::from_millis(200),
+            ..AppOptions::default()
+        },
+    );
+
+    // Notion's pair of events for one edit, delivered concurrently.
+    let (first, second) = tokio::join!(
+        app.clone()
+            .oneshot(signed_request(webhook_payload(&["title"], "page-1"))),
+        app.clone()
+            .oneshot(signed_request(webhook_payload(&["season"], "page-1"))),
+    );
+    assert_eq!(first.unwrap().status(), StatusCode::OK);
+    assert_eq!(second.unwrap().status(), StatusCode::OK);
+
+    wait_for_update_count(&notion, 1).await;
+    tokio::time::sleep(Duration::from_millis(500)).await;
+    assert_eq!(notion.updates.lock().unwrap().len(), 1);
+}
+
+#[tokio::test]
+async fn batched_events_update_every_page_once() {
+    let mut second = make_page("Movie Title ;", "Movie", None);
+    second["id"] = json!("page-2");
+    let (app, notion) = app_with_pages(
+        vec![make_page("Movie Title ;", "Movie", None), second],
+        FakeTmdb {
+            movie: tmdb_movie(),
+            tv: tmdb_tv(),
+        },
+    );
+
+    let payload = json!({
+        "id": "evt-batch",
+        "timestamp": Utc::now().to_rfc3339(),
+        "type": "page.properties_updated",
+        "entity": [{ "id": "page-1", "type": "page" }, { "id": "page-2", "type": "page" }],
+        "data": { "updated_properties": ["title"], "pages": ["page-2"] }
+    })
+    .to_string();
+    let res = app
+        .clone()
+        .oneshot(signed_request(payload.clone()))
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::OK);
+    wait_for_update_count(&notion, 2).await;
+
+    // Redelivery is deduped page by page.
+    let res = app.oneshot(signed_request(payload)).await.unwrap();
+    assert_eq!(res.status(), StatusCode::OK);
+    tokio::time::sleep(Duration::from_millis(100)).await;
+    let updates = notion.updates.lock().unwrap();
+    let mut pages: Vec<&str> = updates.iter().map(|(id, ..)| id.as_str()).collect();
+    pages.sort();
+    assert_eq!(pages, vec!["page-1", "page-2"]);
+}
+
+#[tokio::test]
+async fn later_events_for_a_page_in_flight_are_coalesced_into_one_recheck() {
+    let (app, notion) = app_with_options(
+        vec![make_page("Movie Title ;", "Movie", None)],
+        FakeTmdb {
+            movie: tmdb_movie(),
+            tv: tmdb_tv(),
+        },
+        AppOptions {
+            notion_fetch_delay: Duration::from_millis(2500),
+            ..AppOptions::default()
+        },
+    );
+
+    let res = app
+        .clone()
+        .oneshot(signed_request(webhook_payload(&["title"], "page-1")))
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::OK);
+    // Past the window in which events are simply dropped, while the job still runs.
+    tokio::time::sleep(Duration::from_millis(2200)).await;
+    for updated in [&["season"][..], &["title", "season"]] {
+        let res = app
+            .clone()
+            .oneshot(signed_request(webhook_payload(updated, "page-1")))
+            .await
+            .unwrap();
+        assert_eq!(res.status(), StatusCode::OK);
+    }
+
+    // The first event's job, then a single re-check for the two that arrived meanwhile.
+    tokio::time::sleep(Duration::from_millis(2500)).await;
+    wait_for_update_count(&notion, 2).await;
+    tokio::time::sleep(Duration::from_millis(500)).await;
+    assert_eq!(notion.updates.lock().unwrap().len(), 2);
+}
+
+#[tokio::test]
+async fn dry_run_enriches_pages_without_writing_to_notion() {
+    let (app, notion) = app_with_options(
+        vec![make_page("Movie Title", "Movie", None)],
+        FakeTmdb {
+            movie: tmdb_movie(),
+            tv: tmdb_tv(),
+        },
+        AppOptions {
+            dry_run: true,
+            ..AppOptions::default()
+        },
+    );
+
+    let (status, body) = post_admin(
+        &app,
+        "/admin/process",
+        Some(ADMIN_KEY),
+        json!({ "page_id": "page-1", "force": true }),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["updated"], true);
+    assert_eq!(body["id"], 101);
+    assert!(notion.updates.lock().unwrap().is_empty());
+    assert!(notion.comments.lock().unwrap().is_empty());
+}
+
+#[tokio::test]
+async fn synopsis_as_body_replaces_the_page_body() {
+    let (app, notion) = app_with_options(
+        vec![make_page("Movie Title", "Movie", None)],
+        FakeTmdb {
+            movie: tmdb_movie(),
+            tv: tmdb_tv(),
+        },
+        AppOptions {
+            synopsis_as_body: true,
+            ..AppOptions::default()
+        },
+    );
+    notion
+        .blocks
+        .lock()
+        .unwrap()
+        .insert("page-1".to_string(), vec![json!({ "type": "divider" })]);
+
+    for _ in 0..2 {
+        let (status, body) = post_admin(
+            &app,
+            "/admin/process",
+            Some(ADMIN_KEY),
+            json!({ "page_id": "page-1", "force": true }),
+        )
+        .await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body["updated"], true);
+    }
+
+    let blocks = notion.blocks.lock().unwrap()["page-1"].clone();
+    assert_eq!(blocks.len(), 1);
+    assert_eq!(blocks[0]["type"], "paragraph");
+    assert_eq!(
+        blocks[0]["paragraph"]["rich_text"][0]["text"]["content"],
+        "Movie overview"
+    );
+    // The property is still written.
+    let updates = notion.updates.lock().unwrap();
+    assert_eq!(
+        updates[0].1["Synopsis"]["rich_text"][0]["text"]["content"],
+        "Movie overview"
+    );
+}
+
+#[tokio::test]
+async fn admin_dry_run_flag_skips_notion_writes_for_that_request() {
+    let mut show = make_page("Show", "TV Series", Some("Season 1"));
+    show["id"] = json!("page-show");
+    let mut movie = make_page("Movie Title", "Movie", None);
+    movie["id"] = json!("page-movie");
+    let (app, notion) = app_with_pages(
+        vec![show, movie],
+        FakeTmdb {
+            movie: tmdb_movie(),
+            tv: tmdb_tv(),
+        },
+    );
+
+    let (status, _) = post_admin(
+        &app,
+        "/admin/process",
+        Some(ADMIN_KEY),
+        json!({ "page_id": "page-movie", "force": true, "dry_run": "yes" }),
+    )
+    .await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    let (status, body) = post_admin(
+        &app,
+        "/admin/process",
+        Some(ADMIN_KEY),
+        json!({ "page_id": "page-movie", "force": true, "dry_run": true }),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["updated"], true);
+
+    let (status, _) = post_admin(
+        &app,
+        "/admin/backfill",
+        Some(ADMIN_KEY),
+        json!({ "kind": "all", "dry_run": true }),
+    )
+    .await;
+    assert_eq!(status, StatusCode::ACCEPTED);
+    let status = wait_for_backfill(&app).await;
+    assert_eq!(status["updated"], 2);
+    assert!(notion.updates.lock().unwrap().is_empty());
+
+    // Without the flag the same request writes again.
+    let (_, body) = post_admin(
+        &app,
+        "/admin/process",
+        Some(ADMIN_KEY),
+        json!({ "page_id": "page-movie", "force": true }),
+    )
+    .await;
+    assert_eq!(body["updated"], true);
+    assert_eq!(notion.updates.lock().unwrap().len(), 1);
+}
+
+#[tokio::test]
+async fn all_pages_streams_every_page_across_cursors() {
+    let pages = (1..=5)
+        .map(|i| {
+            let mut page = make_page(&format!("Movie {i}"), "Movie", None);
+            page["id"] = json!(format!("page-{i}"));
+            page
+        })
+        .collect();
+    let (state, _) = state_with_options(
+        pages,
+        FakeTmdb {
+            movie: tmdb_movie(),
+            tv: tmdb_tv(),
+        },
+        AppOptions::default(),
+    );
+
+    let listed: Vec<Value> = all_pages(state.notion.as_ref(), 2)
+        .try_collect()
+        .await
+        .unwrap();
+    let ids: Vec<&str> = listed.iter().map(|p| p["id"].as_str().unwrap()).collect();
+    assert_eq!(ids, ["page-1", "page-2", "page-3", "page-4", "page-5"]);
+}
+
+#[tokio::test]
+async fn backfill_can_filter_by_type_limit_and_only_list_pages() {
+    let pages = ["TV Series", "TV Mini Series", "tv series", "TV Series"]
+        .into_iter()
+        .enumerate()
+        .map(|(i, type_value)| {
+            let mut page = make_page(&format!("Show {i}"), type_value, Some("Season 1"));
+            page["id"] = json!(format!("page-{i}"));
+            page
+        })
+        .collect();
+    let (state, notion) = state_with_options(
+        pages,
+        FakeTmdb {
+            movie: tmdb_movie(),
+            tv: tmdb_tv(),
+        },
+        AppOptions::default(),
+    );
+    let listed = BackfillOptions {
+        type_filter: Some("TV Series".to_string()),
+        limit: Some(2),
+        list_only: true,
+        ..BackfillOptions::default()
+    };
+    let progress = BackfillProgress::default();
+    run_backfill(&state, &listed, &progress).await.unwrap();
+    assert_eq!(progress.candidates(), 2);
+    assert_eq!(progress.scanned(), 3);
+    assert!(notion.updates.lock().unwrap().is_empty());
+
+    let progress = BackfillProgress::default();
+    let options = BackfillOptions {
+        list_only: false,
+        ..listed
+    };
+    run_backfill(&state, &options, &progress).await.unwrap();
+    assert_eq!(progress.updated(), 2);
+    let updated: Vec<String> = notion
+        .updates
+        .lock()
+        .unwrap()
+        .iter()
+        .map(|u| u.0.clone())
+        .collect();
+    assert!(updated.contains(&"page-0".to_string()));
+    assert!(updated.contains(&"page-2".to_string()));
+}
+
+#[tokio::test]
+async fn backfill_skips_pages_that_repeat_a_title() {
+    let pages = [
+        ("Show", "Season 1"),
+        ("show!", "Season 1"),
+        ("Show", "Season 2"),
+    ]
+    .into_iter()
+    .enumerate()
+    .map(|(i, (title, season))| {
+        let mut page = make_page(title, "TV Series", Some(season));
+        page["id"] = json!(format!("page-{i}"));
+        page
+    })
+    .collect();
+    let (state, notion) = state_with_options(
+        pages,
+        FakeTmdb {
+            movie: tmdb_movie(),
+            tv: tmdb_tv(),
+        },
+        AppOptions::default(),
+    );
+    let progress = BackfillProgress::default();
+    run_backfill(&state, &BackfillOptions::default(), &progress)
+        .await
+        .unwrap();
+    assert_eq!(progress.updated(), 2);
+    assert_eq!(progress.to_json()["skipped"]["Duplicate title"], 1);
+    let mut updated: Vec<String> = notion
+        .updates
+        .lock()
+        .unwrap()
+        .iter()
+        .map(|u| u.0.clone())
+        .collect();
+    updated.sort();
+    assert_eq!(updated, ["page-0", "page-2"]);
+}
+
+#[tokio::test]
+async fn anime_backfill_routes_anilist_pages_through_anilist() {
+    let stored = enriched_page(
+        "page-stored",
+        "Frieren",
+        "TV Series",
+        176496,
+        "https://anilist.co/anime/176496",
+    );
+    let mut by_genre = make_page("Frieren", "TV Series", Some("Season 2"));
+    by_genre["id"] = json!("page-genre");
+    by_genre["properties"]["Genre"] = json!({ "multi_select": [{ "name": "Anime" }] });
+    let mut show = make_page("Show", "TV Series", Some("Season 1"));
+    show["id"] = json!("page-show");
+    let (app, notion) = app_with_pages(
+        vec![stored, by_genre, show],
+        FakeTmdb {
+            movie: tmdb_movie(),
+            tv: tmdb_tv(),
+        },
+    );
+
+    let (status, _) = post_admin(
+        &app,
+        "/admin/backfill",
+        Some(ADMIN_KEY),
+        json!({ "kind": "anime" }),
+    )
+    .await;
+    assert_eq!(status, StatusCode::ACCEPTED);
+    let status = wait_for_backfill(&app).await;
+    assert_eq!(status["candidates"], 2);
+    assert_eq!(status["updated"], 2);
+    {
+        let mut updates = notion.updates.lock().unwrap().clone();
+        updates.sort_by(|a, b| a.0.cmp(&b.0));
+        assert_eq!(updates.len(), 2);
+        for (update, page_id) in updates.iter().zip(["page-genre", "page-stored"]) {
+            assert_eq!(update.0, page_id);
+            assert_eq!(update.1["ID"]["number"], json!(176496.0));
+        }
+    }
+
+    // The TV backfill leaves AniList pages alone.
+    let (status, _) = post_admin(&app, "/admin/backfill", Some(ADMIN_KEY), json!({})).await;
+    assert_eq!(status, StatusCode::ACCEPTED);
+    let status = wait_for_backfill(&app).await;
+    assert_eq!(status["candidates"], 1);
+    assert_eq!(status["updated"], 1);
+    assert_eq!(notion.updates.lock().unwrap()[2].0, "page-show");
+}